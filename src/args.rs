@@ -2,7 +2,7 @@
 //  SPDX-License-Identifier: GPL-3.0-or-later
 
 //! Command line arguments.
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(about = "Convert ALFA to XACML.", author = env!("CARGO_PKG_AUTHORS"), version = env!("CARGO_PKG_VERSION"), arg_required_else_help = true)]
@@ -42,4 +42,179 @@ pub struct CLIArgs {
         required = false
     )]
     pub base_namespace: Option<String>,
+    #[arg(
+        long = "policy-version",
+        help = "Version for policies without a version statement (default: 1.0)",
+        required = false
+    )]
+    pub policy_version: Option<String>,
+    #[arg(
+        long = "xacml-version",
+        help = "XACML version to generate, 2.0 or 3.0 (default: 3.0)",
+        required = false
+    )]
+    pub xacml_version: Option<String>,
+    #[arg(
+        long = "id-strategy",
+        help = "Naming of anonymous rules and policies: counter, hash, or uuid (default: counter)",
+        required = false
+    )]
+    pub id_strategy: Option<String>,
+    #[arg(
+        long = "provenance",
+        help = "Record the ALFA source of policies and rules: off, comment, or description (default: off)",
+        required = false
+    )]
+    pub provenance: Option<String>,
+    #[arg(
+        long = "canonical",
+        help = "Write canonical XML (Exclusive C14N), for byte-identical output",
+        required = false
+    )]
+    pub canonical: bool,
+    #[arg(
+        long = "xml-prefix",
+        help = "Namespace prefix for XACML elements (default: xacml3, or xacml2)",
+        required = false,
+        conflicts_with = "xml_default_namespace"
+    )]
+    pub xml_prefix: Option<String>,
+    #[arg(
+        long = "xml-default-namespace",
+        help = "Use a default namespace instead of a prefix for XACML elements",
+        required = false
+    )]
+    pub xml_default_namespace: bool,
+    #[arg(
+        long = "indent",
+        help = "Spaces to indent XML with, or 0 for no indentation (default: 2)",
+        required = false
+    )]
+    pub indent: Option<usize>,
+    #[arg(
+        long = "no-xml-declaration",
+        help = "Omit the XML declaration",
+        required = false
+    )]
+    pub no_xml_declaration: bool,
+    #[arg(
+        long = "encoding",
+        help = "Encoding of XACML files: UTF-8, US-ASCII, or ISO-8859-1 (default: UTF-8)",
+        required = false
+    )]
+    pub encoding: Option<String>,
+    #[arg(
+        long = "schema-location",
+        help = "Add xsi:schemaLocation to XACML files",
+        required = false
+    )]
+    pub schema_location: bool,
+    #[arg(
+        long = "line-endings",
+        help = "Line endings for XACML files: lf or crlf (default: lf)",
+        required = false
+    )]
+    pub line_endings: Option<String>,
+    #[arg(
+        long = "layout",
+        help = "Naming of XACML files: flat, nested, or id (default: flat)",
+        required = false,
+        conflicts_with = "layout_template"
+    )]
+    pub layout: Option<String>,
+    #[arg(
+        long = "layout-template",
+        help = "Name XACML files with a <template>, using {namespace}, {namespace_dir}, {name}, {id}, and {version}",
+        required = false
+    )]
+    pub layout_template: Option<String>,
+    #[arg(
+        long = "check",
+        help = "Compare generated XACML with the output directory, without writing, and fail on differences",
+        required = false,
+        conflicts_with = "prune"
+    )]
+    pub check: bool,
+    #[arg(
+        long = "prune",
+        help = "Remove XACML files generated by a previous run that are no longer generated",
+        required = false
+    )]
+    pub prune: bool,
+    #[arg(
+        long = "watch",
+        help = "Keep running, and convert again whenever the inputs change",
+        required = false,
+        conflicts_with = "check"
+    )]
+    pub watch: bool,
+    #[cfg(feature = "cache")]
+    #[arg(
+        long = "cache",
+        help = "Keep compiled results in <directory>, and only convert ALFA files that changed, or whose dependencies changed",
+        required = false,
+        conflicts_with = "bundle"
+    )]
+    pub cache: Option<String>,
+    #[arg(
+        long = "bundle",
+        help = "Write a single XACML file rooted at an ALFA <policyset>, with references inlined",
+        required = false,
+        conflicts_with = "bundle_all"
+    )]
+    pub bundle: Option<String>,
+    #[arg(
+        long = "bundle-all",
+        help = "Write a single XACML file containing all policies, combined with <algorithm>",
+        required = false
+    )]
+    pub bundle_all: Option<String>,
+    #[arg(
+        long = "decompile",
+        help = "Read XACML from the input paths, and write it as ALFA to <file>",
+        required = false
+    )]
+    pub decompile: Option<String>,
+    #[arg(
+        long = "decompile-namespace",
+        help = "Namespace for decompiled ALFA (default: main)",
+        required = false,
+        requires = "decompile"
+    )]
+    pub decompile_namespace: Option<String>,
+    #[cfg(feature = "serde")]
+    #[arg(
+        long = "dump-json",
+        help = "Write the syntax tree (ast) or the XACML model (xacml) of the input as JSON to stdout",
+        required = false,
+        value_parser = ["ast", "xacml"]
+    )]
+    pub dump_json: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Format ALFA source files in place
+    Fmt {
+        #[arg(help = "ALFA <files> or <directories> to format")]
+        paths: Vec<String>,
+        #[arg(
+            long = "check",
+            help = "List files that are not formatted, without writing, and fail if there are any",
+            required = false
+        )]
+        check: bool,
+    },
+    /// Show the ALFA source of a generated PolicySetId, PolicyId, or RuleId
+    Lookup {
+        #[arg(help = "PolicySetId, PolicyId, or RuleId to find")]
+        id: String,
+        #[arg(help = "ALFA <files> or <directories> the XACML was generated from")]
+        paths: Vec<String>,
+    },
+    /// Run a language server for ALFA, over stdio
+    #[cfg(feature = "lsp")]
+    Lsp,
 }
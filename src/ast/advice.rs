@@ -11,12 +11,14 @@
 //! ```advice myAdvice = "http://example.com/advice"```
 //!
 //!
+use super::AsAlfa;
 use super::PrettyPrint;
 use super::QualifiedName;
 use std::fmt;
 
 /// A definition of an `Advice` URI.
 #[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AdviceDef {
     pub id: String,
     pub uri: String,
@@ -24,6 +26,14 @@ pub struct AdviceDef {
     pub ns: Vec<String>,
 }
 
+impl AsAlfa for AdviceDef {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        let indent = "  ".repeat(indent_level);
+        // Ex: advice myAdvice = "http://example.com/advice"
+        format!("{}advice {} = \"{}\"\n", indent, self.id, self.uri)
+    }
+}
+
 impl QualifiedName for AdviceDef {
     fn fully_qualified_name(&self) -> Option<String> {
         let mut qn = self.ns.join(".");
@@ -8,6 +8,7 @@ use std::fmt;
 
 /// An Attribute definition
 #[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Attribute {
     // short name
     pub id: String,
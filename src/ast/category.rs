@@ -9,6 +9,7 @@ use std::fmt;
 
 /// A category statement
 #[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Category {
     pub id: String,
     pub uri: String,
@@ -90,5 +91,33 @@ pub fn standard_categories() -> Vec<Category> {
         "environmentCat",
         "urn:oasis:names:tc:xacml:3.0:attribute-category:environment",
     ));
+    // XACML Administration and Delegation Profile
+    c.push(make_std_cat(
+        "delegateCat",
+        "urn:oasis:names:tc:xacml:3.0:attribute-category:delegate",
+    ));
+    c.push(make_std_cat(
+        "delegationInfoCat",
+        "urn:oasis:names:tc:xacml:3.0:attribute-category:delegation-info",
+    ));
+    // delegated categories are formed by prefixing the original
+    // category identifier.
+    let delegated = "urn:oasis:names:tc:xacml:3.0:attribute-category:delegated:";
+    c.push(make_std_cat(
+        "delegatedSubjectCat",
+        &format!("{delegated}urn:oasis:names:tc:xacml:1.0:subject-category:access-subject"),
+    ));
+    c.push(make_std_cat(
+        "delegatedResourceCat",
+        &format!("{delegated}urn:oasis:names:tc:xacml:3.0:attribute-category:resource"),
+    ));
+    c.push(make_std_cat(
+        "delegatedActionCat",
+        &format!("{delegated}urn:oasis:names:tc:xacml:3.0:attribute-category:action"),
+    ));
+    c.push(make_std_cat(
+        "delegatedEnvironmentCat",
+        &format!("{delegated}urn:oasis:names:tc:xacml:3.0:attribute-category:environment"),
+    ));
     c
 }
@@ -0,0 +1,45 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::constant::Constant;
+use super::SrcLoc;
+use super::Spanned;
+use std::fmt;
+
+/// A named parameter for a combining algorithm.
+///
+/// Parameters given on an `apply` statement become XACML
+/// `<CombinerParameters>`.  Parameters given on a rule, policy, or
+/// policyset within a parent become `<RuleCombinerParameters>`,
+/// `<PolicyCombinerParameters>`, or `<PolicySetCombinerParameters>`
+/// of the parent.
+///
+/// Ex: `with (weight = 2)`
+#[derive(Debug, PartialEq, Clone, Spanned)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CombinerParam {
+    /// Location of the parameter in the ALFA source
+    pub src_loc: SrcLoc,
+    /// Parameter name
+    pub name: String,
+    /// Literal value; the type is taken from the literal
+    pub value: Constant,
+}
+
+impl fmt::Display for CombinerParam {
+    // This trait requires `fmt` with this exact signature.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} = {}", self.name, self.value)
+    }
+}
+
+/// Parameters as written after a name or combining algorithm
+/// (` with (weight = 2)`), or nothing if there are none.
+#[must_use]
+pub fn alfa_params(params: &[CombinerParam]) -> String {
+    if params.is_empty() {
+        return String::new();
+    }
+    let ps: Vec<String> = params.iter().map(ToString::to_string).collect();
+    format!(" with ({})", ps.join(", "))
+}
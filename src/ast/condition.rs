@@ -2,7 +2,7 @@ use super::constant::Constant;
 use super::designator::AttributeDesignator;
 use super::operator::Operator;
 use super::Spanned;
-use super::{PrettyPrint, SrcLoc};
+use super::{AsAlfa, PrettyPrint, SrcLoc};
 use crate::errors::ParseError;
 use crate::Context;
 use std::fmt;
@@ -18,6 +18,7 @@ use std::rc::Weak;
 
 /// A condition, with unparsed expressions
 #[derive(Debug, Default, Clone, Spanned)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Condition {
     /// conditions contain expressions, which can be nested, this form
     /// has no associativity applied.
@@ -27,6 +28,7 @@ pub struct Condition {
     /// The location of this condition
     pub src_loc: SrcLoc,
     /// Context for conversion
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub ctx: Weak<Context>,
 }
 
@@ -37,6 +39,14 @@ impl PartialEq for Condition {
     }
 }
 
+impl AsAlfa for Condition {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        let indent = "  ".repeat(indent_level);
+        // Ex: condition Attributes.age >= 18 && stringIsIn("a", Attributes.roles)
+        format!("{indent}condition {}\n", self.cond_expr.alfa_expr(false))
+    }
+}
+
 impl fmt::Display for Condition {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Condition: {}", self.cond_expr)
@@ -52,6 +62,7 @@ impl PrettyPrint for Condition {
 
 /// Condition Terms and Operators
 #[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum CondExpression {
     Infix(Box<CondExpression>, Operator, Box<CondExpression>),
     Fn(CondFunctionCall),
@@ -62,6 +73,33 @@ pub enum CondExpression {
     Empty,
 }
 
+impl CondExpression {
+    /// The ALFA text of this expression.  Operator applications that
+    /// are operands of another operator are parenthesized, so that
+    /// precedence does not change the grouping.
+    fn alfa_expr(&self, operand: bool) -> String {
+        match self {
+            CondExpression::Infix(a, op, b) => {
+                let e = format!("{} {op} {}", a.alfa_expr(true), b.alfa_expr(true));
+                if operand { format!("({e})") } else { e }
+            }
+            CondExpression::Fn(f) => format!(
+                "{}({})",
+                f.identifier.join("."),
+                f.arguments
+                    .iter()
+                    .map(|x| x.alfa_expr(false))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            CondExpression::FnRef(f) => format!("function[{}]", f.fully_qualified_name()),
+            CondExpression::Attr(a) => a.to_string(),
+            CondExpression::Lit(c) => c.to_string(),
+            CondExpression::Empty => String::new(),
+        }
+    }
+}
+
 impl fmt::Display for CondExpression {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -89,6 +127,7 @@ impl fmt::Display for CondExpression {
 
 // Function calls which can have unparsed arguments.
 #[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CondFunctionCall {
     pub identifier: Vec<String>, // qualified name
     pub arguments: Vec<CondExpression>,
@@ -257,6 +296,7 @@ pub struct CondFunctionCallUnparsed {
 
 /// A function reference (function-as-argument)
 #[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FunctionReference {
     pub identifier: Vec<String>, // qualified name
 }
@@ -2,6 +2,7 @@ use std::fmt;
 
 /// A constant value
 #[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Constant {
     String(String),
     Integer(String),
@@ -9,12 +10,16 @@ pub enum Constant {
     Boolean(bool),
     /// data type and value (e.g. "192.168.1.1":ipAddress)
     Custom(CustomType, String),
+    /// reference to a declared constant, by (possibly qualified) name
+    /// (e.g. constant[limits.maxAge])
+    Named(String),
     #[default]
     Undefined,
 }
 
 /// Custom type short names, which need to be looked up.
 #[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CustomType {
     pub name: String,
 }
@@ -22,11 +27,90 @@ pub struct CustomType {
 impl fmt::Display for Constant {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Constant::String(s) => write!(f, "{s:?}"),
+            Constant::String(s) => write!(f, "{}", quote_string(s)),
             Constant::Integer(i) => write!(f, "{i}"),
             Constant::Double(d) => write!(f, "{d}"),
             Constant::Boolean(b) => write!(f, "{b}"),
+            Constant::Custom(t, v) => write!(f, "{}:{}", quote_raw(v), t.name),
+            Constant::Named(n) => write!(f, "constant[{n}]"),
             _ => write!(f, "unhandled"),
         }
     }
 }
+
+impl From<&str> for Constant {
+    fn from(s: &str) -> Self {
+        Constant::String(s.to_owned())
+    }
+}
+
+impl From<String> for Constant {
+    fn from(s: String) -> Self {
+        Constant::String(s)
+    }
+}
+
+impl From<i64> for Constant {
+    fn from(i: i64) -> Self {
+        Constant::Integer(i.to_string())
+    }
+}
+
+impl From<f64> for Constant {
+    fn from(d: f64) -> Self {
+        // debug formatting always includes a decimal point
+        Constant::Double(format!("{d:?}"))
+    }
+}
+
+impl From<bool> for Constant {
+    fn from(b: bool) -> Self {
+        Constant::Boolean(b)
+    }
+}
+
+/// A string literal, escaped so that it reads back as the same
+/// string.
+#[must_use]
+pub fn quote_string(s: &str) -> String {
+    let mut q = String::from('"');
+    for c in s.chars() {
+        match c {
+            '\\' => q.push_str("\\\\"),
+            '"' => q.push_str("\\\""),
+            '\n' => q.push_str("\\n"),
+            '\r' => q.push_str("\\r"),
+            '\t' => q.push_str("\\t"),
+            c => q.push(c),
+        }
+    }
+    // an escaped backslash before the closing quote would be read as
+    // an escaped quote.
+    if q.ends_with('\\') {
+        q.truncate(q.len() - 2);
+        q.push_str("\\u{5c}");
+    }
+    q.push('"');
+    q
+}
+
+/// A string that is used without unescaping (URIs, issuers, and
+/// values of custom literals), in whichever quotes it was read from.
+/// Double quotes are used unless the string contains one that is not
+/// escaped.
+#[must_use]
+pub fn quote_raw(s: &str) -> String {
+    let mut escaped = false;
+    let mut bare_quote = false;
+    for c in s.chars() {
+        if c == '"' && !escaped {
+            bare_quote = true;
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    if bare_quote {
+        format!("'{s}'")
+    } else {
+        format!("\"{s}\"")
+    }
+}
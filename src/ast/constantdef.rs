@@ -0,0 +1,69 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::constant::Constant;
+use super::AsAlfa;
+use super::PrettyPrint;
+use super::QualifiedName;
+use super::SrcLoc;
+use super::Spanned;
+use std::fmt;
+
+/// A named constant definition.
+///
+/// Constants bind a name to a literal value with an explicit type,
+/// and can be referenced anywhere a literal is allowed.
+///
+/// Ex: `constant maxAge : integer = 65`
+#[derive(Debug, PartialEq, Clone, Default, Spanned)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ConstantDef {
+    /// short name
+    pub id: String,
+    /// type of the constant (a reference to a type definition)
+    pub typedef: String,
+    /// literal value of the constant
+    pub value: Constant,
+    /// The namespace from general to most specific.
+    pub ns: Vec<String>,
+    /// Location of the declaration in the ALFA source
+    pub src_loc: SrcLoc,
+}
+
+impl AsAlfa for ConstantDef {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        let indent = "  ".repeat(indent_level);
+        // Ex: constant maxAge : integer = 65
+        format!(
+            "{}constant {} : {} = {}\n",
+            indent, self.id, self.typedef, self.value
+        )
+    }
+}
+
+impl QualifiedName for ConstantDef {
+    fn fully_qualified_name(&self) -> Option<String> {
+        let mut qn = self.ns.join(".");
+        qn.push('.');
+        qn.push_str(&self.id);
+        Some(qn.to_string())
+    }
+}
+
+impl PrettyPrint for ConstantDef {
+    fn pretty_print(&self, indent_level: usize) {
+        let indent = "  ".repeat(indent_level);
+        println!("{indent}{self}");
+    }
+}
+
+impl fmt::Display for ConstantDef {
+    // This trait requires `fmt` with this exact signature.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Constant: \"{}\" ({}) => {}",
+            self.id, self.typedef, self.value
+        )
+    }
+}
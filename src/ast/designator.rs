@@ -1,7 +1,9 @@
+use super::constant::quote_raw;
 use std::fmt;
 
 /// An attribute designator
 #[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AttributeDesignator {
     // TODO: rename this to identifier for consistency
     pub attribute: Vec<String>, //qualified name
@@ -11,6 +13,18 @@ pub struct AttributeDesignator {
     pub mustbepresent: bool,
 }
 
+/// A designator for an attribute by (possibly qualified) name, which
+/// need not be present, from any issuer.
+impl From<&str> for AttributeDesignator {
+    fn from(name: &str) -> Self {
+        AttributeDesignator {
+            attribute: name.split('.').map(String::from).collect(),
+            issuer: None,
+            mustbepresent: false,
+        }
+    }
+}
+
 impl AttributeDesignator {
     #[must_use]
     pub fn fully_qualified_name(&self) -> String {
@@ -25,7 +39,7 @@ impl fmt::Display for AttributeDesignator {
             options.push("mustbepresent".to_string());
         }
         if let Some(i) = &self.issuer {
-            options.push(format!("issuer=\"{}\"", i.clone()));
+            options.push(format!("issuer={}", quote_raw(i)));
         }
         if options.is_empty() {
             // simple, no options
@@ -0,0 +1,94 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::version::VersionConstraints;
+use super::AsAlfa;
+use super::PrettyPrint;
+use super::QualifiedName;
+use super::SrcLoc;
+use super::Spanned;
+use std::fmt;
+
+/// Whether an external declaration refers to a policy or policyset.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ExternKind {
+    #[default]
+    Policy,
+    PolicySet,
+}
+
+impl fmt::Display for ExternKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExternKind::Policy => write!(f, "policy"),
+            ExternKind::PolicySet => write!(f, "policyset"),
+        }
+    }
+}
+
+/// A policy or policyset defined outside of ALFA.
+///
+/// External declarations bind a name to the identifier of a policy
+/// or policyset (typically written directly in XACML), so that it
+/// can be referenced from an ALFA policyset.
+///
+/// Ex: `extern policyset legacyAuth = "urn:example:legacy:auth" (latest = "2.*")`
+#[derive(Debug, PartialEq, Clone, Default, Spanned)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExternPolicy {
+    /// short name
+    pub id: String,
+    /// policy or policyset
+    pub kind: ExternKind,
+    /// `PolicyId` or `PolicySetId` of the external element
+    pub uri: String,
+    /// Acceptable versions, used for references that do not specify
+    /// their own constraints
+    pub versions: VersionConstraints,
+    /// The namespace from general to most specific.
+    pub ns: Vec<String>,
+    /// Location of the declaration in the ALFA source
+    pub src_loc: SrcLoc,
+}
+
+impl AsAlfa for ExternPolicy {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        let indent = "  ".repeat(indent_level);
+        // Ex: extern policy legacy = "urn:example:legacy" (version = "1.*")
+        let mut s = format!("{}extern {} {} = {:?}", indent, self.kind, self.id, self.uri);
+        if !self.versions.is_empty() {
+            s.push(' ');
+            s.push_str(&self.versions.to_string());
+        }
+        s.push('\n');
+        s
+    }
+}
+
+impl QualifiedName for ExternPolicy {
+    fn fully_qualified_name(&self) -> Option<String> {
+        let mut qn = self.ns.join(".");
+        qn.push('.');
+        qn.push_str(&self.id);
+        Some(qn.to_string())
+    }
+}
+
+impl PrettyPrint for ExternPolicy {
+    fn pretty_print(&self, indent_level: usize) {
+        let indent = "  ".repeat(indent_level);
+        println!("{indent}{self}");
+    }
+}
+
+impl fmt::Display for ExternPolicy {
+    // This trait requires `fmt` with this exact signature.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Extern {}: \"{}\" => {}{}",
+            self.kind, self.id, self.uri, self.versions
+        )
+    }
+}
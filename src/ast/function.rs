@@ -7,6 +7,7 @@ use super::QualifiedName;
 use std::fmt;
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum FunctionInputArg {
     /// A reference to a specific atomic type (`string`, `inetAddress`, etc.)
     Atomic(String),
@@ -27,6 +28,7 @@ pub enum FunctionInputArg {
 /// Notably, functions cannot produce other functions, or an output
 /// that may be either atomic or bag (`AnyAtomicOrBag`).
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum FunctionOutputArg {
     /// A reference to a specific atomic type (string, inetAddress, etc.)
     Atomic(String),
@@ -40,6 +42,7 @@ pub enum FunctionOutputArg {
 
 /// A collection of inputs to a function definition.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FunctionInputs {
     /// All the input arguments in order
     pub args: Vec<FunctionInputArg>,
@@ -49,6 +52,7 @@ pub struct FunctionInputs {
 
 /// A function declaration
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Function {
     pub id: String,
     /// The namespace from general to most specific
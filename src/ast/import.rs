@@ -1,11 +1,13 @@
 //  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
 //  SPDX-License-Identifier: GPL-3.0-or-later
 
+use super::AsAlfa;
 use super::PrettyPrint;
 use std::fmt;
 
 /// An import statement
 #[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Import {
     /// Components in an import statement, both namespace and element
     /// (policy/rule/etc.)
@@ -14,6 +16,15 @@ pub struct Import {
     pub is_wildcard: bool,
 }
 
+impl AsAlfa for Import {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        let indent = "  ".repeat(indent_level);
+        // Ex: import Attributes.*
+        let wildcard = if self.is_wildcard { ".*" } else { "" };
+        format!("{indent}import {}{wildcard}\n", self.components.join("."))
+    }
+}
+
 impl PrettyPrint for Import {
     fn pretty_print(&self, indent_level: usize) {
         let indent = "  ".repeat(indent_level);
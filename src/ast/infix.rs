@@ -8,6 +8,7 @@ use std::fmt;
 
 /// Infix operator
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Infix {
     /// The operator symbol
     pub operator: String,
@@ -26,6 +27,7 @@ pub struct Infix {
 
 /// Infix signature
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct InfixSignature {
     /// The URI of the function
     pub uri: String,
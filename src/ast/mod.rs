@@ -5,9 +5,12 @@
 pub mod advice;
 pub mod attribute;
 pub mod category;
+pub mod combinerparam;
 pub mod condition;
 pub mod constant;
+pub mod constantdef;
 pub mod designator;
+pub mod externpolicy;
 pub mod function;
 pub mod import;
 pub mod infix;
@@ -17,6 +20,7 @@ pub mod obligation;
 pub mod operator;
 pub mod policy;
 pub mod policycombinator;
+pub mod policyissuer;
 pub mod policyset;
 pub mod prescription;
 pub mod rule;
@@ -25,7 +29,9 @@ pub mod std_attributes;
 pub mod std_functions;
 pub mod std_infix;
 pub mod target;
+pub mod template;
 pub mod typedef;
+pub mod version;
 
 // Re-export the Spanned trait
 use crate::ast::category::Category;
@@ -52,30 +58,41 @@ use crate::ast::target::{
 };
 use crate::ast::typedef::TypeDef;
 use crate::errors::{ParseError, SrcError};
+use crate::AlfaDocParser;
 use crate::AlfaParseTree;
 use crate::Context;
 use crate::Rule;
 pub use a2x_derive::Spanned;
 use advice::AdviceDef;
 use attribute::Attribute;
+use combinerparam::CombinerParam;
+use constantdef::ConstantDef;
 use condition::{
     CondAtomUnparsed, CondExpressionUnparsed, CondFunctionCallUnparsed, CondItemUnparsed,
     Condition, ConditionUnparsed, FunctionReference,
 };
 use designator::AttributeDesignator;
+use externpolicy::{ExternKind, ExternPolicy};
 use log::{debug, error, info, warn};
 use miette::{NamedSource, SourceCode, SourceSpan};
 use naming::GenName;
 use pest::iterators::Pair;
 use pest::iterators::Pairs;
+use pest::Parser;
+use policyissuer::IssuerAttribute;
 use policyset::PolicyCombiningAlgorithm;
 use prescription::{
     AttrAssignmentSource, AttributeAssignment, Prescription, PrescriptionExpr, PrescriptionType,
 };
 use rule::{Effect, RuleDef};
+use version::{is_valid_version, is_valid_version_match, VersionConstraints};
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::sync::Arc;
+use template::{
+    ArgSymbol, ArgSymbolKind, Expansion, ParamRef, TemplateArg, TemplateDef, TemplateInstance,
+    TemplateKind,
+};
 use unescaper::unescape;
 
 #[cfg(test)]
@@ -83,10 +100,12 @@ mod tests;
 
 /// A fully parsed Alfa syntax tree.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AlfaSyntaxTree {
     /// Namespaces which make up the Alfa source file.
     pub namespaces: Vec<Namespace>,
     /// Contextual information used to build and use the tree.
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub ctx: Rc<Context>,
 }
 
@@ -109,6 +128,19 @@ impl AlfaSyntaxTree {
         }
         ps
     }
+    /// Expand the template instances in this AST into policies and
+    /// rules.  Templates may be declared in any tree built with the
+    /// same context.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if any instance cannot be expanded.
+    pub fn expand_templates(&mut self) -> Result<(), ParseError> {
+        for ns in &mut self.namespaces {
+            ns.expand_templates()?;
+        }
+        Ok(())
+    }
 }
 
 /// Identify a specific location in a source tree.
@@ -172,6 +204,20 @@ impl SrcLoc {
     }
 }
 
+/// Source locations are serialized as the file name, and the offset
+/// and length of the span.
+#[cfg(feature = "serde")]
+impl serde::Serialize for SrcLoc {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut s = serializer.serialize_struct("SrcLoc", 3)?;
+        s.serialize_field("file", self.src.name())?;
+        s.serialize_field("offset", &self.span.offset())?;
+        s.serialize_field("length", &self.span.len())?;
+        s.end()
+    }
+}
+
 /// Trait for AST elements that have source location information.
 ///
 /// Types implementing this trait can provide their original source
@@ -281,7 +327,7 @@ fn process_namespace(
                     )?;
                     ns.add_namespace(child_ns);
                 } else if r == Rule::policyset_decl {
-                    ns.add_policyset(process_policyset(
+                    let policyset = process_policyset(
                         first_stmt,
                         src_loc.clone(),
                         ns.path.clone(),
@@ -289,10 +335,12 @@ fn process_namespace(
                         last_comment.clone(),
                         true,
                         ctx.clone(),
-                    )?)?;
+                    )?;
+                    no_entry_params(&policyset.entry_params)?;
+                    ns.add_policyset(policyset)?;
                 } else if r == Rule::policy_decl {
                     // provide namespace to policy
-                    ns.add_policy(process_policy(
+                    let policy = process_policy(
                         first_stmt.into_inner(),
                         &src_loc,
                         ns.path.clone(),
@@ -300,7 +348,9 @@ fn process_namespace(
                         last_comment.clone(),
                         true,
                         ctx.clone(),
-                    )?)?;
+                    )?;
+                    no_entry_params(&policy.entry_params)?;
+                    ns.add_policy(policy)?;
                 } else if r == Rule::rule_combinator_decl {
                     ns.add_rulecombinator(process_rulecombinator(
                         first_stmt.into_inner(),
@@ -327,6 +377,28 @@ fn process_namespace(
                     let attribute_stmt =
                         process_attribute(first_stmt.into_inner(), ns.path.clone())?;
                     ns.add_attribute(attribute_stmt)?;
+                } else if r == Rule::constant_decl {
+                    let constant_stmt = process_constant(first_stmt, &src_loc, ns.path.clone())?;
+                    ns.add_constant(constant_stmt)?;
+                } else if r == Rule::extern_decl {
+                    let extern_stmt = process_extern(first_stmt, &src_loc, ns.path.clone())?;
+                    ns.add_extern(extern_stmt)?;
+                } else if r == Rule::template_decl {
+                    let template = process_template(
+                        first_stmt,
+                        &src_loc,
+                        ns.path.clone(),
+                        last_comment.clone(),
+                    )?;
+                    ns.add_template(template)?;
+                } else if r == Rule::instance_decl {
+                    let instance = process_instance(
+                        first_stmt,
+                        &src_loc,
+                        ns.path.clone(),
+                        last_comment.clone(),
+                    )?;
+                    ns.add_instance(instance);
                 } else if r == Rule::infix_decl {
                     let infix = process_infix(first_stmt.into_inner(), ns.path.clone())?;
                     ns.add_infix(infix)?;
@@ -338,13 +410,14 @@ fn process_namespace(
                     ns.add_obligation(obligation)?;
                 } else if r == Rule::rule_decl {
                     let rule_item = process_rule(
-                        first_stmt.into_inner(),
+                        first_stmt,
                         src_loc.clone(),
                         ns.path.clone(),
                         GenName::default(),
                         last_comment.clone(),
                         &ctx,
                     )?;
+                    no_entry_params(&rule_item.entry_params)?;
                     ns.add_rule(rule_item)?;
                 } else {
                     error!("unexpected rule {r:?}");
@@ -485,14 +558,18 @@ fn process_function_output(mut arg_pairs: Pairs<Rule>) -> Result<FunctionOutputA
 
 // parse a rule declaration, either in policy or namespace.
 fn process_rule(
-    mut rule_pairs: Pairs<Rule>,
+    rule_pair: Pair<Rule>,
     src_loc: SrcLoc,
     ns: Vec<String>,
     policy_ns: GenName,
     description: Option<String>,
     ctx: &Rc<Context>,
 ) -> Result<RuleDef, ParseError> {
+    let sp = rule_pair.as_span();
+    let rule_src_loc = src_loc.with_start_end(sp.start(), sp.end());
+    let mut rule_pairs = rule_pair.into_inner();
     debug!("{rule_pairs:?}");
+    let content_hash = content_hash_of(&rule_pairs);
     // rule name/id is optional
     let mut id: Option<String> = None;
     // a rule may have one target
@@ -503,6 +580,8 @@ fn process_rule(
     let mut found_effect: Option<rule::Effect> = None;
     // rules can have prescriptions (advice/obligations)
     let mut prescriptions: Vec<Prescription> = vec![];
+    // parameters for the parent combining algorithm
+    let mut entry_params = vec![];
     // Find first non-comment rule, and if it is an identifier, use it
     // as the name.  We can't use skip_comments, because a name might
     // not exist.
@@ -541,7 +620,7 @@ fn process_rule(
             }
         } else if tok.as_rule() == Rule::target_stmt {
             if target.is_none() {
-                target = Some(process_target(tok.into_inner(), ns.clone(), ctx)?);
+                target = Some(process_target(tok, &src_loc, ns.clone(), ctx)?);
             } else {
                 return Err(ParseError::DuplicateTarget);
             }
@@ -558,6 +637,8 @@ fn process_rule(
             }
         } else if tok.as_rule() == Rule::on_effect {
             prescriptions.push(process_prescription(tok, ns.clone(), ctx)?);
+        } else if tok.as_rule() == Rule::combiner_params {
+            entry_params = process_combiner_params(tok, &src_loc)?;
         } else {
             let r = tok.as_rule();
             return Err(ParseError::UnexpectedRuleError(format!(
@@ -577,6 +658,10 @@ fn process_rule(
             condition,
             prescriptions,
             effect,
+            entry_params,
+            content_hash,
+            src_loc: rule_src_loc,
+            expansion: None,
             ctx: Rc::<Context>::downgrade(ctx),
         })
     } else {
@@ -586,26 +671,35 @@ fn process_rule(
 }
 // produces single target, which is a collection of disjunctiveseqs.
 fn process_target(
-    mut target_pairs: Pairs<Rule>,
+    target_pair: Pair<Rule>,
+    src_loc: &SrcLoc,
     ns: Vec<String>,
     ctx: &Rc<Context>,
 ) -> Result<Target, ParseError> {
+    let sp = target_pair.as_span();
+    let mut target_pairs = target_pair.into_inner();
     // loop through the target_disjunctions
     // each of the clauses will be ANDed together.
     let mut clauses = vec![];
     while let Some(tok) = skip_comments(&mut target_pairs) {
         assert_eq!(tok.as_rule(), Rule::target_disjunction);
-        clauses.push(process_target_clause(tok.into_inner())?);
+        let clause_sp = tok.as_span();
+        let clause_loc = src_loc.with_start_end(clause_sp.start(), clause_sp.end());
+        clauses.push(process_target_clause(tok.into_inner(), clause_loc)?);
     }
     Ok(Target {
         clauses,
         ns,
+        src_loc: src_loc.with_start_end(sp.start(), sp.end()),
         ctx: Rc::<Context>::downgrade(ctx),
     })
 }
 
 /// Take a clause with or'd entries and produce a `DisjunctiveSeq`
-fn process_target_clause(mut conj_pairs: Pairs<Rule>) -> Result<DisjunctiveSeq, ParseError> {
+fn process_target_clause(
+    mut conj_pairs: Pairs<Rule>,
+    src_loc: SrcLoc,
+) -> Result<DisjunctiveSeq, ParseError> {
     let mut conj_seq = vec![];
     while let Some(tok) = skip_comments(&mut conj_pairs) {
         assert_eq!(tok.as_rule(), Rule::target_conjunction);
@@ -614,6 +708,7 @@ fn process_target_clause(mut conj_pairs: Pairs<Rule>) -> Result<DisjunctiveSeq,
     }
     Ok(DisjunctiveSeq {
         statements: conj_seq,
+        src_loc,
     })
 }
 /// Take a list of and'd matches and produce a `ConjuctiveSeq`
@@ -822,6 +917,7 @@ fn process_condition_atom(
             || r == Rule::boolean_literal
             || r == Rule::string_literal
             || r == Rule::custom_literal
+            || r == Rule::constant_reference
         {
             info!("literal: {:?}", tok.as_str());
             let con = constant_from_token(tok)?;
@@ -839,6 +935,12 @@ fn process_attribute_designator(attr_pair: Pair<Rule>) -> Result<AttributeDesign
     let mut attr_pairs = attr_pair.into_inner();
     // get the element identifier, which must be present
     let ident_tok = skip_comments(&mut attr_pairs).ok_or(ParseError::AstConvertError)?;
+    if ident_tok.as_rule() == Rule::template_param_ref {
+        return Err(ParseError::UnexpectedRuleError(format!(
+            "template parameter {} used outside of a template",
+            ident_tok.as_str()
+        )));
+    }
     if ident_tok.as_rule() == Rule::elem_identifier {
         let attribute: Vec<String> = ident_tok.as_str().split('.').map(String::from).collect();
         // block of options after an attribute (optional)
@@ -940,6 +1042,16 @@ fn constant_from_token(tok: Pair<Rule>) -> Result<Constant, ParseError> {
                 .expect("valid string")
                 .clone(),
         )),
+        Rule::template_param_ref => Err(ParseError::UnexpectedRuleError(format!(
+            "template parameter {} used outside of a template",
+            tok.as_str()
+        ))),
+        Rule::constant_reference => {
+            // get inner, which should be the constant name.
+            let mut i = tok.into_inner();
+            let name = skip_comments(&mut i).ok_or(ParseError::AstConvertError)?;
+            Ok(Constant::Named(name.as_str().to_string()))
+        }
         Rule::custom_literal => {
             // get inner, which should be string_literal, elem_component.
             let mut i = tok.into_inner();
@@ -1296,6 +1408,393 @@ fn process_attribute(
     }
 }
 
+fn process_constant(
+    constant_pair: Pair<Rule>,
+    src_loc: &SrcLoc,
+    ns: Vec<String>,
+) -> Result<ConstantDef, ParseError> {
+    let span = constant_pair.as_span();
+    let src_loc = src_loc.with_start_end(span.start(), span.end());
+    let mut constant_pairs = constant_pair.into_inner();
+    // get the name
+    let id = skip_comments(&mut constant_pairs)
+        .ok_or(ParseError::AstConvertError)?
+        .as_str()
+        .to_string();
+    // then the type
+    let typedef = skip_comments(&mut constant_pairs)
+        .ok_or(ParseError::AstConvertError)?
+        .as_str()
+        .to_string();
+    // finally the literal value
+    let value = constant_from_token(
+        skip_comments(&mut constant_pairs).ok_or(ParseError::AstConvertError)?,
+    )?;
+    debug!("constant {id} : {typedef} = {value}");
+    Ok(ConstantDef {
+        id,
+        typedef,
+        value,
+        ns,
+        src_loc,
+    })
+}
+
+fn process_extern(
+    extern_pair: Pair<Rule>,
+    src_loc: &SrcLoc,
+    ns: Vec<String>,
+) -> Result<ExternPolicy, ParseError> {
+    let span = extern_pair.as_span();
+    let src_loc = src_loc.with_start_end(span.start(), span.end());
+    let mut extern_pairs = extern_pair.into_inner();
+    // policy or policyset
+    let kind = match skip_comments(&mut extern_pairs)
+        .ok_or(ParseError::AstConvertError)?
+        .as_str()
+    {
+        "policyset" => ExternKind::PolicySet,
+        _ => ExternKind::Policy,
+    };
+    // the name
+    let id = skip_comments(&mut extern_pairs)
+        .ok_or(ParseError::AstConvertError)?
+        .as_str()
+        .to_string();
+    // the identifier of the external policy
+    let uri = string_literal_to_string(
+        skip_comments(&mut extern_pairs).ok_or(ParseError::AstConvertError)?,
+    )?;
+    // optional version constraints
+    let versions = match skip_comments(&mut extern_pairs) {
+        Some(c) => process_version_constraints(c, &src_loc)?,
+        None => VersionConstraints::default(),
+    };
+    debug!("extern {kind} {id} = {uri}");
+    Ok(ExternPolicy {
+        id,
+        kind,
+        uri,
+        versions,
+        ns,
+        src_loc,
+    })
+}
+
+fn process_template(
+    template_pair: Pair<Rule>,
+    src_loc: &SrcLoc,
+    ns: Vec<String>,
+    description: Option<String>,
+) -> Result<TemplateDef, ParseError> {
+    let span = template_pair.as_span();
+    let src_loc = src_loc.with_start_end(span.start(), span.end());
+    // the template is either a policy or rule template
+    let mut template_pairs = template_pair.into_inner();
+    let t = skip_comments(&mut template_pairs).ok_or(ParseError::AstConvertError)?;
+    let kind = match t.as_rule() {
+        Rule::template_policy => TemplateKind::Policy,
+        Rule::template_rule => TemplateKind::Rule,
+        r => {
+            return Err(ParseError::UnexpectedRuleError(format!(
+                "expected a policy or rule template, found {r:?}"
+            )));
+        }
+    };
+    let mut inner = t.into_inner();
+    // template name
+    let id = skip_comments(&mut inner)
+        .ok_or(ParseError::AstConvertError)?
+        .as_str()
+        .to_string();
+    // parameter list
+    let params_tok = skip_comments(&mut inner).ok_or(ParseError::AstConvertError)?;
+    let mut params: Vec<String> = vec![];
+    let mut param_pairs = params_tok.into_inner();
+    while let Some(p) = skip_comments(&mut param_pairs) {
+        let name = p.as_str().to_string();
+        if params.contains(&name) {
+            let sp = p.as_span();
+            return Err(SrcError::err(
+                "Template parameters must have unique names",
+                &format!("{name:?} is already a parameter"),
+                src_loc.with_start_end(sp.start(), sp.end()),
+            ));
+        }
+        params.push(name);
+    }
+    // the body is kept as source text, but we record where every
+    // parameter is referenced so that it can be substituted later.
+    let body_tok = skip_comments(&mut inner).ok_or(ParseError::AstConvertError)?;
+    let body_span = body_tok.as_span();
+    let body = src_loc.with_start_end(body_span.start(), body_span.end());
+    let mut param_refs = vec![];
+    for p in body_tok.into_inner().flatten() {
+        if p.as_rule() == Rule::template_param_ref {
+            let sp = p.as_span();
+            let ref_loc = src_loc.with_start_end(sp.start(), sp.end());
+            let mut ref_inner = p.into_inner();
+            let name = skip_comments(&mut ref_inner)
+                .ok_or(ParseError::AstConvertError)?
+                .as_str()
+                .to_string();
+            if !params.contains(&name) {
+                return Err(SrcError::err(
+                    "Template parameters must be declared",
+                    &format!("{name:?} is not a parameter of template {id:?}"),
+                    ref_loc,
+                ));
+            }
+            param_refs.push(ParamRef {
+                name,
+                src_loc: ref_loc,
+            });
+        }
+    }
+    debug!("template {id} with params {params:?}");
+    Ok(TemplateDef {
+        id,
+        kind,
+        params,
+        ns,
+        description,
+        src_loc,
+        body,
+        param_refs,
+    })
+}
+
+fn process_instance(
+    instance_pair: Pair<Rule>,
+    src_loc: &SrcLoc,
+    ns: Vec<String>,
+    description: Option<String>,
+) -> Result<TemplateInstance, ParseError> {
+    let span = instance_pair.as_span();
+    let src_loc = src_loc.with_start_end(span.start(), span.end());
+    let mut instance_pairs = instance_pair.into_inner();
+    // the instance name is optional
+    let mut tok = skip_comments(&mut instance_pairs).ok_or(ParseError::AstConvertError)?;
+    let mut id = None;
+    if tok.as_rule() == Rule::identifier {
+        id = Some(tok.as_str().to_string());
+        tok = skip_comments(&mut instance_pairs).ok_or(ParseError::AstConvertError)?;
+    }
+    let template = tok.as_str().to_string();
+    // parameter bindings
+    let mut args = vec![];
+    while let Some(arg) = skip_comments(&mut instance_pairs) {
+        let sp = arg.as_span();
+        let arg_loc = src_loc.with_start_end(sp.start(), sp.end());
+        let mut arg_pairs = arg.into_inner();
+        let name = skip_comments(&mut arg_pairs)
+            .ok_or(ParseError::AstConvertError)?
+            .as_str()
+            .to_string();
+        let value = skip_comments(&mut arg_pairs).ok_or(ParseError::AstConvertError)?;
+        // instances are not inside a template, so there is nothing
+        // for a parameter reference to refer to.
+        if value
+            .clone()
+            .into_inner()
+            .flatten()
+            .chain(std::iter::once(value.clone()))
+            .any(|p| p.as_rule() == Rule::template_param_ref)
+        {
+            return Err(SrcError::err(
+                "Template parameters can only be used inside a template",
+                "not inside a template",
+                arg_loc,
+            ));
+        }
+        // attributes and constants named by the argument are
+        // resolved from the instance, when it is expanded.
+        let kind = match value.as_rule() {
+            Rule::attribute_designator => Some(ArgSymbolKind::Attribute),
+            Rule::constant_reference => Some(ArgSymbolKind::Constant),
+            _ => None,
+        };
+        let symbol = match kind {
+            Some(kind) => {
+                let mut value_pairs = value.clone().into_inner();
+                let ident = skip_comments(&mut value_pairs).ok_or(ParseError::AstConvertError)?;
+                Some(ArgSymbol {
+                    kind,
+                    name: ident.as_str().to_string(),
+                    offset: ident.as_span().start() - value.as_span().start(),
+                })
+            }
+            None => None,
+        };
+        args.push(TemplateArg {
+            name,
+            value: value.as_str().to_string(),
+            symbol,
+            src_loc: arg_loc,
+        });
+    }
+    Ok(TemplateInstance {
+        id,
+        template,
+        args,
+        ns,
+        description,
+        src_loc,
+    })
+}
+
+/// An element produced by expanding a template instance.
+pub enum ExpandedInstance {
+    Policy(Policy),
+    Rule(RuleDef),
+}
+
+/// Expand a template instance into a policy or rule.
+///
+/// The template is resolved from the namespace of the instance, and
+/// the expanded element is placed in that namespace.  Names in the
+/// template body are resolved in the namespace of the template (with
+/// its imports), while attributes and constants in the arguments are
+/// resolved from the instance.  Errors in the expansion point at both
+/// the template and the instance.
+///
+/// # Errors
+///
+/// Returns `Err` if the template cannot be found, the arguments do
+/// not match its parameters, or the expanded declaration is invalid.
+pub fn expand_instance(
+    inst: &TemplateInstance,
+    ctx: &Rc<Context>,
+) -> Result<ExpandedInstance, ParseError> {
+    let template = ctx.lookup_template(&inst.template, &inst.ns, &inst.src_loc)?;
+    info!("expanding {inst} with {template}");
+    let qualified = qualify_instance_args(inst, ctx)?;
+    let expansion = template.expand(&qualified)?;
+    let src_loc = SrcLoc::new(expansion.src, (0, 0).into());
+    let mut pairs = AlfaDocParser::parse(Rule::template_expansion, &expansion.parse_text)
+        .map_err(|e| {
+            let (start, end) = match e.location {
+                pest::error::InputLocation::Pos(p) => (p, p),
+                pest::error::InputLocation::Span((s, e)) => (s, e),
+            };
+            SrcError::err_at_both(
+                "Template instance could not be parsed",
+                &e.variant.message(),
+                src_loc.with_start_end(start, end),
+                "instantiated here",
+                inst.src_loc.clone(),
+            )
+        })?;
+    // descend to the policy or rule declaration
+    let decl = pairs
+        .next()
+        .and_then(|p| p.into_inner().find(|p| p.as_rule() != Rule::EOI))
+        .ok_or(ParseError::AstConvertError)?;
+    // prefer the comment on the instance, but fall back to the template.
+    let description = inst
+        .description
+        .clone()
+        .or_else(|| template.description.clone());
+    let origin = Rc::new(Expansion {
+        template: template.fully_qualified_name().unwrap_or_default(),
+        scope: template.ns.clone(),
+        template_loc: template.src_loc.clone(),
+        instance: inst.id.as_ref().map(|id| format!("{}.{id}", inst.ns.join("."))),
+        instance_loc: inst.src_loc.clone(),
+    });
+    // the body is processed in the template namespace, so that its
+    // targets, conditions and prescriptions resolve there.  The
+    // element itself (and any named rules within it) then moves to
+    // the namespace of the instance.
+    let expanded = if decl.as_rule() == Rule::policy_decl {
+        process_policy(
+            decl.into_inner(),
+            &src_loc,
+            template.ns.clone(),
+            GenName::default(),
+            description,
+            false,
+            ctx.clone(),
+        )
+        .and_then(|mut p| {
+            p.ns.clone_from(&inst.ns);
+            p.expansion = Some(origin.clone());
+            for r in &mut p.rules {
+                if let RuleEntry::Def(d) = r {
+                    let d = Rc::make_mut(d);
+                    d.ns.clone_from(&inst.ns);
+                    d.expansion = Some(origin.clone());
+                }
+            }
+            // named rules are registered as they would be for a
+            // policy declared in the instance namespace.
+            if p.id != PolicyId::PolicyNoName {
+                for r in &p.rules {
+                    if let RuleEntry::Def(d) = r
+                        && d.id.is_some()
+                    {
+                        ctx.register_rule(d.clone())?;
+                    }
+                }
+            }
+            Ok(ExpandedInstance::Policy(p))
+        })
+    } else {
+        process_rule(
+            decl,
+            src_loc,
+            template.ns.clone(),
+            GenName::default(),
+            description,
+            ctx,
+        )
+        .map(|mut r| {
+            r.ns.clone_from(&inst.ns);
+            r.expansion = Some(origin.clone());
+            ExpandedInstance::Rule(r)
+        })
+    };
+    expanded.map_err(|e| SrcError::with_related(e, "instantiated here", inst.src_loc.clone()))
+}
+
+/// Replace the attributes and constants named in the arguments of an
+/// instance with their fully qualified names, so they resolve the
+/// same way inside the template body.
+fn qualify_instance_args(
+    inst: &TemplateInstance,
+    ctx: &Rc<Context>,
+) -> Result<TemplateInstance, ParseError> {
+    let mut qualified = inst.clone();
+    for a in &mut qualified.args {
+        let Some(sym) = &a.symbol else {
+            continue;
+        };
+        let (type_name, fq_name) = match sym.kind {
+            ArgSymbolKind::Attribute => (
+                "attribute",
+                ctx.lookup_attribute(&sym.name, &inst.ns)
+                    .ok()
+                    .and_then(|x| x.fully_qualified_name()),
+            ),
+            ArgSymbolKind::Constant => (
+                "constant",
+                ctx.lookup_constant(&sym.name, &inst.ns)
+                    .ok()
+                    .and_then(|x| x.fully_qualified_name()),
+            ),
+        };
+        let Some(fq_name) = fq_name else {
+            return Err(SrcError::err(
+                "All referenced symbols must be defined",
+                &format!("this {type_name} could not be resolved"),
+                a.src_loc.clone(),
+            ));
+        };
+        a.value.replace_range(sym.offset..sym.offset + sym.name.len(), &fq_name);
+    }
+    Ok(qualified)
+}
+
 fn process_rulecombinator(
     mut rc_pairs: Pairs<Rule>,
     ns_path: Vec<String>,
@@ -1353,7 +1852,11 @@ fn process_policyset(
     let start_pos = sp.start();
     let end_pos = sp.end();
     let mut policyset_pairs = policyset_pair.into_inner();
+    let content_hash = content_hash_of(&policyset_pairs);
     let policy_id_rule = skip_comments(&mut policyset_pairs).ok_or(ParseError::AstConvertError)?;
+    // the policyset is located by its name, like policies.
+    let id_span = policy_id_rule.as_span();
+    let policyset_src_loc = src_loc.with_start_end(id_span.start(), id_span.end());
     let policy_id = policy_naming(policy_id_rule, ctx.clone())?;
     // an apply statement is required.
     let mut apply = None;
@@ -1365,6 +1868,15 @@ fn process_policyset(
     let mut policies = vec![];
     // prescriptions (obligations/advice)
     let mut prescriptions = vec![];
+    // a version is optional
+    let mut version = None;
+    // the issuer and delegation depth are optional
+    let mut issuer = None;
+    let mut max_delegation_depth = None;
+    // parameters for the combining algorithm, and for the combining
+    // algorithm of the parent
+    let mut apply_params = vec![];
+    let mut entry_params = vec![];
     // only register this policyset if it has a name, and the parent has a name.
     let do_register = (policy_id != PolicyId::PolicyNoName) && register;
 
@@ -1393,6 +1905,10 @@ fn process_policyset(
             // no need to break this apart further
             continue;
         }
+        if policyset_stmt.as_rule() == Rule::combiner_params {
+            entry_params = process_combiner_params(policyset_stmt, &src_loc)?;
+            continue;
+        }
         if policyset_stmt.as_rule() == Rule::policyset_stmt {
             let mut t = policyset_stmt.into_inner();
             let stmt = skip_comments(&mut t).ok_or(ParseError::AstConvertError)?;
@@ -1404,9 +1920,12 @@ fn process_policyset(
                     skip_comments(&mut apply_stmt).ok_or(ParseError::AstConvertError)?;
                 apply = Some(apply_ident.as_str().to_string());
                 info!("apply: {apply:?}");
+                if let Some(params) = skip_comments(&mut apply_stmt) {
+                    apply_params = process_combiner_params(params, &src_loc)?;
+                }
             } else if stmt.as_rule() == Rule::target_stmt {
                 // target
-                target = Some(process_target(stmt.into_inner(), ns_path.clone(), &ctx)?);
+                target = Some(process_target(stmt, &src_loc, ns_path.clone(), &ctx)?);
                 info!("target: {target:?}");
             } else if stmt.as_rule() == Rule::condition_stmt {
                 if condition.is_none() {
@@ -1453,13 +1972,14 @@ fn process_policyset(
                 )?;
                 info!("PS finish");
                 policies.push(PolicyEntry::PolicySet(p));
+            } else if stmt.as_rule() == Rule::version_stmt {
+                set_version(&mut version, stmt, &src_loc)?;
+            } else if stmt.as_rule() == Rule::issuer_stmt {
+                set_issuer(&mut issuer, stmt, &src_loc)?;
+            } else if stmt.as_rule() == Rule::delegation_stmt {
+                set_max_delegation_depth(&mut max_delegation_depth, stmt, &src_loc)?;
             } else if stmt.as_rule() == Rule::policy_reference {
-                let (policy_ns, policy_id) = split_dotted_string(stmt.as_str());
-                let policy_ref = PolicyReference {
-                    id: policy_id,
-                    ns: policy_ns,
-                };
-                policies.push(PolicyEntry::Ref(policy_ref));
+                policies.push(PolicyEntry::Ref(process_policy_reference(stmt, &src_loc)?));
             } else if stmt.as_rule() == Rule::on_effect {
                 info!("adding prescription to policyset");
                 prescriptions.push(process_prescription(stmt, ns_path.clone(), &ctx)?);
@@ -1473,8 +1993,12 @@ fn process_policyset(
         id: policy_id,
         ns: ns_path,
         policy_ns: parent_policy_path,
-        src_loc: src_loc.clone(), // TODO: ensure this covers the full span
+        src_loc: policyset_src_loc,
         description,
+        version,
+        issuer: issuer.unwrap_or_default(),
+        max_delegation_depth,
+        entry_params,
         apply: PolicyCombiningAlgorithm {
             id: apply.ok_or(SrcError::err(
                 "PolicySets must have an apply statement",
@@ -1482,11 +2006,13 @@ fn process_policyset(
                 src_loc.with_start_end(start_pos, end_pos),
             ))?,
             src_loc,
+            params: apply_params,
         },
         target,
         condition,
         policies,
         prescriptions,
+        content_hash,
         ctx,
     })
 }
@@ -1500,6 +2026,7 @@ fn process_policy(
     register: bool,
     ctx: Rc<Context>,
 ) -> Result<Policy, ParseError> {
+    let content_hash = content_hash_of(&policy_pairs);
     let policy_id_rule = skip_comments(&mut policy_pairs).ok_or(ParseError::AstConvertError)?;
     // an apply statement is required.
     let mut apply = None;
@@ -1513,6 +2040,15 @@ fn process_policy(
     let mut rules = vec![];
     // policies can have prescriptions (advice/obligations)
     let mut prescriptions: Vec<Prescription> = vec![];
+    // a version is optional
+    let mut version = None;
+    // the issuer and delegation depth are optional
+    let mut issuer = None;
+    let mut max_delegation_depth = None;
+    // parameters for the combining algorithm, and for the combining
+    // algorithm of the parent
+    let mut apply_params = vec![];
+    let mut entry_params = vec![];
     // keep track of last comment for rule definitions
     let mut last_comment = None;
     // get span info for this policy
@@ -1583,6 +2119,10 @@ fn process_policy(
             // no need to break this apart further
             continue;
         }
+        if policy_stmt.as_rule() == Rule::combiner_params {
+            entry_params = process_combiner_params(policy_stmt, src_loc)?;
+            continue;
+        }
         // these statements are always going to be "alfa_statement" rules.
         // we have to break them open one level deeper.
         //let mut inner_stmt = stmt.into_inner();
@@ -1602,10 +2142,13 @@ fn process_policy(
                 let apply_ident =
                     skip_comments(&mut apply_stmt).ok_or(ParseError::AstConvertError)?;
                 apply = Some(apply_ident.as_str().to_string());
+                if let Some(params) = skip_comments(&mut apply_stmt) {
+                    apply_params = process_combiner_params(params, src_loc)?;
+                }
             } else if stmt.as_rule() == Rule::target_stmt {
                 // target
                 if target.is_none() {
-                    target = Some(process_target(stmt.into_inner(), ns_path.clone(), &ctx)?);
+                    target = Some(process_target(stmt, src_loc, ns_path.clone(), &ctx)?);
                 } else {
                     return Err(ParseError::DuplicateCondition);
                 }
@@ -1646,7 +2189,7 @@ fn process_policy(
 
                 // this used to be the ns_rule_path, but now it is just the ns_path
                 let rule_decl = process_rule(
-                    stmt.into_inner(),
+                    stmt,
                     src_loc.clone(),
                     ns_path.clone(),
                     parent_policy_path.clone(),
@@ -1663,22 +2206,36 @@ fn process_policy(
             } else if stmt.as_rule() == Rule::rule_reference {
                 // an already defined rule is being referenced.
                 debug!("found rule reference {stmt:?}");
-                // a rule reference is just a bare (possibly qualified) name.
-                let (rule_ns, rule_id) = split_dotted_string(stmt.as_str());
+                // a rule reference is a bare (possibly qualified)
+                // name, with optional combiner parameters.
+                let mut ref_inner = stmt.into_inner();
+                let name = skip_comments(&mut ref_inner).ok_or(ParseError::AstConvertError)?;
+                let (rule_ns, rule_id) = split_dotted_string(name.as_str());
                 // determine location
                 // since this is a Pair, we can determine the start and end point.
-                let sp = stmt.as_span();
+                let sp = name.as_span();
                 let start_pos = sp.start();
                 // default ending position
                 let end_pos = sp.end();
                 let new_src_loc = src_loc.with_new_span((start_pos, end_pos - start_pos).into());
+                let entry_params = match skip_comments(&mut ref_inner) {
+                    Some(params) => process_combiner_params(params, src_loc)?,
+                    None => vec![],
+                };
                 let rule_ref = RuleReference {
                     id: rule_id,
                     ns: rule_ns,
                     src_loc: new_src_loc,
+                    entry_params,
                 };
                 rules.push(RuleEntry::Ref(rule_ref));
                 info!("finished pushing ruleentry ref");
+            } else if stmt.as_rule() == Rule::version_stmt {
+                set_version(&mut version, stmt, src_loc)?;
+            } else if stmt.as_rule() == Rule::issuer_stmt {
+                set_issuer(&mut issuer, stmt, src_loc)?;
+            } else if stmt.as_rule() == Rule::delegation_stmt {
+                set_max_delegation_depth(&mut max_delegation_depth, stmt, src_loc)?;
             } else if stmt.as_rule() == Rule::on_effect {
                 info!("adding prescription to policy");
                 prescriptions.push(process_prescription(stmt, ns_path.clone(), &ctx)?);
@@ -1699,6 +2256,10 @@ fn process_policy(
         policy_ns: parent_policy_path,
         src_loc: policy_src_loc.clone(),
         description,
+        version,
+        issuer: issuer.unwrap_or_default(),
+        max_delegation_depth,
+        entry_params,
         apply: policy::RuleCombiningAlgorithm {
             id: apply.ok_or(SrcError::err(
                 "PolicySets must have an apply statement",
@@ -1706,15 +2267,234 @@ fn process_policy(
                 policy_src_loc,
             ))?,
             src_loc: apply_srcloc,
+            params: apply_params,
         },
         target,
         condition,
         rules,
         prescriptions,
+        content_hash,
+        expansion: None,
         ctx,
     })
 }
 
+/// Record the version from a `version_stmt`, ensuring it is valid
+/// and that no version was previously set.
+fn set_version(
+    version: &mut Option<String>,
+    stmt: Pair<Rule>,
+    src_loc: &SrcLoc,
+) -> Result<(), ParseError> {
+    let sp = stmt.as_span();
+    let stmt_loc = src_loc.with_start_end(sp.start(), sp.end());
+    if version.is_some() {
+        return Err(SrcError::err(
+            "Policies and policysets can only have one version",
+            "version was already declared",
+            stmt_loc,
+        ));
+    }
+    let mut stmt_inner = stmt.into_inner();
+    let lit = skip_comments(&mut stmt_inner).ok_or(ParseError::AstConvertError)?;
+    let v = string_literal_to_string(lit)?;
+    if !is_valid_version(&v) {
+        return Err(SrcError::err(
+            "Versions must be dot-separated numbers (ex: \"1.2.0\")",
+            &format!("{v:?} is not a valid version"),
+            stmt_loc,
+        ));
+    }
+    *version = Some(v);
+    Ok(())
+}
+
+/// Convert a `policy_reference` into a `PolicyReference`, including
+/// any version constraints.
+fn process_policy_reference(
+    stmt: Pair<Rule>,
+    src_loc: &SrcLoc,
+) -> Result<PolicyReference, ParseError> {
+    let mut ref_inner = stmt.into_inner();
+    let name = skip_comments(&mut ref_inner).ok_or(ParseError::AstConvertError)?;
+    let (policy_ns, policy_id) = split_dotted_string(name.as_str());
+    let mut versions = VersionConstraints::default();
+    let mut entry_params = vec![];
+    while let Some(p) = skip_comments(&mut ref_inner) {
+        if p.as_rule() == Rule::version_constraints {
+            versions = process_version_constraints(p, src_loc)?;
+        } else if p.as_rule() == Rule::combiner_params {
+            entry_params = process_combiner_params(p, src_loc)?;
+        }
+    }
+    Ok(PolicyReference {
+        id: policy_id,
+        ns: policy_ns,
+        versions,
+        entry_params,
+    })
+}
+
+/// Record the issuer attributes from an `issuer_stmt`, ensuring that
+/// no issuer was previously set.
+fn set_issuer(
+    issuer: &mut Option<Vec<IssuerAttribute>>,
+    stmt: Pair<Rule>,
+    src_loc: &SrcLoc,
+) -> Result<(), ParseError> {
+    let sp = stmt.as_span();
+    if issuer.is_some() {
+        return Err(SrcError::err(
+            "Policies and policysets can only have one issuer",
+            "issuer was already declared",
+            src_loc.with_start_end(sp.start(), sp.end()),
+        ));
+    }
+    let mut attrs = vec![];
+    for a in stmt.into_inner() {
+        if a.as_rule() != Rule::issuer_assignment {
+            continue;
+        }
+        let sp = a.as_span();
+        let a_loc = src_loc.with_start_end(sp.start(), sp.end());
+        let mut a_inner = a.into_inner();
+        let attribute = skip_comments(&mut a_inner)
+            .ok_or(ParseError::AstConvertError)?
+            .as_str()
+            .to_owned();
+        let lit = skip_comments(&mut a_inner).ok_or(ParseError::AstConvertError)?;
+        attrs.push(IssuerAttribute {
+            src_loc: a_loc,
+            attribute,
+            value: constant_from_token(lit)?,
+        });
+    }
+    *issuer = Some(attrs);
+    Ok(())
+}
+
+/// Record the maximum delegation depth from a `delegation_stmt`,
+/// ensuring that no depth was previously set.
+fn set_max_delegation_depth(
+    depth: &mut Option<u32>,
+    stmt: Pair<Rule>,
+    src_loc: &SrcLoc,
+) -> Result<(), ParseError> {
+    let sp = stmt.as_span();
+    let stmt_loc = src_loc.with_start_end(sp.start(), sp.end());
+    if depth.is_some() {
+        return Err(SrcError::err(
+            "Policies and policysets can only have one maximum delegation depth",
+            "maxDelegationDepth was already declared",
+            stmt_loc,
+        ));
+    }
+    let mut stmt_inner = stmt.into_inner();
+    let d = skip_comments(&mut stmt_inner).ok_or(ParseError::AstConvertError)?;
+    let d = d.as_str().parse::<u32>().map_err(|_| {
+        SrcError::err(
+            "Delegation depth must be a non-negative integer",
+            "depth is out of range",
+            stmt_loc,
+        )
+    })?;
+    *depth = Some(d);
+    Ok(())
+}
+
+/// Convert `combiner_params` into a list of parameters, ensuring
+/// each parameter is given at most once.
+fn process_combiner_params(
+    params: Pair<Rule>,
+    src_loc: &SrcLoc,
+) -> Result<Vec<CombinerParam>, ParseError> {
+    let mut result: Vec<CombinerParam> = vec![];
+    for p in params.into_inner() {
+        if p.as_rule() != Rule::combiner_param {
+            continue;
+        }
+        let sp = p.as_span();
+        let p_loc = src_loc.with_start_end(sp.start(), sp.end());
+        let mut p_inner = p.into_inner();
+        let name = skip_comments(&mut p_inner)
+            .ok_or(ParseError::AstConvertError)?
+            .as_str()
+            .to_owned();
+        let lit = skip_comments(&mut p_inner).ok_or(ParseError::AstConvertError)?;
+        let value = constant_from_token(lit)?;
+        if result.iter().any(|c| c.name == name) {
+            return Err(SrcError::err(
+                "Each combiner parameter can only be given once",
+                &format!("{name:?} was already given"),
+                p_loc,
+            ));
+        }
+        result.push(CombinerParam {
+            src_loc: p_loc,
+            name,
+            value,
+        });
+    }
+    Ok(result)
+}
+
+/// Ensure that a top-level rule, policy, or policyset has no
+/// parameters for a parent combining algorithm.
+fn no_entry_params(entry_params: &[CombinerParam]) -> Result<(), ParseError> {
+    match entry_params.first() {
+        Some(p) => Err(SrcError::err(
+            "Combiner parameters can only be given for elements within a policy or policyset",
+            "there is no parent combining algorithm",
+            p.src_loc.clone(),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Convert `version_constraints` into `VersionConstraints`, ensuring
+/// each is valid and given at most once.
+fn process_version_constraints(
+    constraints: Pair<Rule>,
+    src_loc: &SrcLoc,
+) -> Result<VersionConstraints, ParseError> {
+    let mut versions = VersionConstraints::default();
+    for c in constraints.into_inner() {
+        if c.as_rule() != Rule::version_constraint {
+            continue;
+        }
+        let sp = c.as_span();
+        let c_loc = src_loc.with_start_end(sp.start(), sp.end());
+        let mut c_inner = c.into_inner();
+        let kind = skip_comments(&mut c_inner)
+            .ok_or(ParseError::AstConvertError)?
+            .as_str()
+            .to_owned();
+        let lit = skip_comments(&mut c_inner).ok_or(ParseError::AstConvertError)?;
+        let v = string_literal_to_string(lit)?;
+        if !is_valid_version_match(&v) {
+            return Err(SrcError::err(
+                "Version constraints must be dot-separated numbers or wildcards (ex: \"1.*\")",
+                &format!("{v:?} is not a valid version constraint"),
+                c_loc,
+            ));
+        }
+        let slot = match kind.as_str() {
+            "version" => &mut versions.version,
+            "earliest" => &mut versions.earliest,
+            _ => &mut versions.latest,
+        };
+        if slot.is_some() {
+            return Err(SrcError::err(
+                "Each version constraint can only be given once",
+                &format!("{kind} was already constrained"),
+                c_loc,
+            ));
+        }
+        *slot = Some(v);
+    }
+    Ok(versions)
+}
+
 /// Return the next non-comment rule in a Pairs, if one exists,
 /// without consuming anything but comments.
 fn _next_non_comment_rule<'a>(pairs: &'a mut Pairs<Rule>) -> Option<Pair<'a, Rule>> {
@@ -1743,6 +2523,25 @@ fn skip_comments<'a>(pairs: &'a mut Pairs<Rule>) -> Option<Pair<'a, Rule>> {
     Some(n)
 }
 
+/// Hash the canonical source of an element, ignoring comments and
+/// formatting, for content-derived names.
+fn content_hash_of(pairs: &Pairs<Rule>) -> String {
+    let text = pairs.as_str();
+    let start = pairs.clone().next().map_or(0, |p| p.as_span().start());
+    let mut stripped = String::with_capacity(text.len());
+    let mut pos = start;
+    for c in pairs.clone().flatten().filter(|p| p.as_rule() == Rule::COMMENT) {
+        let sp = c.as_span();
+        if sp.start() >= pos {
+            stripped.push_str(&text[pos - start..sp.start() - start]);
+            stripped.push(' ');
+            pos = sp.end();
+        }
+    }
+    stripped.push_str(&text[pos - start..]);
+    naming::content_hash(&naming::canonical_whitespace(&stripped))
+}
+
 // Thanks Claude
 fn split_dotted_string(input: &str) -> (Vec<String>, String) {
     if input.is_empty() {
@@ -1794,9 +2593,11 @@ fn policy_naming(policy_name_rule: Pair<Rule>, _ctx: Rc<Context>) -> Result<Poli
                 name_str = Some(name_ident.as_str().to_string());
             }
             let id_literal = { skip_comments(&mut inner).ok_or(ParseError::AstConvertError)? };
-            if name_str.is_some() && id_literal.as_rule() == Rule::string_literal {
+            if let Some(name_str) = name_str
+                && id_literal.as_rule() == Rule::string_literal
+            {
                 Ok(PolicyId::PolicyNameAndId(
-                    name_str.unwrap(),
+                    name_str,
                     string_literal_to_string(id_literal)?,
                 ))
             } else {
@@ -1847,12 +2648,73 @@ pub trait PrettyPrint {
     fn pretty_print(&self, indent_level: usize);
 }
 
+/// Elements that can be written as ALFA source.
+///
 /// Items should be indented as requested, and emit a trailing
-/// newline.
+/// newline.  Parsing the result produces the same element, apart
+/// from source locations, and names derived from the source text
+/// (see `--id-strategy hash`).  Descriptions are written as comments
+/// preceding the element.
 pub trait AsAlfa {
     fn to_alfa(&self, indent_level: usize) -> String;
 }
 
+impl AsAlfa for AlfaSyntaxTree {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        self.namespaces
+            .iter()
+            .map(|n| n.to_alfa(indent_level))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Statements shared by policies and policysets, which precede the
+/// combining algorithm.
+fn alfa_common_stmts(
+    version: Option<&String>,
+    max_delegation_depth: Option<u32>,
+    issuer: &[IssuerAttribute],
+    target: Option<&Target>,
+    condition: Option<&Condition>,
+    indent_level: usize,
+) -> String {
+    let indent = "  ".repeat(indent_level);
+    let mut output = String::new();
+    if let Some(v) = version {
+        output.push_str(&format!("{indent}version {v:?}\n"));
+    }
+    if let Some(d) = max_delegation_depth {
+        output.push_str(&format!("{indent}maxDelegationDepth {d}\n"));
+    }
+    if !issuer.is_empty() {
+        output.push_str(&format!("{indent}issuer {{\n"));
+        for i in issuer {
+            output.push_str(&format!("{indent}  {i}\n"));
+        }
+        output.push_str(&format!("{indent}}}\n"));
+    }
+    if let Some(t) = target {
+        output.push_str(&t.to_alfa(indent_level));
+    }
+    if let Some(c) = condition {
+        output.push_str(&c.to_alfa(indent_level));
+    }
+    output
+}
+
+/// A description, as a comment preceding an element.  A line comment
+/// is used where possible, since it can contain anything but a line
+/// break.
+fn alfa_description(description: Option<&String>, indent_level: usize) -> String {
+    let indent = "  ".repeat(indent_level);
+    match description {
+        Some(d) if d.contains('\n') => format!("{indent}/* {d} */\n"),
+        Some(d) => format!("{indent}// {d}\n"),
+        None => String::new(),
+    }
+}
+
 /// An `AlfaSyntaxTree` and the source path information.
 #[derive(Debug)]
 pub struct AstSource {
@@ -1886,6 +2748,20 @@ impl AstCollection {
     pub fn add_ast(&mut self, ast: AstSource) {
         self.asts.push(ast);
     }
+    /// Expand all template instances into policies and rules.
+    ///
+    /// This must be done after every source has been added, since
+    /// templates can be declared in any file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if any instance cannot be expanded.
+    pub fn expand_templates(&mut self) -> Result<(), ParseError> {
+        for a in &mut self.asts {
+            a.ast.expand_templates()?;
+        }
+        Ok(())
+    }
     // TODO: it would be better to just return all distinct serializable elements.
 
     /// Retrieve all policy sets
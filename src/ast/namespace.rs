@@ -4,6 +4,8 @@
 use super::advice::AdviceDef;
 use super::attribute::Attribute;
 use super::category::Category;
+use super::constantdef::ConstantDef;
+use super::externpolicy::ExternPolicy;
 use super::function::Function;
 use super::import::Import;
 use super::infix::Infix;
@@ -13,8 +15,9 @@ use super::policycombinator::PolicyCombinator;
 use super::policyset::PolicySet;
 use super::rule::RuleDef;
 use super::rulecombinator::RuleCombinator;
+use super::template::{TemplateDef, TemplateInstance};
 use super::typedef::TypeDef;
-use super::PrettyPrint;
+use super::{expand_instance, AsAlfa, ExpandedInstance, PrettyPrint};
 use crate::context::Context;
 use crate::errors::ParseError;
 use log::{debug, info};
@@ -45,6 +48,7 @@ use std::rc::Rc;
 /// Namespaces are also responsible for populating the context with
 /// fully-qualified references to everything added to them.
 #[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Namespace {
     /// The namespace declared path elements from general to most
     /// specific.  This is the full path from the root.
@@ -69,6 +73,14 @@ pub struct Namespace {
     categories: Vec<Rc<Category>>,
     /// Child attribute definitions.
     attributes: Vec<Rc<Attribute>>,
+    /// Child constant definitions.
+    constants: Vec<Rc<ConstantDef>>,
+    /// Child template definitions.
+    templates: Vec<Rc<TemplateDef>>,
+    /// Child external policy declarations.
+    externs: Vec<Rc<ExternPolicy>>,
+    /// Template instances that have not been expanded yet.
+    instances: Vec<TemplateInstance>,
     /// Child function definitions.
     functions: Vec<Rc<Function>>,
     /// Child infix function definitions.
@@ -78,6 +90,7 @@ pub struct Namespace {
     /// Obligation definitions.
     obligations: Vec<Rc<ObligationDef>>,
     /// Context for conversion
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub ctx: Rc<Context>,
 }
 
@@ -95,6 +108,10 @@ impl Namespace {
             types: vec![],
             categories: vec![],
             attributes: vec![],
+            constants: vec![],
+            templates: vec![],
+            externs: vec![],
+            instances: vec![],
             functions: vec![],
             infix_fns: vec![],
             advice: vec![],
@@ -116,6 +133,10 @@ impl Namespace {
             types: vec![],
             categories: vec![],
             attributes: vec![],
+            constants: vec![],
+            templates: vec![],
+            externs: vec![],
+            instances: vec![],
             functions: vec![],
             infix_fns: vec![],
             advice: vec![],
@@ -171,6 +192,68 @@ impl Namespace {
         self.ctx.register_attribute(attr)
     }
 
+    /// Add a constant definition
+    /// # Errors
+    ///
+    /// Will return `Err` if there is a duplicate symbol.
+    pub fn add_constant(&mut self, constant: ConstantDef) -> Result<(), ParseError> {
+        info!("from namespace, adding constant {constant:?}");
+        let c = Rc::new(constant);
+        self.constants.push(c.clone());
+        self.ctx.register_constant(c)
+    }
+
+    /// Add an external policy declaration
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a policy, policyset, or external declaration
+    /// with the same name exists.
+    pub fn add_extern(&mut self, ext: ExternPolicy) -> Result<(), ParseError> {
+        info!("from namespace, adding extern {ext}");
+        let e = Rc::new(ext);
+        self.externs.push(e.clone());
+        self.ctx.register_extern(e)
+    }
+
+    /// Add a template definition
+    /// # Errors
+    ///
+    /// Will return `Err` if there is a duplicate symbol.
+    pub fn add_template(&mut self, template: TemplateDef) -> Result<(), ParseError> {
+        info!("from namespace, adding template {template}");
+        let t = Rc::new(template);
+        self.templates.push(t.clone());
+        self.ctx.register_template(t)
+    }
+
+    /// Add a template instance, to be expanded once all templates
+    /// are known.
+    pub fn add_instance(&mut self, instance: TemplateInstance) {
+        info!("from namespace, adding instance {instance}");
+        self.instances.push(instance);
+    }
+
+    /// Expand template instances in this namespace and all child
+    /// namespaces, adding the resulting policies and rules.
+    /// # Errors
+    ///
+    /// Will return `Err` if an instance cannot be expanded, or the
+    /// result is a duplicate symbol.
+    pub fn expand_templates(&mut self) -> Result<(), ParseError> {
+        let instances = std::mem::take(&mut self.instances);
+        for i in &instances {
+            match expand_instance(i, &self.ctx)? {
+                ExpandedInstance::Policy(p) => self.add_policy(p)?,
+                ExpandedInstance::Rule(r) => self.add_rule(r)?,
+            }
+        }
+        for n in &mut self.namespaces {
+            n.expand_templates()?;
+        }
+        Ok(())
+    }
+
     /// Add a child policyset
     /// # Errors
     ///
@@ -333,6 +416,56 @@ impl Namespace {
 }
 
 /// Pretty print namespace and children
+impl Namespace {
+    /// Write this namespace as ALFA, declared with the given name.
+    /// Declarations of each kind are grouped together, and every
+    /// policyset, policy, rule, template, and child namespace is
+    /// separated by a blank line.
+    fn alfa_block(&self, name: &str, indent_level: usize) -> String {
+        let indent = "  ".repeat(indent_level);
+        let i = indent_level + 1;
+        let mut sections: Vec<String> = vec![];
+        let mut group = |items: Vec<String>| {
+            if !items.is_empty() {
+                sections.push(items.concat());
+            }
+        };
+        group(self.imports.iter().map(|x| x.to_alfa(i)).collect());
+        group(self.types.iter().map(|x| x.to_alfa(i)).collect());
+        group(self.categories.iter().map(|x| x.to_alfa(i)).collect());
+        group(self.attributes.iter().map(|x| x.to_alfa(i)).collect());
+        group(self.constants.iter().map(|x| x.to_alfa(i)).collect());
+        group(self.functions.iter().map(|x| x.to_alfa(i)).collect());
+        group(self.rulecombinators.iter().map(|x| x.to_alfa(i)).collect());
+        group(self.policycombinators.iter().map(|x| x.to_alfa(i)).collect());
+        group(self.obligations.iter().map(|x| x.to_alfa(i)).collect());
+        group(self.advice.iter().map(|x| x.to_alfa(i)).collect());
+        group(self.externs.iter().map(|x| x.to_alfa(i)).collect());
+        sections.extend(self.infix_fns.iter().map(|x| x.to_alfa(i)));
+        sections.extend(self.templates.iter().map(|x| x.to_alfa(i)));
+        sections.extend(self.instances.iter().map(|x| x.to_alfa(i)));
+        sections.extend(self.rules.iter().map(|x| x.to_alfa(i)));
+        sections.extend(self.policies.iter().map(|x| x.to_alfa(i)));
+        sections.extend(self.policysets.iter().map(|x| x.to_alfa(i)));
+        // child namespaces are declared relative to this one.
+        sections.extend(
+            self.namespaces
+                .iter()
+                .map(|n| n.alfa_block(&n.path[self.path.len()..].join("."), i)),
+        );
+        format!(
+            "{indent}namespace {name} {{\n{}{indent}}}\n",
+            sections.join("\n")
+        )
+    }
+}
+
+impl AsAlfa for Namespace {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        self.alfa_block(&self.dotted_name(), indent_level)
+    }
+}
+
 impl PrettyPrint for Namespace {
     fn pretty_print(&self, indent_level: usize) {
         let indent = "  ".repeat(indent_level);
@@ -373,6 +506,24 @@ impl PrettyPrint for Namespace {
                 child_attr.pretty_print(indent_level + 1);
             }
         }
+        // Show constant definitions
+        if !self.constants.is_empty() {
+            for child_const in &self.constants {
+                child_const.pretty_print(indent_level + 1);
+            }
+        }
+        // Show template definitions
+        if !self.templates.is_empty() {
+            for child_template in &self.templates {
+                child_template.pretty_print(indent_level + 1);
+            }
+        }
+        // Show external policy declarations
+        if !self.externs.is_empty() {
+            for child_extern in &self.externs {
+                child_extern.pretty_print(indent_level + 1);
+            }
+        }
         // Show advice definitions
         if !self.advice.is_empty() {
             for child_advice in &self.advice {
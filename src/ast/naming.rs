@@ -16,6 +16,7 @@ use std::{cell::RefCell, rc::Rc};
 pub type NameSlot = Rc<RefCell<Option<String>>>;
 
 #[derive(Default, Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct GenName {
     name_path: Vec<NameSlot>,
 }
@@ -53,6 +54,19 @@ impl GenName {
             .map(|parts| parts.join(sep))
     }
 
+    /// The qualified name of an element at this path, within a
+    /// namespace.
+    #[must_use]
+    pub fn qualified_name(&self, ns: &[String]) -> String {
+        let mut parts = ns.to_vec();
+        if let Some(p) = self.build_path(".")
+            && !p.is_empty()
+        {
+            parts.push(p);
+        }
+        parts.join(".")
+    }
+
     // Get reference to the last element
     #[must_use]
     pub fn last_elem(&self) -> Option<NameSlot> {
@@ -66,6 +80,59 @@ impl GenName {
     }
 }
 
+/// Collapse whitespace in ALFA source text to single spaces, except
+/// within string literals.  Comments should already be removed.
+#[must_use]
+pub fn canonical_whitespace(src: &str) -> String {
+    let mut out = String::with_capacity(src.len());
+    // the quote character of the current string literal
+    let mut quote = None;
+    let mut escaped = false;
+    let mut pending_space = false;
+    for c in src.chars() {
+        if let Some(q) = quote {
+            out.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+        } else if c.is_whitespace() {
+            pending_space = !out.is_empty();
+        } else {
+            if pending_space {
+                out.push(' ');
+                pending_space = false;
+            }
+            if c == '"' || c == '\'' {
+                quote = Some(c);
+            }
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// A short, stable hash of canonical ALFA source (64-bit FNV-1a, as
+/// hex).
+#[must_use]
+pub fn content_hash(canonical: &str) -> String {
+    hash_bytes(canonical.as_bytes())
+}
+
+/// A short, stable hash of bytes (64-bit FNV-1a, as hex).
+#[must_use]
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in bytes {
+        h ^= u64::from(b);
+        h = h.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{h:016x}")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -96,4 +163,21 @@ mod tests {
         // Now, we can resolve
         assert!(n.is_resolvable());
     }
+
+    #[test]
+    fn test_canonical_whitespace() {
+        assert_eq!(
+            canonical_whitespace("  rule {\n\t permit\n  condition  x == \"a  b\" }\n"),
+            "rule { permit condition x == \"a  b\" }"
+        );
+        // escaped quotes do not end a string
+        assert_eq!(canonical_whitespace(r"'a\'  b'   c"), r"'a\'  b' c");
+    }
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(content_hash(""), "cbf29ce484222325");
+        assert_eq!(content_hash("rule { permit }"), content_hash("rule { permit }"));
+        assert_ne!(content_hash("rule { permit }"), content_hash("rule { deny }"));
+    }
 }
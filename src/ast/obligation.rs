@@ -1,12 +1,14 @@
 //  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
 //  SPDX-License-Identifier: GPL-3.0-or-later
 
+use super::AsAlfa;
 use super::PrettyPrint;
 use super::QualifiedName;
 use std::fmt;
 
 /// An obligation definition statement
 #[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ObligationDef {
     pub id: String,
     pub uri: String,
@@ -14,6 +16,14 @@ pub struct ObligationDef {
     pub ns: Vec<String>,
 }
 
+impl AsAlfa for ObligationDef {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        let indent = "  ".repeat(indent_level);
+        // Ex: obligation notifyOwner = "http://example.com/obligation"
+        format!("{}obligation {} = \"{}\"\n", indent, self.id, self.uri)
+    }
+}
+
 impl QualifiedName for ObligationDef {
     fn fully_qualified_name(&self) -> Option<String> {
         let mut qn = self.ns.join(".");
@@ -13,6 +13,7 @@
 /// defined in.
 
 #[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Operator {
     pub ns: Vec<String>,  // qualified name, not including operator
     pub operator: String, // operator name (symbols)
@@ -1,20 +1,24 @@
 //  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
 //  SPDX-License-Identifier: GPL-3.0-or-later
 
+use super::combinerparam::{alfa_params, CombinerParam};
+use super::constant::quote_raw;
 use super::condition::Condition;
 use super::naming::GenName;
 use super::naming::NameSlot;
+use super::policyissuer::IssuerAttribute;
 use super::policyset::PolicySet;
 use super::prescription::Prescription;
 use super::rule::RuleEntry;
 use super::target::Target;
-use super::PrettyPrint;
+use super::template::Expansion;
+use super::{alfa_common_stmts, alfa_description, AsAlfa, PrettyPrint};
 use super::QualifiedName;
 use super::{Spanned, SrcLoc};
 use crate::ast::policyset::{PolicyCombiningAlgorithm, PolicyEntry};
 use crate::ast::rule::Effect;
 use crate::ast::rule::RuleDef;
-use crate::context::PROTECTED_NS;
+use crate::context::{AnonymousKind, PROTECTED_NS};
 use crate::Context;
 use crate::ParseError;
 use log::info;
@@ -24,6 +28,7 @@ use std::rc::Rc;
 use uuid::Uuid;
 /// An empty Policy.
 #[derive(Debug, PartialEq, Clone, Spanned)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Policy {
     /// The identifier for the policy
     pub id: PolicyId,
@@ -35,6 +40,14 @@ pub struct Policy {
     pub src_loc: SrcLoc,
     /// Optional description
     pub description: Option<String>,
+    /// Optional version ("version" statement)
+    pub version: Option<String>,
+    /// Attributes of the policy issuer ("issuer" statement)
+    pub issuer: Vec<IssuerAttribute>,
+    /// Optional maximum delegation depth ("maxDelegationDepth" statement)
+    pub max_delegation_depth: Option<u32>,
+    /// Parameters for the combining algorithm of the parent policyset
+    pub entry_params: Vec<CombinerParam>,
     /// Optional target
     pub target: Option<Target>,
     /// Optional condition
@@ -45,14 +58,22 @@ pub struct Policy {
     pub rules: Vec<RuleEntry>,
     /// On-Effect blocks (obligations/advice)
     pub prescriptions: Vec<Prescription>,
+    /// Hash of the canonical ALFA source, for content-derived names
+    pub content_hash: String,
+    /// The template instance this policy was expanded from
+    pub expansion: Option<Rc<Expansion>>,
     /// Context for conversion
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub ctx: Rc<Context>,
 }
 
 #[derive(Debug, PartialEq, Clone, Spanned)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RuleCombiningAlgorithm {
     pub id: String,
     pub src_loc: SrcLoc,
+    /// Parameters for the algorithm
+    pub params: Vec<CombinerParam>,
 }
 
 impl fmt::Display for RuleCombiningAlgorithm {
@@ -63,6 +84,7 @@ impl fmt::Display for RuleCombiningAlgorithm {
 
 /// Policy identifier
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum PolicyId {
     PolicyNoName,
     PolicyName(String),
@@ -80,6 +102,53 @@ impl PolicyId {
     }
 }
 
+impl PolicyId {
+    /// The name of a policy or policyset as written after the
+    /// keyword, including the leading space.
+    pub(crate) fn alfa_name(&self) -> String {
+        match self {
+            PolicyId::PolicyNoName => String::new(),
+            PolicyId::PolicyName(n) => format!(" {n}"),
+            PolicyId::PolicyNameAndId(n, i) => format!(" {n} = {}", quote_raw(i)),
+        }
+    }
+}
+
+impl AsAlfa for Policy {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        let indent = "  ".repeat(indent_level);
+        let i = indent_level + 1;
+        let mut output = alfa_description(self.description.as_ref(), indent_level);
+        output.push_str(&format!(
+            "{indent}policy{}{} {{\n",
+            self.id.alfa_name(),
+            alfa_params(&self.entry_params)
+        ));
+        output.push_str(&alfa_common_stmts(
+            self.version.as_ref(),
+            self.max_delegation_depth,
+            &self.issuer,
+            self.target.as_ref(),
+            self.condition.as_ref(),
+            i,
+        ));
+        output.push_str(&format!(
+            "{}apply {}{}\n",
+            "  ".repeat(i),
+            self.apply.id,
+            alfa_params(&self.apply.params)
+        ));
+        for r in &self.rules {
+            output.push_str(&r.to_alfa(i));
+        }
+        for p in &self.prescriptions {
+            output.push_str(&p.to_alfa(i));
+        }
+        output.push_str(&format!("{indent}}}\n"));
+        output
+    }
+}
+
 impl QualifiedName for Policy {
     fn fully_qualified_name(&self) -> Option<String> {
         match &self.id {
@@ -121,15 +190,28 @@ impl Policy {
         self.ctx.config.get_base_namespace()
     }
 
+    /// Namespace where names used by this policy are resolved.  This
+    /// is the template namespace for expanded template instances.
+    #[must_use]
+    pub fn scope(&self) -> &[String] {
+        self.expansion.as_ref().map_or(&self.ns, |x| &x.scope)
+    }
+
+    /// Name (given or generated) within the namespace.
+    #[must_use]
+    pub fn get_name(&self) -> Option<String> {
+        let b = self.policy_ns.last_elem()?;
+        let gen_name = b.borrow();
+        gen_name.clone()
+    }
+
     /// Suggest a filename.
     #[must_use]
     pub fn get_filename(&self) -> Option<String> {
         // join the namespace and the ID
         let mut name = self.ns.join(".");
         name.push('.');
-        let b = self.policy_ns.last_elem()?;
-        let gen_name = b.borrow();
-        name.push_str(gen_name.as_ref()?);
+        name.push_str(&self.get_name()?);
         name.push_str(".xml");
         info!("suggested filename:  {name}");
         Some(name)
@@ -158,14 +240,19 @@ impl Policy {
             // fix the parent policy name if necessary
             if this_id.is_none() {
                 loop {
-                    let next_id = self.ctx.get_next_policy_id(&self.ns.join("."));
-                    let proposed_name = format!("policy_{next_id}");
+                    let proposed_name = self.ctx.generate_name(
+                        AnonymousKind::Policy,
+                        &self.ns.join("."),
+                        &self.ns.join("."),
+                        &self.content_hash,
+                    );
                     // check for collision (covers the case of an explicitly named "policy_0", etc.
-                    let no_conflict = self.ctx.lookup_policy(&proposed_name, &self.ns).is_err();
+                    let no_conflict = self.ctx.lookup_policy(&proposed_name, &self.ns).is_err()
+                        && self.ctx.lookup_policyset(&proposed_name, &self.ns).is_err();
                     if no_conflict {
                         info!("no conflict!");
+                        info!("finalizing policy ID to {proposed_name}");
                         this_id.replace(proposed_name);
-                        info!("finalizing policy ID to {next_id}");
                         break;
                     }
                     info!("conflict, trying again...");
@@ -231,14 +318,22 @@ impl Policy {
             policy_ns: self.policy_ns.clone(),
             src_loc: self.span().clone(),
             description: original.description.take(),
+            version: original.version.clone(),
+            // the container takes the place of the original in any
+            // parent policyset.
+            issuer: std::mem::take(&mut original.issuer),
+            max_delegation_depth: original.max_delegation_depth.take(),
+            entry_params: std::mem::take(&mut original.entry_params),
             target: None,
             condition: None,
             apply: PolicyCombiningAlgorithm {
                 id: format!("{}.{}", PROTECTED_NS, "onPermitApplySecond"),
                 src_loc: original.apply.span().clone(),
+                params: vec![],
             },
             policies: vec![],
             prescriptions: vec![],
+            content_hash: original.content_hash.clone(),
             ctx: self.ctx.clone(),
         };
 
@@ -263,6 +358,10 @@ impl Policy {
             target: None,
             condition: original.condition.take(),
             prescriptions: vec![],
+            entry_params: vec![],
+            content_hash: original.content_hash.clone(),
+            src_loc: original.src_loc.clone(),
+            expansion: original.expansion.clone(),
             ctx: Rc::<Context>::downgrade(&self.ctx),
         };
         // Create a child policy with the rule.
@@ -272,14 +371,21 @@ impl Policy {
             policy_ns: cond_policy_ns,
             src_loc: self.span().clone(),
             description: None,
+            version: original.version.clone(),
+            issuer: vec![],
+            max_delegation_depth: None,
+            entry_params: vec![],
             target: None,
             condition: None,
             apply: RuleCombiningAlgorithm {
                 id: format!("{}.{}", PROTECTED_NS, "permitOverrides"),
                 src_loc: self.apply.span().clone(),
+                params: vec![],
             },
             rules: vec![RuleEntry::Def(Rc::new(condrule))],
             prescriptions: vec![],
+            content_hash: original.content_hash.clone(),
+            expansion: original.expansion.clone(),
             ctx: self.ctx.clone(),
         };
 
@@ -10,6 +10,7 @@ use std::fmt;
 
 /// A policy combining algorithm
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PolicyCombinator {
     pub id: String,
     pub uri: String, // URI definition of algorithm
@@ -0,0 +1,32 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::constant::Constant;
+use super::SrcLoc;
+use super::Spanned;
+use std::fmt;
+
+/// An attribute of the issuer of a policy or policyset.
+///
+/// Issuer attributes become the XACML `<PolicyIssuer>` element, used
+/// by the Administration and Delegation profile.  The value is typed
+/// by the declared type of the attribute.
+///
+/// Ex: `issuer { subjectId = "alice" }`
+#[derive(Debug, PartialEq, Clone, Spanned)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct IssuerAttribute {
+    /// Location of the assignment in the ALFA source
+    pub src_loc: SrcLoc,
+    /// Attribute name, possibly qualified
+    pub attribute: String,
+    /// Literal value
+    pub value: Constant,
+}
+
+impl fmt::Display for IssuerAttribute {
+    // This trait requires `fmt` with this exact signature.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} = {}", self.attribute, self.value)
+    }
+}
@@ -4,22 +4,25 @@
 use log::info;
 use uuid::Uuid;
 
+use super::combinerparam::{alfa_params, CombinerParam};
 use super::condition::Condition;
 use super::naming::GenName;
 use super::naming::NameSlot;
 use super::policy::Policy;
 use super::policy::PolicyId;
+use super::policyissuer::IssuerAttribute;
 use super::prescription::Prescription;
 use super::rule::Effect;
 use super::rule::RuleDef;
 use super::rule::RuleEntry;
 use super::target::Target;
-use super::PrettyPrint;
+use super::{alfa_common_stmts, alfa_description, AsAlfa, PrettyPrint};
 use super::QualifiedName;
 use super::Spanned;
+use super::version::VersionConstraints;
 use super::SrcLoc;
 use crate::ast::policy::RuleCombiningAlgorithm;
-use crate::context::PROTECTED_NS;
+use crate::context::{AnonymousKind, PROTECTED_NS};
 use crate::Context;
 use crate::ParseError;
 use std::cell::RefCell;
@@ -27,6 +30,7 @@ use std::fmt;
 use std::rc::Rc;
 /// An empty `PolicySet`.
 #[derive(Debug, PartialEq, Clone, Spanned)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PolicySet {
     /// The identifier for the policy
     pub id: PolicyId,
@@ -38,6 +42,14 @@ pub struct PolicySet {
     pub src_loc: SrcLoc,
     /// Optional description
     pub description: Option<String>,
+    /// Optional version ("version" statement)
+    pub version: Option<String>,
+    /// Attributes of the policy issuer ("issuer" statement)
+    pub issuer: Vec<IssuerAttribute>,
+    /// Optional maximum delegation depth ("maxDelegationDepth" statement)
+    pub max_delegation_depth: Option<u32>,
+    /// Parameters for the combining algorithm of the parent policyset
+    pub entry_params: Vec<CombinerParam>,
     /// Optional target
     pub target: Option<Target>,
     /// Optional condition
@@ -48,7 +60,10 @@ pub struct PolicySet {
     pub policies: Vec<PolicyEntry>,
     /// Obligations and/or advice
     pub prescriptions: Vec<Prescription>,
+    /// Hash of the canonical ALFA source, for content-derived names
+    pub content_hash: String,
     /// Context for conversion
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub ctx: Rc<Context>,
 }
 
@@ -80,6 +95,14 @@ impl PolicySet {
         }
     }
 
+    /// Name (given or generated) within the namespace.
+    #[must_use]
+    pub fn get_name(&self) -> Option<String> {
+        let b = self.policy_ns.last_elem()?;
+        let gen_name = b.borrow();
+        gen_name.clone()
+    }
+
     /// Suggest a filename.
     #[must_use]
     pub fn get_filename(&self) -> Option<String> {
@@ -87,9 +110,7 @@ impl PolicySet {
         // join the namespace and the ID
         let mut name = self.ns.join(".");
         name.push('.');
-        let b = self.policy_ns.last_elem()?;
-        let gen_name = b.borrow();
-        name.push_str(gen_name.as_ref()?);
+        name.push_str(&self.get_name()?);
         name.push_str(".xml");
         info!("suggested filename:  {name}");
         Some(name)
@@ -108,14 +129,19 @@ impl PolicySet {
             let mut this_id = last.borrow_mut();
             if this_id.is_none() {
                 loop {
-                    let next_id = self.ctx.get_next_policyset_id(&self.ns.join("."));
-                    let proposed_name = format!("policyset_{next_id}");
+                    let proposed_name = self.ctx.generate_name(
+                        AnonymousKind::PolicySet,
+                        &self.ns.join("."),
+                        &self.ns.join("."),
+                        &self.content_hash,
+                    );
                     // check for collision
-                    let no_conflict = self.ctx.lookup_policy(&proposed_name, &self.ns).is_err();
+                    let no_conflict = self.ctx.lookup_policy(&proposed_name, &self.ns).is_err()
+                        && self.ctx.lookup_policyset(&proposed_name, &self.ns).is_err();
                     if no_conflict {
                         info!("no conflict!");
+                        info!("finalizing policyset ID to {proposed_name}");
                         this_id.replace(proposed_name);
-                        info!("finalizing policyset ID to {next_id}");
                         break;
                     }
                     info!("conflict, trying again...");
@@ -186,14 +212,22 @@ impl PolicySet {
             policy_ns: self.policy_ns.clone(),
             src_loc: self.span().clone(),
             description: original.description.take(),
+            version: original.version.clone(),
+            // the container takes the place of the original in any
+            // parent policyset.
+            issuer: std::mem::take(&mut original.issuer),
+            max_delegation_depth: original.max_delegation_depth.take(),
+            entry_params: std::mem::take(&mut original.entry_params),
             target: None,
             condition: None,
             apply: PolicyCombiningAlgorithm {
                 id: format!("{}.{}", PROTECTED_NS, "onPermitApplySecond"),
                 src_loc: self.apply.span().clone(),
+                params: vec![],
             },
             policies: vec![],
             prescriptions: vec![],
+            content_hash: original.content_hash.clone(),
             ctx: self.ctx.clone(),
         };
 
@@ -232,6 +266,10 @@ impl PolicySet {
             target: None,
             condition: original.condition.take(),
             prescriptions: vec![],
+            entry_params: vec![],
+            content_hash: original.content_hash.clone(),
+            src_loc: original.src_loc.clone(),
+            expansion: None,
             ctx: Rc::<Context>::downgrade(&self.ctx),
         };
         let condpolicy = Policy {
@@ -240,14 +278,21 @@ impl PolicySet {
             policy_ns: cond_policy_ns,
             src_loc: self.span().clone(),
             description: None,
+            version: original.version.clone(),
+            issuer: vec![],
+            max_delegation_depth: None,
+            entry_params: vec![],
             target: None,
             condition: None,
             apply: RuleCombiningAlgorithm {
                 id: format!("{}.{}", PROTECTED_NS, "permitOverrides"),
                 src_loc: self.apply.span().clone(),
+                params: vec![],
             },
             rules: vec![RuleEntry::Def(Rc::new(condrule))],
             prescriptions: vec![],
+            content_hash: original.content_hash.clone(),
+            expansion: None,
             ctx: self.ctx.clone(),
         };
 
@@ -307,6 +352,41 @@ impl QualifiedName for PolicySet {
     }
 }
 
+impl AsAlfa for PolicySet {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        let indent = "  ".repeat(indent_level);
+        let i = indent_level + 1;
+        let mut output = alfa_description(self.description.as_ref(), indent_level);
+        output.push_str(&format!(
+            "{indent}policyset{}{} {{\n",
+            self.id.alfa_name(),
+            alfa_params(&self.entry_params)
+        ));
+        output.push_str(&alfa_common_stmts(
+            self.version.as_ref(),
+            self.max_delegation_depth,
+            &self.issuer,
+            self.target.as_ref(),
+            self.condition.as_ref(),
+            i,
+        ));
+        output.push_str(&format!(
+            "{}apply {}{}\n",
+            "  ".repeat(i),
+            self.apply.id,
+            alfa_params(&self.apply.params)
+        ));
+        for p in &self.policies {
+            output.push_str(&p.to_alfa(i));
+        }
+        for p in &self.prescriptions {
+            output.push_str(&p.to_alfa(i));
+        }
+        output.push_str(&format!("{indent}}}\n"));
+        output
+    }
+}
+
 impl fmt::Display for PolicySet {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "policy set")
@@ -322,9 +402,12 @@ impl PrettyPrint for PolicySet {
 }
 
 #[derive(Debug, PartialEq, Clone, Spanned)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PolicyCombiningAlgorithm {
     pub id: String,
     pub src_loc: SrcLoc,
+    /// Parameters for the algorithm
+    pub params: Vec<CombinerParam>,
 }
 
 impl fmt::Display for PolicyCombiningAlgorithm {
@@ -335,6 +418,7 @@ impl fmt::Display for PolicyCombiningAlgorithm {
 
 /// A policy/policyset that can be included in a policyset.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum PolicyEntry {
     /// Reference to a policy/policyset
     Ref(PolicyReference),
@@ -352,6 +436,16 @@ impl fmt::Display for PolicyEntry {
         }
     }
 }
+impl AsAlfa for PolicyEntry {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        match self {
+            PolicyEntry::Ref(p) => p.to_alfa(indent_level),
+            PolicyEntry::Policy(p) => p.to_alfa(indent_level),
+            PolicyEntry::PolicySet(p) => p.to_alfa(indent_level),
+        }
+    }
+}
+
 /// Pretty print policyentry
 impl PrettyPrint for PolicyEntry {
     fn pretty_print(&self, indent_level: usize) {
@@ -365,9 +459,14 @@ impl PrettyPrint for PolicyEntry {
 
 /// Reference to a policy defined elsewhere
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PolicyReference {
     pub id: String,
     pub ns: Vec<String>,
+    /// Acceptable versions of the referenced policy
+    pub versions: VersionConstraints,
+    /// Parameters for the combining algorithm of the parent policyset
+    pub entry_params: Vec<CombinerParam>,
 }
 
 impl PolicyReference {
@@ -381,6 +480,19 @@ impl PolicyReference {
         fqname
     }
 }
+impl AsAlfa for PolicyReference {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        let indent = "  ".repeat(indent_level);
+        // Ex: base.corePolicies(earliest = "1.0") with (weight = 2)
+        format!(
+            "{indent}{}{}{}\n",
+            self.fully_qualified_name(),
+            self.versions,
+            alfa_params(&self.entry_params)
+        )
+    }
+}
+
 /// Pretty print policyreference
 impl PrettyPrint for PolicyReference {
     fn pretty_print(&self, indent_level: usize) {
@@ -3,7 +3,9 @@
 
 use crate::context::Context;
 
-use super::{constant::Constant, designator::AttributeDesignator, rule::Effect, PrettyPrint};
+use super::{
+    constant::Constant, designator::AttributeDesignator, rule::Effect, AsAlfa, PrettyPrint,
+};
 use std::{fmt, rc::Weak};
 
 // A "Prescription" is just our term for the block of obligation and
@@ -15,11 +17,13 @@ use std::{fmt, rc::Weak};
 
 /// Obligation/advice assignment statements for an effect
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Prescription {
     pub effect: Effect, // permit/deny
     pub ns: Vec<String>,
     pub expressions: Vec<PrescriptionExpr>,
     /// Context for conversion
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub ctx: Weak<Context>,
 }
 
@@ -30,6 +34,44 @@ impl PartialEq for Prescription {
     }
 }
 
+impl AsAlfa for Prescription {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        let indent = "  ".repeat(indent_level);
+        let nested_indent = "  ".repeat(indent_level + 1);
+        // Ex: on permit {
+        //       obligation notify {
+        //         Attributes.message = "access granted"
+        //       }
+        //     }
+        let effect = self.effect.to_string().to_lowercase();
+        let mut output = format!("{indent}on {effect} {{\n");
+        for e in &self.expressions {
+            let keyword = match e.ptype {
+                PrescriptionType::Obligation => "obligation",
+                PrescriptionType::Advice => "advice",
+            };
+            if e.assignments.is_empty() {
+                output.push_str(&format!("{nested_indent}{keyword} {} {{}}\n", e.id));
+                continue;
+            }
+            output.push_str(&format!("{nested_indent}{keyword} {} {{\n", e.id));
+            for a in &e.assignments {
+                let value = match &a.source {
+                    AttrAssignmentSource::Attribute(d) => d.to_string(),
+                    AttrAssignmentSource::Value(c) => c.to_string(),
+                };
+                output.push_str(&format!(
+                    "{nested_indent}  {} = {value}\n",
+                    a.destination_id
+                ));
+            }
+            output.push_str(&format!("{nested_indent}}}\n"));
+        }
+        output.push_str(&format!("{indent}}}\n"));
+        output
+    }
+}
+
 impl PrettyPrint for Prescription {
     fn pretty_print(&self, indent_level: usize) {
         let indent = "  ".repeat(indent_level);
@@ -46,6 +88,7 @@ impl fmt::Display for Prescription {
 
 /// An obligation/advice assignment
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PrescriptionExpr {
     pub ptype: PrescriptionType,
     pub id: String,                            // obligation/advice ALFA id
@@ -54,6 +97,7 @@ pub struct PrescriptionExpr {
 
 /// An assignment of some source value to a destination attribute ALFA ID
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AttributeAssignment {
     pub destination_id: String,
     pub source: AttrAssignmentSource,
@@ -61,6 +105,7 @@ pub struct AttributeAssignment {
 
 /// Right-hand of an attribute assignment expression.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum AttrAssignmentSource {
     // use the designator from Conditions (AttributeDesignator)
     Attribute(AttributeDesignator), // an attribute identifier
@@ -69,6 +114,7 @@ pub enum AttrAssignmentSource {
 
 /// The effect of a rule (or obligation/advice)
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PrescriptionType {
     Obligation,
     Advice,
@@ -1,13 +1,18 @@
 //  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
 //  SPDX-License-Identifier: GPL-3.0-or-later
 
+use super::alfa_description;
+use super::combinerparam::{alfa_params, CombinerParam};
 use super::condition::Condition;
 use super::naming::GenName;
 use super::prescription::Prescription;
 use super::target::Target;
+use super::template::Expansion;
+use super::AsAlfa;
 use super::PrettyPrint;
 use super::QualifiedName;
 use super::SrcLoc;
+use crate::context::AnonymousKind;
 use crate::Context;
 use log::warn;
 use std::fmt;
@@ -17,6 +22,7 @@ use uuid::Uuid;
 
 /// A rule that can be included in a policy.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum RuleEntry {
     /// Reference to a rule
     Ref(RuleReference),
@@ -24,6 +30,15 @@ pub enum RuleEntry {
 }
 
 /// Pretty print namespace and children
+impl AsAlfa for RuleEntry {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        match self {
+            RuleEntry::Ref(r) => r.to_alfa(indent_level),
+            RuleEntry::Def(r) => r.to_alfa(indent_level),
+        }
+    }
+}
+
 impl PrettyPrint for RuleEntry {
     fn pretty_print(&self, indent_level: usize) {
         match self {
@@ -35,6 +50,7 @@ impl PrettyPrint for RuleEntry {
 
 /// The effect of a rule (or obligation/advice)
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Effect {
     Permit,
     Deny,
@@ -52,6 +68,7 @@ impl fmt::Display for Effect {
 
 /// A rule definition
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RuleDef {
     pub id: Option<String>, // rule names are optional when declared
     pub ns: Vec<String>,
@@ -62,6 +79,15 @@ pub struct RuleDef {
     pub target: Option<Target>,
     pub condition: Option<Condition>,
     pub prescriptions: Vec<Prescription>, // on <effect> blocks
+    /// Parameters for the combining algorithm of the parent policy
+    pub entry_params: Vec<CombinerParam>,
+    /// Hash of the canonical ALFA source, for content-derived names
+    pub content_hash: String,
+    /// The location of the rule declaration
+    pub src_loc: SrcLoc,
+    /// The template instance this rule was expanded from
+    pub expansion: Option<Rc<Expansion>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub ctx: Weak<Context>,
 }
 
@@ -74,6 +100,12 @@ impl RuleDef {
             String::default()
         }
     }
+    /// Namespace where names used by this rule are resolved.  This
+    /// is the template namespace for expanded template instances.
+    #[must_use]
+    pub fn scope(&self) -> &[String] {
+        self.expansion.as_ref().map_or(&self.ns, |x| &x.scope)
+    }
     #[must_use]
     pub fn get_id(&self) -> String {
         // Rules cannot have IDs defined in ALFA, so we always must
@@ -110,8 +142,14 @@ impl RuleDef {
                 None => {
                     // generate rule ID suffix if the rule had no name
                     if let Some(ctx) = self.ctx.upgrade() {
-                        let next_id = ctx.get_next_rule_id(&self.ns.join("."));
-                        pn.push_str(&format!("#rule_{next_id}"));
+                        let name = ctx.generate_name(
+                            AnonymousKind::Rule,
+                            &self.ns.join("."),
+                            &pn,
+                            &self.content_hash,
+                        );
+                        pn.push('#');
+                        pn.push_str(&name);
                     } else {
                         panic!("Context no longer exists, could not generate Rule ID");
                     }
@@ -137,6 +175,38 @@ impl RuleDef {
 }
 
 /// Target equality, ignoring context
+impl AsAlfa for RuleDef {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        let indent = "  ".repeat(indent_level);
+        let i = indent_level + 1;
+        let mut output = alfa_description(self.description.as_ref(), indent_level);
+        let name = match &self.id {
+            Some(id) => format!(" {id}"),
+            None => String::new(),
+        };
+        output.push_str(&format!(
+            "{indent}rule{name}{} {{\n",
+            alfa_params(&self.entry_params)
+        ));
+        output.push_str(&format!(
+            "{}{}\n",
+            "  ".repeat(i),
+            self.effect.to_string().to_lowercase()
+        ));
+        if let Some(t) = &self.target {
+            output.push_str(&t.to_alfa(i));
+        }
+        if let Some(c) = &self.condition {
+            output.push_str(&c.to_alfa(i));
+        }
+        for p in &self.prescriptions {
+            output.push_str(&p.to_alfa(i));
+        }
+        output.push_str(&format!("{indent}}}\n"));
+        output
+    }
+}
+
 impl PartialEq for RuleDef {
     fn eq(&self, other: &Self) -> bool {
         self.id == other.id
@@ -148,10 +218,25 @@ impl PartialEq for RuleDef {
 
 /// Reference to a rule defined elsewhere
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RuleReference {
     pub id: String,
     pub ns: Vec<String>,
     pub src_loc: SrcLoc,
+    /// Parameters for the combining algorithm of the parent policy
+    pub entry_params: Vec<CombinerParam>,
+}
+
+impl AsAlfa for RuleReference {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        let indent = "  ".repeat(indent_level);
+        // Ex: common.denyAll with (weight = 2)
+        format!(
+            "{indent}{}{}\n",
+            self.fully_qualified_name().unwrap_or_default(),
+            alfa_params(&self.entry_params)
+        )
+    }
 }
 
 impl QualifiedName for RuleDef {
@@ -162,11 +247,11 @@ impl QualifiedName for RuleDef {
                 qn.push('.');
             }
             // push the policy namespaces elements, if they exist.
-            if !self.policy_ns.is_empty() {
-                if let Some(policy_path) = self.policy_ns.build_path(".") {
-                    qn.push_str(&policy_path);
-                    qn.push('.');
-                }
+            if !self.policy_ns.is_empty()
+                && let Some(policy_path) = self.policy_ns.build_path(".")
+            {
+                qn.push_str(&policy_path);
+                qn.push('.');
             }
             qn.push_str(i);
             Some(qn.to_string())
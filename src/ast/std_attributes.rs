@@ -122,6 +122,19 @@ pub fn standard_attributes() -> Vec<Attribute> {
         "actionCat",
         "urn:oasis:names:tc:xacml:1.0:action:implied-action",
     ));
+    // XACML Administration and Delegation Profile
+    a.push(mk_attr(
+        "delegateId",
+        "string",
+        "delegateCat",
+        "urn:oasis:names:tc:xacml:1.0:subject:subject-id",
+    ));
+    a.push(mk_attr(
+        "delegationDecision",
+        "string",
+        "delegationInfoCat",
+        "urn:oasis:names:tc:xacml:3.0:delegation:decision",
+    ));
 
     a
 }
@@ -2,20 +2,27 @@
 //  SPDX-License-Identifier: GPL-3.0-or-later
 
 use super::constant::Constant;
+use super::designator::AttributeDesignator;
 use super::operator::Operator;
+use super::AsAlfa;
 use super::PrettyPrint;
+use super::SrcLoc;
 use crate::Context;
 use std::fmt;
 use std::rc::Weak;
 
 /// A target statement
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Target {
     // targets are made up of clauses (AND'd)
     pub clauses: Vec<DisjunctiveSeq>,
     /// The namespace this target is located in
     pub ns: Vec<String>,
+    /// The location of this target
+    pub src_loc: SrcLoc,
     /// Context for conversion
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub ctx: Weak<Context>,
 }
 
@@ -26,6 +33,23 @@ impl PartialEq for Target {
     }
 }
 
+impl AsAlfa for Target {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        let indent = "  ".repeat(indent_level);
+        // Ex: target clause Attributes.role == "admin" or Attributes.role == "owner"
+        //            clause Attributes.action == "read"
+        if self.clauses.is_empty() {
+            return format!("{indent}target\n");
+        }
+        let mut output = String::new();
+        for (i, c) in self.clauses.iter().enumerate() {
+            let keyword = if i == 0 { "target" } else { "      " };
+            output.push_str(&format!("{indent}{keyword} clause {}\n", c.alfa_clause()));
+        }
+        output
+    }
+}
+
 impl fmt::Display for Target {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Target: {} AnyOf", self.clauses.len())
@@ -44,9 +68,36 @@ impl PrettyPrint for Target {
 }
 
 /// A disjunctive sequence
-#[derive(Debug, Default, Clone, PartialEq)]
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct DisjunctiveSeq {
     pub statements: Vec<ConjunctiveSeq>,
+    /// The location of this clause
+    pub src_loc: SrcLoc,
+}
+
+/// Clause equality, ignoring location
+impl PartialEq for DisjunctiveSeq {
+    fn eq(&self, other: &Self) -> bool {
+        self.statements == other.statements
+    }
+}
+
+impl DisjunctiveSeq {
+    /// The ALFA text of this clause, following `clause`.
+    fn alfa_clause(&self) -> String {
+        self.statements
+            .iter()
+            .map(|c| {
+                c.matches
+                    .iter()
+                    .map(Match::alfa_match)
+                    .collect::<Vec<_>>()
+                    .join(" and ")
+            })
+            .collect::<Vec<_>>()
+            .join(" or ")
+    }
 }
 
 impl fmt::Display for DisjunctiveSeq {
@@ -68,6 +119,7 @@ impl PrettyPrint for DisjunctiveSeq {
 
 /// A conjunctive sequence
 #[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ConjunctiveSeq {
     pub matches: Vec<Match>,
 }
@@ -91,11 +143,40 @@ impl PrettyPrint for ConjunctiveSeq {
 
 /// Match statement in a target
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Match {
     MatchFunc(MatchFunction),
     MatchOp(MatchOperation),
 }
 
+impl Match {
+    /// The ALFA text of this match.
+    fn alfa_match(&self) -> String {
+        match self {
+            Match::MatchFunc(m) => {
+                let attr = AttributeDesignator {
+                    attribute: m.attribute.clone(),
+                    issuer: m.issuer.clone(),
+                    mustbepresent: m.mustbepresent,
+                };
+                format!("{}({}, {attr})", m.function_id.join("."), m.literal)
+            }
+            Match::MatchOp(m) => {
+                let attr = AttributeDesignator {
+                    attribute: m.attribute.clone(),
+                    issuer: m.issuer.clone(),
+                    mustbepresent: m.mustbepresent,
+                };
+                if m.reversed {
+                    format!("{attr} {} {}", m.operator, m.literal)
+                } else {
+                    format!("{} {} {attr}", m.literal, m.operator)
+                }
+            }
+        }
+    }
+}
+
 impl fmt::Display for Match {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -114,6 +195,7 @@ impl PrettyPrint for Match {
 
 /// A match function application (target)
 #[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MatchFunction {
     /// fully qualified namespace for the function
     pub function_id: Vec<String>,
@@ -128,6 +210,7 @@ pub struct MatchFunction {
 
 /// A match operation (target)
 #[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MatchOperation {
     pub attribute: Vec<String>, //qualified name
     pub operator: Operator,     // ==, +, etc.
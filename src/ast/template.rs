@@ -0,0 +1,332 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Policy and rule templates, and their instantiations.
+//!
+//! Templates are kept as source text, along with the locations of
+//! every parameter reference in the body.  Once all ALFA sources are
+//! parsed, each instance is expanded by substituting the argument
+//! text for the parameter references, and the result is parsed as an
+//! ordinary policy or rule declaration.
+use super::{alfa_description, AsAlfa, PrettyPrint};
+use super::QualifiedName;
+use super::SrcLoc;
+use super::Spanned;
+use crate::errors::{ParseError, SrcError};
+use miette::NamedSource;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The kind of element a template produces.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum TemplateKind {
+    #[default]
+    Policy,
+    Rule,
+}
+
+impl fmt::Display for TemplateKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateKind::Policy => write!(f, "policy"),
+            TemplateKind::Rule => write!(f, "rule"),
+        }
+    }
+}
+
+/// A reference to a parameter within a template body.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ParamRef {
+    /// Name of the referenced parameter
+    pub name: String,
+    /// Location of the reference (including the `param[...]` syntax)
+    pub src_loc: SrcLoc,
+}
+
+/// A template definition.
+///
+/// Ex: `template policy addToCart(role, act) { ... }`
+#[derive(Debug, PartialEq, Clone, Default, Spanned)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TemplateDef {
+    /// short name
+    pub id: String,
+    /// whether this template produces a policy or a rule
+    pub kind: TemplateKind,
+    /// parameter names, in declaration order
+    pub params: Vec<String>,
+    /// The namespace from general to most specific.
+    pub ns: Vec<String>,
+    /// Comment preceding the template declaration
+    pub description: Option<String>,
+    /// Location of the full template declaration
+    pub src_loc: SrcLoc,
+    /// Location of the body (from the opening to the closing brace)
+    pub body: SrcLoc,
+    /// Every parameter reference in the body, in source order
+    pub param_refs: Vec<ParamRef>,
+}
+
+/// The kind of element named by a template argument.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ArgSymbolKind {
+    Attribute,
+    Constant,
+}
+
+/// A name within a template argument.  Names in arguments are
+/// resolved from the namespace of the instance, not the template.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ArgSymbol {
+    /// What the name refers to
+    pub kind: ArgSymbolKind,
+    /// The (possibly qualified) name, as written
+    pub name: String,
+    /// Byte offset of the name within the argument value
+    pub offset: usize,
+}
+
+/// A single parameter binding in a template instance.
+#[derive(Debug, PartialEq, Clone, Default, Spanned)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TemplateArg {
+    /// Parameter name
+    pub name: String,
+    /// Source text of the literal or attribute bound to the parameter
+    pub value: String,
+    /// Attribute or constant named in the value, if any
+    pub symbol: Option<ArgSymbol>,
+    /// Location of the binding
+    pub src_loc: SrcLoc,
+}
+
+/// An instantiation of a template.
+///
+/// Ex: `instance customerAddToCart = addToCart(role = "customer", act = "add")`
+#[derive(Debug, PartialEq, Clone, Default, Spanned)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TemplateInstance {
+    /// name of the produced policy or rule (anonymous if `None`)
+    pub id: Option<String>,
+    /// (possibly qualified) name of the template
+    pub template: String,
+    /// parameter bindings
+    pub args: Vec<TemplateArg>,
+    /// The namespace from general to most specific.
+    pub ns: Vec<String>,
+    /// Comment preceding the instance
+    pub description: Option<String>,
+    /// Location of the instance statement
+    pub src_loc: SrcLoc,
+}
+
+/// Where an element expanded from a template instance came from.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Expansion {
+    /// Fully qualified name of the template
+    pub template: String,
+    /// Namespace of the template, where names in the body resolve
+    pub scope: Vec<String>,
+    /// Location of the template declaration
+    pub template_loc: SrcLoc,
+    /// Fully qualified name of the instance (anonymous if `None`)
+    pub instance: Option<String>,
+    /// Location of the instance statement
+    pub instance_loc: SrcLoc,
+}
+
+impl Expansion {
+    /// Add the template and instance locations to an error in the
+    /// expanded element.  Errors without a location of their own are
+    /// placed at the template.
+    #[must_use]
+    pub fn locate(&self, err: ParseError) -> ParseError {
+        let err = SrcError::with_fallback_location(err, self.template_loc.clone());
+        let err = SrcError::with_related(
+            err,
+            "expanded from this template",
+            self.template_loc.clone(),
+        );
+        SrcError::with_related(err, "instantiated here", self.instance_loc.clone())
+    }
+}
+
+/// Source text for an expanded template instance.
+#[derive(Debug)]
+pub struct TemplateExpansion {
+    /// The template source file, with the template declaration
+    /// replaced by the expanded declaration.  This is used for
+    /// reporting errors in the expanded element.
+    pub src: NamedSource<String>,
+    /// Text for the parser.  This has the same offsets as `src`, but
+    /// everything before the expanded declaration is blanked and
+    /// everything after it is removed.
+    pub parse_text: String,
+}
+
+impl TemplateDef {
+    /// Substitute the arguments of an instance into this template,
+    /// producing the source text of a policy or rule declaration.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the instance binds an unknown parameter,
+    /// binds a parameter twice, or leaves a parameter unbound.
+    pub fn expand(&self, inst: &TemplateInstance) -> Result<TemplateExpansion, ParseError> {
+        let mut bindings: HashMap<&str, &TemplateArg> = HashMap::new();
+        for a in &inst.args {
+            if !self.params.contains(&a.name) {
+                return Err(SrcError::err_at_both(
+                    "Template instances can only bind declared parameters",
+                    &format!("{:?} is not a parameter of template {:?}", a.name, self.id),
+                    a.src_loc.clone(),
+                    "template declared here",
+                    self.src_loc.clone(),
+                ));
+            }
+            if bindings.insert(&a.name, a).is_some() {
+                return Err(SrcError::err(
+                    "Template parameters can only be bound once",
+                    &format!("{:?} is already bound", a.name),
+                    a.src_loc.clone(),
+                ));
+            }
+        }
+        if let Some(missing) = self
+            .params
+            .iter()
+            .find(|p| !bindings.contains_key(p.as_str()))
+        {
+            return Err(SrcError::err_at_both(
+                "Template instances must bind every parameter",
+                &format!("missing a value for {missing:?}"),
+                inst.src_loc.clone(),
+                "template declared here",
+                self.src_loc.clone(),
+            ));
+        }
+        let src = self.src_loc.get_src();
+        let text = src.inner();
+        let decl_start = self.src_loc.get_span().offset();
+        let body_start = self.body.get_span().offset();
+        let body_end = body_start + self.body.get_span().len();
+        // build the replacement declaration, starting with a header
+        // that names the instance.
+        let mut decl = match &inst.id {
+            Some(id) => format!("{} {} ", self.kind, id),
+            None => format!("{} ", self.kind),
+        };
+        let mut pos = body_start;
+        for r in &self.param_refs {
+            let ref_start = r.src_loc.get_span().offset();
+            let ref_end = ref_start + r.src_loc.get_span().len();
+            decl.push_str(&text[pos..ref_start]);
+            // every reference was checked against the parameter
+            // list when the template was declared.
+            decl.push_str(&bindings[r.name.as_str()].value);
+            pos = ref_end;
+        }
+        decl.push_str(&text[pos..body_end]);
+        // blank out everything before the declaration, keeping the
+        // byte offsets identical.
+        let mut parse_text: String = text[..decl_start]
+            .bytes()
+            .map(|b| if b == b'\n' { '\n' } else { ' ' })
+            .collect();
+        parse_text.push_str(&decl);
+        let mut full_text = text[..decl_start].to_string();
+        full_text.push_str(&decl);
+        full_text.push_str(&text[body_end..]);
+        Ok(TemplateExpansion {
+            src: NamedSource::new(src.name(), full_text).with_language("ALFA"),
+            parse_text,
+        })
+    }
+}
+
+impl AsAlfa for TemplateDef {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        let indent = "  ".repeat(indent_level);
+        // the body is written as it appeared in the source, from the
+        // opening to the closing brace.
+        let src = self.body.get_src();
+        let start = self.body.get_span().offset();
+        let body = &src.inner()[start..start + self.body.get_span().len()];
+        let mut output = alfa_description(self.description.as_ref(), indent_level);
+        output.push_str(&format!(
+            "{indent}template {} {}({}) {body}\n",
+            self.kind,
+            self.id,
+            self.params.join(", ")
+        ));
+        output
+    }
+}
+
+impl AsAlfa for TemplateInstance {
+    fn to_alfa(&self, indent_level: usize) -> String {
+        let indent = "  ".repeat(indent_level);
+        // Ex: instance customerRead = allowRead(role = "customer")
+        let name = match &self.id {
+            Some(id) => format!("{id} = "),
+            None => String::new(),
+        };
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|a| format!("{} = {}", a.name, a.value))
+            .collect();
+        let mut output = alfa_description(self.description.as_ref(), indent_level);
+        output.push_str(&format!(
+            "{indent}instance {name}{}({})\n",
+            self.template,
+            args.join(", ")
+        ));
+        output
+    }
+}
+
+impl QualifiedName for TemplateDef {
+    fn fully_qualified_name(&self) -> Option<String> {
+        let mut qn = self.ns.join(".");
+        qn.push('.');
+        qn.push_str(&self.id);
+        Some(qn.to_string())
+    }
+}
+
+impl PrettyPrint for TemplateDef {
+    fn pretty_print(&self, indent_level: usize) {
+        let indent = "  ".repeat(indent_level);
+        println!("{indent}{self}");
+    }
+}
+
+impl fmt::Display for TemplateDef {
+    // This trait requires `fmt` with this exact signature.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Template: {} \"{}\" ({})",
+            self.kind,
+            self.id,
+            self.params.join(", ")
+        )
+    }
+}
+
+impl fmt::Display for TemplateInstance {
+    // This trait requires `fmt` with this exact signature.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Instance: {:?} of \"{}\"",
+            self.id, self.template
+        )
+    }
+}
@@ -866,3 +866,66 @@ fn test_permit_advice_expression_var() -> Result<(), Box<dyn std::error::Error>>
     );
     Ok(())
 }
+
+#[test]
+fn test_valid_versions() {
+    use version::{is_valid_version, is_valid_version_match};
+    assert!(is_valid_version("1"));
+    assert!(is_valid_version("1.0.12"));
+    assert!(!is_valid_version(""));
+    assert!(!is_valid_version("1."));
+    assert!(!is_valid_version("1.*"));
+    assert!(!is_valid_version("v1"));
+    assert!(is_valid_version_match("1.*.3"));
+    assert!(is_valid_version_match("2.+"));
+    assert!(is_valid_version_match("*"));
+    assert!(!is_valid_version_match("2.+.1"));
+    assert!(!is_valid_version_match("1..2"));
+}
+
+#[test]
+fn test_version_constraints() {
+    use version::VersionConstraints;
+    let c = |version: Option<&str>, earliest: Option<&str>, latest: Option<&str>| {
+        VersionConstraints {
+            version: version.map(str::to_owned),
+            earliest: earliest.map(str::to_owned),
+            latest: latest.map(str::to_owned),
+        }
+    };
+    assert!(c(None, None, None).matches("1.0"));
+    assert!(c(Some("1.*"), None, None).matches("1.2"));
+    assert!(!c(Some("1.*"), None, None).matches("1.2.3"));
+    assert!(c(Some("1.+"), None, None).matches("1.2.3"));
+    assert!(!c(Some("1.0"), None, None).matches("2.0"));
+    assert!(c(None, Some("1.2"), Some("2.+")).matches("2.0.1"));
+    assert!(!c(None, Some("1.3"), None).matches("1.2"));
+    assert!(c(None, Some("1.3"), None).matches("1.10"));
+    assert!(!c(None, None, Some("1.*")).matches("2.0"));
+}
+
+#[test]
+fn test_valid_literals() {
+    use typedef::{
+        is_valid_literal, ANYURI_URI, BOOLEAN_URI, DATETIME_URI, DATE_URI, DOUBLE_URI,
+        INTEGER_URI, STRING_URI, TIME_URI,
+    };
+    assert!(is_valid_literal(INTEGER_URI, "-12"));
+    assert!(!is_valid_literal(INTEGER_URI, "abc"));
+    assert!(!is_valid_literal(INTEGER_URI, "1.5"));
+    assert!(is_valid_literal(DOUBLE_URI, "1.5e3"));
+    assert!(is_valid_literal(DOUBLE_URI, "-INF"));
+    assert!(!is_valid_literal(DOUBLE_URI, "inf"));
+    assert!(is_valid_literal(BOOLEAN_URI, "false"));
+    assert!(!is_valid_literal(BOOLEAN_URI, "yes"));
+    assert!(is_valid_literal(DATE_URI, "2025-07-10"));
+    assert!(is_valid_literal(DATE_URI, "2025-07-10-05:00"));
+    assert!(!is_valid_literal(DATE_URI, "2025-13-10"));
+    assert!(is_valid_literal(TIME_URI, "08:00:00.5Z"));
+    assert!(!is_valid_literal(TIME_URI, "8:00:00"));
+    assert!(is_valid_literal(DATETIME_URI, "2025-07-10T17:00:00+01:00"));
+    assert!(!is_valid_literal(DATETIME_URI, "2025-07-10"));
+    assert!(is_valid_literal(ANYURI_URI, "urn:example:a"));
+    assert!(!is_valid_literal(ANYURI_URI, "not a uri"));
+    assert!(is_valid_literal(STRING_URI, "anything at all"));
+}
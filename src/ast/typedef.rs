@@ -12,9 +12,98 @@ pub const BOOLEAN_URI: &str = "http://www.w3.org/2001/XMLSchema#boolean";
 pub const STRING_URI: &str = "http://www.w3.org/2001/XMLSchema#string";
 pub const INTEGER_URI: &str = "http://www.w3.org/2001/XMLSchema#integer";
 pub const DOUBLE_URI: &str = "http://www.w3.org/2001/XMLSchema#double";
+pub const DATE_URI: &str = "http://www.w3.org/2001/XMLSchema#date";
+pub const TIME_URI: &str = "http://www.w3.org/2001/XMLSchema#time";
+pub const DATETIME_URI: &str = "http://www.w3.org/2001/XMLSchema#dateTime";
+pub const ANYURI_URI: &str = "http://www.w3.org/2001/XMLSchema#anyURI";
+
+/// Check that a string is a valid lexical form for a data type.
+/// Only the primitive XML Schema types are checked; any value is
+/// accepted for other types.
+///
+/// Ex: `-12` for `integer`, `2025-07-10` for `date`
+#[must_use]
+pub fn is_valid_literal(type_uri: &str, value: &str) -> bool {
+    match type_uri {
+        INTEGER_URI => is_integer(value),
+        DOUBLE_URI => {
+            matches!(value, "INF" | "+INF" | "-INF" | "NaN")
+                || (value
+                    .bytes()
+                    .all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b))
+                    && value.parse::<f64>().is_ok())
+        }
+        BOOLEAN_URI => matches!(value, "true" | "false" | "1" | "0"),
+        DATE_URI => without_timezone(value).is_some_and(is_date),
+        TIME_URI => without_timezone(value).is_some_and(is_time),
+        DATETIME_URI => without_timezone(value)
+            .and_then(|v| v.split_once('T'))
+            .is_some_and(|(d, t)| is_date(d) && is_time(t)),
+        ANYURI_URI => !value.chars().any(char::is_whitespace),
+        _ => true,
+    }
+}
+
+fn is_integer(v: &str) -> bool {
+    let digits = v.strip_prefix(['+', '-']).unwrap_or(v);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Whether `v` is a number of exactly `len` digits, within a range.
+fn is_number(v: &str, len: usize, range: std::ops::RangeInclusive<u32>) -> bool {
+    v.len() == len
+        && v.bytes().all(|b| b.is_ascii_digit())
+        && v.parse().is_ok_and(|n| range.contains(&n))
+}
+
+/// A date or time without its optional timezone (`Z` or `+hh:mm`),
+/// or `None` if the timezone is invalid.
+fn without_timezone(v: &str) -> Option<&str> {
+    if let Some(v) = v.strip_suffix('Z') {
+        return Some(v);
+    }
+    match v.len().checked_sub(6).and_then(|i| v.split_at_checked(i)) {
+        Some((rest, tz)) if tz.starts_with(['+', '-']) && tz.get(3..4) == Some(":") => {
+            let valid = tz.get(1..3).is_some_and(|h| is_number(h, 2, 0..=14))
+                && tz.get(4..).is_some_and(|m| is_number(m, 2, 0..=59));
+            valid.then_some(rest)
+        }
+        _ => Some(v),
+    }
+}
+
+/// `[-]yyyy-mm-dd`
+fn is_date(v: &str) -> bool {
+    let v = v.strip_prefix('-').unwrap_or(v);
+    let mut parts = v.split('-');
+    let (Some(y), Some(m), Some(d), None) = (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+    y.len() >= 4
+        && y.bytes().all(|b| b.is_ascii_digit())
+        && is_number(m, 2, 1..=12)
+        && is_number(d, 2, 1..=31)
+}
+
+/// `hh:mm:ss[.s+]`
+fn is_time(v: &str) -> bool {
+    let (v, fraction) = v.split_once('.').unwrap_or((v, "0"));
+    let mut parts = v.split(':');
+    let (Some(h), Some(m), Some(s), None) = (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+    is_number(h, 2, 0..=24)
+        && is_number(m, 2, 0..=59)
+        && is_number(s, 2, 0..=59)
+        && !fraction.is_empty()
+        && fraction.bytes().all(|b| b.is_ascii_digit())
+}
 
 /// A type definition statement
 #[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TypeDef {
     pub id: String,
     pub uri: String,
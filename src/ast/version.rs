@@ -0,0 +1,137 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Policy versions, and version constraints on policy references.
+//!
+//! XACML restricts versions to dot-separated sequences of numbers
+//! (`VersionType`), and version constraints may additionally use `*`
+//! to match any single component, or a final `+` to match that
+//! component or any following it (`VersionMatchType`).
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Version used when neither the policy nor the configuration
+/// specify one.
+pub const DEFAULT_VERSION: &str = "1.0";
+
+/// Check that a string is a valid XACML `VersionType`.
+///
+/// Ex: `1`, `1.0`, `2.10.3`
+#[must_use]
+pub fn is_valid_version(v: &str) -> bool {
+    v.split('.')
+        .all(|c| !c.is_empty() && c.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Check that a string is a valid XACML `VersionMatchType`.
+///
+/// Ex: `1.*`, `1.*.3`, `2.+`
+#[must_use]
+pub fn is_valid_version_match(v: &str) -> bool {
+    let comps: Vec<&str> = v.split('.').collect();
+    let last = comps.len() - 1;
+    comps.iter().enumerate().all(|(i, c)| {
+        *c == "*"
+            || (i == last && *c == "+")
+            || (!c.is_empty() && c.bytes().all(|b| b.is_ascii_digit()))
+    })
+}
+
+/// Constraints on which versions of a policy or policyset a
+/// reference can resolve to.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct VersionConstraints {
+    /// Required version pattern (`Version`)
+    pub version: Option<String>,
+    /// Earliest acceptable version pattern (`EarliestVersion`)
+    pub earliest: Option<String>,
+    /// Latest acceptable version pattern (`LatestVersion`)
+    pub latest: Option<String>,
+}
+
+impl VersionConstraints {
+    /// Check if no constraints are present.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.version.is_none() && self.earliest.is_none() && self.latest.is_none()
+    }
+
+    /// Check if a version satisfies these constraints.
+    ///
+    /// Ex: `1.2` satisfies `version = "1.*"`, and `latest = "2.+"`,
+    /// but not `earliest = "1.3"`
+    #[must_use]
+    pub fn matches(&self, version: &str) -> bool {
+        self.version
+            .as_ref()
+            .is_none_or(|p| compare_to_pattern(version, p) == Ordering::Equal)
+            && self
+                .earliest
+                .as_ref()
+                .is_none_or(|p| compare_to_pattern(version, p) != Ordering::Less)
+            && self
+                .latest
+                .as_ref()
+                .is_none_or(|p| compare_to_pattern(version, p) != Ordering::Greater)
+    }
+
+    /// Combine with another set of constraints, which are used only
+    /// where these constraints are absent.
+    #[must_use]
+    pub fn or(&self, defaults: &VersionConstraints) -> VersionConstraints {
+        VersionConstraints {
+            version: self.version.clone().or_else(|| defaults.version.clone()),
+            earliest: self.earliest.clone().or_else(|| defaults.earliest.clone()),
+            latest: self.latest.clone().or_else(|| defaults.latest.clone()),
+        }
+    }
+}
+
+/// Compare a version with a version pattern, component by component.
+/// A `*` component is equal to any component, and a final `+` is
+/// equal to any remaining components.
+fn compare_to_pattern(version: &str, pattern: &str) -> Ordering {
+    let mut comps = version.split('.');
+    for p in pattern.split('.') {
+        if p == "+" {
+            return Ordering::Equal;
+        }
+        let Some(c) = comps.next() else {
+            return Ordering::Less;
+        };
+        if p == "*" {
+            continue;
+        }
+        let c = c.parse::<u64>().unwrap_or_default();
+        let p = p.parse::<u64>().unwrap_or_default();
+        if c != p {
+            return c.cmp(&p);
+        }
+    }
+    if comps.next().is_some() {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    }
+}
+
+impl fmt::Display for VersionConstraints {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = vec![];
+        if let Some(v) = &self.version {
+            parts.push(format!("version = {v:?}"));
+        }
+        if let Some(v) = &self.earliest {
+            parts.push(format!("earliest = {v:?}"));
+        }
+        if let Some(v) = &self.latest {
+            parts.push(format!("latest = {v:?}"));
+        }
+        if parts.is_empty() {
+            Ok(())
+        } else {
+            write!(f, "({})", parts.join(", "))
+        }
+    }
+}
@@ -0,0 +1,1072 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Build policies in Rust, without writing ALFA source.
+//!
+//! The builders in this module produce the same syntax tree that
+//! parsing ALFA would, and register every declaration in a
+//! [`Context`] as they are built.  The resulting namespaces are
+//! compiled with [`crate::ast_compile`], so names are resolved,
+//! conditions are moved out of policies, and identifiers are
+//! generated exactly as for ALFA source.
+//!
+//! Builders take their arguments by value and return themselves, so
+//! a policy is written as a single expression:
+//!
+//! ```
+//! use a2x::builder::{attr, call, lit, match_op};
+//! use a2x::builder::{NamespaceBuilder, PolicyBuilder, RuleBuilder, TargetBuilder};
+//! use a2x::context::Context;
+//! use std::rc::Rc;
+//!
+//! let ctx = Rc::new(Context::default());
+//! let ns = NamespaceBuilder::new("main")
+//!     .policy(
+//!         PolicyBuilder::new("documents", "firstApplicable")
+//!             .target(TargetBuilder::new().clause(vec![match_op("actionId", "==", "read")]))
+//!             .rule(RuleBuilder::permit().condition(call("stringIsIn", vec![lit("admin"), attr("subjectId")]))),
+//!     )
+//!     .build(&ctx)
+//!     .unwrap();
+//! let tree = a2x::builder::syntax_tree(&ctx, vec![ns]);
+//! assert_eq!(a2x::ast_compile(&ctx, vec![tree]).unwrap().len(), 1);
+//! ```
+//!
+//! Source locations of the built elements are empty, and generated
+//! identifiers from the `hash` strategy are derived from the ALFA
+//! that the element would be written as.
+
+use crate::ast::advice::AdviceDef;
+use crate::ast::attribute::Attribute;
+use crate::ast::combinerparam::CombinerParam;
+use crate::ast::condition::{CondExpression, CondFunctionCall, Condition, FunctionReference};
+use crate::ast::constant::Constant;
+use crate::ast::designator::AttributeDesignator;
+use crate::ast::import::Import;
+use crate::ast::namespace::Namespace;
+use crate::ast::naming::{GenName, canonical_whitespace, content_hash};
+use crate::ast::obligation::ObligationDef;
+use crate::ast::operator::Operator;
+use crate::ast::policy::{Policy, PolicyId, RuleCombiningAlgorithm};
+use crate::ast::policyissuer::IssuerAttribute;
+use crate::ast::policyset::{PolicyCombiningAlgorithm, PolicyEntry, PolicyReference, PolicySet};
+use crate::ast::prescription::{
+    AttrAssignmentSource, AttributeAssignment, Prescription, PrescriptionExpr, PrescriptionType,
+};
+use crate::ast::rule::{Effect, RuleDef, RuleEntry, RuleReference};
+use crate::ast::target::{
+    ConjunctiveSeq, DisjunctiveSeq, Match, MatchFunction, MatchOperation, Target,
+};
+use crate::ast::version::{VersionConstraints, is_valid_version, is_valid_version_match};
+use crate::ast::{AlfaSyntaxTree, AsAlfa, SrcLoc};
+use crate::context::Context;
+use crate::errors::ParseError;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Builds a namespace, and the declarations within it.
+#[derive(Debug, Clone)]
+pub struct NamespaceBuilder {
+    path: Vec<String>,
+    items: Vec<NamespaceItem>,
+}
+
+/// A declaration within a namespace, in the order it was added.
+#[derive(Debug, Clone)]
+enum NamespaceItem {
+    Namespace(NamespaceBuilder),
+    Import(Import),
+    Attribute(Attribute),
+    Obligation(ObligationDef),
+    Advice(AdviceDef),
+    Rule(RuleBuilder),
+    Policy(PolicyBuilder),
+    PolicySet(PolicySetBuilder),
+}
+
+impl NamespaceBuilder {
+    /// A namespace with a (possibly dotted) name.  The name of a
+    /// child namespace is relative to its parent.
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        NamespaceBuilder {
+            path: dotted(name),
+            items: vec![],
+        }
+    }
+
+    /// Add a child namespace.
+    #[must_use]
+    pub fn namespace(mut self, ns: NamespaceBuilder) -> Self {
+        self.items.push(NamespaceItem::Namespace(ns));
+        self
+    }
+
+    /// Import a name, or every name in a namespace if the path ends
+    /// in `.*` (ex: `attr.*`).
+    #[must_use]
+    pub fn import(mut self, path: &str) -> Self {
+        let (path, is_wildcard) = match path.strip_suffix(".*") {
+            Some(p) => (p, true),
+            None => (path, false),
+        };
+        self.items.push(NamespaceItem::Import(Import {
+            components: dotted(path),
+            is_wildcard,
+        }));
+        self
+    }
+
+    /// Declare an attribute with an identifier, type, and category.
+    #[must_use]
+    pub fn attribute(mut self, name: &str, id: &str, typedef: &str, category: &str) -> Self {
+        self.items.push(NamespaceItem::Attribute(Attribute {
+            id: name.to_owned(),
+            typedef: typedef.to_owned(),
+            category: category.to_owned(),
+            uri: id.to_owned(),
+            ns: vec![],
+        }));
+        self
+    }
+
+    /// Declare an obligation with an identifier.
+    #[must_use]
+    pub fn obligation(mut self, name: &str, id: &str) -> Self {
+        self.items.push(NamespaceItem::Obligation(ObligationDef {
+            id: name.to_owned(),
+            uri: id.to_owned(),
+            ns: vec![],
+        }));
+        self
+    }
+
+    /// Declare advice with an identifier.
+    #[must_use]
+    pub fn advice(mut self, name: &str, id: &str) -> Self {
+        self.items.push(NamespaceItem::Advice(AdviceDef {
+            id: name.to_owned(),
+            uri: id.to_owned(),
+            ns: vec![],
+        }));
+        self
+    }
+
+    /// Add a rule, which policies can refer to by name.
+    #[must_use]
+    pub fn rule(mut self, rule: RuleBuilder) -> Self {
+        self.items.push(NamespaceItem::Rule(rule));
+        self
+    }
+
+    /// Add a policy.
+    #[must_use]
+    pub fn policy(mut self, policy: PolicyBuilder) -> Self {
+        self.items.push(NamespaceItem::Policy(policy));
+        self
+    }
+
+    /// Add a policyset.
+    #[must_use]
+    pub fn policyset(mut self, policyset: PolicySetBuilder) -> Self {
+        self.items.push(NamespaceItem::PolicySet(policyset));
+        self
+    }
+
+    /// Build the namespace, registering its declarations in the
+    /// context.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a declaration is invalid, or duplicates a
+    /// symbol already in the context.
+    pub fn build(self, ctx: &Rc<Context>) -> Result<Namespace, ParseError> {
+        self.build_in(&[], ctx)
+    }
+
+    fn build_in(self, parent: &[String], ctx: &Rc<Context>) -> Result<Namespace, ParseError> {
+        let mut path = parent.to_vec();
+        path.extend(self.path);
+        let mut ns = Namespace::from_components(path.clone(), ctx.clone());
+        for item in self.items {
+            match item {
+                NamespaceItem::Namespace(n) => ns.add_namespace(n.build_in(&path, ctx)?),
+                NamespaceItem::Import(i) => ns.add_import(i),
+                NamespaceItem::Attribute(a) => ns.add_attribute(Attribute {
+                    ns: path.clone(),
+                    ..a
+                })?,
+                NamespaceItem::Obligation(o) => ns.add_obligation(ObligationDef {
+                    ns: path.clone(),
+                    ..o
+                })?,
+                NamespaceItem::Advice(a) => ns.add_advice(AdviceDef {
+                    ns: path.clone(),
+                    ..a
+                })?,
+                NamespaceItem::Rule(r) => {
+                    no_entry_params(&r.entry_params)?;
+                    ns.add_rule(r.build(&path, GenName::default(), ctx)?)?;
+                }
+                NamespaceItem::Policy(p) => {
+                    no_entry_params(&p.common.entry_params)?;
+                    ns.add_policy(p.build(&path, GenName::default(), true, ctx)?)?;
+                }
+                NamespaceItem::PolicySet(p) => {
+                    no_entry_params(&p.common.entry_params)?;
+                    ns.add_policyset(p.build(&path, GenName::default(), true, ctx)?)?;
+                }
+            }
+        }
+        Ok(ns)
+    }
+}
+
+/// A syntax tree of built namespaces, which can be compiled with
+/// [`crate::ast_compile`].
+#[must_use]
+pub fn syntax_tree(ctx: &Rc<Context>, namespaces: Vec<Namespace>) -> AlfaSyntaxTree {
+    AlfaSyntaxTree {
+        namespaces,
+        ctx: ctx.clone(),
+    }
+}
+
+/// Statements shared by policies and policysets.
+#[derive(Debug, Clone, Default)]
+struct PolicyCommon {
+    name: Option<String>,
+    id: Option<String>,
+    description: Option<String>,
+    version: Option<String>,
+    issuer: Vec<(String, Constant)>,
+    max_delegation_depth: Option<u32>,
+    target: Option<TargetBuilder>,
+    condition: Option<CondExpression>,
+    apply: String,
+    apply_params: Vec<(String, Constant)>,
+    entry_params: Vec<(String, Constant)>,
+    prescriptions: Vec<(Effect, PrescriptionBuilder)>,
+}
+
+impl PolicyCommon {
+    fn new(name: Option<&str>, apply: &str) -> Self {
+        PolicyCommon {
+            name: name.map(str::to_owned),
+            apply: apply.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn policy_id(&self) -> Result<PolicyId, ParseError> {
+        match (&self.name, &self.id) {
+            (None, None) => Ok(PolicyId::PolicyNoName),
+            (Some(n), None) => Ok(PolicyId::PolicyName(n.clone())),
+            (Some(n), Some(i)) => Ok(PolicyId::PolicyNameAndId(n.clone(), i.clone())),
+            (None, Some(i)) => Err(ParseError::BuildError(format!(
+                "anonymous policies cannot have an identifier ({i:?})"
+            ))),
+        }
+    }
+
+    fn checked_version(&self) -> Result<Option<String>, ParseError> {
+        match &self.version {
+            Some(v) if !is_valid_version(v) => Err(ParseError::InvalidVersion(v.clone())),
+            v => Ok(v.clone()),
+        }
+    }
+
+    fn issuer(&self) -> Vec<IssuerAttribute> {
+        self.issuer
+            .iter()
+            .map(|(attribute, value)| IssuerAttribute {
+                src_loc: SrcLoc::default(),
+                attribute: attribute.clone(),
+                value: value.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Builds a policy, made up of rules.
+#[derive(Debug, Clone)]
+pub struct PolicyBuilder {
+    common: PolicyCommon,
+    rules: Vec<RuleItem>,
+}
+
+/// A rule within a policy.
+#[derive(Debug, Clone)]
+enum RuleItem {
+    Def(RuleBuilder),
+    Ref(String, Vec<(String, Constant)>),
+}
+
+impl PolicyBuilder {
+    /// A named policy, combining its rules with the given algorithm
+    /// (ex: `firstApplicable`).
+    #[must_use]
+    pub fn new(name: &str, apply: &str) -> Self {
+        PolicyBuilder {
+            common: PolicyCommon::new(Some(name), apply),
+            rules: vec![],
+        }
+    }
+
+    /// A policy without a name, which cannot be referred to.
+    #[must_use]
+    pub fn anonymous(apply: &str) -> Self {
+        PolicyBuilder {
+            common: PolicyCommon::new(None, apply),
+            rules: vec![],
+        }
+    }
+
+    /// Set the XACML `PolicyId`, instead of generating one.
+    #[must_use]
+    pub fn id(mut self, id: &str) -> Self {
+        self.common.id = Some(id.to_owned());
+        self
+    }
+
+    /// Set the description.
+    #[must_use]
+    pub fn description(mut self, description: &str) -> Self {
+        self.common.description = Some(description.to_owned());
+        self
+    }
+
+    /// Set the version (ex: `1.2`).
+    #[must_use]
+    pub fn version(mut self, version: &str) -> Self {
+        self.common.version = Some(version.to_owned());
+        self
+    }
+
+    /// Add an attribute of the policy issuer.
+    #[must_use]
+    pub fn issuer(mut self, attribute: &str, value: impl Into<Constant>) -> Self {
+        self.common
+            .issuer
+            .push((attribute.to_owned(), value.into()));
+        self
+    }
+
+    /// Set the maximum delegation depth.
+    #[must_use]
+    pub fn max_delegation_depth(mut self, depth: u32) -> Self {
+        self.common.max_delegation_depth = Some(depth);
+        self
+    }
+
+    /// Set the target.
+    #[must_use]
+    pub fn target(mut self, target: TargetBuilder) -> Self {
+        self.common.target = Some(target);
+        self
+    }
+
+    /// Set the condition.
+    #[must_use]
+    pub fn condition(mut self, condition: CondExpression) -> Self {
+        self.common.condition = Some(condition);
+        self
+    }
+
+    /// Add a parameter for the rule-combining algorithm.
+    #[must_use]
+    pub fn apply_param(mut self, name: &str, value: impl Into<Constant>) -> Self {
+        self.common
+            .apply_params
+            .push((name.to_owned(), value.into()));
+        self
+    }
+
+    /// Add a parameter for the combining algorithm of the parent
+    /// policyset.
+    #[must_use]
+    pub fn with_param(mut self, name: &str, value: impl Into<Constant>) -> Self {
+        self.common
+            .entry_params
+            .push((name.to_owned(), value.into()));
+        self
+    }
+
+    /// Add a rule.
+    #[must_use]
+    pub fn rule(mut self, rule: RuleBuilder) -> Self {
+        self.rules.push(RuleItem::Def(rule));
+        self
+    }
+
+    /// Add a reference to a rule declared in a namespace, by a
+    /// (possibly qualified) name.
+    #[must_use]
+    pub fn rule_ref(mut self, name: &str) -> Self {
+        self.rules.push(RuleItem::Ref(name.to_owned(), vec![]));
+        self
+    }
+
+    /// Add an obligation or advice for a permit decision.
+    #[must_use]
+    pub fn on_permit(mut self, p: PrescriptionBuilder) -> Self {
+        self.common.prescriptions.push((Effect::Permit, p));
+        self
+    }
+
+    /// Add an obligation or advice for a deny decision.
+    #[must_use]
+    pub fn on_deny(mut self, p: PrescriptionBuilder) -> Self {
+        self.common.prescriptions.push((Effect::Deny, p));
+        self
+    }
+
+    fn build(
+        self,
+        ns: &[String],
+        mut parent_policy_path: GenName,
+        register: bool,
+        ctx: &Rc<Context>,
+    ) -> Result<Policy, ParseError> {
+        let c = self.common;
+        let id = c.policy_id()?;
+        // only register rules if this policy, and every parent, has a
+        // name.
+        let do_register = id != PolicyId::PolicyNoName && register;
+        parent_policy_path.push_name(Rc::new(RefCell::new(id.get_name())));
+        let mut rules = vec![];
+        for r in self.rules {
+            match r {
+                RuleItem::Def(r) => {
+                    let rule = Rc::new(r.build(ns, parent_policy_path.clone(), ctx)?);
+                    if rule.id.is_some() && do_register {
+                        ctx.register_rule(rule.clone())?;
+                    }
+                    rules.push(RuleEntry::Def(rule));
+                }
+                RuleItem::Ref(name, params) => {
+                    let (rule_ns, rule_id) = split_name(&name);
+                    rules.push(RuleEntry::Ref(RuleReference {
+                        id: rule_id,
+                        ns: rule_ns,
+                        src_loc: SrcLoc::default(),
+                        entry_params: combiner_params(params)?,
+                    }));
+                }
+            }
+        }
+        let mut policy = Policy {
+            id,
+            ns: ns.to_vec(),
+            policy_ns: parent_policy_path,
+            src_loc: SrcLoc::default(),
+            description: None,
+            version: c.checked_version()?,
+            issuer: c.issuer(),
+            max_delegation_depth: c.max_delegation_depth,
+            entry_params: combiner_params(c.entry_params)?,
+            target: c.target.map(|t| t.build(ns, ctx)),
+            condition: c.condition.map(|e| condition(e, ns, ctx)),
+            apply: RuleCombiningAlgorithm {
+                id: c.apply,
+                src_loc: SrcLoc::default(),
+                params: combiner_params(c.apply_params)?,
+            },
+            rules,
+            prescriptions: prescriptions(c.prescriptions, ns, ctx),
+            content_hash: String::new(),
+            expansion: None,
+            ctx: ctx.clone(),
+        };
+        policy.content_hash = alfa_hash(&policy);
+        policy.description = c.description;
+        Ok(policy)
+    }
+}
+
+/// Builds a policyset, made up of policies and policysets.
+#[derive(Debug, Clone)]
+pub struct PolicySetBuilder {
+    common: PolicyCommon,
+    policies: Vec<PolicyItem>,
+}
+
+/// A policy or policyset within a policyset.
+#[derive(Debug, Clone)]
+enum PolicyItem {
+    Policy(PolicyBuilder),
+    PolicySet(PolicySetBuilder),
+    Ref(String, VersionConstraints),
+}
+
+impl PolicySetBuilder {
+    /// A named policyset, combining its policies with the given
+    /// algorithm (ex: `denyOverrides`).
+    #[must_use]
+    pub fn new(name: &str, apply: &str) -> Self {
+        PolicySetBuilder {
+            common: PolicyCommon::new(Some(name), apply),
+            policies: vec![],
+        }
+    }
+
+    /// A policyset without a name, which cannot be referred to.
+    #[must_use]
+    pub fn anonymous(apply: &str) -> Self {
+        PolicySetBuilder {
+            common: PolicyCommon::new(None, apply),
+            policies: vec![],
+        }
+    }
+
+    /// Set the XACML `PolicySetId`, instead of generating one.
+    #[must_use]
+    pub fn id(mut self, id: &str) -> Self {
+        self.common.id = Some(id.to_owned());
+        self
+    }
+
+    /// Set the description.
+    #[must_use]
+    pub fn description(mut self, description: &str) -> Self {
+        self.common.description = Some(description.to_owned());
+        self
+    }
+
+    /// Set the version (ex: `1.2`).
+    #[must_use]
+    pub fn version(mut self, version: &str) -> Self {
+        self.common.version = Some(version.to_owned());
+        self
+    }
+
+    /// Add an attribute of the policyset issuer.
+    #[must_use]
+    pub fn issuer(mut self, attribute: &str, value: impl Into<Constant>) -> Self {
+        self.common
+            .issuer
+            .push((attribute.to_owned(), value.into()));
+        self
+    }
+
+    /// Set the maximum delegation depth.
+    #[must_use]
+    pub fn max_delegation_depth(mut self, depth: u32) -> Self {
+        self.common.max_delegation_depth = Some(depth);
+        self
+    }
+
+    /// Set the target.
+    #[must_use]
+    pub fn target(mut self, target: TargetBuilder) -> Self {
+        self.common.target = Some(target);
+        self
+    }
+
+    /// Set the condition.
+    #[must_use]
+    pub fn condition(mut self, condition: CondExpression) -> Self {
+        self.common.condition = Some(condition);
+        self
+    }
+
+    /// Add a parameter for the policy-combining algorithm.
+    #[must_use]
+    pub fn apply_param(mut self, name: &str, value: impl Into<Constant>) -> Self {
+        self.common
+            .apply_params
+            .push((name.to_owned(), value.into()));
+        self
+    }
+
+    /// Add a parameter for the combining algorithm of the parent
+    /// policyset.
+    #[must_use]
+    pub fn with_param(mut self, name: &str, value: impl Into<Constant>) -> Self {
+        self.common
+            .entry_params
+            .push((name.to_owned(), value.into()));
+        self
+    }
+
+    /// Add a policy.
+    #[must_use]
+    pub fn policy(mut self, policy: PolicyBuilder) -> Self {
+        self.policies.push(PolicyItem::Policy(policy));
+        self
+    }
+
+    /// Add a child policyset.
+    #[must_use]
+    pub fn policyset(mut self, policyset: PolicySetBuilder) -> Self {
+        self.policies.push(PolicyItem::PolicySet(policyset));
+        self
+    }
+
+    /// Add a reference to a policy or policyset, by a (possibly
+    /// qualified) name.
+    #[must_use]
+    pub fn policy_ref(self, name: &str) -> Self {
+        self.policy_ref_versions(name, VersionConstraints::default())
+    }
+
+    /// Add a reference to a policy or policyset, constrained to
+    /// matching versions.
+    #[must_use]
+    pub fn policy_ref_versions(mut self, name: &str, versions: VersionConstraints) -> Self {
+        self.policies
+            .push(PolicyItem::Ref(name.to_owned(), versions));
+        self
+    }
+
+    /// Add an obligation or advice for a permit decision.
+    #[must_use]
+    pub fn on_permit(mut self, p: PrescriptionBuilder) -> Self {
+        self.common.prescriptions.push((Effect::Permit, p));
+        self
+    }
+
+    /// Add an obligation or advice for a deny decision.
+    #[must_use]
+    pub fn on_deny(mut self, p: PrescriptionBuilder) -> Self {
+        self.common.prescriptions.push((Effect::Deny, p));
+        self
+    }
+
+    fn build(
+        self,
+        ns: &[String],
+        mut parent_policy_path: GenName,
+        register: bool,
+        ctx: &Rc<Context>,
+    ) -> Result<PolicySet, ParseError> {
+        let c = self.common;
+        let id = c.policy_id()?;
+        let do_register = id != PolicyId::PolicyNoName && register;
+        parent_policy_path.push_name(Rc::new(RefCell::new(id.get_name())));
+        let mut policies = vec![];
+        for p in self.policies {
+            policies.push(match p {
+                PolicyItem::Policy(p) => PolicyEntry::Policy(p.build(
+                    ns,
+                    parent_policy_path.clone(),
+                    do_register,
+                    ctx,
+                )?),
+                PolicyItem::PolicySet(p) => PolicyEntry::PolicySet(p.build(
+                    ns,
+                    parent_policy_path.clone(),
+                    do_register,
+                    ctx,
+                )?),
+                PolicyItem::Ref(name, versions) => {
+                    for v in [&versions.version, &versions.earliest, &versions.latest]
+                        .into_iter()
+                        .flatten()
+                    {
+                        if !is_valid_version_match(v) {
+                            return Err(ParseError::BuildError(format!(
+                                "{v:?} is not a valid version constraint"
+                            )));
+                        }
+                    }
+                    let (policy_ns, policy_id) = split_name(&name);
+                    PolicyEntry::Ref(PolicyReference {
+                        id: policy_id,
+                        ns: policy_ns,
+                        versions,
+                        entry_params: vec![],
+                    })
+                }
+            });
+        }
+        let mut policyset = PolicySet {
+            id,
+            ns: ns.to_vec(),
+            policy_ns: parent_policy_path,
+            src_loc: SrcLoc::default(),
+            description: None,
+            version: c.checked_version()?,
+            issuer: c.issuer(),
+            max_delegation_depth: c.max_delegation_depth,
+            entry_params: combiner_params(c.entry_params)?,
+            apply: PolicyCombiningAlgorithm {
+                id: c.apply,
+                src_loc: SrcLoc::default(),
+                params: combiner_params(c.apply_params)?,
+            },
+            target: c.target.map(|t| t.build(ns, ctx)),
+            condition: c.condition.map(|e| condition(e, ns, ctx)),
+            policies,
+            prescriptions: prescriptions(c.prescriptions, ns, ctx),
+            content_hash: String::new(),
+            ctx: ctx.clone(),
+        };
+        policyset.content_hash = alfa_hash(&policyset);
+        policyset.description = c.description;
+        Ok(policyset)
+    }
+}
+
+/// Builds a rule.
+#[derive(Debug, Clone)]
+pub struct RuleBuilder {
+    name: Option<String>,
+    description: Option<String>,
+    effect: Effect,
+    target: Option<TargetBuilder>,
+    condition: Option<CondExpression>,
+    prescriptions: Vec<(Effect, PrescriptionBuilder)>,
+    entry_params: Vec<(String, Constant)>,
+}
+
+impl RuleBuilder {
+    /// An anonymous rule with the given effect.
+    #[must_use]
+    pub fn new(effect: Effect) -> Self {
+        RuleBuilder {
+            name: None,
+            description: None,
+            effect,
+            target: None,
+            condition: None,
+            prescriptions: vec![],
+            entry_params: vec![],
+        }
+    }
+
+    /// An anonymous rule that permits.
+    #[must_use]
+    pub fn permit() -> Self {
+        Self::new(Effect::Permit)
+    }
+
+    /// An anonymous rule that denies.
+    #[must_use]
+    pub fn deny() -> Self {
+        Self::new(Effect::Deny)
+    }
+
+    /// Set the name, so that the rule can be referred to.
+    #[must_use]
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    /// Set the description.
+    #[must_use]
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_owned());
+        self
+    }
+
+    /// Set the target.
+    #[must_use]
+    pub fn target(mut self, target: TargetBuilder) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    /// Set the condition.
+    #[must_use]
+    pub fn condition(mut self, condition: CondExpression) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    /// Add a parameter for the combining algorithm of the parent
+    /// policy.
+    #[must_use]
+    pub fn with_param(mut self, name: &str, value: impl Into<Constant>) -> Self {
+        self.entry_params.push((name.to_owned(), value.into()));
+        self
+    }
+
+    /// Add an obligation or advice for a permit decision.
+    #[must_use]
+    pub fn on_permit(mut self, p: PrescriptionBuilder) -> Self {
+        self.prescriptions.push((Effect::Permit, p));
+        self
+    }
+
+    /// Add an obligation or advice for a deny decision.
+    #[must_use]
+    pub fn on_deny(mut self, p: PrescriptionBuilder) -> Self {
+        self.prescriptions.push((Effect::Deny, p));
+        self
+    }
+
+    fn build(
+        self,
+        ns: &[String],
+        policy_ns: GenName,
+        ctx: &Rc<Context>,
+    ) -> Result<RuleDef, ParseError> {
+        let mut rule = RuleDef {
+            id: self.name,
+            ns: ns.to_vec(),
+            policy_ns,
+            description: None,
+            effect: self.effect,
+            target: self.target.map(|t| t.build(ns, ctx)),
+            condition: self.condition.map(|e| condition(e, ns, ctx)),
+            prescriptions: prescriptions(self.prescriptions, ns, ctx),
+            entry_params: combiner_params(self.entry_params)?,
+            content_hash: String::new(),
+            src_loc: SrcLoc::default(),
+            expansion: None,
+            ctx: Rc::downgrade(ctx),
+        };
+        rule.content_hash = alfa_hash(&rule);
+        rule.description = self.description;
+        Ok(rule)
+    }
+}
+
+/// Builds a target, from clauses that must all match.
+#[derive(Debug, Clone, Default)]
+pub struct TargetBuilder {
+    clauses: Vec<DisjunctiveSeq>,
+}
+
+impl TargetBuilder {
+    /// A target without clauses, which matches every request.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a clause that requires every one of the matches.
+    #[must_use]
+    pub fn clause(self, matches: Vec<Match>) -> Self {
+        self.clause_any(vec![matches])
+    }
+
+    /// Add a clause that requires every one of the matches, from any
+    /// one of the alternatives.
+    #[must_use]
+    pub fn clause_any(mut self, alternatives: Vec<Vec<Match>>) -> Self {
+        self.clauses.push(DisjunctiveSeq {
+            statements: alternatives
+                .into_iter()
+                .map(|matches| ConjunctiveSeq { matches })
+                .collect(),
+            src_loc: SrcLoc::default(),
+        });
+        self
+    }
+
+    fn build(self, ns: &[String], ctx: &Rc<Context>) -> Target {
+        Target {
+            clauses: self.clauses,
+            ns: ns.to_vec(),
+            src_loc: SrcLoc::default(),
+            ctx: Rc::downgrade(ctx),
+        }
+    }
+}
+
+/// Builds an obligation or advice, with attribute assignments.
+#[derive(Debug, Clone)]
+pub struct PrescriptionBuilder {
+    expr: PrescriptionExpr,
+}
+
+impl PrescriptionBuilder {
+    /// An obligation, by (possibly qualified) name.
+    #[must_use]
+    pub fn obligation(name: &str) -> Self {
+        Self::new(PrescriptionType::Obligation, name)
+    }
+
+    /// Advice, by (possibly qualified) name.
+    #[must_use]
+    pub fn advice(name: &str) -> Self {
+        Self::new(PrescriptionType::Advice, name)
+    }
+
+    fn new(ptype: PrescriptionType, name: &str) -> Self {
+        PrescriptionBuilder {
+            expr: PrescriptionExpr {
+                ptype,
+                id: name.to_owned(),
+                assignments: vec![],
+            },
+        }
+    }
+
+    /// Assign the values of an attribute from the request.
+    #[must_use]
+    pub fn attribute(mut self, destination: &str, source: impl Into<AttributeDesignator>) -> Self {
+        self.expr.assignments.push(AttributeAssignment {
+            destination_id: destination.to_owned(),
+            source: AttrAssignmentSource::Attribute(source.into()),
+        });
+        self
+    }
+
+    /// Assign a literal value.
+    #[must_use]
+    pub fn value(mut self, destination: &str, value: impl Into<Constant>) -> Self {
+        self.expr.assignments.push(AttributeAssignment {
+            destination_id: destination.to_owned(),
+            source: AttrAssignmentSource::Value(value.into()),
+        });
+        self
+    }
+}
+
+/// A target match of an attribute against a literal, with an
+/// operator (ex: `match_op("actionId", "==", "read")`).
+#[must_use]
+pub fn match_op(
+    attribute: impl Into<AttributeDesignator>,
+    operator: &str,
+    literal: impl Into<Constant>,
+) -> Match {
+    let attr = attribute.into();
+    Match::MatchOp(MatchOperation {
+        attribute: attr.attribute,
+        operator: operator_named(operator),
+        literal: literal.into(),
+        reversed: true,
+        issuer: attr.issuer,
+        mustbepresent: attr.mustbepresent,
+    })
+}
+
+/// A target match of a literal and an attribute, with a function
+/// (ex: `match_fn("stringStartsWith", "/docs", "resourceId")`).
+#[must_use]
+pub fn match_fn(
+    function: &str,
+    literal: impl Into<Constant>,
+    attribute: impl Into<AttributeDesignator>,
+) -> Match {
+    let attr = attribute.into();
+    Match::MatchFunc(MatchFunction {
+        function_id: dotted(function),
+        literal: literal.into(),
+        attribute: attr.attribute,
+        issuer: attr.issuer,
+        mustbepresent: attr.mustbepresent,
+    })
+}
+
+/// An attribute in a condition.
+#[must_use]
+pub fn attr(attribute: impl Into<AttributeDesignator>) -> CondExpression {
+    CondExpression::Attr(attribute.into())
+}
+
+/// A literal in a condition.
+#[must_use]
+pub fn lit(value: impl Into<Constant>) -> CondExpression {
+    CondExpression::Lit(value.into())
+}
+
+/// A function call in a condition.
+#[must_use]
+pub fn call(function: &str, arguments: Vec<CondExpression>) -> CondExpression {
+    CondExpression::Fn(CondFunctionCall {
+        identifier: dotted(function),
+        arguments,
+    })
+}
+
+/// An operator applied to two expressions in a condition (ex:
+/// `infix(attr("age"), ">=", lit(18))`).
+#[must_use]
+pub fn infix(lhs: CondExpression, operator: &str, rhs: CondExpression) -> CondExpression {
+    CondExpression::Infix(Box::new(lhs), operator_named(operator), Box::new(rhs))
+}
+
+/// A function passed as an argument in a condition.
+#[must_use]
+pub fn function_ref(function: &str) -> CondExpression {
+    CondExpression::FnRef(FunctionReference {
+        identifier: dotted(function),
+    })
+}
+
+fn condition(cond_expr: CondExpression, ns: &[String], ctx: &Rc<Context>) -> Condition {
+    Condition {
+        cond_expr,
+        ns: ns.to_vec(),
+        src_loc: SrcLoc::default(),
+        ctx: Rc::downgrade(ctx),
+    }
+}
+
+/// Group obligations and advice into one `on` block per effect.
+fn prescriptions(
+    items: Vec<(Effect, PrescriptionBuilder)>,
+    ns: &[String],
+    ctx: &Rc<Context>,
+) -> Vec<Prescription> {
+    let mut result: Vec<Prescription> = vec![];
+    for (effect, p) in items {
+        match result.iter_mut().find(|x| x.effect == effect) {
+            Some(x) => x.expressions.push(p.expr),
+            None => result.push(Prescription {
+                effect,
+                ns: ns.to_vec(),
+                expressions: vec![p.expr],
+                ctx: Rc::downgrade(ctx),
+            }),
+        }
+    }
+    result
+}
+
+/// Convert combiner parameters, ensuring each is given at most once.
+fn combiner_params(params: Vec<(String, Constant)>) -> Result<Vec<CombinerParam>, ParseError> {
+    let mut result: Vec<CombinerParam> = vec![];
+    for (name, value) in params {
+        if result.iter().any(|c| c.name == name) {
+            return Err(ParseError::BuildError(format!(
+                "combiner parameter {name:?} was given more than once"
+            )));
+        }
+        result.push(CombinerParam {
+            src_loc: SrcLoc::default(),
+            name,
+            value,
+        });
+    }
+    Ok(result)
+}
+
+/// Ensure that a top-level rule, policy, or policyset has no
+/// parameters for a parent combining algorithm.
+fn no_entry_params(params: &[(String, Constant)]) -> Result<(), ParseError> {
+    match params.first() {
+        Some((name, _)) => Err(ParseError::BuildError(format!(
+            "combiner parameter {name:?} needs a parent policy or policyset"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Content hash of an element, from the ALFA it would be written
+/// as.
+fn alfa_hash(elem: &impl AsAlfa) -> String {
+    content_hash(&canonical_whitespace(&elem.to_alfa(0)))
+}
+
+fn operator_named(name: &str) -> Operator {
+    let (ns, operator) = split_name(name);
+    Operator { ns, operator }
+}
+
+/// Split a qualified name into the namespace and the final name.
+fn split_name(name: &str) -> (Vec<String>, String) {
+    let mut path = dotted(name);
+    let last = path.pop().unwrap_or_default();
+    (path, last)
+}
+
+fn dotted(name: &str) -> Vec<String> {
+    name.split('.').map(str::to_owned).collect()
+}
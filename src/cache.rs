@@ -0,0 +1,456 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Incremental compilation, with a cache of results for each ALFA
+//! source.
+//!
+//! The cache directory holds a record for every source of the last
+//! successful compilation: a hash of its contents, the names it
+//! defined and looked up (see [`SymbolLog`]), the IDs it generated,
+//! and the XACML it was converted into.
+//!
+//! On the next compilation, a source is converted again if its
+//! contents changed, or if it looked up a name defined by a source
+//! that is converted again (or that used to define it).  Sources
+//! defining names that those conversions look up are parsed, but
+//! not converted.  Everything else comes from the cache.
+//!
+//! IDs of anonymous rules, policies, and policysets come from
+//! counters shared between sources.  Each record keeps the counter
+//! values it started from, and how far it advanced them.  Counters
+//! are restored before a source is converted, and a source whose
+//! starting counters moved is converted again, so that IDs are the
+//! same as in a clean build.
+//!
+//! Results are only reused by the same version of a2x, with the same
+//! configuration.
+//!
+//! [`SymbolLog`]: crate::context::SymbolLog
+
+use crate::ast::AlfaSyntaxTree;
+use crate::ast::naming::hash_bytes;
+use crate::ast::version::is_valid_version;
+use crate::context::{Config, Context};
+use crate::errors::ParseError;
+use crate::xacml::XTopPolicy;
+use crate::{AlfaFile, annotate_parse_error, convert_policy, convert_policyset, make_alfa_ast};
+use log::{info, warn};
+use miette::NamedSource;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Filename of the cache, within the cache directory.
+pub const CACHE_FILENAME: &str = "a2x-cache.json";
+
+/// Format of the cache file.
+const CACHE_VERSION: u32 = 1;
+
+/// Contents of the cache file.
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+struct CacheIndex {
+    version: u32,
+    /// a2x version and configuration the results were compiled with.
+    fingerprint: String,
+    /// Records in filename order.
+    sources: Vec<SourceRecord>,
+}
+
+/// What was learned about an ALFA source by compiling it.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+struct SourceRecord {
+    filename: String,
+    /// Hash of the contents.
+    hash: String,
+    /// Names defined by the source.
+    defined: BTreeSet<String>,
+    /// Names looked up while parsing and converting the source.
+    probed: BTreeSet<String>,
+    /// IDs generated while converting policysets, then policies.
+    ids: [IdRange; 2],
+    /// Converted policysets, then policies.
+    outputs: [Vec<XTopPolicy>; 2],
+}
+
+/// IDs generated by converting part of a source.
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+struct IdRange {
+    /// Value of each counter used, before conversion.
+    start: BTreeMap<String, usize>,
+    /// Number of IDs taken from each counter.
+    used: BTreeMap<String, usize>,
+}
+
+/// How the sources of the last compilation were handled.  Each list
+/// is in filename order.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct CacheStats {
+    /// Sources that were parsed and converted.
+    pub converted: Vec<String>,
+    /// Sources that were parsed for their definitions, but whose
+    /// XACML came from the cache.
+    pub parsed: Vec<String>,
+    /// Sources taken entirely from the cache.
+    pub reused: Vec<String>,
+}
+
+/// Sources to parse and to convert.  Both only grow, as compiling
+/// finds more sources that are needed.
+#[derive(Debug, Default)]
+struct Plan {
+    parse: BTreeSet<usize>,
+    convert: BTreeSet<usize>,
+}
+
+/// Result of one attempt at compiling with a plan.
+enum Attempt {
+    /// Records for every source, in order.
+    Done(Vec<SourceRecord>),
+    /// The plan grew, and compiling must start again.
+    Grow,
+}
+
+/// Everything recorded about a source while compiling it.
+#[derive(Default)]
+struct Compiled {
+    defined: BTreeSet<String>,
+    probed: BTreeSet<String>,
+    ids: [IdRange; 2],
+    outputs: [Vec<XTopPolicy>; 2],
+}
+
+/// A cache of compilation results, in a directory.
+#[derive(Debug)]
+pub struct BuildCache {
+    dir: PathBuf,
+    config: Config,
+    index: CacheIndex,
+    stats: CacheStats,
+}
+
+impl BuildCache {
+    /// Open the cache in a directory, for compiling with a
+    /// configuration.  A cache that is missing, unreadable, or was
+    /// written by another version or configuration starts empty.
+    #[must_use]
+    pub fn open(dir: &Path, config: Config) -> BuildCache {
+        let fingerprint = format!("a2x {} {config:?}", env!("CARGO_PKG_VERSION"));
+        let path = dir.join(CACHE_FILENAME);
+        let index = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice::<CacheIndex>(&bytes).unwrap_or_else(|e| {
+                warn!("ignoring unreadable cache {}: {e}", path.display());
+                CacheIndex::default()
+            }),
+            Err(_) => CacheIndex::default(),
+        };
+        let index = if index.version == CACHE_VERSION && index.fingerprint == fingerprint {
+            index
+        } else {
+            info!("cache {} does not match, starting empty", path.display());
+            CacheIndex {
+                version: CACHE_VERSION,
+                fingerprint,
+                sources: vec![],
+            }
+        };
+        BuildCache {
+            dir: dir.to_path_buf(),
+            config,
+            index,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// How the sources of the last compilation were handled.
+    #[must_use]
+    pub fn stats(&self) -> &CacheStats {
+        &self.stats
+    }
+
+    /// Compile ALFA sources, as [`crate::alfa_compile`] does, reusing
+    /// cached results where the sources allow.  The cache is updated
+    /// if compilation succeeds, but not written.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the conversion fails for syntactical or
+    /// semantic reasons.
+    pub fn compile(&mut self, mut sources: Vec<AlfaFile>) -> Result<Vec<XTopPolicy>, ParseError> {
+        sources.sort_by(|a, b| a.filename.cmp(&b.filename));
+        if let Some(v) = &self.config.version
+            && !is_valid_version(v)
+        {
+            return Err(ParseError::InvalidVersion(v.clone()));
+        }
+        let cached: HashMap<&str, &SourceRecord> = self
+            .index
+            .sources
+            .iter()
+            .map(|r| (r.filename.as_str(), r))
+            .collect();
+        // records of sources that did not change
+        let records: Vec<Option<&SourceRecord>> = sources
+            .iter()
+            .map(|s| {
+                let hash = hash_bytes(s.contents.as_bytes());
+                cached
+                    .get(s.filename.as_str())
+                    .filter(|r| r.hash == hash)
+                    .copied()
+            })
+            .collect();
+        // names that were defined by sources that changed, or were
+        // removed.
+        let unchanged: BTreeSet<&str> = records
+            .iter()
+            .flatten()
+            .map(|r| r.filename.as_str())
+            .collect();
+        let mut moved = BTreeSet::new();
+        for r in &self.index.sources {
+            if !unchanged.contains(r.filename.as_str()) {
+                moved.extend(r.defined.iter().cloned());
+            }
+        }
+        let changed: BTreeSet<usize> = (0..sources.len())
+            .filter(|&i| records[i].is_none())
+            .collect();
+        let mut plan = Plan {
+            parse: changed.clone(),
+            convert: changed,
+        };
+        let compiled = loop {
+            match self.attempt(&sources, &records, &moved, &mut plan)? {
+                Attempt::Done(compiled) => break compiled,
+                Attempt::Grow => info!(
+                    "compiling again, parsing {} and converting {} sources",
+                    plan.parse.len(),
+                    plan.convert.len()
+                ),
+            }
+        };
+        let mut stats = CacheStats::default();
+        for (i, s) in sources.iter().enumerate() {
+            let name = s.filename.clone();
+            if plan.convert.contains(&i) {
+                stats.converted.push(name);
+            } else if plan.parse.contains(&i) {
+                stats.parsed.push(name);
+            } else {
+                stats.reused.push(name);
+            }
+        }
+        let mut xtoppolicies = vec![];
+        for phase in 0..2 {
+            for r in &compiled {
+                xtoppolicies.extend(r.outputs[phase].iter().cloned());
+            }
+        }
+        self.index.sources = compiled;
+        self.stats = stats;
+        Ok(xtoppolicies)
+    }
+
+    /// Write the cache into its directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the cache cannot be written.
+    pub fn write(&self) -> Result<(), ParseError> {
+        let json = serde_json::to_vec(&self.index)
+            .map_err(|e| ParseError::XacmlSerializeError(e.to_string()))?;
+        std::fs::create_dir_all(&self.dir).map_err(|_x| ParseError::XacmlWriteIoError)?;
+        // replace the cache in one step, so an interrupted write
+        // leaves the old one.
+        let tmp = self.dir.join(format!("{CACHE_FILENAME}.tmp"));
+        std::fs::write(&tmp, json).map_err(|_x| ParseError::XacmlWriteIoError)?;
+        std::fs::rename(&tmp, self.dir.join(CACHE_FILENAME))
+            .map_err(|_x| ParseError::XacmlWriteIoError)
+    }
+
+    /// Compile the sources in a plan, in a fresh context.  If this
+    /// finds that more sources must be parsed or converted, the plan
+    /// grows, and nothing is returned.
+    fn attempt(
+        &self,
+        sources: &[AlfaFile],
+        records: &[Option<&SourceRecord>],
+        moved: &BTreeSet<String>,
+        plan: &mut Plan,
+    ) -> Result<Attempt, ParseError> {
+        let ctx = Rc::new(Context::new(self.config.clone()));
+        let log = ctx.symbol_log();
+        log.enable();
+        let mut compiled: BTreeMap<usize, Compiled> = BTreeMap::new();
+        let mut probed = BTreeSet::new();
+        // sources outside the plan that define a name that was
+        // looked up.  If there are any, the result (even an error)
+        // could be wrong.
+        let providers = |plan: &Plan, probed: &BTreeSet<String>| -> BTreeSet<usize> {
+            (0..sources.len())
+                .filter(|i| !plan.parse.contains(i))
+                .filter(|&i| records[i].is_some_and(|r| !r.defined.is_disjoint(probed)))
+                .collect()
+        };
+        macro_rules! check {
+            ($res:expr, $i:expr) => {{
+                let res = $res;
+                let c = compiled.entry($i).or_default();
+                c.defined.extend(log.take_defined());
+                let p = log.take_probed();
+                probed.extend(p.iter().cloned());
+                c.probed.extend(p);
+                match res {
+                    Ok(v) => v,
+                    Err(e) => {
+                        let needed = providers(plan, &probed);
+                        if needed.is_empty() {
+                            return Err(e);
+                        }
+                        plan.parse.extend(needed);
+                        return Ok(Attempt::Grow);
+                    }
+                }
+            }};
+        }
+        let mut asts: BTreeMap<usize, AlfaSyntaxTree> = BTreeMap::new();
+        for &i in &plan.parse {
+            let s = &sources[i];
+            let src =
+                NamedSource::new(s.filename.clone(), s.contents.clone()).with_language("ALFA");
+            let ast = check!(
+                make_alfa_ast(&s.contents, &src, ctx.clone())
+                    .map_err(|e| annotate_parse_error(e, &s.filename)),
+                i
+            );
+            asts.insert(i, ast);
+        }
+        // templates may be declared in any source, so instances are
+        // only expanded once everything has been parsed.
+        for (&i, ast) in &mut asts {
+            check!(ast.expand_templates(), i);
+        }
+        let parsing = plan.parse.len();
+        grow_conversions(plan, records, moved, &compiled);
+        plan.parse.extend(providers(plan, &probed));
+        if plan.parse.len() != parsing {
+            return Ok(Attempt::Grow);
+        }
+        // convert policysets from every source, then policies, as a
+        // clean build does, so IDs are generated in the same order.
+        let mut totals: BTreeMap<String, usize> = BTreeMap::new();
+        for phase in 0..2 {
+            for (i, record) in records.iter().enumerate() {
+                if let Some(ast) = asts.get(&i).filter(|_| plan.convert.contains(&i)) {
+                    ctx.set_id_usage(&totals);
+                    let outputs = check!(
+                        if phase == 0 {
+                            ast.policysets()
+                                .iter()
+                                .map(|p| convert_policyset(p))
+                                .collect()
+                        } else {
+                            ast.policies().iter().map(|p| convert_policy(p)).collect()
+                        },
+                        i
+                    );
+                    let after = ctx.id_usage();
+                    let c = compiled.entry(i).or_default();
+                    c.ids[phase] = id_range(&totals, &after);
+                    c.outputs[phase] = outputs;
+                    totals = after;
+                } else if let Some(r) = record {
+                    let range = &r.ids[phase];
+                    let moved_ids = range
+                        .start
+                        .iter()
+                        .any(|(k, &n)| totals.get(k).copied().unwrap_or_default() != n);
+                    if moved_ids {
+                        info!("IDs of {} moved", r.filename);
+                        plan.parse.insert(i);
+                        plan.convert.insert(i);
+                        return Ok(Attempt::Grow);
+                    }
+                    for (k, n) in &range.used {
+                        *totals.entry(k.clone()).or_default() += n;
+                    }
+                }
+            }
+        }
+        // conversion can define and look up names too.
+        let (parsing, converting) = (plan.parse.len(), plan.convert.len());
+        grow_conversions(plan, records, moved, &compiled);
+        plan.parse.extend(providers(plan, &probed));
+        if plan.parse.len() != parsing || plan.convert.len() != converting {
+            return Ok(Attempt::Grow);
+        }
+        let mut records_out = vec![];
+        for (i, s) in sources.iter().enumerate() {
+            match (records[i], compiled.remove(&i)) {
+                (Some(r), _) if !plan.convert.contains(&i) => records_out.push(r.clone()),
+                (_, Some(c)) => records_out.push(SourceRecord {
+                    filename: s.filename.clone(),
+                    hash: hash_bytes(s.contents.as_bytes()),
+                    defined: c.defined,
+                    probed: c.probed,
+                    ids: c.ids,
+                    outputs: c.outputs,
+                }),
+                // every source that is converted was compiled
+                _ => return Err(ParseError::AstConvertError),
+            }
+        }
+        Ok(Attempt::Done(records_out))
+    }
+}
+
+/// Add sources that looked up a name defined by a source being
+/// converted (now, or when it was cached) to the conversions, along
+/// with the sources that depend on them in turn.  Every source that
+/// is converted is parsed.
+fn grow_conversions(
+    plan: &mut Plan,
+    records: &[Option<&SourceRecord>],
+    moved: &BTreeSet<String>,
+    compiled: &BTreeMap<usize, Compiled>,
+) {
+    let mut moved = moved.clone();
+    for &i in &plan.convert {
+        if let Some(c) = compiled.get(&i) {
+            moved.extend(c.defined.iter().cloned());
+        }
+        if let Some(r) = records[i] {
+            moved.extend(r.defined.iter().cloned());
+        }
+    }
+    loop {
+        let dependents: Vec<usize> = (0..records.len())
+            .filter(|i| !plan.convert.contains(i))
+            .filter(|&i| records[i].is_some_and(|r| !r.probed.is_disjoint(&moved)))
+            .collect();
+        if dependents.is_empty() {
+            break;
+        }
+        for i in dependents {
+            if let Some(r) = records[i] {
+                moved.extend(r.defined.iter().cloned());
+            }
+            plan.convert.insert(i);
+        }
+    }
+    plan.parse.extend(plan.convert.iter().copied());
+}
+
+/// IDs generated between two counter states.
+fn id_range(before: &BTreeMap<String, usize>, after: &BTreeMap<String, usize>) -> IdRange {
+    let mut range = IdRange::default();
+    for (k, &n) in after {
+        let start = before.get(k).copied().unwrap_or_default();
+        if n > start {
+            range.start.insert(k.clone(), start);
+            range.used.insert(k.clone(), n - start);
+        }
+    }
+    range
+}
@@ -0,0 +1,85 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Compare generated XACML with the files in an output directory,
+//! without writing anything.
+//!
+//! This finds XACML that is out of date with its ALFA source, or
+//! that was edited by hand.
+
+use crate::errors::ParseError;
+use crate::manifest::Manifest;
+use crate::XacmlFile;
+use similar::TextDiff;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Differences between generated XACML and an output directory.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct CheckReport {
+    /// Files whose contents differ, with a unified diff from the
+    /// existing file to the generated one.
+    pub changed: Vec<(PathBuf, String)>,
+    /// Generated files that do not exist in the directory.
+    pub added: Vec<PathBuf>,
+    /// Previously generated files in the directory that would not be
+    /// generated now.
+    pub removed: Vec<PathBuf>,
+}
+
+impl CheckReport {
+    /// Whether the directory matches the generated files.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.changed.is_empty() && self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Compare generated files with the contents of an output directory.
+/// Files listed in the manifest of the directory that are not
+/// generated, and still exist, are reported as removed.  Other files
+/// were not created by a2x, and are not reported.
+///
+/// # Errors
+///
+/// Returns `Err` if an existing file or the manifest cannot be read.
+pub fn check_output(dir: &Path, files: &[XacmlFile]) -> Result<CheckReport, ParseError> {
+    let mut report = CheckReport::default();
+    for f in files {
+        let path = dir.join(&f.filename);
+        if !path.exists() {
+            report.added.push(f.filename.clone());
+            continue;
+        }
+        let existing = std::fs::read(&path).map_err(|_x| ParseError::XacmlWriteIoError)?;
+        if existing != f.contents {
+            let diff = unified_diff(&f.filename, &existing, &f.contents);
+            report.changed.push((f.filename.clone(), diff));
+        }
+    }
+    let generated: HashSet<&PathBuf> = files.iter().map(|f| &f.filename).collect();
+    if let Some(manifest) = Manifest::read(dir)? {
+        report.removed = manifest
+            .entries
+            .into_iter()
+            .map(|e| e.path)
+            .filter(|p| !generated.contains(p) && dir.join(p).is_file())
+            .collect();
+        report.removed.sort();
+    }
+    Ok(report)
+}
+
+/// Unified diff of a file, from its existing contents to the
+/// generated contents.
+#[must_use]
+pub fn unified_diff(path: &Path, existing: &[u8], generated: &[u8]) -> String {
+    let existing = String::from_utf8_lossy(existing);
+    let generated = String::from_utf8_lossy(generated);
+    let name = path.display();
+    TextDiff::from_lines(existing.as_ref(), generated.as_ref())
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{name}"), &format!("b/{name}"))
+        .to_string()
+}
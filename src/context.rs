@@ -7,6 +7,8 @@ use crate::ast::advice::AdviceDef;
 use crate::ast::attribute::Attribute;
 use crate::ast::category::{standard_categories, Category};
 use crate::ast::constant::Constant;
+use crate::ast::constantdef::ConstantDef;
+use crate::ast::externpolicy::ExternPolicy;
 use crate::ast::function::Function;
 use crate::ast::import::Import;
 use crate::ast::infix::Infix;
@@ -21,21 +23,29 @@ use crate::ast::rulecombinator::{
     protected_rulecombinators, standard_rulecombinators, RuleCombinator,
 };
 use crate::ast::std_attributes::standard_attributes;
+use crate::ast::template::{Expansion, TemplateDef};
 use crate::ast::std_functions::standard_functions;
 use crate::ast::std_infix::standard_infix;
-use crate::ast::typedef::{standard_types, TypeDef};
+use crate::ast::typedef::{is_valid_literal, standard_types, TypeDef};
 use crate::ast::QualifiedName;
+use crate::ast::version::DEFAULT_VERSION;
 use crate::ast::{AsAlfa, SrcLoc};
 use crate::errors::{ParseError, SrcError};
+use crate::sourcemap::SourceRange;
+use crate::xacml::layout::OutputLayout;
+use crate::xacml::style::OutputStyle;
 use log::debug;
 use log::info;
 use std::any::type_name;
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::collections::HashSet;
+use std::fmt;
 use std::fmt::Debug;
 use std::rc::Rc;
+use std::str::FromStr;
 use std::time::Instant;
+use uuid::Uuid;
 
 pub const SYSTEM_NS: &str = "_A2X";
 pub const PROTECTED_NS: &str = "_A2X.PROTECTED";
@@ -44,14 +54,27 @@ pub const PROTECTED_NS: &str = "_A2X.PROTECTED";
 // state that the conversion needs to keep track of.
 
 /// Configuration for conversions.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Config {
     /// Default namespace prefix.
     pub base_namespace: Option<String>,
     /// Use built-in functions, attributes, etc.
     pub enable_builtins: bool,
-    /// Version string to be included in all policies.
+    /// Version for policies and policysets without a version
+    /// statement.
     pub version: Option<String>,
+    /// XACML version of generated policies.
+    pub target_version: XacmlVersion,
+    /// How names are generated for anonymous rules, policies, and
+    /// policysets.
+    pub id_strategy: IdStrategy,
+    /// Style of generated XML.
+    pub output_style: OutputStyle,
+    /// Paths of generated XACML files.
+    pub output_layout: OutputLayout,
+    /// Where the ALFA source of generated policies and rules is
+    /// recorded.
+    pub provenance: Provenance,
 }
 
 impl Default for Config {
@@ -60,6 +83,178 @@ impl Default for Config {
             base_namespace: None,
             enable_builtins: true,
             version: None,
+            target_version: XacmlVersion::default(),
+            id_strategy: IdStrategy::default(),
+            output_style: OutputStyle::default(),
+            output_layout: OutputLayout::default(),
+            provenance: Provenance::default(),
+        }
+    }
+}
+
+/// XACML specification version that policies are generated for.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum XacmlVersion {
+    /// XACML 2.0, for older PDPs.
+    V2,
+    /// XACML 3.0 (the default).
+    #[default]
+    V3,
+}
+
+impl FromStr for XacmlVersion {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "2" | "2.0" => Ok(XacmlVersion::V2),
+            "3" | "3.0" => Ok(XacmlVersion::V3),
+            _ => Err(ParseError::InvalidXacmlVersion(s.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for XacmlVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XacmlVersion::V2 => write!(f, "2.0"),
+            XacmlVersion::V3 => write!(f, "3.0"),
+        }
+    }
+}
+
+/// Strategy for naming anonymous rules, policies, and policysets.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum IdStrategy {
+    /// An incrementing number per namespace (`rule_0`, `rule_1`,
+    /// ...).  Adding an element may renumber later elements.
+    #[default]
+    Counter,
+    /// A hash of the element's canonical ALFA source, which is
+    /// unaffected by comments, formatting, or other elements.
+    ContentHash,
+    /// A random, time-ordered UUID (version 7).
+    Uuid,
+}
+
+impl FromStr for IdStrategy {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "counter" => Ok(IdStrategy::Counter),
+            "hash" => Ok(IdStrategy::ContentHash),
+            "uuid" => Ok(IdStrategy::Uuid),
+            _ => Err(ParseError::InvalidIdStrategy(s.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for IdStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IdStrategy::Counter => write!(f, "counter"),
+            IdStrategy::ContentHash => write!(f, "hash"),
+            IdStrategy::Uuid => write!(f, "uuid"),
+        }
+    }
+}
+
+/// Recording of the ALFA source that generated policies, policysets,
+/// and rules came from.
+///
+/// Provenance is a single line, in a stable format, such as `a2x
+/// provenance: source=main.alfa:12 name=main.audit.check
+/// version=0.1.1`.  The name is the qualified ALFA name (of the
+/// containing policy, for anonymous rules), and the version is the
+/// a2x version.  Elements expanded from a template instance also have
+/// `template=` and (for named instances) `instance=` keys, with
+/// qualified names, and their source is the template.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Provenance {
+    /// Provenance is not recorded.
+    #[default]
+    Off,
+    /// An XML comment at the start of each element.  Canonical
+    /// output has no comments, so this cannot be combined with it.
+    Comment,
+    /// A line appended to the `<Description>` of each element.
+    Description,
+}
+
+impl Provenance {
+    /// The description and XML comment of a generated element, with
+    /// provenance added.  Elements without a location in an ALFA
+    /// source are unchanged.
+    #[must_use]
+    pub fn annotate(
+        self,
+        description: Option<String>,
+        location: Option<&SourceRange>,
+        name: &str,
+        expansion: Option<&Expansion>,
+    ) -> (Option<String>, Option<String>) {
+        let Some(loc) = location else {
+            return (description, None);
+        };
+        let mut line = format!(
+            "a2x provenance: source={}:{} name={name}",
+            loc.file, loc.start_line
+        );
+        if let Some(x) = expansion {
+            line.push_str(&format!(" template={}", x.template));
+            if let Some(i) = &x.instance {
+                line.push_str(&format!(" instance={i}"));
+            }
+        }
+        line.push_str(&format!(" version={}", env!("CARGO_PKG_VERSION")));
+        match self {
+            Provenance::Off => (description, None),
+            // comments cannot contain "--"
+            Provenance::Comment => (description, Some(format!(" {} ", line.replace("--", "- -")))),
+            Provenance::Description => match description {
+                Some(d) => (Some(format!("{d}\n{line}")), None),
+                None => (Some(line), None),
+            },
+        }
+    }
+}
+
+impl FromStr for Provenance {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Provenance::Off),
+            "comment" => Ok(Provenance::Comment),
+            "description" => Ok(Provenance::Description),
+            _ => Err(ParseError::InvalidProvenance(s.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Provenance::Off => write!(f, "off"),
+            Provenance::Comment => write!(f, "comment"),
+            Provenance::Description => write!(f, "description"),
+        }
+    }
+}
+
+/// Kinds of elements that may need a generated name.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AnonymousKind {
+    Rule,
+    Policy,
+    PolicySet,
+}
+
+impl AnonymousKind {
+    /// Prefix for generated names.
+    fn prefix(self) -> &'static str {
+        match self {
+            AnonymousKind::Rule => "rule",
+            AnonymousKind::Policy => "policy",
+            AnonymousKind::PolicySet => "policyset",
         }
     }
 }
@@ -73,6 +268,58 @@ impl Config {
             .unwrap_or("https://sr.ht/~gheartsfield/a2x/alfa/ident/")
             .to_owned()
     }
+
+    /// Retrieve the version for policies that do not declare one.
+    #[must_use]
+    pub fn get_version(&self) -> String {
+        self.version
+            .as_deref()
+            .unwrap_or(DEFAULT_VERSION)
+            .to_owned()
+    }
+}
+
+/// A record of the names defined and looked up during a conversion.
+///
+/// Recording is off until [`SymbolLog::enable`] is called.  Symbols
+/// are recorded by fully-qualified name.  Imports are recorded as
+/// `import <namespace>`, and assigned policy URIs as `uri <URI>`.
+/// Every name that was looked up is recorded, whether or not it was
+/// found, since defining it later could change the result.
+#[derive(Debug, PartialEq, Default)]
+pub struct SymbolLog {
+    enabled: Cell<bool>,
+    defined: RefCell<BTreeSet<String>>,
+    probed: RefCell<BTreeSet<String>>,
+}
+
+impl SymbolLog {
+    /// Start recording.
+    pub fn enable(&self) {
+        self.enabled.set(true);
+    }
+
+    fn define(&self, name: &str) {
+        if self.enabled.get() {
+            self.defined.borrow_mut().insert(name.to_owned());
+        }
+    }
+
+    fn probe(&self, name: &str) {
+        if self.enabled.get() {
+            self.probed.borrow_mut().insert(name.to_owned());
+        }
+    }
+
+    /// Names defined since the last call.
+    pub fn take_defined(&self) -> BTreeSet<String> {
+        self.defined.take()
+    }
+
+    /// Names looked up since the last call.
+    pub fn take_probed(&self) -> BTreeSet<String> {
+        self.probed.take()
+    }
 }
 
 /// Store and provide lookup facilities for an ALFA type.
@@ -83,6 +330,8 @@ impl Config {
 pub struct Resolver<T> {
     /// Mapping of fully-qualified names to specific elements.
     pub elements: RefCell<HashMap<String, Rc<T>>>,
+    /// Record of registrations and lookups.
+    log: Rc<SymbolLog>,
 }
 
 impl<T> Resolver<T>
@@ -93,11 +342,24 @@ where
     /// Create a new, empty resolver.
     #[must_use]
     pub fn new() -> Self {
+        Resolver::with_log(Rc::default())
+    }
+
+    /// Create a new, empty resolver, recording into a shared log.
+    #[must_use]
+    pub fn with_log(log: Rc<SymbolLog>) -> Self {
         Resolver {
             elements: RefCell::new(HashMap::new()),
+            log,
         }
     }
 
+    /// Element with a fully-qualified name, recording the lookup.
+    fn get(&self, fq_name: &str) -> Option<Rc<T>> {
+        self.log.probe(fq_name);
+        self.elements.borrow().get(fq_name).cloned()
+    }
+
     /// Get the (Rust) type name this resolver contains, without any
     /// parent module names.
     ///
@@ -138,6 +400,8 @@ where
         // determine the fully-qualified name:
         if let Some(n) = elem.fully_qualified_name() {
             // add to map
+            self.log.probe(&n);
+            self.log.define(&n);
             let mut m = self.elements.borrow_mut();
             let type_name = Resolver::<T>::short_type_name();
             debug!("registering {type_name}: {n:?}",);
@@ -158,8 +422,7 @@ where
     /// Existence check of fully-qualified name
     pub fn exists_fq(&self, fq_name: &str) -> bool {
         // check if elements contains this:
-        let e = self.elements.borrow();
-        e.get(fq_name).is_some()
+        self.get(fq_name).is_some()
     }
 
     fn match_one_and_only(matches: &[Rc<T>]) -> Result<Option<Rc<T>>, ParseError> {
@@ -197,9 +460,8 @@ where
             candidate.push_str(symbol);
             debug!("R1: candidate is {candidate}");
             // check if elements contains this:
-            let e = self.elements.borrow();
             //debug!("did a get: {:?}", x);
-            if let Some(k) = e.get(&candidate) {
+            if let Some(k) = self.get(&candidate) {
                 //debug!("R1: Found key {:?}", k);
                 // does the debug for function fail?
                 return Some(k.clone());
@@ -214,8 +476,7 @@ where
     fn lookup_root(&self, symbol: &str) -> Option<Rc<T>> {
         // Rule #2: root match: attempt to match the full symbol.
         debug!("R2: candidate to match is [{symbol}]");
-        let e = self.elements.borrow();
-        if let Some(k) = e.get(symbol) {
+        if let Some(k) = self.get(symbol) {
             debug!("R2 Found key");
             return Some(k.clone());
         } else {
@@ -242,20 +503,19 @@ where
         let mut matches = vec![];
         for i in static_imports {
             // last component must match the candidate symbol in scope
-            if let Some(last_component) = i.components.last() {
-                if last_component == symbol {
-                    debug!("R3: checking static import (relative): {i:?}");
-                    let mut candidate = source_ns.join(".");
-                    candidate.push('.');
-                    let c = i.components.join(".");
-                    candidate.push_str(&c);
-                    debug!("R3: candidate is {candidate}");
-                    // check if elements contains this:
-                    let e = self.elements.borrow();
-                    if let Some(k) = e.get(&candidate) {
-                        debug!("R3: Found value {k:?}");
-                        matches.push(k.clone());
-                    }
+            if let Some(last_component) = i.components.last()
+                && last_component == symbol
+            {
+                debug!("R3: checking static import (relative): {i:?}");
+                let mut candidate = source_ns.join(".");
+                candidate.push('.');
+                let c = i.components.join(".");
+                candidate.push_str(&c);
+                debug!("R3: candidate is {candidate}");
+                // check if elements contains this:
+                if let Some(k) = self.get(&candidate) {
+                    debug!("R3: Found value {k:?}");
+                    matches.push(k.clone());
                 }
             }
         }
@@ -277,17 +537,16 @@ where
         let mut matches = vec![];
         for i in static_imports {
             // last component must match the candidate symbol in scope
-            if let Some(last_component) = i.components.last() {
-                if last_component == symbol {
-                    debug!("R4: checking static import (absolute): {i:?}");
-                    let candidate = i.components.join(".");
-                    debug!("R4: candidate is {candidate}");
-                    // check if elements contains this:
-                    let e = self.elements.borrow();
-                    if let Some(k) = e.get(&candidate) {
-                        debug!("R4: Found key {k:?}");
-                        matches.push(k.clone());
-                    }
+            if let Some(last_component) = i.components.last()
+                && last_component == symbol
+            {
+                debug!("R4: checking static import (absolute): {i:?}");
+                let candidate = i.components.join(".");
+                debug!("R4: candidate is {candidate}");
+                // check if elements contains this:
+                if let Some(k) = self.get(&candidate) {
+                    debug!("R4: Found key {k:?}");
+                    matches.push(k.clone());
                 }
             }
         }
@@ -321,8 +580,7 @@ where
             candidate.push_str(symbol);
             debug!("R5: candidate is {candidate}");
             // check if elements contains this:
-            let e = self.elements.borrow();
-            if let Some(k) = e.get(&candidate) {
+            if let Some(k) = self.get(&candidate) {
                 debug!("R5: Found key {k:?}");
                 matches.push(k.clone());
             }
@@ -352,8 +610,7 @@ where
             candidate.push_str(symbol);
             debug!("R6: candidate is {candidate}");
             // check if elements contains this:
-            let e = self.elements.borrow();
-            if let Some(k) = e.get(&candidate) {
+            if let Some(k) = self.get(&candidate) {
                 debug!("R6: Found key {k:?}");
                 matches.push(k.clone());
             }
@@ -474,6 +731,7 @@ pub struct AttributeValue {
 
 /// a literal is just a type URI + string value.
 #[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TypedLiteral {
     pub type_uri: String,
     pub value: String,
@@ -496,6 +754,9 @@ pub struct Context {
     policy_id_mapping: RefCell<HashMap<String, usize>>,
     /// Mapping of namespaces to the next available rule ID.
     rule_id_mapping: RefCell<HashMap<String, usize>>,
+    /// Number of times each content-derived name has been generated
+    /// within a scope.
+    content_name_mapping: RefCell<HashMap<String, usize>>,
     /// Mapping of fully qualified namespaces to `PolicySet` instances.
     policyset_resolver: Resolver<PolicySet>,
     /// Mapping of fully qualified namespaces to `Policy` instances.
@@ -522,8 +783,17 @@ pub struct Context {
     obligation_resolver: Resolver<ObligationDef>,
     /// Mapping of fully qualified namespaces to `Category` instances.
     category_resolver: Resolver<Category>,
+    /// Mapping of fully qualified namespaces to `ConstantDef` instances.
+    constant_resolver: Resolver<ConstantDef>,
+    /// Mapping of fully qualified namespaces to `TemplateDef` instances.
+    template_resolver: Resolver<TemplateDef>,
+    /// Mapping of fully qualified namespaces to `ExternPolicy` instances.
+    extern_resolver: Resolver<ExternPolicy>,
     /// Set of used URIs for identifying policysets, policies, and rules.
     used_uris: RefCell<HashSet<String>>,
+    /// Record of the names defined and looked up, shared with every
+    /// resolver.
+    symbol_log: Rc<SymbolLog>,
 }
 
 impl Default for Context {
@@ -542,26 +812,32 @@ impl Context {
     /// definitions.
     #[must_use]
     pub fn new(cfg: Config) -> Self {
+        let log = Rc::new(SymbolLog::default());
         let mut c = Context {
             config: cfg,
             next_id: RefCell::new(0),
             policy_id_mapping: RefCell::new(HashMap::new()),
             policyset_id_mapping: RefCell::new(HashMap::new()),
             rule_id_mapping: RefCell::new(HashMap::new()),
-            policyset_resolver: Resolver::<PolicySet>::new(),
-            policy_resolver: Resolver::<Policy>::new(),
-            rule_resolver: Resolver::<RuleDef>::new(),
-            rulecombinator_resolver: Resolver::<RuleCombinator>::new(),
-            policycombinator_resolver: Resolver::<PolicyCombinator>::new(),
-            function_resolver: Resolver::<Function>::new(),
-            infix_resolver: Resolver::<Infix>::new(),
-            attribute_resolver: Resolver::<Attribute>::new(),
-            typedef_resolver: Resolver::<TypeDef>::new(),
-            advice_resolver: Resolver::<AdviceDef>::new(),
-            obligation_resolver: Resolver::<ObligationDef>::new(),
-            category_resolver: Resolver::<Category>::new(),
+            content_name_mapping: RefCell::new(HashMap::new()),
+            policyset_resolver: Resolver::with_log(log.clone()),
+            policy_resolver: Resolver::with_log(log.clone()),
+            rule_resolver: Resolver::with_log(log.clone()),
+            rulecombinator_resolver: Resolver::with_log(log.clone()),
+            policycombinator_resolver: Resolver::with_log(log.clone()),
+            function_resolver: Resolver::with_log(log.clone()),
+            infix_resolver: Resolver::with_log(log.clone()),
+            attribute_resolver: Resolver::with_log(log.clone()),
+            typedef_resolver: Resolver::with_log(log.clone()),
+            advice_resolver: Resolver::with_log(log.clone()),
+            obligation_resolver: Resolver::with_log(log.clone()),
+            category_resolver: Resolver::with_log(log.clone()),
+            constant_resolver: Resolver::with_log(log.clone()),
+            template_resolver: Resolver::with_log(log.clone()),
+            extern_resolver: Resolver::with_log(log.clone()),
             imports: RefCell::new(HashMap::new()),
             used_uris: RefCell::new(HashSet::new()),
+            symbol_log: log,
         };
         if c.config.enable_builtins {
             let start = Instant::now();
@@ -623,6 +899,113 @@ impl Context {
             .or_insert(0)
     }
 
+    /// Number of IDs and names generated so far, by counter.
+    ///
+    /// Counters are named `rule <ns>`, `policy <ns>`, and `policyset
+    /// <ns>` for the per-namespace IDs, `name <scope>#<name>` for
+    /// content-derived names, and `fresh` for [`Context::get_fresh_id`].
+    /// Counters that were never used are left out.
+    #[must_use]
+    pub fn id_usage(&self) -> BTreeMap<String, usize> {
+        let mut usage = BTreeMap::new();
+        let counters = [
+            ("rule", &self.rule_id_mapping),
+            ("policy", &self.policy_id_mapping),
+            ("policyset", &self.policyset_id_mapping),
+            ("name", &self.content_name_mapping),
+        ];
+        for (prefix, mapping) in counters {
+            for (k, last) in mapping.borrow().iter() {
+                usage.insert(format!("{prefix} {k}"), last + 1);
+            }
+        }
+        let fresh = *self.next_id.borrow();
+        if fresh > 0 {
+            usage.insert("fresh".to_owned(), fresh);
+        }
+        usage
+    }
+
+    /// Restore the counters to a state returned by
+    /// [`Context::id_usage`], so that the next IDs generated are the
+    /// ones a conversion would generate after reaching that state.
+    pub fn set_id_usage(&self, usage: &BTreeMap<String, usize>) {
+        let counters = [
+            ("rule ", &self.rule_id_mapping),
+            ("policy ", &self.policy_id_mapping),
+            ("policyset ", &self.policyset_id_mapping),
+            ("name ", &self.content_name_mapping),
+        ];
+        for (prefix, mapping) in counters {
+            *mapping.borrow_mut() = usage
+                .iter()
+                .filter(|&(_, &n)| n > 0)
+                .filter_map(|(k, n)| Some((k.strip_prefix(prefix)?.to_owned(), n - 1)))
+                .collect();
+        }
+        *self.next_id.borrow_mut() = usage.get("fresh").copied().unwrap_or_default();
+    }
+
+    /// Record of the names defined and looked up.
+    #[must_use]
+    pub fn symbol_log(&self) -> &SymbolLog {
+        &self.symbol_log
+    }
+
+    /// Generate a name for an anonymous element, with the configured
+    /// ID strategy.
+    ///
+    /// Counters are allocated per namespace (`ns`).  Identical
+    /// content within the same `scope` receives a numeric suffix, so
+    /// that repeated calls never return the same name.
+    pub fn generate_name(
+        &self,
+        kind: AnonymousKind,
+        ns: &str,
+        scope: &str,
+        content_hash: &str,
+    ) -> String {
+        let prefix = kind.prefix();
+        match self.config.id_strategy {
+            IdStrategy::Counter => {
+                let next_id = match kind {
+                    AnonymousKind::Rule => self.get_next_rule_id(ns),
+                    AnonymousKind::Policy => self.get_next_policy_id(ns),
+                    AnonymousKind::PolicySet => self.get_next_policyset_id(ns),
+                };
+                format!("{prefix}_{next_id}")
+            }
+            IdStrategy::ContentHash => {
+                let name = format!("{prefix}_{content_hash}");
+                let mut names = self.content_name_mapping.borrow_mut();
+                let seen = names
+                    .entry(format!("{scope}#{name}"))
+                    .and_modify(|e| *e += 1)
+                    .or_insert(0);
+                if *seen == 0 {
+                    name
+                } else {
+                    format!("{name}_{seen}")
+                }
+            }
+            IdStrategy::Uuid => format!("{prefix}_{}", Uuid::now_v7()),
+        }
+    }
+
+    /// Check that a generated URI does not collide with one assigned
+    /// explicitly in ALFA.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the URI was assigned to a policy or policyset.
+    pub fn check_generated_uri(&self, uri: &str) -> Result<(), ParseError> {
+        self.symbol_log.probe(&format!("uri {uri}"));
+        if self.used_uris.borrow().contains(uri) {
+            return Err(ParseError::DuplicateURI(uri.to_owned()));
+        }
+        Ok(())
+    }
+
     /// Add minimal required XACML3 definitions.
     ///
     /// These will not be added to the import path, and must always be
@@ -683,6 +1066,33 @@ impl Context {
         Ok(())
     }
 
+    /// Fully qualified names of all registered elements, with the
+    /// ALFA keyword that declares them (such as `attribute`).
+    #[must_use]
+    pub fn symbol_names(&self) -> Vec<(&'static str, String)> {
+        let mut names = vec![];
+        let mut add = |keyword: &'static str, fq_names: Vec<String>| {
+            names.extend(fq_names.into_iter().map(|n| (keyword, n)));
+        };
+        add("policyset", self.policyset_resolver.names());
+        add("policy", self.policy_resolver.names());
+        add("rule", self.rule_resolver.names());
+        add("ruleCombinator", self.rulecombinator_resolver.names());
+        add("policyCombinator", self.policycombinator_resolver.names());
+        add("function", self.function_resolver.names());
+        add("infix", self.infix_resolver.names());
+        add("attribute", self.attribute_resolver.names());
+        add("type", self.typedef_resolver.names());
+        add("advice", self.advice_resolver.names());
+        add("obligation", self.obligation_resolver.names());
+        add("category", self.category_resolver.names());
+        add("constant", self.constant_resolver.names());
+        add("template", self.template_resolver.names());
+        add("extern", self.extern_resolver.names());
+        names.sort();
+        names
+    }
+
     /// Serialize builtins
     ///
     /// # Errors
@@ -739,6 +1149,7 @@ impl Context {
     /// Import Insertion
     pub fn register_import(&self, ns: &[String], rc: Rc<Import>) {
         let n = ns.join(".");
+        self.symbol_log.define(&format!("import {n}"));
         let mut i = self.imports.borrow_mut();
         // attempt to get a mutable Vec against ns, add rc.
         if let Some(v) = i.get_mut(&n) {
@@ -750,8 +1161,10 @@ impl Context {
     }
 
     /// Get imports for a namespace, combining with system default if configured
-    fn get_imports(&self, source_ns: &[String]) -> Option<Vec<Rc<Import>>> {
+    pub(crate) fn get_imports(&self, source_ns: &[String]) -> Option<Vec<Rc<Import>>> {
         let imports = self.imports.borrow();
+        self.symbol_log.probe(&format!("import {SYSTEM_NS}"));
+        self.symbol_log.probe(&format!("import {}", source_ns.join(".")));
         // get imports from the default system namespace
         // TODO, only if configuration is set to use defaults
         let imports_default = imports.get(SYSTEM_NS);
@@ -1026,23 +1439,27 @@ impl Context {
                 }
             }
         }
-        // TODO: prevent collisions from generated and assigned names.
+        // collisions between generated and assigned URIs are
+        // checked when the IDs are generated, once every name is
+        // registered.
 
         // if the policyset was assigned an ID, record that for
         // collision detection.
         if let PolicyId::PolicyNameAndId(_, i) = &elem.id {
+            self.symbol_log.probe(&format!("uri {i}"));
+            self.symbol_log.define(&format!("uri {i}"));
             let mut uu = self.used_uris.borrow_mut();
             let unique = uu.insert(i.clone());
             if !unique {
                 return Err(ParseError::DuplicateURI(i.clone()));
             }
         };
-        // check if a policy exists in the same namespace with the
-        // same name.
-        if let Some(fq) = &elem.fully_qualified_name() {
-            if self.policy_resolver.exists_fq(fq) {
-                return Err(ParseError::DuplicatePolicyEntity(fq.clone()));
-            }
+        // check if a policy (or external policy) exists in the same
+        // namespace with the same name.
+        if let Some(fq) = &elem.fully_qualified_name()
+            && (self.policy_resolver.exists_fq(fq) || self.extern_resolver.exists_fq(fq))
+        {
+            return Err(ParseError::DuplicatePolicyEntity(fq.clone()));
         }
 
         self.policyset_resolver.register(elem)
@@ -1058,13 +1475,12 @@ impl Context {
         symbol: &str,
         source_ns: &[String],
     ) -> Result<Rc<PolicySet>, ParseError> {
-        let p = self.policyset_resolver.lookup(
+        self.policyset_resolver.lookup(
             symbol,
             source_ns,
             &SrcLoc::default(),
             self.get_imports(source_ns).as_ref(),
-        );
-        p
+        )
     }
 
     /// Register policy
@@ -1078,23 +1494,27 @@ impl Context {
         // policies/policysets by reference under a policyset be
         // ambiguous.
 
-        // TODO: prevent collisions from generated and assigned names.
+        // collisions between generated and assigned URIs are
+        // checked when the IDs are generated, once every name is
+        // registered.
 
         // if the policyset was assigned an ID, record that for
         // collision detection.
         if let PolicyId::PolicyNameAndId(_, i) = &elem.id {
+            self.symbol_log.probe(&format!("uri {i}"));
+            self.symbol_log.define(&format!("uri {i}"));
             let mut uu = self.used_uris.borrow_mut();
             let unique = uu.insert(i.clone());
             if !unique {
                 return Err(ParseError::DuplicateURI(i.clone()));
             }
         };
-        // check if a policyset exists in the same namespace with the
-        // same name.
-        if let Some(fq) = &elem.fully_qualified_name() {
-            if self.policyset_resolver.exists_fq(fq) {
-                return Err(ParseError::DuplicatePolicyEntity(fq.clone()));
-            }
+        // check if a policyset (or external policyset) exists in the
+        // same namespace with the same name.
+        if let Some(fq) = &elem.fully_qualified_name()
+            && (self.policyset_resolver.exists_fq(fq) || self.extern_resolver.exists_fq(fq))
+        {
+            return Err(ParseError::DuplicatePolicyEntity(fq.clone()));
         }
         self.policy_resolver.register(elem)
     }
@@ -1179,6 +1599,99 @@ impl Context {
         )
     }
 
+    /// Register constant
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a constant with the same name exists.
+    pub fn register_constant(&self, elem: Rc<ConstantDef>) -> Result<(), ParseError> {
+        self.constant_resolver.register(elem)
+    }
+
+    /// Lookup constant
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the constant does not exist, or is
+    /// ambiguous.
+    pub fn lookup_constant(
+        &self,
+        symbol: &str,
+        source_ns: &[String],
+    ) -> Result<Rc<ConstantDef>, ParseError> {
+        self.constant_resolver.lookup(
+            symbol,
+            source_ns,
+            &SrcLoc::default(),
+            self.get_imports(source_ns).as_ref(),
+        )
+    }
+
+    /// Register template
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a template with the same name exists.
+    pub fn register_template(&self, elem: Rc<TemplateDef>) -> Result<(), ParseError> {
+        self.template_resolver.register(elem)
+    }
+
+    /// Lookup template
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the template does not exist, or is
+    /// ambiguous.
+    pub fn lookup_template(
+        &self,
+        symbol: &str,
+        source_ns: &[String],
+        src_loc: &SrcLoc,
+    ) -> Result<Rc<TemplateDef>, ParseError> {
+        self.template_resolver.lookup(
+            symbol,
+            source_ns,
+            src_loc,
+            self.get_imports(source_ns).as_ref(),
+        )
+    }
+
+    /// Register external policy/policyset
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a policy, policyset, or external declaration
+    /// with the same name exists.
+    pub fn register_extern(&self, elem: Rc<ExternPolicy>) -> Result<(), ParseError> {
+        // external declarations are referenced exactly like local
+        // policies and policysets, so they cannot share a name.
+        if let Some(fq) = &elem.fully_qualified_name()
+            && (self.policy_resolver.exists_fq(fq) || self.policyset_resolver.exists_fq(fq))
+        {
+            return Err(ParseError::DuplicatePolicyEntity(fq.clone()));
+        }
+        self.extern_resolver.register(elem)
+    }
+
+    /// Lookup external policy/policyset
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the declaration does not exist, or is
+    /// ambiguous.
+    pub fn lookup_extern(
+        &self,
+        symbol: &str,
+        source_ns: &[String],
+    ) -> Result<Rc<ExternPolicy>, ParseError> {
+        self.extern_resolver.lookup(
+            symbol,
+            source_ns,
+            &SrcLoc::default(),
+            self.get_imports(source_ns).as_ref(),
+        )
+    }
+
     /// Register category
     ///
     /// # Errors
@@ -1249,7 +1762,92 @@ impl Context {
                     value: s,
                 })
             }
+            Constant::Named(n) => {
+                // named constants are resolved from where they are
+                // referenced, but their value and type are resolved
+                // from where they were declared.
+                let c = self.lookup_constant(&n, source_ns)?;
+                self.resolve_constant_def(&c)
+            }
             Constant::Undefined => Err(ParseError::AstConvertError),
         }
     }
+
+    /// Resolve a constant definition to a typed literal, using the
+    /// declared type of the constant.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the declared type cannot be found, or if the
+    /// literal value is incompatible with the declared type.
+    pub fn resolve_constant_def(&self, c: &ConstantDef) -> Result<TypedLiteral, ParseError> {
+        let declared = self.lookup_type(&c.typedef, &c.ns)?;
+        self.literal_as_type(
+            &c.value,
+            &declared,
+            &c.ns,
+            &c.src_loc,
+            "Constant values must match the declared type",
+        )
+    }
+
+    /// Resolve the value given for an attribute to a typed literal,
+    /// using the declared type of the attribute.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the attribute or its type cannot be found, or
+    /// if the literal value is incompatible with the attribute type.
+    pub fn resolve_attribute_value(
+        &self,
+        attribute: &str,
+        value: &Constant,
+        source_ns: &[String],
+        src_loc: &SrcLoc,
+    ) -> Result<TypedLiteral, ParseError> {
+        let attr = self.lookup_attribute(attribute, source_ns)?;
+        let declared = self.lookup_type(&attr.typedef, &attr.ns)?;
+        self.literal_as_type(
+            value,
+            &declared,
+            source_ns,
+            src_loc,
+            "Attribute values must match the attribute type",
+        )
+    }
+
+    /// Convert a literal to a typed literal of a declared type.
+    fn literal_as_type(
+        &self,
+        value: &Constant,
+        declared: &TypeDef,
+        source_ns: &[String],
+        src_loc: &SrcLoc,
+        mismatch_msg: &str,
+    ) -> Result<TypedLiteral, ParseError> {
+        // string literals supply the lexical form for any type, which
+        // must be valid for the declared type.
+        if let Constant::String(s) = value {
+            if !is_valid_literal(&declared.uri, s) {
+                return Err(SrcError::err(
+                    mismatch_msg,
+                    &format!("{s:?} is not a valid {}", declared.id),
+                    src_loc.clone(),
+                ));
+            }
+            return Ok(TypedLiteral {
+                type_uri: declared.uri.clone(),
+                value: s.clone(),
+            });
+        }
+        let tl = self.constant_to_typedliteral(value.clone(), source_ns)?;
+        if tl.type_uri != declared.uri {
+            return Err(SrcError::err(
+                mismatch_msg,
+                &format!("value has type {:?}, declared as {:?}", tl.type_uri, declared.uri),
+                src_loc.clone(),
+            ));
+        }
+        Ok(tl)
+    }
 }
@@ -0,0 +1,1236 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Decompile XACML policies into ALFA.
+//!
+//! Policies and policysets that have been read from XML (see
+//! [`crate::xacml::reader`]) are written out as a single ALFA
+//! namespace.  URIs are mapped back to the builtin names for types,
+//! categories, attributes, functions, and combining algorithms.
+//! Anything that is not builtin gets a declaration in the namespace,
+//! named after the last segment of its URI.
+//!
+//! Where a match or an application can be written with an infix
+//! operator that compiles back to the same function, it is;
+//! otherwise it is written as a function call.  The policyset that
+//! a2x creates for a policy or policyset with a condition is
+//! recognized, and turned back into a `condition` statement.
+//!
+//! References to policies that are not being decompiled become
+//! `extern` declarations.
+//!
+//! ALFA cannot give a rule an arbitrary RuleId.  Rules whose RuleId
+//! a2x would generate (from the namespace, policy, and rule name) are
+//! named so they keep it; any other RuleId is named in a comment
+//! within the rule.
+
+use crate::ast::advice::AdviceDef;
+use crate::ast::attribute::Attribute;
+use crate::ast::category::{standard_categories, Category};
+use crate::ast::constant::quote_string;
+use crate::ast::externpolicy::{ExternKind, ExternPolicy};
+use crate::ast::function::{Function, FunctionInputArg, FunctionInputs, FunctionOutputArg};
+use crate::ast::infix::Infix;
+use crate::ast::obligation::ObligationDef;
+use crate::ast::policycombinator::{standard_policycombinators, PolicyCombinator};
+use crate::ast::prescription::PrescriptionType;
+use crate::ast::rule::Effect;
+use crate::ast::rulecombinator::{standard_rulecombinators, RuleCombinator};
+use crate::ast::std_attributes::standard_attributes;
+use crate::ast::std_functions::standard_functions;
+use crate::ast::std_infix::standard_infix;
+use crate::ast::typedef::{
+    standard_types, TypeDef, BOOLEAN_URI, DOUBLE_URI, INTEGER_URI, STRING_URI,
+};
+use crate::ast::version::{VersionConstraints, DEFAULT_VERSION};
+use crate::ast::AsAlfa;
+use crate::context::TypedLiteral;
+use crate::errors::ParseError;
+use crate::xacml::xapply::XApply;
+use crate::xacml::xcombinerparam::XCombinerParam;
+use crate::xacml::xcondition::{FunctionTypeResolved, ResolvedAtomicName, XCondition};
+use crate::xacml::xexpression::XExpression;
+use crate::xacml::xpolicy::XPolicy;
+use crate::xacml::xpolicyentry::{XIdReference, XPolicyEntry};
+use crate::xacml::xpolicyissuer::XIssuerAttribute;
+use crate::xacml::xpolicyset::XPolicySet;
+use crate::xacml::xprescription::{XAttributeAssignmentArgument, XPrescriptions};
+use crate::xacml::xrule::XRule;
+use crate::xacml::xtarget::{XMatch, XTarget};
+use crate::xacml::XTopPolicy;
+use log::warn;
+use std::collections::{HashMap, HashSet};
+
+/// Namespace that decompiled policies are placed in, if none is given.
+pub const DEFAULT_NAMESPACE: &str = "main";
+
+const ANY_OF_ANY_URI: &str = "urn:oasis:names:tc:xacml:3.0:function:any-of-any";
+const ON_PERMIT_APPLY_SECOND_URI: &str =
+    "urn:oasis:names:tc:xacml:3.0:policy-combining-algorithm:on-permit-apply-second";
+const RULE_PERMIT_OVERRIDES_URI: &str =
+    "urn:oasis:names:tc:xacml:3.0:rule-combining-algorithm:permit-overrides";
+const SUBJECT_CATEGORY_URI: &str = "urn:oasis:names:tc:xacml:1.0:subject-category:access-subject";
+
+/// Words that cannot be used as names, since the grammar would read
+/// them as part of a statement.
+const KEYWORDS: &[&str] = &[
+    "namespace",
+    "import",
+    "policyset",
+    "policy",
+    "rule",
+    "target",
+    "clause",
+    "condition",
+    "apply",
+    "permit",
+    "deny",
+    "on",
+    "advice",
+    "obligation",
+    "attribute",
+    "and",
+    "or",
+    "true",
+    "false",
+    "function",
+    "infix",
+    "type",
+    "category",
+    "ruleCombinator",
+    "policyCombinator",
+    "version",
+    "issuer",
+    "maxDelegationDepth",
+    "extern",
+    "constant",
+    "template",
+    "instance",
+    "param",
+    "with",
+    "id",
+    "mustbepresent",
+];
+
+/// Kinds of names, each of which is unique within the namespace.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+enum Kind {
+    Type,
+    Category,
+    Attribute,
+    Function,
+    Obligation,
+    Advice,
+    RuleCombinator,
+    PolicyCombinator,
+    Policy,
+    Rule,
+}
+
+impl Kind {
+    /// Name to use when a URI has nothing usable, or starts with a
+    /// digit.
+    fn prefix(self) -> &'static str {
+        match self {
+            Kind::Type => "type",
+            Kind::Category => "category",
+            Kind::Attribute => "attribute",
+            Kind::Function => "function",
+            Kind::Obligation => "obligation",
+            Kind::Advice => "advice",
+            Kind::RuleCombinator => "ruleCombinator",
+            Kind::PolicyCombinator => "policyCombinator",
+            Kind::Policy => "policy",
+            Kind::Rule => "rule",
+        }
+    }
+}
+
+/// Decompile policies and policysets into the text of an ALFA
+/// namespace.
+///
+/// # Errors
+///
+/// Returns `Err` if the namespace is not a valid ALFA namespace, or
+/// if a value or identifier cannot be written in ALFA.
+pub fn decompile(policies: &[XTopPolicy], namespace: &str) -> Result<String, ParseError> {
+    if !namespace.split('.').all(is_identifier) {
+        return Err(ParseError::DecompileError(format!(
+            "{namespace:?} is not a valid namespace"
+        )));
+    }
+    let mut d = Decompiler::new(namespace);
+    // top-level policies are named first, so that references
+    // between them can be resolved in any order.
+    for p in policies {
+        let id = match p {
+            XTopPolicy::Policy(xp) => &xp.id,
+            XTopPolicy::PolicySet(xps) => &xps.id,
+        };
+        if !d.policy_names.contains_key(id) {
+            let name = d.unique_name(Kind::Policy, id);
+            d.policy_names.insert(id.clone(), name);
+        }
+    }
+    let mut body = String::new();
+    for p in policies {
+        body.push('\n');
+        match p {
+            XTopPolicy::Policy(xp) => {
+                let name = d.policy_names[&xp.id].clone();
+                d.policy(xp, &name, None, &mut body, 1)?;
+            }
+            XTopPolicy::PolicySet(xps) => {
+                let name = d.policy_names[&xps.id].clone();
+                d.policyset(xps, &name, None, &mut body, 1)?;
+            }
+        }
+    }
+    Ok(d.finish(namespace, &body))
+}
+
+/// Mapping of URIs to names, and the declarations that were needed.
+struct Decompiler {
+    ns: Vec<String>,
+    /// Builtin type names, by URI.
+    std_types: HashMap<String, String>,
+    /// Builtin type URIs, by name.
+    std_type_uris: HashMap<String, String>,
+    /// Builtin category names, by URI.
+    std_categories: HashMap<String, String>,
+    /// Builtin category URIs, by name.
+    std_category_uris: HashMap<String, String>,
+    std_attributes: Vec<Attribute>,
+    /// Builtin functions, by URI.
+    std_functions: HashMap<String, Function>,
+    std_rule_combinators: HashMap<String, String>,
+    std_policy_combinators: HashMap<String, String>,
+    infix: Vec<Infix>,
+    /// Names that are taken, by kind.
+    used: HashMap<Kind, HashSet<String>>,
+    /// Names of the policies and policysets being decompiled, by id.
+    policy_names: HashMap<String, String>,
+    /// Names of the policysets and policy currently being written,
+    /// outermost first.  RuleIds that a2x generates include these.
+    path: Vec<String>,
+    types: Vec<TypeDef>,
+    categories: Vec<Category>,
+    attributes: Vec<Attribute>,
+    functions: Vec<Function>,
+    obligations: Vec<ObligationDef>,
+    advice: Vec<AdviceDef>,
+    rule_combinators: Vec<RuleCombinator>,
+    policy_combinators: Vec<PolicyCombinator>,
+    externs: Vec<ExternPolicy>,
+}
+
+impl Decompiler {
+    fn new(namespace: &str) -> Self {
+        let types = standard_types();
+        let categories = standard_categories();
+        let functions = standard_functions();
+        let rcs = standard_rulecombinators();
+        let pcs = standard_policycombinators();
+        let attributes = standard_attributes();
+        let mut used: HashMap<Kind, HashSet<String>> = HashMap::new();
+        used.insert(Kind::Type, types.iter().map(|t| t.id.clone()).collect());
+        used.insert(
+            Kind::Category,
+            categories.iter().map(|c| c.id.clone()).collect(),
+        );
+        used.insert(
+            Kind::Attribute,
+            attributes.iter().map(|a| a.id.clone()).collect(),
+        );
+        used.insert(
+            Kind::Function,
+            functions.iter().map(|f| f.id.clone()).collect(),
+        );
+        used.insert(
+            Kind::RuleCombinator,
+            rcs.iter().map(|c| c.id.clone()).collect(),
+        );
+        used.insert(
+            Kind::PolicyCombinator,
+            pcs.iter().map(|c| c.id.clone()).collect(),
+        );
+        Decompiler {
+            ns: namespace.split('.').map(str::to_owned).collect(),
+            std_types: by_uri(
+                types
+                    .iter()
+                    .map(|t| (t.uri.clone(), t.id.clone()))
+                    .collect(),
+            ),
+            std_type_uris: types.into_iter().map(|t| (t.id, t.uri)).collect(),
+            std_categories: by_uri(
+                categories
+                    .iter()
+                    .map(|c| (c.uri.clone(), c.id.clone()))
+                    .collect(),
+            ),
+            std_category_uris: categories.into_iter().map(|c| (c.id, c.uri)).collect(),
+            std_attributes: attributes,
+            std_functions: by_uri(
+                functions
+                    .into_iter()
+                    .map(|f| (f.function_uri.clone(), f))
+                    .collect(),
+            ),
+            std_rule_combinators: by_uri(rcs.into_iter().map(|c| (c.uri, c.id)).collect()),
+            std_policy_combinators: by_uri(pcs.into_iter().map(|c| (c.uri, c.id)).collect()),
+            infix: standard_infix(),
+            used,
+            policy_names: HashMap::new(),
+            path: vec![],
+            types: vec![],
+            categories: vec![],
+            attributes: vec![],
+            functions: vec![],
+            obligations: vec![],
+            advice: vec![],
+            rule_combinators: vec![],
+            policy_combinators: vec![],
+            externs: vec![],
+        }
+    }
+
+    /// Wrap the rendered policies in a namespace, preceded by the
+    /// declarations they need.
+    fn finish(&self, namespace: &str, body: &str) -> String {
+        let mut decls: Vec<String> = vec![];
+        let mut group = |items: Vec<String>| {
+            if !items.is_empty() {
+                decls.push(items.concat());
+            }
+        };
+        group(self.types.iter().map(|x| x.to_alfa(1)).collect());
+        group(self.categories.iter().map(|x| x.to_alfa(1)).collect());
+        group(self.attributes.iter().map(|x| x.to_alfa(1)).collect());
+        group(self.functions.iter().map(|x| x.to_alfa(1)).collect());
+        group(self.rule_combinators.iter().map(|x| x.to_alfa(1)).collect());
+        group(
+            self.policy_combinators
+                .iter()
+                .map(|x| x.to_alfa(1))
+                .collect(),
+        );
+        group(self.obligations.iter().map(|x| x.to_alfa(1)).collect());
+        group(self.advice.iter().map(|x| x.to_alfa(1)).collect());
+        group(self.externs.iter().map(|x| x.to_alfa(1)).collect());
+        let mut out = format!("namespace {namespace} {{\n");
+        out.push_str(&decls.join("\n"));
+        out.push_str(body);
+        out.push_str("}\n");
+        out
+    }
+
+    /// A name for a URI that is not yet taken by anything of the
+    /// same kind, which is then reserved.
+    fn unique_name(&mut self, kind: Kind, uri: &str) -> String {
+        let used = self.used.entry(kind).or_default();
+        let name = unique_in(used, &base_name(kind, uri));
+        used.insert(name.clone());
+        name
+    }
+
+    fn type_name(&mut self, uri: &str) -> String {
+        if let Some(n) = self.std_types.get(uri) {
+            return n.clone();
+        }
+        if let Some(t) = self.types.iter().find(|t| t.uri == uri) {
+            return t.id.clone();
+        }
+        let id = self.unique_name(Kind::Type, uri);
+        self.types.push(TypeDef {
+            id: id.clone(),
+            uri: uri.to_owned(),
+            ns: self.ns.clone(),
+        });
+        id
+    }
+
+    fn category_name(&mut self, uri: &str) -> String {
+        if let Some(n) = self.std_categories.get(uri) {
+            return n.clone();
+        }
+        if let Some(c) = self.categories.iter().find(|c| c.uri == uri) {
+            return c.id.clone();
+        }
+        let id = self.unique_name(Kind::Category, uri);
+        self.categories.push(Category {
+            id: id.clone(),
+            uri: uri.to_owned(),
+            ns: self.ns.clone(),
+        });
+        id
+    }
+
+    /// Name of an existing attribute (builtin, or already declared)
+    /// with this URI, and optionally a specific category and type.
+    fn find_attribute(
+        &self,
+        uri: &str,
+        category: Option<&str>,
+        type_uri: Option<&str>,
+    ) -> Option<String> {
+        let std = self.std_attributes.iter().find(|a| {
+            a.uri == uri
+                && category.is_none_or(|c| {
+                    self.std_category_uris
+                        .get(&a.category)
+                        .is_some_and(|u| u == c)
+                })
+                && type_uri
+                    .is_none_or(|t| self.std_type_uris.get(&a.typedef).is_some_and(|u| u == t))
+        });
+        if let Some(a) = std {
+            return Some(a.id.clone());
+        }
+        self.attributes
+            .iter()
+            .find(|a| {
+                a.uri == uri
+                    && category.is_none_or(|c| self.category_uri(&a.category) == c)
+                    && type_uri.is_none_or(|t| self.type_uri(&a.typedef) == t)
+            })
+            .map(|a| a.id.clone())
+    }
+
+    /// URI of a declared category.
+    fn category_uri(&self, name: &str) -> &str {
+        self.std_category_uris
+            .get(name)
+            .map(String::as_str)
+            .or_else(|| {
+                self.categories
+                    .iter()
+                    .find(|c| c.id == name)
+                    .map(|c| c.uri.as_str())
+            })
+            .unwrap_or_default()
+    }
+
+    /// URI of a declared type.
+    fn type_uri(&self, name: &str) -> &str {
+        self.std_type_uris
+            .get(name)
+            .map(String::as_str)
+            .or_else(|| {
+                self.types
+                    .iter()
+                    .find(|t| t.id == name)
+                    .map(|t| t.uri.as_str())
+            })
+            .unwrap_or_default()
+    }
+
+    /// Declare a new attribute.
+    fn declare_attribute(&mut self, uri: &str, category: &str, type_uri: &str) -> String {
+        let category = self.category_name(category);
+        let typedef = self.type_name(type_uri);
+        let id = self.unique_name(Kind::Attribute, uri);
+        self.attributes.push(Attribute {
+            id: id.clone(),
+            typedef,
+            category,
+            uri: uri.to_owned(),
+            ns: self.ns.clone(),
+        });
+        id
+    }
+
+    /// Name of an attribute, as it is used by a designator.
+    fn attribute_name(&mut self, uri: &str, category: &str, type_uri: &str) -> String {
+        self.find_attribute(uri, Some(category), Some(type_uri))
+            .unwrap_or_else(|| self.declare_attribute(uri, category, type_uri))
+    }
+
+    /// Name of the attribute that an obligation or advice value is
+    /// assigned to.  The type of the attribute does not affect the
+    /// assignment, so any attribute with the right URI and category
+    /// will do.
+    fn assignment_name(&mut self, uri: &str, category: &str, type_uri: &str) -> String {
+        self.find_attribute(uri, Some(category), Some(type_uri))
+            .or_else(|| self.find_attribute(uri, Some(category), None))
+            .unwrap_or_else(|| self.declare_attribute(uri, category, type_uri))
+    }
+
+    /// Name of the attribute for a policy issuer.  Issuers have no
+    /// category, but the value must have the type of the attribute.
+    fn issuer_name(&mut self, uri: &str, type_uri: &str) -> String {
+        self.find_attribute(uri, None, Some(type_uri))
+            .unwrap_or_else(|| self.declare_attribute(uri, SUBJECT_CATEGORY_URI, type_uri))
+    }
+
+    /// Name of a function.  Unknown functions are declared to accept
+    /// any arguments; if they are used where a boolean is required,
+    /// they are declared to return one.
+    fn function_name(&mut self, uri: &str, boolean: bool) -> String {
+        if let Some(f) = self.std_functions.get(uri) {
+            return f.id.clone();
+        }
+        if let Some(f) = self.functions.iter_mut().find(|f| f.function_uri == uri) {
+            if boolean {
+                f.output_arg = FunctionOutputArg::Atomic("boolean".to_owned());
+            }
+            return f.id.clone();
+        }
+        let id = self.unique_name(Kind::Function, uri);
+        self.functions.push(Function {
+            id: id.clone(),
+            ns: self.ns.clone(),
+            function_uri: uri.to_owned(),
+            input_args: FunctionInputs {
+                args: vec![FunctionInputArg::AnyAtomicOrBag],
+                wildcard: true,
+            },
+            output_arg: if boolean {
+                FunctionOutputArg::Atomic("boolean".to_owned())
+            } else {
+                FunctionOutputArg::AnyAtomic
+            },
+        });
+        id
+    }
+
+    fn rule_combinator_name(&mut self, uri: &str) -> String {
+        if let Some(n) = self.std_rule_combinators.get(uri) {
+            return n.clone();
+        }
+        if let Some(c) = self.rule_combinators.iter().find(|c| c.uri == uri) {
+            return c.id.clone();
+        }
+        let id = self.unique_name(Kind::RuleCombinator, uri);
+        self.rule_combinators.push(RuleCombinator {
+            id: id.clone(),
+            uri: uri.to_owned(),
+            ns: self.ns.clone(),
+        });
+        id
+    }
+
+    fn policy_combinator_name(&mut self, uri: &str) -> String {
+        if let Some(n) = self.std_policy_combinators.get(uri) {
+            return n.clone();
+        }
+        if let Some(c) = self.policy_combinators.iter().find(|c| c.uri == uri) {
+            return c.id.clone();
+        }
+        let id = self.unique_name(Kind::PolicyCombinator, uri);
+        self.policy_combinators.push(PolicyCombinator {
+            id: id.clone(),
+            uri: uri.to_owned(),
+            ns: self.ns.clone(),
+        });
+        id
+    }
+
+    fn obligation_name(&mut self, uri: &str) -> String {
+        if let Some(o) = self.obligations.iter().find(|o| o.uri == uri) {
+            return o.id.clone();
+        }
+        let id = self.unique_name(Kind::Obligation, uri);
+        self.obligations.push(ObligationDef {
+            id: id.clone(),
+            uri: uri.to_owned(),
+            ns: self.ns.clone(),
+        });
+        id
+    }
+
+    fn advice_name(&mut self, uri: &str) -> String {
+        if let Some(a) = self.advice.iter().find(|a| a.uri == uri) {
+            return a.id.clone();
+        }
+        let id = self.unique_name(Kind::Advice, uri);
+        self.advice.push(AdviceDef {
+            id: id.clone(),
+            uri: uri.to_owned(),
+            ns: self.ns.clone(),
+        });
+        id
+    }
+
+    /// Name of a referenced policy or policyset; one that is not
+    /// being decompiled is declared `extern`.
+    fn reference_name(&mut self, id: &str, kind: ExternKind) -> String {
+        if let Some(n) = self.policy_names.get(id) {
+            return n.clone();
+        }
+        if let Some(e) = self.externs.iter().find(|e| e.uri == id && e.kind == kind) {
+            return e.id.clone();
+        }
+        let name = self.unique_name(Kind::Policy, id);
+        self.externs.push(ExternPolicy {
+            id: name.clone(),
+            kind,
+            uri: id.to_owned(),
+            ns: self.ns.clone(),
+            ..Default::default()
+        });
+        name
+    }
+
+    fn policy(
+        &mut self,
+        p: &XPolicy,
+        name: &str,
+        condition: Option<&XCondition>,
+        out: &mut String,
+        indent: usize,
+    ) -> Result<(), ParseError> {
+        let pad = "  ".repeat(indent);
+        description(p.description.as_deref(), out, indent);
+        let params = self.params(&p.entry_params)?;
+        out.push_str(&format!(
+            "{pad}policy {name} = {}{params} {{\n",
+            raw_quote(&p.id)?
+        ));
+        self.common(
+            &p.version,
+            p.max_delegation_depth,
+            &p.issuer,
+            &p.target,
+            condition,
+            out,
+            indent + 1,
+        )?;
+        let alg = self.rule_combinator_name(&p.combining_alg);
+        out.push_str(&format!(
+            "{pad}  apply {alg}{}\n",
+            self.params(&p.combiner_params)?
+        ));
+        let mut rule_names = HashSet::new();
+        self.path.push(name.to_owned());
+        for r in &p.rules {
+            self.rule(r, &mut rule_names, out, indent + 1)?;
+        }
+        self.path.pop();
+        self.prescriptions(&p.prescriptions, out, indent + 1)?;
+        out.push_str(&format!("{pad}}}\n"));
+        Ok(())
+    }
+
+    fn policyset(
+        &mut self,
+        ps: &XPolicySet,
+        name: &str,
+        condition: Option<&XCondition>,
+        out: &mut String,
+        indent: usize,
+    ) -> Result<(), ParseError> {
+        if condition.is_none()
+            && let Some((orig, cond)) = undecondition(ps)
+        {
+            return match &orig {
+                XPolicyEntry::Policy(p) => self.policy(p, name, Some(cond), out, indent),
+                XPolicyEntry::PolicySet(s) => self.policyset(s, name, Some(cond), out, indent),
+                _ => unreachable!("only policies and policysets are deconditioned"),
+            };
+        }
+        let pad = "  ".repeat(indent);
+        description(ps.description.as_deref(), out, indent);
+        let params = self.params(&ps.entry_params)?;
+        out.push_str(&format!(
+            "{pad}policyset {name} = {}{params} {{\n",
+            raw_quote(&ps.id)?
+        ));
+        self.common(
+            &ps.version,
+            ps.max_delegation_depth,
+            &ps.issuer,
+            &ps.target,
+            condition,
+            out,
+            indent + 1,
+        )?;
+        let alg = self.policy_combinator_name(&ps.combining_alg);
+        out.push_str(&format!(
+            "{pad}  apply {alg}{}\n",
+            self.params(&ps.combiner_params)?
+        ));
+        self.path.push(name.to_owned());
+        for c in &ps.children {
+            match c {
+                XPolicyEntry::Policy(p) => {
+                    let n = self.unique_name(Kind::Policy, &p.id);
+                    self.policy(p, &n, None, out, indent + 1)?;
+                }
+                XPolicyEntry::PolicySet(s) => {
+                    let n = self.unique_name(Kind::Policy, &s.id);
+                    self.policyset(s, &n, None, out, indent + 1)?;
+                }
+                XPolicyEntry::PolicyIdRef(r) => {
+                    self.reference(r, ExternKind::Policy, out, indent + 1)?;
+                }
+                XPolicyEntry::PolicySetIdRef(r) => {
+                    self.reference(r, ExternKind::PolicySet, out, indent + 1)?;
+                }
+            }
+        }
+        self.path.pop();
+        self.prescriptions(&ps.prescriptions, out, indent + 1)?;
+        out.push_str(&format!("{pad}}}\n"));
+        Ok(())
+    }
+
+    /// Statements shared by policies and policysets, which precede
+    /// the combining algorithm.
+    #[allow(clippy::too_many_arguments)]
+    fn common(
+        &mut self,
+        version: &str,
+        max_delegation_depth: Option<u32>,
+        issuer: &[XIssuerAttribute],
+        target: &XTarget,
+        condition: Option<&XCondition>,
+        out: &mut String,
+        indent: usize,
+    ) -> Result<(), ParseError> {
+        let pad = "  ".repeat(indent);
+        if version != DEFAULT_VERSION {
+            out.push_str(&format!("{pad}version {}\n", raw_quote(version)?));
+        }
+        if let Some(d) = max_delegation_depth {
+            out.push_str(&format!("{pad}maxDelegationDepth {d}\n"));
+        }
+        if !issuer.is_empty() {
+            out.push_str(&format!("{pad}issuer {{\n"));
+            for i in issuer {
+                let name = self.issuer_name(&i.id, &i.value.v.type_uri);
+                let value = self.literal(&i.value.v)?;
+                out.push_str(&format!("{pad}  {name} = {value}\n"));
+            }
+            out.push_str(&format!("{pad}}}\n"));
+        }
+        self.target(target, out, indent)?;
+        if let Some(c) = condition {
+            let c = self.expression(&c.expr, true, false)?;
+            out.push_str(&format!("{pad}condition {c}\n"));
+        }
+        Ok(())
+    }
+
+    fn rule(
+        &mut self,
+        r: &XRule,
+        names: &mut HashSet<String>,
+        out: &mut String,
+        indent: usize,
+    ) -> Result<(), ParseError> {
+        let pad = "  ".repeat(indent);
+        description(r.description.as_deref(), out, indent);
+        // a2x generates RuleIds from the namespace, the policy path,
+        // and the rule name (or a fragment, for anonymous rules), so
+        // those RuleIds are kept by naming the rule the same way.
+        let scope = format!("{}/{}", self.ns.join("/"), self.path.join("/"));
+        let generated = r
+            .id
+            .rfind(&scope)
+            .map(|i| &r.id[i + scope.len()..])
+            .filter(|rest| rest.starts_with('#') || rest.starts_with('/'));
+        let (name, lost) = match generated {
+            Some(rest) if rest.starts_with('#') => (String::new(), false),
+            Some(rest)
+                if is_identifier(&rest[1..])
+                    && !KEYWORDS.contains(&&rest[1..])
+                    && !names.contains(&rest[1..]) =>
+            {
+                (format!(" {}", &rest[1..]), false)
+            }
+            _ => (
+                format!(" {}", unique_in(names, &base_name(Kind::Rule, &r.id))),
+                true,
+            ),
+        };
+        if !name.is_empty() {
+            names.insert(name.trim_start().to_owned());
+        }
+        let params = self.params(&r.entry_params)?;
+        out.push_str(&format!("{pad}rule{name}{params} {{\n"));
+        if lost {
+            warn!("RuleId {:?} cannot be kept in ALFA", r.id);
+            out.push_str(&format!("{pad}  // RuleId {:?} cannot be kept in ALFA\n", r.id));
+        }
+        out.push_str(&format!("{pad}  {}\n", r.effect.to_lowercase()));
+        self.target(&r.target, out, indent + 1)?;
+        if let Some(c) = &r.condition {
+            let c = self.expression(&c.expr, true, false)?;
+            out.push_str(&format!("{pad}  condition {c}\n"));
+        }
+        self.prescriptions(&r.prescriptions, out, indent + 1)?;
+        out.push_str(&format!("{pad}}}\n"));
+        Ok(())
+    }
+
+    fn reference(
+        &mut self,
+        r: &XIdReference,
+        kind: ExternKind,
+        out: &mut String,
+        indent: usize,
+    ) -> Result<(), ParseError> {
+        let pad = "  ".repeat(indent);
+        let name = self.reference_name(&r.id, kind);
+        let versions = VersionConstraints {
+            version: r.version.clone(),
+            earliest: r.earliest_version.clone(),
+            latest: r.latest_version.clone(),
+        };
+        let versions = if versions.is_empty() {
+            String::new()
+        } else {
+            versions.to_string()
+        };
+        let params = self.params(&r.entry_params)?;
+        out.push_str(&format!("{pad}{name}{versions}{params}\n"));
+        Ok(())
+    }
+
+    /// Combiner parameters, as `with (name = value, ...)`.  These
+    /// follow the name of a child, or the combining algorithm.
+    fn params(&mut self, params: &[XCombinerParam]) -> Result<String, ParseError> {
+        if params.is_empty() {
+            return Ok(String::new());
+        }
+        let mut ps = vec![];
+        for p in params {
+            if !is_identifier(&p.name) {
+                return Err(ParseError::DecompileError(format!(
+                    "combiner parameter {:?} is not a valid ALFA identifier",
+                    p.name
+                )));
+            }
+            ps.push(format!("{} = {}", p.name, self.literal(&p.value.v)?));
+        }
+        Ok(format!(" with ({})", ps.join(", ")))
+    }
+
+    fn target(&mut self, t: &XTarget, out: &mut String, indent: usize) -> Result<(), ParseError> {
+        let pad = "  ".repeat(indent);
+        for (i, anyof) in t.anyofs.iter().enumerate() {
+            let mut allofs = vec![];
+            for allof in &anyof.allofs {
+                let mut matches = vec![];
+                for m in &allof.matches {
+                    matches.push(self.target_match(m)?);
+                }
+                allofs.push(matches.join(" and "));
+            }
+            let keyword = if i == 0 { "target" } else { "      " };
+            out.push_str(&format!("{pad}{keyword} clause {}\n", allofs.join(" or ")));
+        }
+        Ok(())
+    }
+
+    /// A match, using an infix operator if there is one that
+    /// compiles to the same function.
+    fn target_match(&mut self, m: &XMatch) -> Result<String, ParseError> {
+        let attr =
+            self.attribute_name(&m.designator_id, &m.designator_category, &m.designator_type);
+        let attr = designator(attr, m.must_be_present, m.issuer.as_deref())?;
+        let lit = self.literal(&TypedLiteral {
+            type_uri: m.value_type.clone(),
+            value: m.value.clone(),
+        })?;
+        for infix in &self.infix {
+            let sig = infix.signatures.iter().find(|s| {
+                s.output == "boolean"
+                    && self.std_type_uris.get(&s.first_arg) == Some(&m.value_type)
+                    && self.std_type_uris.get(&s.second_arg) == Some(&m.designator_type)
+            });
+            if sig.is_some_and(|s| s.uri == m.matchid) {
+                let op = &infix.operator;
+                return Ok(if infix.commutative {
+                    format!("{attr} {op} {lit}")
+                } else {
+                    format!("{lit} {op} {attr}")
+                });
+            }
+        }
+        let f = self.function_name(&m.matchid, true);
+        Ok(format!("{f}({lit}, {attr})"))
+    }
+
+    /// An expression within a condition.  Infix applications that
+    /// are operands of another infix operator are parenthesized.
+    fn expression(
+        &mut self,
+        e: &XExpression,
+        boolean: bool,
+        operand: bool,
+    ) -> Result<String, ParseError> {
+        match e {
+            XExpression::Value(v) => self.literal(&v.v),
+            XExpression::Attrib(a) => {
+                let name = self.attribute_name(&a.uri, &a.category, &a.type_uri);
+                designator(name, a.must_be_present, a.issuer.as_deref())
+            }
+            XExpression::Function(f) => Ok(format!(
+                "function[{}]",
+                self.function_name(&f.function_uri, false)
+            )),
+            XExpression::Apply(a) => {
+                if let Some((op, l, r)) = self.infix_form(a) {
+                    let s = format!(
+                        "{} {op} {}",
+                        self.expression(l, false, true)?,
+                        self.expression(r, false, true)?
+                    );
+                    return Ok(if operand { format!("({s})") } else { s });
+                }
+                let name = self.function_name(&a.function_uri, boolean);
+                let mut args = vec![];
+                for (i, x) in a.arguments.iter().enumerate() {
+                    let boolean = self.boolean_arg(&a.function_uri, i);
+                    args.push(self.expression(x, boolean, false)?);
+                }
+                Ok(format!("{name}({})", args.join(", ")))
+            }
+        }
+    }
+
+    /// Whether a builtin function requires a boolean for an argument.
+    fn boolean_arg(&self, uri: &str, index: usize) -> bool {
+        let Some(f) = self.std_functions.get(uri) else {
+            return false;
+        };
+        let args = &f.input_args.args;
+        let arg = args.get(index).or(if f.input_args.wildcard {
+            args.last()
+        } else {
+            None
+        });
+        arg.is_some_and(|a| *a == FunctionInputArg::Atomic("boolean".to_owned()))
+    }
+
+    /// The operator and operands, if an application would be
+    /// compiled from an infix operator.  This is either a direct
+    /// application of the operator's function, or `any-of-any` of
+    /// that function when an operand is a bag.
+    fn infix_form<'a>(&self, a: &'a XApply) -> Option<(String, &'a XExpression, &'a XExpression)> {
+        let (uri, bags, l, r) = match a.arguments.as_slice() {
+            [XExpression::Function(f), l, r] if a.function_uri == ANY_OF_ANY_URI => {
+                (f.function_uri.as_str(), true, l, r)
+            }
+            [l, r] => (a.function_uri.as_str(), false, l, r),
+            _ => return None,
+        };
+        let lt = operand_type(l)?;
+        let rt = operand_type(r)?;
+        if (lt.is_bag() || rt.is_bag()) != bags {
+            return None;
+        }
+        let accepts = |t: &FunctionTypeResolved, sig_type: &str| match t {
+            FunctionTypeResolved::Atomic(n) => self
+                .std_type_uris
+                .get(sig_type)
+                .is_some_and(|u| u == n.uri()),
+            _ => true,
+        };
+        for infix in &self.infix {
+            let sig = infix
+                .signatures
+                .iter()
+                .find(|s| accepts(&lt, &s.first_arg) && accepts(&rt, &s.second_arg));
+            if let Some(sig) = sig
+                && sig.uri == uri
+                && (!bags || (infix.allow_bags && sig.output == "boolean"))
+            {
+                return Some((infix.operator.clone(), l, r));
+            }
+        }
+        None
+    }
+
+    fn prescriptions(
+        &mut self,
+        p: &XPrescriptions,
+        out: &mut String,
+        indent: usize,
+    ) -> Result<(), ParseError> {
+        let pad = "  ".repeat(indent);
+        // consecutive prescriptions for the same effect share a block
+        let mut effect: Option<&Effect> = None;
+        for x in &p.exprs {
+            if effect != Some(&x.fulfill_on) {
+                if effect.is_some() {
+                    out.push_str(&format!("{pad}}}\n"));
+                }
+                let e = x.fulfill_on.to_string().to_lowercase();
+                out.push_str(&format!("{pad}on {e} {{\n"));
+                effect = Some(&x.fulfill_on);
+            }
+            let (keyword, name) = match x.ptype {
+                PrescriptionType::Obligation => ("obligation", self.obligation_name(&x.id)),
+                PrescriptionType::Advice => ("advice", self.advice_name(&x.id)),
+            };
+            if x.assignments.is_empty() {
+                out.push_str(&format!("{pad}  {keyword} {name} {{}}\n"));
+                continue;
+            }
+            out.push_str(&format!("{pad}  {keyword} {name} {{\n"));
+            for a in &x.assignments {
+                let (type_uri, value) = match &a.arg {
+                    XAttributeAssignmentArgument::Value(v) => {
+                        (v.v.type_uri.clone(), self.literal(&v.v)?)
+                    }
+                    XAttributeAssignmentArgument::Attrib(d) => {
+                        let n = self.attribute_name(&d.uri, &d.category, &d.type_uri);
+                        (
+                            d.type_uri.clone(),
+                            designator(n, d.must_be_present, d.issuer.as_deref())?,
+                        )
+                    }
+                };
+                let dest = self.assignment_name(&a.id, &a.category, &type_uri);
+                out.push_str(&format!("{pad}    {dest} = {value}\n"));
+            }
+            out.push_str(&format!("{pad}  }}\n"));
+        }
+        if effect.is_some() {
+            out.push_str(&format!("{pad}}}\n"));
+        }
+        Ok(())
+    }
+
+    /// A literal value.  Strings, numbers, and booleans are written
+    /// directly; anything else is a string with a type.
+    fn literal(&mut self, v: &TypedLiteral) -> Result<String, ParseError> {
+        let s = &v.value;
+        match v.type_uri.as_str() {
+            STRING_URI => return Ok(quote_string(s)),
+            INTEGER_URI if is_integer(s) => return Ok(s.clone()),
+            DOUBLE_URI if is_double(s) => return Ok(s.clone()),
+            BOOLEAN_URI if s == "true" || s == "false" => return Ok(s.clone()),
+            _ => {}
+        }
+        let t = self.type_name(&v.type_uri);
+        Ok(format!("{}:{t}", raw_quote(s)?))
+    }
+}
+
+/// Builtins by URI.  The first of several builtins with the same URI
+/// is used.
+fn by_uri<T>(pairs: Vec<(String, T)>) -> HashMap<String, T> {
+    let mut m = HashMap::new();
+    for (uri, x) in pairs {
+        m.entry(uri).or_insert(x);
+    }
+    m
+}
+
+/// If this policyset is the container that a2x creates for a policy
+/// or policyset with a condition, return the original (with the
+/// container's identity restored) and the condition.
+fn undecondition(ps: &XPolicySet) -> Option<(XPolicyEntry, &XCondition)> {
+    if ps.combining_alg != ON_PERMIT_APPLY_SECOND_URI
+        || !ps.target.anyofs.is_empty()
+        || !ps.prescriptions.exprs.is_empty()
+        || !ps.combiner_params.is_empty()
+    {
+        return None;
+    }
+    let [XPolicyEntry::Policy(cond), orig] = ps.children.as_slice() else {
+        return None;
+    };
+    if cond.combining_alg != RULE_PERMIT_OVERRIDES_URI
+        || cond.version != ps.version
+        || !cond.target.anyofs.is_empty()
+        || !cond.prescriptions.exprs.is_empty()
+        || !cond.issuer.is_empty()
+        || cond.max_delegation_depth.is_some()
+        || !cond.combiner_params.is_empty()
+        || !cond.entry_params.is_empty()
+    {
+        return None;
+    }
+    let [rule] = cond.rules.as_slice() else {
+        return None;
+    };
+    let condition = rule.condition.as_ref()?;
+    if rule.effect != "Permit"
+        || !rule.target.anyofs.is_empty()
+        || !rule.prescriptions.exprs.is_empty()
+        || !rule.entry_params.is_empty()
+    {
+        return None;
+    }
+    let mut orig = orig.clone();
+    match &mut orig {
+        XPolicyEntry::Policy(p) => {
+            if !restore(ps, &mut p.id, &mut p.description, &p.version, &p.issuer)
+                || p.max_delegation_depth.is_some()
+                || !p.entry_params.is_empty()
+            {
+                return None;
+            }
+            p.issuer.clone_from(&ps.issuer);
+            p.max_delegation_depth = ps.max_delegation_depth;
+            p.entry_params.clone_from(&ps.entry_params);
+        }
+        XPolicyEntry::PolicySet(s) => {
+            if !restore(ps, &mut s.id, &mut s.description, &s.version, &s.issuer)
+                || s.max_delegation_depth.is_some()
+                || !s.entry_params.is_empty()
+            {
+                return None;
+            }
+            s.issuer.clone_from(&ps.issuer);
+            s.max_delegation_depth = ps.max_delegation_depth;
+            s.entry_params.clone_from(&ps.entry_params);
+        }
+        _ => return None,
+    }
+    Some((orig, condition))
+}
+
+/// Give the original element the container's id and description,
+/// if it has no description or issuer of its own, and the same
+/// version.
+fn restore(
+    ps: &XPolicySet,
+    id: &mut String,
+    description: &mut Option<String>,
+    version: &str,
+    issuer: &[XIssuerAttribute],
+) -> bool {
+    if description.is_some() || !issuer.is_empty() || version != ps.version {
+        return false;
+    }
+    id.clone_from(&ps.id);
+    description.clone_from(&ps.description);
+    true
+}
+
+/// Type of an infix operand, if it can be one.
+fn operand_type(e: &XExpression) -> Option<FunctionTypeResolved> {
+    match e {
+        XExpression::Value(v) => Some(FunctionTypeResolved::Atomic(ResolvedAtomicName::new(
+            &v.v.type_uri,
+        ))),
+        XExpression::Attrib(a) => Some(FunctionTypeResolved::AtomicBag(ResolvedAtomicName::new(
+            &a.type_uri,
+        ))),
+        XExpression::Apply(a) => match &a.return_type {
+            t @ (FunctionTypeResolved::Atomic(_) | FunctionTypeResolved::AtomicBag(_)) => {
+                Some(t.clone())
+            }
+            _ => None,
+        },
+        XExpression::Function(_) => None,
+    }
+}
+
+/// An attribute designator, with its options.
+fn designator(
+    name: String,
+    must_be_present: bool,
+    issuer: Option<&str>,
+) -> Result<String, ParseError> {
+    let mut options = vec![];
+    if must_be_present {
+        options.push("mustbepresent".to_owned());
+    }
+    if let Some(i) = issuer {
+        options.push(format!("issuer={}", raw_quote(i)?));
+    }
+    if options.is_empty() {
+        Ok(name)
+    } else {
+        Ok(format!("{name}[{}]", options.join(" ")))
+    }
+}
+
+/// A description, as a comment.
+fn description(d: Option<&str>, out: &mut String, indent: usize) {
+    let Some(d) = d.filter(|d| !d.trim().is_empty()) else {
+        return;
+    };
+    let d = if d.contains("*/") {
+        warn!("description {d:?} contains the end of a comment, which was replaced");
+        d.replace("*/", "* /")
+    } else {
+        d.to_owned()
+    };
+    out.push_str(&format!("{}/* {d} */\n", "  ".repeat(indent)));
+}
+
+/// A string that is used without unescaping (identifiers, and values
+/// of custom literals).
+fn raw_quote(s: &str) -> Result<String, ParseError> {
+    if !s.ends_with('\\') {
+        if !s.contains('"') {
+            return Ok(format!("\"{s}\""));
+        }
+        if !s.contains('\'') {
+            return Ok(format!("'{s}'"));
+        }
+    }
+    Err(ParseError::DecompileError(format!(
+        "{s:?} cannot be written as an ALFA string"
+    )))
+}
+
+fn is_integer(s: &str) -> bool {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_double(s: &str) -> bool {
+    let s = s.strip_prefix('-').unwrap_or(s);
+    s.split_once('.').is_some_and(|(whole, frac)| {
+        whole.chars().all(|c| c.is_ascii_digit())
+            && !frac.is_empty()
+            && frac.chars().all(|c| c.is_ascii_digit())
+    })
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic())
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// An identifier from the last segment of a URI, in camel case.
+fn base_name(kind: Kind, uri: &str) -> String {
+    let segment = uri
+        .rsplit([':', '/', '#'])
+        .find(|s| s.chars().any(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or_default();
+    let mut name = String::new();
+    let mut upper = false;
+    for c in segment.chars() {
+        if c.is_ascii_alphanumeric() {
+            if upper && !name.is_empty() {
+                name.push(c.to_ascii_uppercase());
+            } else {
+                name.push(c);
+            }
+            upper = false;
+        } else {
+            upper = true;
+        }
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name = format!("{}{name}", kind.prefix());
+    }
+    // an attribute starting with `true` or `false` would be read as
+    // a boolean within a condition.
+    if name.starts_with("true") || name.starts_with("false") {
+        name[..1].make_ascii_uppercase();
+    }
+    name
+}
+
+/// A name that is not a keyword or in `used`, adding a number to
+/// `base` if needed.
+fn unique_in(used: &HashSet<String>, base: &str) -> String {
+    if !used.contains(base) && !KEYWORDS.contains(&base) {
+        return base.to_owned();
+    }
+    (2..)
+        .map(|n| format!("{base}{n}"))
+        .find(|n| !used.contains(n))
+        .unwrap_or_default()
+}
@@ -50,6 +50,12 @@ pub enum ParseError {
     XacmlWriteIoError,
     #[error("Could not determine policy filename")]
     XacmlMissingFilename,
+    #[error("Could not serialize XACML: {}", _0)]
+    XacmlSerializeError(String),
+    #[error("Bundle root is not a policyset: {}", _0)]
+    BundleRootNotFound(String),
+    #[error("Policy references form a cycle: {}", _0)]
+    ReferenceCycle(String),
     #[error("Duplicate URI for Policy/PolicySet: {}", _0)]
     DuplicateURI(String),
     #[error("PolicySet has no condition, cannot convert to condition structure")]
@@ -60,6 +66,32 @@ pub enum ParseError {
     PolicyHasCondition,
     #[error("A PolicySet and Policy have the same name in the same policy: {}", _0)]
     DuplicatePolicyEntity(String),
+    #[error("Invalid default policy version: {}", _0)]
+    InvalidVersion(String),
+    #[error("Unsupported XACML version: {} (expected 2.0 or 3.0)", _0)]
+    InvalidXacmlVersion(String),
+    #[error("Unsupported ID strategy: {} (expected counter, hash, or uuid)", _0)]
+    InvalidIdStrategy(String),
+    #[error("Unsupported provenance: {} (expected off, comment, or description)", _0)]
+    InvalidProvenance(String),
+    #[error("Invalid output style: {}", _0)]
+    InvalidOutputStyle(String),
+    #[error("Invalid output layout: {}", _0)]
+    InvalidOutputLayout(String),
+    #[error("Output path {} is used by both {} and {}", _0, _1, _2)]
+    OutputPathCollision(String, String, String),
+    #[error("Invalid manifest of generated files: {}", _0)]
+    InvalidManifest(String),
+    #[error("Could not read XACML: {}", _0)]
+    XacmlReadError(String),
+    #[error("Could not read ALFA: {}", _0)]
+    AlfaReadError(String),
+    #[error("Could not decompile XACML into ALFA: {}", _0)]
+    DecompileError(String),
+    #[error("Could not format ALFA: {}", _0)]
+    FormatError(String),
+    #[error("Could not build policy: {}", _0)]
+    BuildError(String),
     #[error(transparent)]
     #[diagnostic(transparent)]
     SrcError(#[from] SrcError),
@@ -75,6 +107,9 @@ pub struct SrcError {
     msg: String,
     //    #[source_code]
     src: Arc<NamedSource<String>>,
+    /// Additional locations (possibly in other files) that are
+    /// relevant to this error.
+    related: Vec<SrcError>,
 }
 
 impl Diagnostic for SrcError {
@@ -85,6 +120,14 @@ impl Diagnostic for SrcError {
     fn source_code(&self) -> Option<&dyn miette::SourceCode> {
         Some(&*self.src)
     }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        if self.related.is_empty() {
+            None
+        } else {
+            Some(Box::new(self.related.iter().map(|r| r as &dyn Diagnostic)))
+        }
+    }
 }
 
 impl fmt::Display for SrcError {
@@ -102,8 +145,101 @@ impl SrcError {
                 src_loc.get_span(),
             )],
             msg: msg.to_owned(),
+            related: vec![],
+        })
+    }
+
+    /// Create an error that points at two locations.
+    ///
+    /// If both locations are in the same source, a single error with
+    /// two labels is created.  Otherwise, the second location is
+    /// attached as a related diagnostic.
+    pub fn err_at_both(
+        msg: &str,
+        label: &str,
+        src_loc: SrcLoc,
+        other_label: &str,
+        other_src_loc: SrcLoc,
+    ) -> ParseError {
+        let mut labels = vec![LabeledSpan::new_with_span(
+            Some(label.to_owned()),
+            src_loc.get_span(),
+        )];
+        let mut related = vec![];
+        if src_loc.get_src() == other_src_loc.get_src() {
+            labels.push(LabeledSpan::new_with_span(
+                Some(other_label.to_owned()),
+                other_src_loc.get_span(),
+            ));
+        } else {
+            related.push(SrcError::related_at(other_label, other_src_loc));
+        }
+        ParseError::SrcError(SrcError {
+            src: src_loc.get_src(),
+            labels,
+            msg: msg.to_owned(),
+            related,
         })
     }
+
+    /// Attach an additional location to an existing error.  Errors
+    /// without source information, or which already point at this
+    /// location, are returned unchanged.
+    pub fn with_related(err: ParseError, label: &str, src_loc: SrcLoc) -> ParseError {
+        match err {
+            ParseError::SrcError(mut e) => {
+                let related = SrcError::related_at(label, src_loc);
+                let labeled = e.src == related.src
+                    && e.labels.iter().any(|l| l.inner() == related.labels[0].inner());
+                if !labeled && !e.related.contains(&related) {
+                    e.related.push(related);
+                }
+                ParseError::SrcError(e)
+            }
+            e => e,
+        }
+    }
+
+    /// Place an error that was raised without a source location (such
+    /// as from a symbol lookup) at `src_loc`.  Other errors are
+    /// returned unchanged.
+    pub fn with_fallback_location(err: ParseError, src_loc: SrcLoc) -> ParseError {
+        match err {
+            ParseError::SrcError(mut e) if e.src == SrcLoc::default().get_src() => {
+                e.src = src_loc.get_src();
+                e.labels = e
+                    .labels
+                    .iter()
+                    .map(|l| {
+                        LabeledSpan::new_with_span(l.label().map(str::to_owned), src_loc.get_span())
+                    })
+                    .collect();
+                ParseError::SrcError(e)
+            }
+            e => e,
+        }
+    }
+
+    /// Name of the source the error refers to.
+    #[must_use]
+    pub fn source_name(&self) -> &str {
+        self.src.name()
+    }
+
+    /// Additional locations relevant to this error.
+    #[must_use]
+    pub fn related_errors(&self) -> &[SrcError] {
+        &self.related
+    }
+
+    fn related_at(label: &str, src_loc: SrcLoc) -> SrcError {
+        SrcError {
+            src: src_loc.get_src(),
+            labels: vec![LabeledSpan::new_with_span(None, src_loc.get_span())],
+            msg: label.to_owned(),
+            related: vec![],
+        }
+    }
 }
 
 impl From<pest::error::Error<Rule>> for ParseError {
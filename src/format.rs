@@ -0,0 +1,1262 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Format ALFA source in a canonical style.
+//!
+//! The source is parsed, and re-emitted from the parse tree, so
+//! every comment is kept where it was written, including those that
+//! become the `<Description>` of a policy or rule.  Statements are
+//! indented by two spaces, and a single blank line is kept wherever
+//! the source separated statements, or the braces of a block from
+//! its statements, with one or more.  The matches
+//! of a target clause are written one per line, with their
+//! operators aligned, and conditions that do not fit within
+//! [`MAX_WIDTH`] columns are wrapped at their function arguments,
+//! and at their loosest operators, such as `||` before `&&` and `&&`
+//! before `==`, wrapping operands only when they still do not fit.
+//!
+//! The formatted source is parsed again, and compared with the
+//! original, so formatting never changes the meaning of a policy.
+//! Generated identifiers from the `hash` strategy are derived from
+//! the source text, and may change when it is reformatted.
+
+use crate::errors::ParseError;
+use crate::{AlfaDocParser, Rule};
+use pest::iterators::Pair;
+use pest::Parser;
+use std::collections::HashSet;
+
+/// Conditions are wrapped to fit within this many columns, where
+/// possible.
+pub const MAX_WIDTH: usize = 80;
+
+const INDENT: &str = "  ";
+
+/// Format ALFA source.
+///
+/// # Errors
+///
+/// Returns `Err` if the source cannot be parsed, or if the formatted
+/// source would not parse into the same policies.
+pub fn format_alfa(src: &str) -> Result<String, ParseError> {
+    let doc = AlfaDocParser::parse(Rule::alfa_doc, src)?
+        .next()
+        .ok_or(ParseError::AstConvertError)?;
+    let mut f = Formatter::new(src);
+    let items = doc
+        .into_inner()
+        .filter(|p| p.as_rule() != Rule::EOI)
+        .collect();
+    f.block(items, 0);
+    let formatted = f.finish();
+    verify(src, &formatted)?;
+    Ok(formatted)
+}
+
+/// Whether ALFA source is already formatted.
+///
+/// # Errors
+///
+/// Returns `Err` if the source cannot be formatted.
+pub fn is_formatted(src: &str) -> Result<bool, ParseError> {
+    Ok(format_alfa(src)? == src)
+}
+
+/// Writes lines of formatted source.
+struct Formatter<'a> {
+    src: &'a str,
+    lines: Vec<String>,
+    /// The line being written, including its indentation.
+    cur: String,
+    /// Current indentation level.
+    indent: usize,
+    /// Source positions of comments that are written after the
+    /// statement they were parsed in.
+    trailing: HashSet<usize>,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(src: &'a str) -> Self {
+        Formatter {
+            src,
+            lines: vec![],
+            cur: String::new(),
+            indent: 0,
+            trailing: HashSet::new(),
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.flush();
+        let mut out = self.lines.join("\n");
+        out.push('\n');
+        out
+    }
+
+    /// End the current line, if anything was written to it.
+    fn flush(&mut self) {
+        if !self.cur.trim().is_empty() {
+            self.lines.push(self.cur.trim_end().to_owned());
+        }
+        self.cur.clear();
+    }
+
+    /// Start a new, indented, line.
+    fn new_line(&mut self) {
+        self.flush();
+        self.cur = INDENT.repeat(self.indent);
+    }
+
+    fn blank_line(&mut self) {
+        self.flush();
+        if self.lines.last().is_some_and(|l| !l.is_empty()) {
+            self.lines.push(String::new());
+        }
+    }
+
+    /// Append text to the current line.  Any newlines in the text
+    /// are kept, and the lines following them are written as-is.
+    fn text(&mut self, s: &str) {
+        let mut parts = s.split('\n');
+        if let Some(first) = parts.next() {
+            self.cur.push_str(first);
+        }
+        for part in parts {
+            self.flush();
+            self.cur.push_str(part);
+        }
+    }
+
+    /// Append a word to the current line, separated by a space.
+    fn word(&mut self, s: &str) {
+        if !self.cur.trim().is_empty() && !self.cur.ends_with(' ') {
+            self.cur.push(' ');
+        }
+        self.text(s);
+    }
+
+    /// Column at the end of the current line.
+    fn col(&self) -> usize {
+        self.cur.chars().count()
+    }
+
+    /// A comment within a statement, kept in place.  Anything after
+    /// a single-line comment continues on the next line.
+    fn inline_comment(&mut self, c: &Pair<'a, Rule>) {
+        let s = self.comment(c);
+        if !s.is_empty() {
+            self.word(&s);
+        }
+    }
+
+    /// Text of a comment, as it is written within a statement, or
+    /// nothing if it is written after the statement.
+    fn comment(&self, c: &Pair<'a, Rule>) -> String {
+        self.comment_text(c)
+            .map(|t| self.break_after(t))
+            .unwrap_or_default()
+    }
+
+    /// A comment, followed by a line break if it is a single-line
+    /// comment.
+    fn break_after(&self, text: String) -> String {
+        if text.starts_with("//") {
+            format!("{text}\n{}", INDENT.repeat(self.indent + 2))
+        } else {
+            text
+        }
+    }
+
+    /// Text of a comment, unless it is written after the statement.
+    fn comment_text(&self, c: &Pair<'a, Rule>) -> Option<String> {
+        let start = c.as_span().start();
+        (!self.trailing.contains(&start)).then(|| c.as_str().trim_end().to_owned())
+    }
+
+    /// Write a sequence of statements and comments, each on its own
+    /// line.  Comments that followed a statement on the same line
+    /// stay there.  `prev_end` is the source position where the
+    /// sequence started, and the position where it ends is returned.
+    fn block(&mut self, items: Vec<Pair<'a, Rule>>, mut prev_end: usize) -> usize {
+        // comments after the end of a statement are parsed as part of
+        // it, but are written after it, like those between statements
+        let mut entries = vec![];
+        for item in items {
+            let trailing = self.trailing_comments(&item);
+            self.trailing
+                .extend(trailing.iter().map(|c| c.as_span().start()));
+            entries.push(item);
+            entries.extend(trailing);
+        }
+        for item in entries {
+            let span = item.as_span();
+            let newlines = self.src[prev_end.min(span.start())..span.start()]
+                .matches('\n')
+                .count();
+            let is_comment = item.as_rule() == Rule::COMMENT;
+            prev_end = if is_comment {
+                span.start() + item.as_str().trim_end().len()
+            } else {
+                self.code_end(&item)
+            };
+            if is_comment && newlines == 0 {
+                self.flush();
+                if let Some(last) = self.lines.last_mut()
+                    && !last.is_empty()
+                {
+                    last.push(' ');
+                    last.push_str(item.as_str().trim_end());
+                    continue;
+                }
+            }
+            if newlines > 1 {
+                self.blank_line();
+            }
+            self.new_line();
+            if is_comment {
+                self.text(item.as_str().trim_end());
+            } else {
+                self.statement(item);
+            }
+            self.flush();
+        }
+        prev_end
+    }
+
+    /// Comments parsed at the end of a statement, after all of its
+    /// code.
+    fn trailing_comments(&self, item: &Pair<'a, Rule>) -> Vec<Pair<'a, Rule>> {
+        let end = item.as_span().end();
+        item.clone()
+            .into_inner()
+            .flatten()
+            .filter(|c| {
+                c.as_rule() == Rule::COMMENT && skip_space(self.src, c.as_span().start()) >= end
+            })
+            .collect()
+    }
+
+    /// Source position at the end of the code of a statement, before
+    /// any trailing comments.
+    fn code_end(&self, item: &Pair<'a, Rule>) -> usize {
+        let start = item.as_span().start();
+        let end = self
+            .trailing_comments(item)
+            .first()
+            .map_or(item.as_span().end(), |c| c.as_span().start());
+        start + self.src[start..end].trim_end().len()
+    }
+
+    fn statement(&mut self, p: Pair<'a, Rule>) {
+        let rule = p.as_rule();
+        match rule {
+            Rule::alfa_statement
+            | Rule::policy_stmt
+            | Rule::policyset_stmt
+            | Rule::apply_prescription => {
+                self.children(p, Self::statement);
+            }
+            Rule::namespace => self.braced(p, "namespace", &[Rule::ns_identifier]),
+            Rule::policyset_decl | Rule::policy_decl => {
+                let keyword = if rule == Rule::policy_decl {
+                    "policy"
+                } else {
+                    "policyset"
+                };
+                let head = [
+                    Rule::policy_with_id,
+                    Rule::policy_with_name,
+                    Rule::policy_empty,
+                    Rule::combiner_params,
+                ];
+                self.braced(p, keyword, &head);
+            }
+            Rule::rule_decl => {
+                self.braced(p, "rule", &[Rule::identifier, Rule::combiner_params]);
+            }
+            Rule::on_effect => {
+                self.braced(p, "on", &[Rule::effect_permit, Rule::effect_deny]);
+            }
+            Rule::issuer_stmt => self.braced(p, "issuer", &[]),
+            Rule::attribute_decl => self.braced(p, "attribute", &[Rule::identifier]),
+            Rule::apply_obligation | Rule::apply_advice => {
+                let keyword = if rule == Rule::apply_obligation {
+                    "obligation"
+                } else {
+                    "advice"
+                };
+                let name_end = p
+                    .clone()
+                    .into_inner()
+                    .find(|c| c.as_rule() == Rule::elem_identifier)
+                    .map_or(p.as_span().end(), |c| c.as_span().end());
+                if self.src.as_bytes().get(skip_space(self.src, name_end)) == Some(&b'{') {
+                    self.braced(p, keyword, &[Rule::elem_identifier]);
+                } else {
+                    self.word(keyword);
+                    self.children(p, Self::head);
+                }
+            }
+            Rule::template_decl => {
+                self.word("template");
+                self.children(p, Self::template);
+            }
+            Rule::infix_decl => self.infix(p),
+            Rule::target_stmt => self.target(p),
+            Rule::condition_stmt => self.condition(p),
+            Rule::effect_permit | Rule::effect_deny => self.word(p.as_str()),
+            _ => self.simple(p),
+        }
+    }
+
+    /// Call `f` for each child of a pair, keeping comments in place.
+    fn children(&mut self, p: Pair<'a, Rule>, mut f: impl FnMut(&mut Self, Pair<'a, Rule>)) {
+        for c in p.into_inner() {
+            if c.as_rule() == Rule::COMMENT {
+                self.inline_comment(&c);
+            } else {
+                f(self, c);
+            }
+        }
+    }
+
+    /// A statement with a keyword, a head with the rules in `head`,
+    /// and a body of statements in braces.
+    fn braced(&mut self, p: Pair<'a, Rule>, keyword: &str, head: &[Rule]) {
+        let start = p.as_span().start();
+        let inner: Vec<Pair<'a, Rule>> = p.into_inner().collect();
+        let head_end = inner
+            .iter()
+            .filter(|c| head.contains(&c.as_rule()))
+            .map(|c| c.as_span().end())
+            .max()
+            .unwrap_or(start + keyword.len());
+        let brace = skip_space(self.src, head_end);
+        self.word(keyword);
+        let mut body = vec![];
+        for c in inner {
+            if c.as_rule() == Rule::policy_empty {
+                continue;
+            }
+            if c.as_span().start() < brace {
+                if c.as_rule() == Rule::COMMENT {
+                    self.inline_comment(&c);
+                } else {
+                    self.head(c);
+                }
+            } else {
+                body.push(c);
+            }
+        }
+        self.body(body, brace);
+    }
+
+    /// Statements in braces, starting at the source position of the
+    /// opening brace.
+    fn body(&mut self, items: Vec<Pair<'a, Rule>>, brace: usize) {
+        if items.is_empty() {
+            self.word("{}");
+            return;
+        }
+        self.word("{");
+        self.flush();
+        self.indent += 1;
+        let end = self.block(items, brace + 1);
+        self.indent -= 1;
+        let space = &self.src[end..];
+        let close = space.find(|c: char| !c.is_whitespace()).unwrap_or(space.len());
+        if space[..close].matches('\n').count() > 1 {
+            self.blank_line();
+        }
+        self.new_line();
+        self.text("}");
+    }
+
+    /// Part of the head of a statement, before its body.
+    fn head(&mut self, p: Pair<'a, Rule>) {
+        match p.as_rule() {
+            Rule::policy_with_id | Rule::policy_with_name => self.children(p, Self::head),
+            Rule::string_literal => {
+                self.word("=");
+                self.word(p.as_str());
+            }
+            Rule::combiner_params => {
+                let s = self.combiner_params(p);
+                self.word(&s);
+            }
+            _ => self.word(p.as_str()),
+        }
+    }
+
+    fn template(&mut self, p: Pair<'a, Rule>) {
+        match p.as_rule() {
+            Rule::template_policy => {
+                self.word("policy");
+                self.children(p, Self::template);
+            }
+            Rule::template_rule => {
+                self.word("rule");
+                self.children(p, Self::template);
+            }
+            Rule::template_params => {
+                let params = self.list(p, |_, c| c.as_str().to_owned());
+                self.text(&format!("({params})"));
+            }
+            Rule::template_policy_body | Rule::template_rule_body => {
+                let brace = p.as_span().start();
+                self.body(p.into_inner().collect(), brace);
+            }
+            _ => self.word(p.as_str()),
+        }
+    }
+
+    fn infix(&mut self, p: Pair<'a, Rule>) {
+        self.word("infix");
+        let inner: Vec<Pair<'a, Rule>> = p.into_inner().collect();
+        let end_of = |rule| {
+            inner
+                .iter()
+                .filter(|c| c.as_rule() == rule)
+                .map(|c| c.as_span().end())
+                .max()
+                .unwrap_or(0)
+        };
+        let brace = skip_to(self.src, end_of(Rule::operator_name), b'{');
+        let close = skip_to(self.src, end_of(Rule::infix_arg_decl), b'}');
+        let mut args = vec![];
+        for c in inner {
+            let start = c.as_span().start();
+            if start > brace && start < close {
+                args.push(c);
+                continue;
+            }
+            if start > close && !args.is_empty() {
+                self.body(std::mem::take(&mut args), brace);
+            }
+            match c.as_rule() {
+                Rule::COMMENT => self.inline_comment(&c),
+                Rule::operator_name => {
+                    self.word(&format!("({})", c.as_str()));
+                    self.word("=");
+                }
+                Rule::infix_inverse => {
+                    self.word("inv");
+                    self.children(c, |f, op| f.word(op.as_str()));
+                }
+                _ => self.word(c.as_str()),
+            }
+        }
+        if !args.is_empty() {
+            self.body(args, brace);
+        }
+    }
+
+    /// Statements that fit on a single line.
+    fn simple(&mut self, p: Pair<'a, Rule>) {
+        let rule = p.as_rule();
+        if let Some(keyword) = keyword(rule) {
+            self.word(keyword);
+        }
+        match rule {
+            Rule::function_decl => self.children(p, |f, c| match c.as_rule() {
+                Rule::string_literal => {
+                    f.word("=");
+                    f.word(c.as_str());
+                }
+                Rule::function_args => {
+                    f.word(":");
+                    f.children(c, |f, a| {
+                        let s = f.func_identifier(a);
+                        f.word(&s);
+                    });
+                }
+                Rule::func_out => {
+                    f.word("->");
+                    let s = f.func_identifier(c);
+                    f.word(&s);
+                }
+                _ => f.word(c.as_str()),
+            }),
+            Rule::infix_arg_decl => {
+                let mut identifiers = 0;
+                self.children(p, |f, c| {
+                    if c.as_rule() == Rule::string_literal {
+                        f.word(c.as_str());
+                        f.word(":");
+                    } else {
+                        identifiers += 1;
+                        if identifiers == 3 {
+                            f.word("->");
+                        }
+                        f.word(c.as_str());
+                    }
+                });
+            }
+            Rule::constant_decl => self.children(p, |f, c| match c.as_rule() {
+                Rule::identifier => f.word(c.as_str()),
+                Rule::elem_identifier => {
+                    f.word(":");
+                    f.word(c.as_str());
+                }
+                _ => {
+                    f.word("=");
+                    let s = f.value(c);
+                    f.word(&s);
+                }
+            }),
+            Rule::instance_decl => {
+                let mut args = vec![];
+                let mut open = false;
+                for c in p.into_inner() {
+                    match c.as_rule() {
+                        Rule::COMMENT if !open => self.inline_comment(&c),
+                        Rule::identifier => {
+                            self.word(c.as_str());
+                            self.word("=");
+                        }
+                        Rule::elem_identifier => {
+                            self.word(c.as_str());
+                            open = true;
+                        }
+                        _ => args.push(c),
+                    }
+                }
+                let args = self.pieces(args, |f, a| f.assignment(a), ", ");
+                self.text(&format!("({args})"));
+            }
+            Rule::policy_reference => self.children(p, |f, c| match c.as_rule() {
+                Rule::version_constraints => {
+                    let s = f.version_constraints(c);
+                    f.text(&s);
+                }
+                _ => f.head(c),
+            }),
+            Rule::extern_decl => self.children(p, |f, c| match c.as_rule() {
+                Rule::version_constraints => {
+                    let s = f.version_constraints(c);
+                    f.word(&s);
+                }
+                _ => f.head(c),
+            }),
+            Rule::version_stmt | Rule::delegation_stmt | Rule::import_decl => {
+                self.children(p, |f, c| f.word(c.as_str()));
+            }
+            Rule::issuer_assignment
+            | Rule::prescription_assignment
+            | Rule::attr_id_assoc
+            | Rule::attr_type_assoc
+            | Rule::attr_category_assoc => {
+                let s = self.assignment(p);
+                self.word(&s);
+            }
+            _ => self.children(p, Self::head),
+        }
+    }
+
+    /// Text of an `<identifier> = <value>` assignment.
+    fn assignment(&self, p: Pair<'a, Rule>) -> String {
+        let keyword = match p.as_rule() {
+            Rule::attr_id_assoc => Some("id"),
+            Rule::attr_type_assoc => Some("type"),
+            Rule::attr_category_assoc => Some("category"),
+            _ => None,
+        };
+        let mut parts: Vec<String> = keyword.iter().map(|k| (*k).to_owned()).collect();
+        let mut named = keyword.is_some();
+        for c in p.into_inner() {
+            if c.as_rule() == Rule::COMMENT {
+                parts.push(self.comment(&c));
+            } else if named {
+                parts.push("=".to_owned());
+                parts.push(self.value(c));
+            } else {
+                parts.push(c.as_str().to_owned());
+                named = true;
+            }
+        }
+        join_words(&parts)
+    }
+
+    /// Text of the children of a pair, separated by commas, with
+    /// comments kept in place.
+    fn list(&self, p: Pair<'a, Rule>, f: impl Fn(&Self, Pair<'a, Rule>) -> String) -> String {
+        self.pieces(p.into_inner().collect(), f, ", ")
+    }
+
+    /// Text of `items`, separated by `sep`, with comments kept in
+    /// place.
+    fn pieces(
+        &self,
+        items: Vec<Pair<'a, Rule>>,
+        f: impl Fn(&Self, Pair<'a, Rule>) -> String,
+        sep: &str,
+    ) -> String {
+        let mut out = String::new();
+        let mut first = true;
+        for c in items {
+            if c.as_rule() == Rule::COMMENT {
+                let s = self.comment(&c);
+                if !out.is_empty() && !out.ends_with(' ') && !out.ends_with('\n') {
+                    out.push(' ');
+                }
+                out.push_str(&s);
+                if !s.contains('\n') {
+                    out.push(' ');
+                }
+                continue;
+            }
+            if !first {
+                out = out.trim_end_matches(' ').to_owned();
+                out.push_str(sep);
+            }
+            first = false;
+            out.push_str(&f(self, c));
+        }
+        out.trim_end_matches(' ').to_owned()
+    }
+
+    fn combiner_params(&self, p: Pair<'a, Rule>) -> String {
+        format!("with ({})", self.list(p, |f, c| f.assignment(c)))
+    }
+
+    fn version_constraints(&self, p: Pair<'a, Rule>) -> String {
+        let constraints = self.list(p, |f, c| {
+            let parts: Vec<String> = c
+                .into_inner()
+                .map(|k| match k.as_rule() {
+                    Rule::COMMENT => f.comment(&k),
+                    Rule::string_literal => format!("= {}", k.as_str()),
+                    _ => k.as_str().to_owned(),
+                })
+                .collect();
+            join_words(&parts)
+        });
+        format!("({constraints})")
+    }
+
+    /// Text of a function argument or result type.
+    fn func_identifier(&self, p: Pair<'a, Rule>) -> String {
+        match p.as_rule() {
+            Rule::func_arg | Rule::func_out | Rule::func_identifier => {
+                self.pieces(p.into_inner().collect(), Self::func_identifier, " ")
+            }
+            Rule::wildcard_arg => {
+                format!(
+                    "{}*",
+                    self.pieces(p.into_inner().collect(), Self::func_identifier, " ")
+                )
+            }
+            Rule::func_bag_ident => {
+                format!(
+                    "bag[{}]",
+                    self.pieces(p.into_inner().collect(), Self::func_identifier, " ")
+                )
+            }
+            Rule::func_bag_anyatomic => "bag[anyAtomic]".to_owned(),
+            _ => p.as_str().to_owned(),
+        }
+    }
+
+    /// Text of a literal or attribute designator.
+    fn value(&self, p: Pair<'a, Rule>) -> String {
+        match p.as_rule() {
+            Rule::custom_literal => self.pieces(p.into_inner().collect(), Self::value, ":"),
+            Rule::constant_reference => {
+                format!(
+                    "constant[{}]",
+                    self.pieces(p.into_inner().collect(), Self::value, "")
+                )
+            }
+            Rule::template_param_ref => {
+                format!(
+                    "param[{}]",
+                    self.pieces(p.into_inner().collect(), Self::value, "")
+                )
+            }
+            Rule::cond_function_ref => {
+                format!(
+                    "function[{}]",
+                    self.pieces(p.into_inner().collect(), Self::value, "")
+                )
+            }
+            Rule::attribute_designator => self.pieces(p.into_inner().collect(), Self::value, ""),
+            Rule::attribute_designator_options => {
+                format!(
+                    "[{}]",
+                    self.pieces(p.into_inner().collect(), Self::value, " ")
+                )
+            }
+            Rule::issuer => {
+                let value = self.pieces(p.into_inner().collect(), Self::value, "");
+                format!("issuer={value}")
+            }
+            _ => p.as_str().to_owned(),
+        }
+    }
+
+    /// A target, with each clause on its own line, and the matches of
+    /// a clause aligned.
+    fn target(&mut self, p: Pair<'a, Rule>) {
+        self.word("target");
+        let clause_col = self.col() + 1;
+        let mut first = true;
+        for c in p.into_inner() {
+            if c.as_rule() == Rule::COMMENT {
+                self.inline_comment(&c);
+                continue;
+            }
+            if !first {
+                self.flush();
+                self.cur = " ".repeat(clause_col);
+            }
+            first = false;
+            self.word("clause");
+            let match_col = self.col() + 1;
+            let matches = self.target_matches(c);
+            let width = matches
+                .iter()
+                .filter(|m| m.op.is_some())
+                .map(|m| m.left.chars().count())
+                .max()
+                .unwrap_or(0);
+            for (i, m) in matches.into_iter().enumerate() {
+                let (line, block): (Vec<_>, Vec<_>) =
+                    m.comments.iter().partition(|c| c.starts_with("//"));
+                if i > 0 {
+                    // line comments end the line of the previous match
+                    for comment in line {
+                        self.word(comment);
+                    }
+                    self.flush();
+                    let sep = m.sep.unwrap_or("and");
+                    self.cur = format!("{}{sep} ", " ".repeat(match_col - sep.len() - 1));
+                } else if !line.is_empty() {
+                    for comment in line {
+                        self.word(comment);
+                    }
+                    self.flush();
+                    self.cur = " ".repeat(match_col);
+                }
+                for comment in block {
+                    self.word(comment);
+                }
+                let s = match &m.op {
+                    Some(op) => format!("{:width$} {op} {}", m.left, m.right),
+                    None => join_words(&[m.left, m.right]),
+                };
+                self.word(&s);
+            }
+        }
+    }
+
+    /// The matches of a target clause, in order.
+    fn target_matches(&self, p: Pair<'a, Rule>) -> Vec<TargetMatch> {
+        let mut matches = vec![];
+        let mut comments = vec![];
+        let mut sep = None;
+        for conj in p.into_inner() {
+            if conj.as_rule() == Rule::COMMENT {
+                comments.extend(self.comment_text(&conj));
+                continue;
+            }
+            for m in conj.into_inner() {
+                if m.as_rule() == Rule::COMMENT {
+                    comments.extend(self.comment_text(&m));
+                    continue;
+                }
+                let rule = m.as_rule();
+                let mut parts = vec![];
+                let mut op = None;
+                for c in m.into_inner() {
+                    match c.as_rule() {
+                        Rule::COMMENT => parts.push(self.comment(&c)),
+                        Rule::operator_identifier => op = Some((parts.len(), c.as_str())),
+                        _ => parts.push(self.value(c)),
+                    }
+                }
+                let (left, op, right) = match (rule, op) {
+                    (Rule::target_match_func, _) => {
+                        let name = parts.remove(0);
+                        (
+                            format!("{name}({})", join_args(&parts)),
+                            None,
+                            String::new(),
+                        )
+                    }
+                    (_, Some((at, op))) => {
+                        let right = parts.split_off(at);
+                        (join_words(&parts), Some(op.to_owned()), join_words(&right))
+                    }
+                    _ => (join_words(&parts), None, String::new()),
+                };
+                matches.push(TargetMatch {
+                    sep,
+                    comments: std::mem::take(&mut comments),
+                    left,
+                    op,
+                    right,
+                });
+                sep = Some("and");
+            }
+            sep = Some("or");
+        }
+        if let Some(last) = matches.last_mut() {
+            let mut right = vec![std::mem::take(&mut last.right)];
+            right.extend(comments.into_iter().map(|c| self.break_after(c)));
+            last.right = join_words(&right);
+        }
+        matches
+    }
+
+    fn condition(&mut self, p: Pair<'a, Rule>) {
+        self.word("condition");
+        for c in p.into_inner() {
+            if c.as_rule() == Rule::COMMENT {
+                self.inline_comment(&c);
+                continue;
+            }
+            let e = self.expr(c);
+            if !self.cur.trim().is_empty() {
+                self.cur.push(' ');
+            }
+            let col = self.col();
+            let lines = layout(&e, col);
+            self.text(&lines.lines.join("\n"));
+        }
+    }
+
+    fn expr(&self, p: Pair<'a, Rule>) -> Expr {
+        let mut operands: Vec<Expr> = vec![];
+        let mut ops = vec![];
+        let mut comments = vec![];
+        for c in p.into_inner() {
+            match c.as_rule() {
+                Rule::COMMENT => comments.extend(self.comment_text(&c)),
+                Rule::operator_identifier => {
+                    // comments before an operator stay with the operand
+                    // they followed
+                    if let Some(last) = operands.last_mut() {
+                        last.after.append(&mut comments);
+                    }
+                    ops.push(c.as_str().to_owned());
+                }
+                _ => {
+                    let mut a = self.atom(c);
+                    comments.append(&mut a.before);
+                    a.before = std::mem::take(&mut comments);
+                    operands.push(a);
+                }
+            }
+        }
+        if let Some(last) = operands.last_mut() {
+            last.after.append(&mut comments);
+        }
+        group(operands, ops)
+    }
+
+    fn atom(&self, p: Pair<'a, Rule>) -> Expr {
+        let mut before = vec![];
+        let mut after = vec![];
+        let mut kind = None;
+        for c in p.into_inner() {
+            match c.as_rule() {
+                Rule::COMMENT if kind.is_none() => before.extend(self.comment_text(&c)),
+                Rule::COMMENT => after.extend(self.comment_text(&c)),
+                Rule::cond_expr => kind = Some(ExprKind::Paren(Box::new(self.expr(c)))),
+                Rule::cond_function_call => kind = Some(self.call(c, &mut after)),
+                _ => kind = Some(ExprKind::Atom(self.value(c))),
+            }
+        }
+        Expr {
+            before,
+            kind: kind.unwrap_or(ExprKind::Atom(String::new())),
+            after,
+        }
+    }
+
+    fn call(&self, p: Pair<'a, Rule>, after: &mut Vec<String>) -> ExprKind {
+        let mut name = String::new();
+        let mut args: Vec<Expr> = vec![];
+        let mut comments = vec![];
+        for c in p.into_inner() {
+            match c.as_rule() {
+                Rule::elem_identifier => name = c.as_str().to_owned(),
+                Rule::COMMENT => comments.extend(self.comment_text(&c)),
+                _ => {
+                    for a in c.into_inner() {
+                        if a.as_rule() == Rule::COMMENT {
+                            comments.extend(self.comment_text(&a));
+                        } else {
+                            let mut e = self.expr(a);
+                            comments.append(&mut e.before);
+                            e.before = std::mem::take(&mut comments);
+                            args.push(e);
+                        }
+                    }
+                }
+            }
+        }
+        match args.last_mut() {
+            Some(last) => last.after.append(&mut comments),
+            None => after.append(&mut comments),
+        }
+        ExprKind::Call(name, args)
+    }
+}
+
+/// A match in a target clause.
+struct TargetMatch {
+    /// `and` or `or`, joining this match to the previous one.
+    sep: Option<&'static str>,
+    /// Comments before the match.
+    comments: Vec<String>,
+    /// The left operand of an infix match, or the whole match.
+    left: String,
+    op: Option<String>,
+    right: String,
+}
+
+/// A condition expression, with the comments around it.
+struct Expr {
+    before: Vec<String>,
+    kind: ExprKind,
+    after: Vec<String>,
+}
+
+enum ExprKind {
+    Atom(String),
+    Paren(Box<Expr>),
+    Call(String, Vec<Expr>),
+    Infix(Vec<Expr>, Vec<String>),
+}
+
+impl Expr {
+    fn new(kind: ExprKind) -> Self {
+        Expr {
+            before: vec![],
+            kind,
+            after: vec![],
+        }
+    }
+
+    /// The expression on a single line, if it can be written that way.
+    fn flat(&self) -> Option<String> {
+        let comments = self.before.iter().chain(&self.after);
+        if comments.clone().any(|c| c.starts_with("//")) {
+            return None;
+        }
+        let kind = match &self.kind {
+            ExprKind::Atom(s) if s.contains('\n') => return None,
+            ExprKind::Atom(s) => s.clone(),
+            ExprKind::Paren(e) => format!("({})", e.flat()?),
+            ExprKind::Call(name, args) => {
+                let args: Option<Vec<String>> = args.iter().map(Expr::flat).collect();
+                format!("{name}({})", args?.join(", "))
+            }
+            ExprKind::Infix(operands, ops) => {
+                let mut s = operands[0].flat()?;
+                for (op, e) in ops.iter().zip(&operands[1..]) {
+                    s = format!("{s} {op} {}", e.flat()?);
+                }
+                s
+            }
+        };
+        let mut parts: Vec<String> = self.before.clone();
+        parts.push(kind);
+        parts.extend(self.after.iter().cloned());
+        Some(parts.join(" "))
+    }
+}
+
+/// Lines of a wrapped expression.  The first line continues the line
+/// the expression started on; the others include their indentation.
+struct Lines {
+    lines: Vec<String>,
+    /// Whether the last line ends with a single-line comment.
+    open: bool,
+}
+
+impl Lines {
+    fn new() -> Self {
+        Lines {
+            lines: vec![String::new()],
+            open: false,
+        }
+    }
+
+    /// Column at the end of the last line, for lines starting at `col`.
+    fn column(&self, col: usize) -> usize {
+        let last = self.lines.last().map_or(0, |l| l.chars().count());
+        if self.lines.len() == 1 {
+            col + last
+        } else {
+            last
+        }
+    }
+
+    /// Append text, starting a new line at `col` after a single-line
+    /// comment.
+    fn push(&mut self, s: &str, col: usize) {
+        if self.open {
+            self.newline(col);
+        }
+        if let Some(last) = self.lines.last_mut() {
+            last.push_str(s);
+        }
+    }
+
+    fn newline(&mut self, col: usize) {
+        self.lines.push(" ".repeat(col));
+        self.open = false;
+    }
+
+    fn comment(&mut self, c: &str, col: usize) {
+        if self.lines.last().is_some_and(|l| !l.trim().is_empty()) && !self.open {
+            self.push(" ", col);
+        }
+        self.push(c, col);
+        if c.starts_with("//") {
+            self.open = true;
+        } else {
+            self.push(" ", col);
+        }
+    }
+
+    fn extend(&mut self, other: Lines, col: usize) {
+        let mut lines = other.lines.into_iter();
+        if let Some(first) = lines.next() {
+            self.push(&first, col);
+        }
+        self.lines.extend(lines);
+        self.open = other.open;
+    }
+}
+
+/// Nest a flat list of operands by the precedence of their operators,
+/// so that an expression is wrapped at its loosest operators first.
+/// No parentheses are added, so the text is unchanged.
+fn group(mut operands: Vec<Expr>, ops: Vec<String>) -> Expr {
+    let Some(loosest) = ops.iter().map(|op| looseness(op)).max() else {
+        return operands.remove(0);
+    };
+    if ops.iter().all(|op| looseness(op) == loosest) {
+        return Expr::new(ExprKind::Infix(operands, ops));
+    }
+    let mut groups = vec![];
+    let mut outer_ops = vec![];
+    let mut inner_operands = vec![];
+    let mut inner_ops = vec![];
+    let mut operands = operands.into_iter();
+    inner_operands.extend(operands.next());
+    for (op, operand) in ops.into_iter().zip(operands) {
+        if looseness(&op) == loosest {
+            let inner = std::mem::take(&mut inner_operands);
+            groups.push(group(inner, std::mem::take(&mut inner_ops)));
+            outer_ops.push(op);
+        } else {
+            inner_ops.push(op);
+        }
+        inner_operands.push(operand);
+    }
+    groups.push(group(inner_operands, inner_ops));
+    Expr::new(ExprKind::Infix(groups, outer_ops))
+}
+
+/// How loosely an infix operator binds, from the groups of operators
+/// by first character, `|` being the loosest and `*` the tightest.
+fn looseness(op: &str) -> u8 {
+    match op.chars().next() {
+        Some('|') => 5,
+        Some('&') => 4,
+        Some('=' | '<' | '>' | '$') => 3,
+        Some('@' | '^') => 2,
+        Some('+' | '-') => 1,
+        _ => 0,
+    }
+}
+
+/// Lay out an expression starting at column `col`, wrapping it if it
+/// does not fit.  Function arguments are aligned after the opening
+/// parenthesis, and infix operators under the first operand.
+fn layout(e: &Expr, col: usize) -> Lines {
+    let mut out = Lines::new();
+    if let Some(flat) = e.flat()
+        && col + flat.chars().count() <= MAX_WIDTH
+    {
+        out.push(&flat, col);
+        return out;
+    }
+    for c in &e.before {
+        out.comment(c, col);
+    }
+    match &e.kind {
+        ExprKind::Atom(s) => out.push(s, col),
+        ExprKind::Paren(inner) => {
+            out.push("(", col);
+            let inner_col = out.column(col);
+            out.extend(layout(inner, inner_col), inner_col);
+            out.push(")", col);
+        }
+        ExprKind::Call(name, args) => {
+            out.push(&format!("{name}("), col);
+            let arg_col = out.column(col);
+            for (i, a) in args.iter().enumerate() {
+                if i > 0 {
+                    out.push(",", arg_col);
+                    out.newline(arg_col);
+                }
+                out.extend(layout(a, arg_col), arg_col);
+            }
+            out.push(")", col);
+        }
+        ExprKind::Infix(operands, ops) => {
+            let start = out.column(col);
+            out.extend(layout(&operands[0], start), start);
+            for (op, operand) in ops.iter().zip(&operands[1..]) {
+                out.newline(start);
+                out.push(&format!("{op} "), start);
+                let operand_col = start + op.chars().count() + 1;
+                out.extend(layout(operand, operand_col), operand_col);
+            }
+        }
+    }
+    for c in &e.after {
+        out.comment(c, col);
+    }
+    if let Some(last) = out.lines.last_mut() {
+        *last = last.trim_end().to_owned();
+    }
+    out
+}
+
+/// Keyword that starts a single-line statement.
+fn keyword(rule: Rule) -> Option<&'static str> {
+    Some(match rule {
+        Rule::import_decl => "import",
+        Rule::type_decl => "type",
+        Rule::cat_decl => "category",
+        Rule::rule_combinator_decl => "ruleCombinator",
+        Rule::policy_combinator_decl => "policyCombinator",
+        Rule::advice_decl => "advice",
+        Rule::obligation_decl => "obligation",
+        Rule::function_decl => "function",
+        Rule::constant_decl => "constant",
+        Rule::instance_decl => "instance",
+        Rule::extern_decl => "extern",
+        Rule::apply_stmt => "apply",
+        Rule::version_stmt => "version",
+        Rule::delegation_stmt => "maxDelegationDepth",
+        _ => return None,
+    })
+}
+
+/// Join words with spaces, without a space after a single-line
+/// comment, which already ends its line.
+fn join_words(words: &[String]) -> String {
+    let mut out = String::new();
+    for w in words.iter().filter(|w| !w.is_empty()) {
+        if !out.is_empty() && !out.ends_with(' ') && !out.ends_with('\n') {
+            out.push(' ');
+        }
+        out.push_str(w);
+    }
+    out
+}
+
+/// Join function arguments, keeping comments before the argument
+/// they precede.
+fn join_args(parts: &[String]) -> String {
+    let mut out = String::new();
+    let mut comment = false;
+    for (i, p) in parts.iter().enumerate() {
+        let is_comment = p.starts_with("//") || p.starts_with("/*");
+        if i > 0 && !comment && !is_comment {
+            out.push(',');
+        }
+        if !out.is_empty() && !out.ends_with(' ') && !out.ends_with('\n') {
+            out.push(' ');
+        }
+        out.push_str(p);
+        comment = is_comment;
+    }
+    out
+}
+
+/// Position of the next character after whitespace and comments.
+fn skip_space(src: &str, mut pos: usize) -> usize {
+    let bytes = src.as_bytes();
+    loop {
+        match bytes.get(pos) {
+            Some(b' ' | b'\t' | b'\n' | b'\r') => pos += 1,
+            Some(b'/') if bytes.get(pos + 1) == Some(&b'/') => {
+                pos = src[pos..].find('\n').map_or(src.len(), |n| pos + n);
+            }
+            Some(b'/') if bytes.get(pos + 1) == Some(&b'*') => {
+                pos = src[pos + 2..].find("*/").map_or(src.len(), |n| pos + n + 4);
+            }
+            _ => return pos,
+        }
+    }
+}
+
+/// Position of the next `c`, after whitespace and comments.
+fn skip_to(src: &str, mut pos: usize, c: u8) -> usize {
+    loop {
+        pos = skip_space(src, pos);
+        if pos >= src.len() || src.as_bytes()[pos] == c {
+            return pos;
+        }
+        pos += 1;
+    }
+}
+
+/// The parse tree of ALFA source, without layout.  Each rule is
+/// recorded when it is entered, with the text of tokens, and
+/// comments are kept separately.
+type Shape = (Vec<(Rule, Option<String>)>, Vec<String>);
+
+fn shape(src: &str) -> Result<Shape, ParseError> {
+    fn walk(p: Pair<Rule>, rules: &mut Vec<(Rule, Option<String>)>, comments: &mut Vec<String>) {
+        if p.as_rule() == Rule::COMMENT {
+            comments.push(p.as_str().trim_end().to_owned());
+            return;
+        }
+        let mut inner = p.clone().into_inner().peekable();
+        if inner.peek().is_none() {
+            rules.push((p.as_rule(), Some(p.as_str().to_owned())));
+        } else {
+            rules.push((p.as_rule(), None));
+            for c in inner {
+                walk(c, rules, comments);
+            }
+        }
+    }
+    let mut rules = vec![];
+    let mut comments = vec![];
+    for p in AlfaDocParser::parse(Rule::alfa_doc, src)? {
+        walk(p, &mut rules, &mut comments);
+    }
+    Ok((rules, comments))
+}
+
+/// Check that formatted source has the same parse tree and comments
+/// as the original.
+fn verify(src: &str, formatted: &str) -> Result<(), ParseError> {
+    let original = shape(src)?;
+    let Ok(reformatted) = shape(formatted) else {
+        return Err(ParseError::FormatError(
+            "the formatted source does not parse".to_owned(),
+        ));
+    };
+    if original.1 != reformatted.1 {
+        return Err(ParseError::FormatError(
+            "comments would be changed".to_owned(),
+        ));
+    }
+    if original.0 != reformatted.0 {
+        return Err(ParseError::FormatError(
+            "the formatted source would have a different meaning".to_owned(),
+        ));
+    }
+    Ok(())
+}
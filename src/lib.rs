@@ -28,24 +28,36 @@ use xacml::xpolicyentry::XPolicyEntry;
 use xacml::XTopPolicy;
 pub mod args;
 pub mod ast;
+pub mod builder;
+#[cfg(feature = "cache")]
+pub mod cache;
+pub mod check;
 pub mod context;
+pub mod decompile;
 pub mod errors;
+pub mod format;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+pub mod manifest;
+pub mod sourcemap;
+pub mod watch;
 pub mod xacml;
 use crate::ast::AstCollection;
 use crate::ast::AstSource;
+use crate::ast::policy::Policy;
+use crate::ast::policyset::PolicySet;
+use crate::ast::version::is_valid_version;
+use crate::xacml::style::{OutputStyle, XmlEncoding};
 use crate::xacml::xpolicyset::XPolicySet;
 //use crate::ast::PrettyPrint;
-use crate::context::Context;
+use crate::context::{Context, XacmlVersion};
 pub use crate::errors::ParseError;
-use crate::xacml::XacmlWriter;
 use log::{info, warn};
-use std::fs::File;
 //use std::io::{self};
 use miette::NamedSource;
 use std::path::Path;
 use std::path::PathBuf;
 use std::rc::Rc;
-use xml::writer::EmitterConfig;
 
 /// A pest parser for the ALFA authorization language.
 #[derive(Parser)]
@@ -142,58 +154,143 @@ pub struct XacmlFile {
     pub contents: Vec<u8>,
 }
 
-/// Write policy files to disk.
+/// Serialize a top-level policy or policyset into an in-memory XACML
+/// file.
+///
+/// # Arguments
+/// * `p` - A converted top-level policy or policyset
+/// * `version` - The XACML version to serialize as
+/// * `style` - Options for XML output (indentation, prefixes, etc.)
 ///
 /// # Errors
 ///
-/// An `Err` is returned if the individual policy file cannot be
-/// created or written to.
+/// Returns `Err` if the policy has no filename, or the XML could not
+/// be serialized.
+pub fn serialize_xentry(
+    p: &XTopPolicy,
+    version: XacmlVersion,
+    style: &OutputStyle,
+) -> Result<XacmlFile, ParseError> {
+    let filename = p.filename().ok_or(ParseError::XacmlMissingFilename)?;
+    let mut contents = vec![];
+    let mut writer = style.emitter_config().create_writer(&mut contents);
+    p.write_xml_version(version, &mut writer, style)
+        .map_err(|e| ParseError::XacmlSerializeError(e.to_string()))?;
+    Ok(XacmlFile {
+        filename: PathBuf::from(filename),
+        contents: style.encoding.encode(contents),
+    })
+}
+
+/// Serialize a top-level policy or policyset into canonical XML
+/// (see [`xacml::canonical`]), so that identical policies always
+/// produce identical bytes.  Only the naming options of the style
+/// (prefix, default namespace, and schema location) apply.
+///
+/// # Errors
+///
+/// Returns `Err` if the policy has no filename, or the XML could not
+/// be serialized.
+pub fn serialize_xentry_canonical(
+    p: &XTopPolicy,
+    version: XacmlVersion,
+    style: &OutputStyle,
+) -> Result<XacmlFile, ParseError> {
+    let style = OutputStyle {
+        indent: None,
+        xml_declaration: false,
+        encoding: XmlEncoding::Utf8,
+        ..style.clone()
+    };
+    let file = serialize_xentry(p, version, &style)?;
+    Ok(XacmlFile {
+        filename: file.filename,
+        contents: xacml::canonical::canonicalize(&file.contents)?,
+    })
+}
+
+/// Serialize top-level policies and policysets into in-memory XACML
+/// files, without touching the filesystem.
+///
+/// # Errors
+///
+/// Returns `Err` if any policy could not be serialized.
+pub fn serialize_xentries(
+    ps: &[XTopPolicy],
+    version: XacmlVersion,
+    style: &OutputStyle,
+) -> Result<Vec<XacmlFile>, ParseError> {
+    ps.iter()
+        .map(|p| serialize_xentry(p, version, style))
+        .collect()
+}
+
+/// Write policy files to disk, as the given version of XACML.
+///
+/// # Errors
+///
+/// An `Err` is returned if the policy cannot be serialized, or if
+/// the directory or policy file cannot be created or written to.
 ///
 /// # Returns
 /// Returns a `Result` containing:
-/// * `Ok(AlfaSyntaxTree)` - Successfully parsed policy combined with `ctx` object
-/// * `Err(ParseError)` - Parse error if the policy string contains invalid syntax
+/// * `Ok(String)` - The path of the written policy file
+/// * `Err(ParseError)` - Error if the policy could not be written
+pub fn write_xentry(
+    dir: &Path,
+    p: &XTopPolicy,
+    version: XacmlVersion,
+) -> Result<String, ParseError> {
+    let file = serialize_xentry(p, version, &OutputStyle::default())?;
+    write_xacml_file(dir, &file)
+}
+
+/// Write a serialized XACML file into a directory.  Directories
+/// within the file's relative path are created as needed.
 ///
-/// # Panics
+/// # Errors
+///
+/// An `Err` is returned if the directory or file cannot be created
+/// or written to.
 ///
-/// A panic occurs if the target directory cannot be created, or if
-/// there are any errors serializing XML.
-pub fn write_xentry(dir: &Path, p: &XTopPolicy) -> Result<String, ParseError> {
-    // ensure directory exists
-    std::fs::create_dir_all(dir).expect("could not create directory");
-    match p {
-        XTopPolicy::PolicySet(xps) => {
-            info!("found xpolicyset");
-            let output_filename = xps
-                .filename
-                .as_ref()
-                .ok_or(ParseError::XacmlMissingFilename)?;
-            let full_path = dir.join(output_filename);
-            let buffer = File::create(&full_path)
-                .map_err(|_x| ParseError::XacmlWriteIoError)?;
-            let mut writer = EmitterConfig::new()
-                .perform_indent(true)
-                .create_writer(buffer);
-            xps.write_xml(&mut writer).expect("unable to write");
-                Ok(full_path.to_str().unwrap_or("<unknown>").to_owned())
+/// # Returns
+/// Returns a `Result` containing:
+/// * `Ok(String)` - The path of the written policy file
+/// * `Err(ParseError)` - Error if the policy could not be written
+pub fn write_xacml_file(dir: &Path, file: &XacmlFile) -> Result<String, ParseError> {
+    let full_path = dir.join(&file.filename);
+    let parent = full_path.parent().unwrap_or(dir);
+    std::fs::create_dir_all(parent).map_err(|_x| ParseError::XacmlWriteIoError)?;
+    std::fs::write(&full_path, &file.contents).map_err(|_x| ParseError::XacmlWriteIoError)?;
+    Ok(full_path.to_str().unwrap_or("<unknown>").to_owned())
+}
 
-        }
-        XTopPolicy::Policy(xp) => {
-            info!("found xpolicy");
-            let output_filename = xp
-                .filename
-                .as_ref()
-                .ok_or(ParseError::XacmlMissingFilename)?;
-            let full_path = dir.join(output_filename);
-            let buffer = File::create(&full_path)
-                .map_err(|_x| ParseError::XacmlWriteIoError)?;
-            let mut writer = EmitterConfig::new()
-                .perform_indent(true)
-                .create_writer(buffer);
-            xp.write_xml(&mut writer).expect("unable to write");
-            Ok(full_path.to_str().unwrap_or("<unknown>").to_owned())
-        }
-   }
+/// Outcome of writing an XACML file that may already exist.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Written {
+    /// The file was created or replaced.
+    Written(String),
+    /// The file already had the same contents, and was left alone.
+    Unchanged(String),
+}
+
+/// Write a serialized XACML file into a directory, unless a file
+/// with the same contents is already there.  Unchanged files keep
+/// their modification time, so tools watching the output directory
+/// only see files whose policies changed.
+///
+/// # Errors
+///
+/// An `Err` is returned if the directory or file cannot be created
+/// or written to.
+pub fn write_xacml_file_if_changed(dir: &Path, file: &XacmlFile) -> Result<Written, ParseError> {
+    let full_path = dir.join(&file.filename);
+    if std::fs::read(&full_path).is_ok_and(|existing| existing == file.contents) {
+        return Ok(Written::Unchanged(
+            full_path.to_str().unwrap_or("<unknown>").to_owned(),
+        ));
+    }
+    write_xacml_file(dir, file).map(Written::Written)
 }
 
 // Simpler method for integration tests, take Alfa Sources, and convert to XACML types, but do not serialize.
@@ -208,6 +305,10 @@ pub fn write_xentry(dir: &Path, p: &XTopPolicy) -> Result<String, ParseError> {
 /// * `Ok(Vec<XTopPolicy>)` - Successfully converted top-level policies
 /// * `Err(ParseError)` - Parse error information if conversion failed
 ///
+/// Sources are compiled in order of filename, and the top-level
+/// policies are returned in that order (policysets first, then
+/// policies), so the same sources always produce the same output.
+///
 /// # Errors
 ///
 /// Returns `Err` if the conversion fails for syntactical or semantic
@@ -218,9 +319,17 @@ pub fn write_xentry(dir: &Path, p: &XTopPolicy) -> Result<String, ParseError> {
 ///
 pub fn alfa_compile(
     ctx: &Rc<Context>,
-    alfa_sources: Vec<AlfaFile>,
+    mut alfa_sources: Vec<AlfaFile>,
 ) -> Result<Vec<XTopPolicy>, ParseError> {
     info!("compiling...");
+    // sources are processed in filename order, so that outputs and
+    // generated IDs do not depend on the order they were found in.
+    alfa_sources.sort_by(|a, b| a.filename.cmp(&b.filename));
+    if let Some(v) = &ctx.config.version
+        && !is_valid_version(v)
+    {
+        return Err(ParseError::InvalidVersion(v.clone()));
+    }
     let mut ast_collection = AstCollection::new(ctx.clone());
     // alfa ast conversion
     eprintln!("Parsing ALFA policies:");
@@ -243,19 +352,60 @@ pub fn alfa_compile(
 
                 ast_collection.add_ast(AstSource { src: alfasrc, ast });
             }
-            Err(e) => {
-                if let ParseError::PestParseError(pe) = e {
-                    // annotate with filename
-                    let ppe = pe.with_path(&asource.filename);
-                    warn!("Parsing error at {ppe}");
-                    return Err(ParseError::PestParseError(Box::new(ppe)));
-                }
-                warn!("Failed to parse document: {e:?}");
-                return Err(e);
-            }
+            Err(e) => return Err(annotate_parse_error(e, &asource.filename)),
         }
     }
     info!("Parsed {} alfa sources into ASTs", ast_collection.len());
+    compile_collection(ast_collection)
+}
+
+/// Add the filename to errors from parsing an ALFA source.
+pub(crate) fn annotate_parse_error(e: ParseError, filename: &str) -> ParseError {
+    if let ParseError::PestParseError(pe) = e {
+        // annotate with filename
+        let ppe = pe.with_path(filename);
+        warn!("Parsing error at {ppe}");
+        return ParseError::PestParseError(Box::new(ppe));
+    }
+    warn!("Failed to parse document: {e:?}");
+    e
+}
+
+/// Compile syntax trees that were built without ALFA source (see
+/// [`builder`]) into XACML.
+///
+/// The trees must have been built with the same context.  Template
+/// instances are expanded, and references resolved, just as for
+/// [`alfa_compile`].
+///
+/// # Errors
+///
+/// Returns `Err` if the conversion fails for semantic reasons.
+pub fn ast_compile(
+    ctx: &Rc<Context>,
+    asts: Vec<ast::AlfaSyntaxTree>,
+) -> Result<Vec<XTopPolicy>, ParseError> {
+    if let Some(v) = &ctx.config.version
+        && !is_valid_version(v)
+    {
+        return Err(ParseError::InvalidVersion(v.clone()));
+    }
+    let mut ast_collection = AstCollection::new(ctx.clone());
+    for ast in asts {
+        ast_collection.add_ast(AstSource {
+            src: NamedSource::new("<builder>", String::new()),
+            ast,
+        });
+    }
+    compile_collection(ast_collection)
+}
+
+/// Convert parsed (or built) syntax trees into top-level XACML
+/// policies.
+pub(crate) fn compile_collection(mut ast_collection: AstCollection) -> Result<Vec<XTopPolicy>, ParseError> {
+    // templates may be declared in any source, so instances are only
+    // expanded once everything has been parsed.
+    ast_collection.expand_templates()?;
     info!(
         "This AST Collection has {} policysets",
         ast_collection.policysets().len()
@@ -279,36 +429,14 @@ pub fn alfa_compile(
     // top policies that will correspond to output files.
     let mut xtoppolicies = vec![];
     for p in ast_collection.policysets() {
-        let xp = XPolicySet::try_from(p.as_ref())?;
-        let fname = xp
-            .filename
-            .as_ref()
-            .expect("filename not determined for this policy");
-        info!("=== Converted PolicySet:  {fname} ===");
-        xtoppolicies.push(XTopPolicy::PolicySet(xp));
+        xtoppolicies.push(convert_policyset(&p)?);
     }
 
     // we will return XFiles
 
     // Each top-level policy becomes an XFile.
     for p in ast_collection.policies() {
-        let xpe = XPolicyEntry::try_from(p.as_ref())?;
-        match xpe {
-            XPolicyEntry::Policy(xp) => {
-                info!("=== Converted Policy:  {:?} ===", xp.filename);
-                xtoppolicies.push(XTopPolicy::Policy(xp));
-            }
-            XPolicyEntry::PolicySet(xpset) => {
-                info!("=== Converted PolicySet:  {:?} ===", xpset.filename);
-                xtoppolicies.push(XTopPolicy::PolicySet(xpset));
-            }
-            _ => {
-                // it should not be possible for the policyEntry
-                // conversion to produce anything other than a policy
-                // or policyset (refs can't be emitted)
-                return Err(ParseError::AstConvertError);
-            }
-        }
+        xtoppolicies.push(convert_policy(&p)?);
     }
 
     // Now we have a pile of ASTs, and we need to convert to (starting
@@ -324,6 +452,38 @@ pub fn alfa_compile(
     Ok(xtoppolicies)
 }
 
+/// Convert a top-level policyset.
+pub(crate) fn convert_policyset(p: &PolicySet) -> Result<XTopPolicy, ParseError> {
+    let xp = XPolicySet::try_from(p)?;
+    let fname = xp
+        .filename
+        .as_ref()
+        .expect("filename not determined for this policy");
+    info!("=== Converted PolicySet:  {fname} ===");
+    Ok(XTopPolicy::PolicySet(xp))
+}
+
+/// Convert a top-level policy.  Policies with a condition become
+/// policysets.
+pub(crate) fn convert_policy(p: &Policy) -> Result<XTopPolicy, ParseError> {
+    match XPolicyEntry::try_from(p)? {
+        XPolicyEntry::Policy(xp) => {
+            info!("=== Converted Policy:  {:?} ===", xp.filename);
+            Ok(XTopPolicy::Policy(xp))
+        }
+        XPolicyEntry::PolicySet(xpset) => {
+            info!("=== Converted PolicySet:  {:?} ===", xpset.filename);
+            Ok(XTopPolicy::PolicySet(xpset))
+        }
+        _ => {
+            // it should not be possible for the policyEntry
+            // conversion to produce anything other than a policy
+            // or policyset (refs can't be emitted)
+            Err(ParseError::AstConvertError)
+        }
+    }
+}
+
 /// The raw parse tree and context of an ALFA source file.
 #[derive(Debug)]
 pub struct AlfaParseTree<'a> {
@@ -366,6 +526,68 @@ mod tests {
         let _ = AlfaDocParser::parse(Rule::cat_decl, input).unwrap();
     }
 
+    #[test]
+    fn test_parse_constant_decl() {
+        // ensure we can parse constants with each kind of literal
+        let input = "constant maxAge : integer = 65";
+        let _ = AlfaDocParser::parse(Rule::constant_decl, input).unwrap();
+        let input = "constant admin : string = \"admin\"";
+        let _ = AlfaDocParser::parse(Rule::constant_decl, input).unwrap();
+        let input = "constant launch : date = \"2025-07-10\":date";
+        let _ = AlfaDocParser::parse(Rule::constant_decl, input).unwrap();
+        // constants cannot be defined in terms of other constants
+        let input = "constant other : integer = constant[maxAge]";
+        assert!(AlfaDocParser::parse(Rule::constant_decl, input).is_err());
+    }
+
+    #[test]
+    fn test_parse_combiner_params() {
+        // parameters on algorithms, and named or anonymous children
+        let input = "apply weighted with (threshold = 5, mode = \"strict\")";
+        let _ = AlfaDocParser::parse(Rule::apply_stmt, input).unwrap();
+        let input = "rule with (weight = 2) { permit }";
+        let _ = AlfaDocParser::parse(Rule::rule_decl, input).unwrap();
+        let input = "rule r with (weight = 2) { permit }";
+        let _ = AlfaDocParser::parse(Rule::rule_decl, input).unwrap();
+        let input = "policy with (weight = 2) { apply firstApplicable rule { permit } }";
+        let _ = AlfaDocParser::parse(Rule::policy_decl, input).unwrap();
+        let input = "child(latest = \"2.*\") with (weight = 2)";
+        let _ = AlfaDocParser::parse(Rule::policy_reference, input).unwrap();
+    }
+
+    #[test]
+    fn test_parse_extern_decl() {
+        // external policies and policysets, optionally with versions
+        let input = "extern policyset legacy = \"urn:example:legacy\"";
+        let _ = AlfaDocParser::parse(Rule::extern_decl, input).unwrap();
+        let input = "extern policy legacy = 'urn:example:legacy' (version = \"1.*\")";
+        let _ = AlfaDocParser::parse(Rule::extern_decl, input).unwrap();
+    }
+
+    #[test]
+    fn test_parse_policy_reference_versions() {
+        // references may be bare, or constrained by version
+        let input = "base.corePolicies";
+        let _ = AlfaDocParser::parse(Rule::policy_reference, input).unwrap();
+        let input = "corePolicies(earliest = \"1.0\", latest = \"1.*\")";
+        let _ = AlfaDocParser::parse(Rule::policy_reference, input).unwrap();
+        let input = "corePolicies(version = '2.+')";
+        let _ = AlfaDocParser::parse(Rule::policy_reference, input).unwrap();
+    }
+
+    #[test]
+    fn test_parse_delegation_stmts() {
+        // issuer attributes are keyed by (possibly qualified) names
+        let input = "issuer { subjectId = \"alice\" main.clearance = 3 }";
+        let _ = AlfaDocParser::parse(Rule::issuer_stmt, input).unwrap();
+        let input = "issuer { }";
+        let _ = AlfaDocParser::parse(Rule::issuer_stmt, input).unwrap();
+        let input = "maxDelegationDepth 3";
+        let _ = AlfaDocParser::parse(Rule::delegation_stmt, input).unwrap();
+        let input = "maxDelegationDepth -1";
+        assert!(AlfaDocParser::parse(Rule::delegation_stmt, input).is_err());
+    }
+
     #[test]
     fn test_parse_infix_no_mods() {
         let input = r#"infix (<) = {
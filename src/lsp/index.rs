@@ -0,0 +1,426 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Declarations and references within a single ALFA document.
+//!
+//! The index is built from the PEG parse tree, rather than the
+//! syntax tree, since the syntax tree does not keep the location of
+//! every name.  Names are recorded as written; resolving a reference
+//! is left to the [`Context`](crate::context::Context).
+
+use crate::{AlfaDocParser, Rule};
+use pest::Parser;
+use pest::iterators::Pair;
+use std::ops::Range;
+
+/// Kinds of ALFA elements that can be declared or referenced.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SymbolKind {
+    Namespace,
+    PolicySet,
+    Policy,
+    Rule,
+    Attribute,
+    Type,
+    Category,
+    Function,
+    Operator,
+    Advice,
+    Obligation,
+    RuleCombinator,
+    PolicyCombinator,
+    Constant,
+    Template,
+    Instance,
+    Extern,
+}
+
+impl SymbolKind {
+    /// The ALFA keyword that declares this kind of element.
+    #[must_use]
+    pub fn keyword(self) -> &'static str {
+        match self {
+            SymbolKind::Namespace => "namespace",
+            SymbolKind::PolicySet => "policyset",
+            SymbolKind::Policy => "policy",
+            SymbolKind::Rule => "rule",
+            SymbolKind::Attribute => "attribute",
+            SymbolKind::Type => "type",
+            SymbolKind::Category => "category",
+            SymbolKind::Function => "function",
+            SymbolKind::Operator => "infix",
+            SymbolKind::Advice => "advice",
+            SymbolKind::Obligation => "obligation",
+            SymbolKind::RuleCombinator => "ruleCombinator",
+            SymbolKind::PolicyCombinator => "policyCombinator",
+            SymbolKind::Constant => "constant",
+            SymbolKind::Template => "template",
+            SymbolKind::Instance => "instance",
+            SymbolKind::Extern => "extern",
+        }
+    }
+}
+
+/// A declaration of a namespace or element.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Definition {
+    pub kind: SymbolKind,
+    /// The name as written, or the keyword for anonymous policies
+    /// and rules.
+    pub label: String,
+    /// Fully qualified name, if it can be determined from the source.
+    pub name: Option<String>,
+    /// Span of the name (or of the declaration, if anonymous).
+    pub selection: Range<usize>,
+    /// Span of the whole declaration.
+    pub span: Range<usize>,
+    /// Index of the enclosing namespace, policyset, policy, or
+    /// template.
+    pub parent: Option<usize>,
+}
+
+/// A name that refers to an element declared elsewhere.
+///
+/// References to policies may resolve to a policyset, policy, or
+/// extern.  Imports are recorded with the `Namespace` kind, with the
+/// text of the import (such as `Attributes.*`).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Reference {
+    pub kind: SymbolKind,
+    /// The name as written.
+    pub text: String,
+    pub span: Range<usize>,
+    /// Namespace where the reference was made.
+    pub ns: Vec<String>,
+}
+
+/// Declarations and references within a document.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct SymbolIndex {
+    pub definitions: Vec<Definition>,
+    pub references: Vec<Reference>,
+}
+
+/// Where a statement appears.
+#[derive(Clone, Default)]
+struct Scope {
+    /// Enclosing namespace.
+    ns: Vec<String>,
+    /// Namespace and enclosing policy names, which qualify declared
+    /// names.  `None` within anonymous policies, whose children are
+    /// named only once IDs are generated.
+    path: Option<Vec<String>>,
+    /// Is this within a policyset (so `apply` names a policy
+    /// combinator)?
+    in_policyset: bool,
+    /// Index of the enclosing definition.
+    parent: Option<usize>,
+}
+
+impl Scope {
+    fn qualify(&self, name: &str) -> Option<String> {
+        self.path.as_ref().map(|p| {
+            let mut v = p.clone();
+            v.push(name.to_owned());
+            v.join(".")
+        })
+    }
+}
+
+impl SymbolIndex {
+    /// Index an ALFA document.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the document is not syntactically valid.
+    pub fn new(text: &str) -> Result<SymbolIndex, Box<pest::error::Error<Rule>>> {
+        let pairs = AlfaDocParser::parse(Rule::alfa_doc, text)?;
+        let mut index = SymbolIndex::default();
+        let scope = Scope {
+            path: Some(vec![]),
+            ..Scope::default()
+        };
+        for p in pairs {
+            index.walk(p, &scope);
+        }
+        Ok(index)
+    }
+
+    /// The reference at a byte offset.
+    #[must_use]
+    pub fn reference_at(&self, offset: usize) -> Option<&Reference> {
+        self.references
+            .iter()
+            .find(|r| r.span.start <= offset && offset <= r.span.end)
+    }
+
+    /// The definition whose name is at a byte offset.
+    #[must_use]
+    pub fn definition_at(&self, offset: usize) -> Option<&Definition> {
+        self.definitions
+            .iter()
+            .find(|d| d.selection.start <= offset && offset <= d.selection.end)
+    }
+
+    /// The innermost namespace containing a byte offset.
+    #[must_use]
+    pub fn namespace_at(&self, offset: usize) -> Option<&Definition> {
+        self.definitions
+            .iter()
+            .filter(|d| {
+                d.kind == SymbolKind::Namespace && d.span.start <= offset && offset <= d.span.end
+            })
+            .max_by_key(|d| d.span.start)
+    }
+
+    fn define(
+        &mut self,
+        kind: SymbolKind,
+        name_pair: Option<&Pair<Rule>>,
+        decl: &Pair<Rule>,
+        scope: &Scope,
+    ) -> usize {
+        let (label, name, selection) = match name_pair {
+            Some(n) => (n.as_str().to_owned(), scope.qualify(n.as_str()), span_of(n)),
+            None => (kind.keyword().to_owned(), None, span_of(decl)),
+        };
+        self.definitions.push(Definition {
+            kind,
+            label,
+            name,
+            selection,
+            span: span_of(decl),
+            parent: scope.parent,
+        });
+        self.definitions.len() - 1
+    }
+
+    fn refer(&mut self, kind: SymbolKind, pair: &Pair<Rule>, scope: &Scope) {
+        self.references.push(Reference {
+            kind,
+            text: pair.as_str().to_owned(),
+            span: span_of(pair),
+            ns: scope.ns.clone(),
+        });
+    }
+
+    /// Record the first inner pair as a reference, and index the rest.
+    fn refer_first(&mut self, kind: SymbolKind, pair: Pair<Rule>, scope: &Scope) {
+        let mut inner = pair.into_inner();
+        if let Some(first) = inner.next() {
+            self.refer(kind, &first, scope);
+        }
+        for p in inner {
+            self.walk(p, scope);
+        }
+    }
+
+    /// Record every `ns_identifier` within a pair as a type reference.
+    fn refer_types(&mut self, pair: Pair<Rule>, scope: &Scope) {
+        for p in pair.into_inner().flatten() {
+            if p.as_rule() == Rule::ns_identifier {
+                self.refer(SymbolKind::Type, &p, scope);
+            }
+        }
+    }
+
+    /// Declarations of the form `keyword name = ...`.
+    fn simple_decl(&mut self, kind: SymbolKind, pair: &Pair<Rule>, scope: &Scope) {
+        let name = pair
+            .clone()
+            .into_inner()
+            .find(|p| matches!(p.as_rule(), Rule::identifier | Rule::function_name));
+        self.define(kind, name.as_ref(), pair, scope);
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn walk(&mut self, pair: Pair<Rule>, scope: &Scope) {
+        match pair.as_rule() {
+            Rule::namespace => {
+                let mut inner = pair.clone().into_inner();
+                let Some(ns_id) = inner.next() else {
+                    return;
+                };
+                let mut ns = scope.ns.clone();
+                ns.extend(ns_id.as_str().split('.').map(str::to_owned));
+                let idx = self.definitions.len();
+                self.definitions.push(Definition {
+                    kind: SymbolKind::Namespace,
+                    label: ns_id.as_str().to_owned(),
+                    name: Some(ns.join(".")),
+                    selection: span_of(&ns_id),
+                    span: span_of(&pair),
+                    parent: scope.parent,
+                });
+                let child = Scope {
+                    path: Some(ns.clone()),
+                    ns,
+                    in_policyset: false,
+                    parent: Some(idx),
+                };
+                for p in inner {
+                    self.walk(p, &child);
+                }
+            }
+            Rule::policyset_decl | Rule::policy_decl => {
+                let kind = if pair.as_rule() == Rule::policyset_decl {
+                    SymbolKind::PolicySet
+                } else {
+                    SymbolKind::Policy
+                };
+                let mut inner = pair.clone().into_inner();
+                let name = inner
+                    .next()
+                    .and_then(|id| id.into_inner().find(|p| p.as_rule() == Rule::identifier));
+                let idx = self.define(kind, name.as_ref(), &pair, scope);
+                let path = match (&scope.path, &name) {
+                    (Some(p), Some(n)) => {
+                        let mut v = p.clone();
+                        v.push(n.as_str().to_owned());
+                        Some(v)
+                    }
+                    _ => None,
+                };
+                let child = Scope {
+                    ns: scope.ns.clone(),
+                    path,
+                    in_policyset: kind == SymbolKind::PolicySet,
+                    parent: Some(idx),
+                };
+                for p in inner {
+                    self.walk(p, &child);
+                }
+            }
+            Rule::rule_decl => {
+                let mut inner = pair.clone().into_inner().peekable();
+                let name = inner.next_if(|p| p.as_rule() == Rule::identifier);
+                self.define(SymbolKind::Rule, name.as_ref(), &pair, scope);
+                for p in inner {
+                    self.walk(p, scope);
+                }
+            }
+            Rule::template_policy | Rule::template_rule => {
+                let mut inner = pair.clone().into_inner();
+                let name = inner.next();
+                let idx = self.define(SymbolKind::Template, name.as_ref(), &pair, scope);
+                // names within a template body are only qualified
+                // once it is instantiated.
+                let child = Scope {
+                    ns: scope.ns.clone(),
+                    path: None,
+                    in_policyset: false,
+                    parent: Some(idx),
+                };
+                for p in inner {
+                    self.walk(p, &child);
+                }
+            }
+            Rule::instance_decl => {
+                let mut inner = pair.clone().into_inner().peekable();
+                let name = inner.next_if(|p| p.as_rule() == Rule::identifier);
+                self.define(SymbolKind::Instance, name.as_ref(), &pair, scope);
+                if let Some(template) = inner.next() {
+                    self.refer(SymbolKind::Template, &template, scope);
+                }
+                for arg in inner {
+                    // skip the parameter name
+                    for p in arg.into_inner().skip(1) {
+                        self.walk(p, scope);
+                    }
+                }
+            }
+            Rule::attribute_decl => {
+                self.simple_decl(SymbolKind::Attribute, &pair, scope);
+                for p in pair.into_inner() {
+                    self.walk(p, scope);
+                }
+            }
+            Rule::attr_type_assoc => self.refer_first(SymbolKind::Type, pair, scope),
+            Rule::attr_category_assoc => self.refer_first(SymbolKind::Category, pair, scope),
+            Rule::type_decl => self.simple_decl(SymbolKind::Type, &pair, scope),
+            Rule::cat_decl => self.simple_decl(SymbolKind::Category, &pair, scope),
+            Rule::advice_decl => self.simple_decl(SymbolKind::Advice, &pair, scope),
+            Rule::obligation_decl => self.simple_decl(SymbolKind::Obligation, &pair, scope),
+            Rule::rule_combinator_decl => {
+                self.simple_decl(SymbolKind::RuleCombinator, &pair, scope);
+            }
+            Rule::policy_combinator_decl => {
+                self.simple_decl(SymbolKind::PolicyCombinator, &pair, scope);
+            }
+            Rule::extern_decl => self.simple_decl(SymbolKind::Extern, &pair, scope),
+            Rule::constant_decl => {
+                self.simple_decl(SymbolKind::Constant, &pair, scope);
+                if let Some(t) = pair
+                    .into_inner()
+                    .find(|p| p.as_rule() == Rule::elem_identifier)
+                {
+                    self.refer(SymbolKind::Type, &t, scope);
+                }
+            }
+            Rule::function_decl => {
+                self.simple_decl(SymbolKind::Function, &pair, scope);
+                for p in pair.into_inner() {
+                    if matches!(p.as_rule(), Rule::function_args | Rule::func_out) {
+                        self.refer_types(p, scope);
+                    }
+                }
+            }
+            Rule::infix_decl => {
+                let name = pair
+                    .clone()
+                    .into_inner()
+                    .find(|p| p.as_rule() == Rule::operator_name);
+                self.define(SymbolKind::Operator, name.as_ref(), &pair, scope);
+                for p in pair.into_inner() {
+                    self.walk(p, scope);
+                }
+            }
+            Rule::infix_arg_decl => {
+                for p in pair.into_inner() {
+                    if p.as_rule() == Rule::identifier {
+                        self.refer(SymbolKind::Type, &p, scope);
+                    }
+                }
+            }
+            Rule::import_decl => self.refer_first(SymbolKind::Namespace, pair, scope),
+            Rule::apply_stmt => {
+                let kind = if scope.in_policyset {
+                    SymbolKind::PolicyCombinator
+                } else {
+                    SymbolKind::RuleCombinator
+                };
+                self.refer_first(kind, pair, scope);
+            }
+            Rule::policy_reference => self.refer_first(SymbolKind::Policy, pair, scope),
+            Rule::rule_reference => self.refer_first(SymbolKind::Rule, pair, scope),
+            Rule::attribute_designator => {
+                let mut inner = pair.into_inner();
+                if let Some(first) = inner.next()
+                    && first.as_rule() == Rule::elem_identifier
+                {
+                    self.refer(SymbolKind::Attribute, &first, scope);
+                }
+            }
+            Rule::prescription_assignment | Rule::issuer_assignment => {
+                self.refer_first(SymbolKind::Attribute, pair, scope);
+            }
+            Rule::apply_obligation => self.refer_first(SymbolKind::Obligation, pair, scope),
+            Rule::apply_advice => self.refer_first(SymbolKind::Advice, pair, scope),
+            Rule::target_match_func | Rule::cond_function_call | Rule::cond_function_ref => {
+                self.refer_first(SymbolKind::Function, pair, scope);
+            }
+            Rule::constant_reference => self.refer_first(SymbolKind::Constant, pair, scope),
+            Rule::operator_identifier => self.refer(SymbolKind::Operator, &pair, scope),
+            _ => {
+                for p in pair.into_inner() {
+                    self.walk(p, scope);
+                }
+            }
+        }
+    }
+}
+
+fn span_of(pair: &Pair<Rule>) -> Range<usize> {
+    let s = pair.as_span();
+    s.start()..s.end()
+}
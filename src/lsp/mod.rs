@@ -0,0 +1,209 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! A Language Server Protocol server for ALFA (`a2x lsp`).
+//!
+//! The server speaks LSP over stdio, and provides:
+//!
+//! * diagnostics, from compiling every `.alfa` file in the workspace
+//! * go-to-definition and find-references, resolving names through
+//!   namespaces and imports as the compiler does
+//! * hover, showing the declaration and resolved URIs of an element
+//! * completion of names declared, imported, or built in
+//! * document symbols for namespaces, policysets, policies, and rules
+//!
+//! Documents are synchronized in full on each change.
+
+pub mod index;
+pub mod lines;
+pub mod workspace;
+
+use crate::context::Config;
+use log::{info, warn};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+    PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, References, Request as _,
+};
+use lsp_types::{
+    CompletionOptions, CompletionResponse, DocumentSymbolResponse, GotoDefinitionResponse,
+    HoverProviderCapability, InitializeParams, OneOf, PublishDiagnosticsParams, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind,
+};
+use std::error::Error;
+pub use workspace::Workspace;
+use workspace::uri_to_path;
+
+/// Result of running the server.
+pub type ServerResult = Result<(), Box<dyn Error + Send + Sync>>;
+
+/// Run a language server on stdin and stdout, until the client
+/// shuts it down.
+///
+/// # Errors
+///
+/// Returns `Err` if the connection fails.
+pub fn run(config: Config) -> ServerResult {
+    let (connection, io_threads) = Connection::stdio();
+    serve(&connection, config)?;
+    io_threads.join()?;
+    Ok(())
+}
+
+/// Serve a client on an established connection, until it shuts the
+/// server down.
+///
+/// # Errors
+///
+/// Returns `Err` if the connection fails.
+pub fn serve(connection: &Connection, config: Config) -> ServerResult {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![".".to_owned()]),
+            ..CompletionOptions::default()
+        }),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    };
+    let init = connection.initialize(serde_json::to_value(capabilities)?)?;
+    let params: InitializeParams = serde_json::from_value(init)?;
+    let mut ws = Workspace::new(config);
+    #[allow(deprecated)]
+    let roots: Vec<_> = match params.workspace_folders {
+        Some(folders) => folders.into_iter().map(|f| f.uri).collect(),
+        None => params.root_uri.into_iter().collect(),
+    };
+    for root in roots.iter().filter_map(uri_to_path) {
+        info!("loading ALFA files under {}", root.display());
+        ws.load_folder(&root);
+    }
+    publish(connection, ws.analyze())?;
+    for msg in &connection.receiver {
+        match msg {
+            Message::Request(req) => {
+                if connection.handle_shutdown(&req)? {
+                    return Ok(());
+                }
+                connection
+                    .sender
+                    .send(Message::Response(handle_request(&ws, req)))?;
+            }
+            Message::Notification(n) => {
+                if handle_notification(&mut ws, n) {
+                    publish(connection, ws.analyze())?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+fn publish(connection: &Connection, params: Vec<PublishDiagnosticsParams>) -> ServerResult {
+    for p in params {
+        let n = Notification::new(PublishDiagnostics::METHOD.to_owned(), p);
+        connection.sender.send(Message::Notification(n))?;
+    }
+    Ok(())
+}
+
+fn handle_request(ws: &Workspace, req: Request) -> Response {
+    let id = req.id.clone();
+    let result = match req.method.as_str() {
+        GotoDefinition::METHOD => reply::<GotoDefinition>(req, |p| {
+            let pos = p.text_document_position_params;
+            ws.definition(&pos.text_document.uri, pos.position)
+                .map(GotoDefinitionResponse::Scalar)
+        }),
+        References::METHOD => reply::<References>(req, |p| {
+            let pos = p.text_document_position;
+            Some(ws.references(
+                &pos.text_document.uri,
+                pos.position,
+                p.context.include_declaration,
+            ))
+        }),
+        HoverRequest::METHOD => reply::<HoverRequest>(req, |p| {
+            let pos = p.text_document_position_params;
+            ws.hover(&pos.text_document.uri, pos.position)
+        }),
+        Completion::METHOD => reply::<Completion>(req, |p| {
+            let pos = p.text_document_position;
+            Some(CompletionResponse::Array(
+                ws.completion(&pos.text_document.uri, pos.position),
+            ))
+        }),
+        DocumentSymbolRequest::METHOD => reply::<DocumentSymbolRequest>(req, |p| {
+            Some(DocumentSymbolResponse::Nested(
+                ws.document_symbols(&p.text_document.uri),
+            ))
+        }),
+        method => {
+            warn!("unsupported request: {method}");
+            return Response::new_err(
+                id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request: {method}"),
+            );
+        }
+    };
+    match result {
+        Ok(value) => Response::new_ok(id, value),
+        Err(e) => Response::new_err(id, ErrorCode::InvalidParams as i32, e.to_string()),
+    }
+}
+
+/// Decode the parameters of a request, and encode the result.
+fn reply<R: lsp_types::request::Request>(
+    req: Request,
+    f: impl FnOnce(R::Params) -> R::Result,
+) -> Result<serde_json::Value, serde_json::Error> {
+    let params = serde_json::from_value::<R::Params>(req.params)?;
+    serde_json::to_value(f(params))
+}
+
+/// Apply a document notification.  Returns whether documents changed.
+fn handle_notification(ws: &mut Workspace, n: Notification) -> bool {
+    match n.method.as_str() {
+        DidOpenTextDocument::METHOD => {
+            let Ok(p) =
+                n.extract::<lsp_types::DidOpenTextDocumentParams>(DidOpenTextDocument::METHOD)
+            else {
+                return false;
+            };
+            ws.set_document(p.text_document.uri, p.text_document.text);
+            true
+        }
+        DidChangeTextDocument::METHOD => {
+            let Ok(mut p) =
+                n.extract::<lsp_types::DidChangeTextDocumentParams>(DidChangeTextDocument::METHOD)
+            else {
+                return false;
+            };
+            // with full synchronization, the last change is the
+            // whole document.
+            let Some(change) = p.content_changes.pop() else {
+                return false;
+            };
+            ws.set_document(p.text_document.uri, change.text);
+            true
+        }
+        DidCloseTextDocument::METHOD => {
+            let Ok(p) =
+                n.extract::<lsp_types::DidCloseTextDocumentParams>(DidCloseTextDocument::METHOD)
+            else {
+                return false;
+            };
+            ws.close_document(&p.text_document.uri);
+            true
+        }
+        _ => false,
+    }
+}
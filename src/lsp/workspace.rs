@@ -0,0 +1,742 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! ALFA documents being edited, and queries against them.
+//!
+//! Every change re-analyzes all documents with a fresh
+//! [`Context`], just as a compile would.  Names are resolved with
+//! the context's resolvers, so that imports behave exactly as they
+//! do when compiling.
+
+use super::index::{Definition, Reference, SymbolIndex, SymbolKind};
+use super::lines::LineIndex;
+use crate::ast::{AsAlfa, AstCollection, AstSource, QualifiedName, SrcLoc};
+use crate::context::{Config, Context, SYSTEM_NS};
+use crate::errors::{ParseError, SrcError};
+use crate::{compile_collection, make_alfa_ast};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionTextEdit, Diagnostic,
+    DiagnosticRelatedInformation, DiagnosticSeverity, DocumentSymbol, Hover, HoverContents,
+    Location, MarkupContent, MarkupKind, Position, PublishDiagnosticsParams, Range,
+    SymbolKind as LspSymbolKind, TextEdit, Uri,
+};
+use miette::{Diagnostic as _, NamedSource};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::str::FromStr;
+use walkdir::WalkDir;
+
+/// Source of diagnostics.
+const DIAGNOSTIC_SOURCE: &str = "a2x";
+
+/// An ALFA source file.
+struct Document {
+    text: String,
+    lines: LineIndex,
+    /// Index of the most recent syntactically valid text.
+    index: Option<SymbolIndex>,
+    /// Is the document open in the editor (rather than only on disk)?
+    open: bool,
+}
+
+/// An element found by resolving a name.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Resolved {
+    kind: SymbolKind,
+    /// Fully qualified name.
+    name: String,
+    /// Markdown description, for hovers.
+    hover: String,
+}
+
+/// All ALFA documents in the workspace.
+pub struct Workspace {
+    config: Config,
+    docs: BTreeMap<Uri, Document>,
+    /// Context of the most recent analysis in which every document
+    /// was syntactically valid.
+    ctx: Rc<Context>,
+    /// Documents with diagnostics at the last analysis.
+    published: BTreeSet<Uri>,
+}
+
+impl Default for Workspace {
+    fn default() -> Self {
+        Workspace::new(Config::default())
+    }
+}
+
+impl Workspace {
+    /// Create an empty workspace, compiling with the given
+    /// configuration.
+    #[must_use]
+    pub fn new(config: Config) -> Workspace {
+        Workspace {
+            ctx: Rc::new(Context::new(config.clone())),
+            config,
+            docs: BTreeMap::new(),
+            published: BTreeSet::new(),
+        }
+    }
+
+    /// Add all ALFA files under a directory.  Files already open in
+    /// the editor are left alone.
+    pub fn load_folder(&mut self, dir: &Path) {
+        for entry in WalkDir::new(dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(Result::ok)
+        {
+            let path = entry.path();
+            if path.extension().is_some_and(|e| e == "alfa")
+                && let Some(uri) = path_to_uri(path)
+                && !self.docs.get(&uri).is_some_and(|d| d.open)
+                && let Ok(text) = std::fs::read_to_string(path)
+            {
+                self.insert(uri, text, false);
+            }
+        }
+    }
+
+    /// Set the contents of a document open in the editor.
+    pub fn set_document(&mut self, uri: Uri, text: String) {
+        self.insert(uri, text, true);
+    }
+
+    /// Close a document.  Its contents revert to the file on disk,
+    /// or it is removed if there is no such file.
+    pub fn close_document(&mut self, uri: &Uri) {
+        match uri_to_path(uri).and_then(|p| std::fs::read_to_string(p).ok()) {
+            Some(text) => self.insert(uri.clone(), text, false),
+            None => {
+                self.docs.remove(uri);
+            }
+        }
+    }
+
+    fn insert(&mut self, uri: Uri, text: String, open: bool) {
+        let previous = self.docs.remove(&uri).and_then(|d| d.index);
+        let index = SymbolIndex::new(&text).ok().or(previous);
+        self.docs.insert(
+            uri,
+            Document {
+                lines: LineIndex::new(&text),
+                text,
+                index,
+                open,
+            },
+        );
+    }
+
+    /// Compile all documents, and report diagnostics for each.
+    /// Documents that no longer have diagnostics are included, with
+    /// none, so that earlier diagnostics are cleared.
+    // `Uri` caches parsed components in cells, which do not affect
+    // its ordering.
+    #[allow(clippy::mutable_key_type)]
+    pub fn analyze(&mut self) -> Vec<PublishDiagnosticsParams> {
+        let ctx = Rc::new(Context::new(self.config.clone()));
+        let mut diagnostics: BTreeMap<Uri, Vec<Diagnostic>> =
+            self.docs.keys().map(|u| (u.clone(), vec![])).collect();
+        let mut asts = AstCollection::new(ctx.clone());
+        let mut parsed = true;
+        for (uri, doc) in &self.docs {
+            let src = NamedSource::new(uri.as_str(), doc.text.clone()).with_language("ALFA");
+            match make_alfa_ast(&doc.text, &src, ctx.clone()) {
+                Ok(ast) => asts.add_ast(AstSource { src, ast }),
+                Err(e) => {
+                    parsed = false;
+                    self.add_diagnostic(&mut diagnostics, Some(uri), &e);
+                }
+            }
+        }
+        // unresolved names are expected if some document could not
+        // be parsed, so compiling waits until they all can be.
+        if parsed {
+            let compiled = compile_collection(asts);
+            self.ctx = ctx;
+            let unresolved = self.add_unresolved(&mut diagnostics);
+            // the compiler does not locate every unresolved name, so
+            // its error is left out if the names explain it.
+            if let Err(e) = compiled
+                && (unresolved == 0 || self.is_located(&e))
+            {
+                self.add_diagnostic(&mut diagnostics, None, &e);
+            }
+        }
+        let mut params = vec![];
+        let mut published = BTreeSet::new();
+        for (uri, diags) in diagnostics {
+            if !diags.is_empty() {
+                published.insert(uri.clone());
+            } else if !self.published.contains(&uri) {
+                continue;
+            }
+            params.push(PublishDiagnosticsParams::new(uri, diags, None));
+        }
+        for uri in &self.published {
+            if !self.docs.contains_key(uri) {
+                params.push(PublishDiagnosticsParams::new(uri.clone(), vec![], None));
+            }
+        }
+        self.published = published;
+        params
+    }
+
+    /// Record an error.  Errors are placed at their source location,
+    /// or else at the start of `uri`, or of every open document.
+    #[allow(clippy::mutable_key_type)]
+    fn add_diagnostic(
+        &self,
+        diagnostics: &mut BTreeMap<Uri, Vec<Diagnostic>>,
+        uri: Option<&Uri>,
+        err: &ParseError,
+    ) {
+        let (located, diag) = match err {
+            ParseError::PestParseError(pe) => {
+                let span = match pe.location {
+                    pest::error::InputLocation::Pos(p) => p..p,
+                    pest::error::InputLocation::Span((s, e)) => s..e,
+                };
+                let diag = uri
+                    .and_then(|u| self.docs.get(u))
+                    .map(|d| d.lines.range(&d.text, &span))
+                    .map(|range| error_diagnostic(range, pe.variant.message().to_string()));
+                (uri.cloned(), diag)
+            }
+            ParseError::SrcError(se) => match self.src_location(se) {
+                Some((uri, range)) => (
+                    Some(uri),
+                    Some(Diagnostic {
+                        related_information: self.related_information(se),
+                        ..error_diagnostic(range, src_message(se))
+                    }),
+                ),
+                None => (uri.cloned(), None),
+            },
+            _ => (uri.cloned(), None),
+        };
+        let targets: Vec<Uri> = match located {
+            Some(u) => vec![u],
+            None => self
+                .docs
+                .iter()
+                .filter(|(_, d)| d.open)
+                .map(|(u, _)| u.clone())
+                .collect(),
+        };
+        let diag = diag.unwrap_or_else(|| error_diagnostic(Range::default(), err.to_string()));
+        for t in targets {
+            diagnostics.entry(t).or_default().push(diag.clone());
+        }
+    }
+
+    /// Record a diagnostic for each name that cannot be resolved.
+    /// Returns the number recorded.
+    #[allow(clippy::mutable_key_type)]
+    fn add_unresolved(&self, diagnostics: &mut BTreeMap<Uri, Vec<Diagnostic>>) -> usize {
+        let mut count = 0;
+        for (uri, doc) in &self.docs {
+            let Some(index) = &doc.index else {
+                continue;
+            };
+            for r in &index.references {
+                if r.kind != SymbolKind::Namespace && self.resolve_reference(r).is_none() {
+                    let message = format!("Could not resolve {} `{}`", r.kind.keyword(), r.text);
+                    let range = doc.lines.range(&doc.text, &r.span);
+                    diagnostics
+                        .entry(uri.clone())
+                        .or_default()
+                        .push(error_diagnostic(range, message));
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// Whether an error refers to a location in a workspace document.
+    fn is_located(&self, err: &ParseError) -> bool {
+        matches!(err, ParseError::SrcError(se) if self.src_location(se).is_some())
+    }
+
+    /// Location of the first label of an error, if it is within a
+    /// workspace document.
+    fn src_location(&self, se: &SrcError) -> Option<(Uri, Range)> {
+        let uri = Uri::from_str(se.source_name()).ok()?;
+        let doc = self.docs.get(&uri)?;
+        let label = se.labels()?.next()?;
+        let span = label.offset()..label.offset() + label.len();
+        let range = doc.lines.range(&doc.text, &span);
+        Some((uri, range))
+    }
+
+    fn related_information(&self, se: &SrcError) -> Option<Vec<DiagnosticRelatedInformation>> {
+        let mut info = vec![];
+        // further labels in the same source
+        if let Some((uri, _)) = self.src_location(se)
+            && let Some(doc) = self.docs.get(&uri)
+            && let Some(labels) = se.labels()
+        {
+            for l in labels.skip(1) {
+                info.push(DiagnosticRelatedInformation {
+                    location: Location::new(
+                        uri.clone(),
+                        doc.lines
+                            .range(&doc.text, &(l.offset()..l.offset() + l.len())),
+                    ),
+                    message: l.label().unwrap_or_default().to_owned(),
+                });
+            }
+        }
+        for r in se.related_errors() {
+            if let Some((uri, range)) = self.src_location(r) {
+                info.push(DiagnosticRelatedInformation {
+                    location: Location::new(uri, range),
+                    message: r.to_string(),
+                });
+            }
+        }
+        (!info.is_empty()).then_some(info)
+    }
+
+    /// Location of the declaration of the name at a position.
+    #[must_use]
+    pub fn definition(&self, uri: &Uri, pos: Position) -> Option<Location> {
+        let (doc, offset) = self.doc_offset(uri, pos)?;
+        let index = doc.index.as_ref()?;
+        if let Some(r) = index.reference_at(offset) {
+            if r.kind == SymbolKind::Namespace {
+                return self.import_definition(r);
+            }
+            let target = self.resolve_reference(r)?;
+            return self.declaration_of(&target);
+        }
+        let d = index.definition_at(offset)?;
+        Some(Location::new(
+            uri.clone(),
+            doc.lines.range(&doc.text, &d.selection),
+        ))
+    }
+
+    /// Locations of every reference to the element named at a
+    /// position, and optionally of its declaration.
+    #[must_use]
+    pub fn references(&self, uri: &Uri, pos: Position, include_declaration: bool) -> Vec<Location> {
+        let Some(target) = self.target_at(uri, pos) else {
+            return vec![];
+        };
+        let mut locations = vec![];
+        if include_declaration && let Some(l) = self.declaration_of(&target) {
+            locations.push(l);
+        }
+        for (u, doc) in &self.docs {
+            let Some(index) = &doc.index else {
+                continue;
+            };
+            for r in &index.references {
+                if r.kind != SymbolKind::Namespace
+                    && self
+                        .resolve_reference(r)
+                        .is_some_and(|x| same_element(&x, &target))
+                {
+                    locations.push(Location::new(
+                        u.clone(),
+                        doc.lines.range(&doc.text, &r.span),
+                    ));
+                }
+            }
+        }
+        locations
+    }
+
+    /// Description of the element named at a position.
+    #[must_use]
+    pub fn hover(&self, uri: &Uri, pos: Position) -> Option<Hover> {
+        let (doc, offset) = self.doc_offset(uri, pos)?;
+        let index = doc.index.as_ref()?;
+        let (target, span) = if let Some(r) = index.reference_at(offset) {
+            (self.resolve_reference(r)?, r.span.clone())
+        } else {
+            let d = index.definition_at(offset)?;
+            (self.resolve_definition(d)?, d.selection.clone())
+        };
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: target.hover,
+            }),
+            range: Some(doc.lines.range(&doc.text, &span)),
+        })
+    }
+
+    /// Names that can be used at a position: those declared in the
+    /// enclosing namespace, imported into it, or built in.
+    #[must_use]
+    pub fn completion(&self, uri: &Uri, pos: Position) -> Vec<CompletionItem> {
+        let Some((doc, offset)) = self.doc_offset(uri, pos) else {
+            return vec![];
+        };
+        let ns: Vec<String> = doc
+            .index
+            .as_ref()
+            .and_then(|i| i.namespace_at(offset))
+            .and_then(|d| d.name.as_ref())
+            .map(|n| n.split('.').map(str::to_owned).collect())
+            .unwrap_or_default();
+        // the (possibly dotted) name being typed
+        let start = doc.text[..offset]
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+            .map_or(0, |i| i + 1);
+        let prefix = &doc.text[start..offset];
+        let range = doc.lines.range(&doc.text, &(start..offset));
+        let mut items: BTreeMap<String, CompletionItem> = BTreeMap::new();
+        for (keyword, name) in self.ctx.symbol_names() {
+            if keyword == "infix" {
+                continue;
+            }
+            for label in self.visible_names(&name, &ns) {
+                if label.starts_with(prefix) {
+                    items
+                        .entry(label.clone())
+                        .or_insert_with(|| CompletionItem {
+                            label: label.clone(),
+                            kind: Some(completion_kind(keyword)),
+                            detail: Some(format!("{keyword} {name}")),
+                            text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(range, label))),
+                            ..CompletionItem::default()
+                        });
+                }
+            }
+        }
+        items.into_values().collect()
+    }
+
+    /// Namespaces, policysets, policies, and rules declared in a
+    /// document.
+    #[must_use]
+    pub fn document_symbols(&self, uri: &Uri) -> Vec<DocumentSymbol> {
+        let Some(doc) = self.docs.get(uri) else {
+            return vec![];
+        };
+        let Some(index) = &doc.index else {
+            return vec![];
+        };
+        let outlined = |d: &Definition| {
+            matches!(
+                d.kind,
+                SymbolKind::Namespace
+                    | SymbolKind::PolicySet
+                    | SymbolKind::Policy
+                    | SymbolKind::Rule
+            )
+        };
+        // nearest outlined ancestor of each definition
+        let parent_of = |d: &Definition| {
+            let mut p = d.parent;
+            while let Some(i) = p {
+                if outlined(&index.definitions[i]) {
+                    break;
+                }
+                p = index.definitions[i].parent;
+            }
+            p
+        };
+        let mut symbols: Vec<Option<DocumentSymbol>> = index
+            .definitions
+            .iter()
+            .map(|d| outlined(d).then(|| document_symbol(doc, d)))
+            .collect();
+        // children follow their parents, so attach them in reverse.
+        let mut roots = vec![];
+        for i in (0..symbols.len()).rev() {
+            let Some(s) = symbols[i].take() else {
+                continue;
+            };
+            match parent_of(&index.definitions[i]).and_then(|p| symbols[p].as_mut()) {
+                Some(parent) => parent.children.get_or_insert_with(Vec::new).insert(0, s),
+                None => roots.insert(0, s),
+            }
+        }
+        roots
+    }
+
+    fn doc_offset(&self, uri: &Uri, pos: Position) -> Option<(&Document, usize)> {
+        let doc = self.docs.get(uri)?;
+        Some((doc, doc.lines.offset(&doc.text, pos)))
+    }
+
+    /// The element named at a position, by reference or declaration.
+    fn target_at(&self, uri: &Uri, pos: Position) -> Option<Resolved> {
+        let (doc, offset) = self.doc_offset(uri, pos)?;
+        let index = doc.index.as_ref()?;
+        match index.reference_at(offset) {
+            Some(r) => self.resolve_reference(r),
+            None => self.resolve_definition(index.definition_at(offset)?),
+        }
+    }
+
+    fn resolve_reference(&self, r: &Reference) -> Option<Resolved> {
+        self.resolve(r.kind, &r.text, &r.ns)
+    }
+
+    /// Resolve a declaration by its fully qualified name.
+    fn resolve_definition(&self, d: &Definition) -> Option<Resolved> {
+        let name = d.name.as_ref()?;
+        match d.kind {
+            SymbolKind::Instance => self
+                .resolve(SymbolKind::Policy, name, &[])
+                .or_else(|| self.resolve(SymbolKind::Rule, name, &[])),
+            SymbolKind::PolicySet | SymbolKind::Extern => {
+                self.resolve(SymbolKind::Policy, name, &[])
+            }
+            SymbolKind::Namespace => None,
+            kind => self.resolve(kind, name, &[]),
+        }
+    }
+
+    /// Resolve a name, as written in a namespace.
+    fn resolve(&self, kind: SymbolKind, symbol: &str, ns: &[String]) -> Option<Resolved> {
+        let ns = ns.to_vec();
+        let ctx = &self.ctx;
+        let src_loc = SrcLoc::default();
+        match kind {
+            SymbolKind::Attribute => {
+                let a = ctx.lookup_attribute(symbol, &ns).ok()?;
+                let type_uri = ctx.lookup_type(&a.typedef, &a.ns).map(|t| t.uri.clone());
+                let cat_uri = ctx
+                    .lookup_category(&a.category, &a.ns)
+                    .map(|c| c.uri.clone());
+                let hover = format!(
+                    "{}\n- id: `{}`\n- type: `{}` ({})\n- category: `{}` ({})\n",
+                    hover_text(kind, a.as_ref()),
+                    a.uri,
+                    a.typedef,
+                    type_uri.map_or("unresolved".to_owned(), |u| format!("`{u}`")),
+                    a.category,
+                    cat_uri.map_or("unresolved".to_owned(), |u| format!("`{u}`")),
+                );
+                Some(Resolved {
+                    kind,
+                    name: a.fully_qualified_name()?,
+                    hover,
+                })
+            }
+            SymbolKind::Type => resolved(kind, ctx.lookup_type(symbol, &ns)),
+            SymbolKind::Category => resolved(kind, ctx.lookup_category(symbol, &ns)),
+            SymbolKind::Function => resolved(kind, ctx.lookup_function(symbol, &ns)),
+            SymbolKind::Operator => resolved(kind, ctx.lookup_infix(symbol, &ns)),
+            SymbolKind::Advice => resolved(kind, ctx.lookup_advice(symbol, &ns)),
+            SymbolKind::Obligation => resolved(kind, ctx.lookup_obligation(symbol, &ns)),
+            SymbolKind::RuleCombinator => {
+                resolved(kind, ctx.lookup_rule_combinator(symbol, &ns, &src_loc))
+            }
+            SymbolKind::PolicyCombinator => {
+                resolved(kind, ctx.lookup_policy_combinator(symbol, &ns))
+            }
+            SymbolKind::Constant => resolved(kind, ctx.lookup_constant(symbol, &ns)),
+            SymbolKind::Extern => resolved(kind, ctx.lookup_extern(symbol, &ns)),
+            SymbolKind::Rule => named(kind, ctx.lookup_rule(symbol, &ns, &src_loc)),
+            SymbolKind::Template => named(kind, ctx.lookup_template(symbol, &ns, &src_loc)),
+            SymbolKind::Policy => named(SymbolKind::PolicySet, ctx.lookup_policyset(symbol, &ns))
+                .or_else(|| named(kind, ctx.lookup_policy(symbol, &ns)))
+                .or_else(|| resolved(SymbolKind::Extern, ctx.lookup_extern(symbol, &ns))),
+            SymbolKind::PolicySet | SymbolKind::Instance | SymbolKind::Namespace => None,
+        }
+    }
+
+    /// Location of the declaration of a resolved element.  Built-in
+    /// elements have no declaration.
+    fn declaration_of(&self, target: &Resolved) -> Option<Location> {
+        self.find_definition(|d| {
+            d.name.as_ref() == Some(&target.name)
+                && (d.kind == target.kind
+                    || (d.kind == SymbolKind::Instance
+                        && matches!(target.kind, SymbolKind::Policy | SymbolKind::Rule)))
+        })
+    }
+
+    /// Location of the namespace or element named by an import.
+    /// Imports may be relative to the importing namespace.
+    fn import_definition(&self, r: &Reference) -> Option<Location> {
+        let (name, wildcard) = match r.text.strip_suffix(".*") {
+            Some(n) => (n, true),
+            None => (r.text.as_str(), false),
+        };
+        let candidates = [format!("{}.{name}", r.ns.join(".")), name.to_owned()];
+        candidates.iter().find_map(|c| {
+            self.find_definition(|d| {
+                d.name.as_ref() == Some(c) && (d.kind == SymbolKind::Namespace) == wildcard
+            })
+        })
+    }
+
+    fn find_definition(&self, pred: impl Fn(&Definition) -> bool) -> Option<Location> {
+        self.docs.iter().find_map(|(uri, doc)| {
+            let d = doc.index.as_ref()?.definitions.iter().find(|d| pred(d))?;
+            Some(Location::new(
+                uri.clone(),
+                doc.lines.range(&doc.text, &d.selection),
+            ))
+        })
+    }
+
+    /// Names by which an element can be referred to from a namespace,
+    /// following the same rules as resolution.
+    fn visible_names(&self, name: &str, ns: &[String]) -> Vec<String> {
+        let mut names = vec![];
+        let here = format!("{}.", ns.join("."));
+        if let Some(rest) = name.strip_prefix(&here) {
+            names.push(rest.to_owned());
+        }
+        for i in self.ctx.get_imports(ns).unwrap_or_default() {
+            let imported = i.components.join(".");
+            for base in [format!("{here}{imported}"), imported] {
+                if i.is_wildcard {
+                    if let Some(rest) = name.strip_prefix(&format!("{base}.")) {
+                        names.push(rest.to_owned());
+                    }
+                } else if name == base
+                    && let Some(last) = i.components.last()
+                {
+                    names.push(last.clone());
+                }
+            }
+        }
+        if !name.starts_with(SYSTEM_NS) {
+            names.push(name.to_owned());
+        }
+        names
+    }
+}
+
+/// Whether two resolved names are the same element.
+fn same_element(a: &Resolved, b: &Resolved) -> bool {
+    a.kind == b.kind && a.name == b.name
+}
+
+/// A resolved element, described by its ALFA declaration.
+fn resolved<T: AsAlfa + QualifiedName>(
+    kind: SymbolKind,
+    lookup: Result<Rc<T>, ParseError>,
+) -> Option<Resolved> {
+    let elem = lookup.ok()?;
+    Some(Resolved {
+        kind,
+        name: elem.fully_qualified_name()?,
+        hover: hover_text(kind, elem.as_ref()),
+    })
+}
+
+/// A resolved element, described only by its name.
+fn named<T: QualifiedName>(
+    kind: SymbolKind,
+    lookup: Result<Rc<T>, ParseError>,
+) -> Option<Resolved> {
+    let name = lookup.ok()?.fully_qualified_name()?;
+    Some(Resolved {
+        kind,
+        hover: format!("{} `{name}`\n", kind.keyword()),
+        name,
+    })
+}
+
+fn hover_text<T: AsAlfa + QualifiedName>(kind: SymbolKind, elem: &T) -> String {
+    format!(
+        "{} `{}`\n\n```alfa\n{}```\n",
+        kind.keyword(),
+        elem.fully_qualified_name().unwrap_or_default(),
+        elem.to_alfa(0)
+    )
+}
+
+fn error_diagnostic(range: Range, message: String) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::ERROR),
+        source: Some(DIAGNOSTIC_SOURCE.to_owned()),
+        message,
+        ..Diagnostic::default()
+    }
+}
+
+/// Message of an error, with the text of its first label.
+fn src_message(se: &SrcError) -> String {
+    match se
+        .labels()
+        .and_then(|mut l| l.next())
+        .and_then(|l| l.label().map(str::to_owned))
+    {
+        Some(label) => format!("{se}: {label}"),
+        None => se.to_string(),
+    }
+}
+
+fn completion_kind(keyword: &str) -> CompletionItemKind {
+    match keyword {
+        "attribute" => CompletionItemKind::FIELD,
+        "function" => CompletionItemKind::FUNCTION,
+        "type" => CompletionItemKind::CLASS,
+        "category" => CompletionItemKind::ENUM_MEMBER,
+        "constant" => CompletionItemKind::CONSTANT,
+        "ruleCombinator" | "policyCombinator" => CompletionItemKind::OPERATOR,
+        "advice" | "obligation" => CompletionItemKind::EVENT,
+        _ => CompletionItemKind::MODULE,
+    }
+}
+
+#[allow(deprecated)]
+fn document_symbol(doc: &Document, d: &Definition) -> DocumentSymbol {
+    DocumentSymbol {
+        name: d.label.clone(),
+        detail: d.name.clone(),
+        kind: match d.kind {
+            SymbolKind::Namespace => LspSymbolKind::NAMESPACE,
+            SymbolKind::Rule => LspSymbolKind::METHOD,
+            _ => LspSymbolKind::CLASS,
+        },
+        tags: None,
+        deprecated: None,
+        range: doc.lines.range(&doc.text, &d.span),
+        selection_range: doc.lines.range(&doc.text, &d.selection),
+        children: None,
+    }
+}
+
+/// Local path of a `file:` URI.
+#[must_use]
+pub fn uri_to_path(uri: &Uri) -> Option<PathBuf> {
+    let path = uri.as_str().strip_prefix("file://")?;
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(hex) = path.get(i + 1..i + 3)
+            && let Ok(b) = u8::from_str_radix(hex, 16)
+        {
+            decoded.push(b);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+/// `file:` URI of a local path.
+#[must_use]
+pub fn path_to_uri(path: &Path) -> Option<Uri> {
+    let path = std::path::absolute(path).ok()?;
+    let mut s = String::from("file://");
+    for b in path.to_str()?.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~/".contains(&b) {
+            s.push(char::from(b));
+        } else {
+            s.push_str(&format!("%{b:02X}"));
+        }
+    }
+    Uri::from_str(&s).ok()
+}
@@ -1,17 +1,31 @@
 //  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
 //  SPDX-License-Identifier: GPL-3.0-or-later
 //! Main CLI program for converting Alfa source files to XACML 3.0.
-use a2x::args::CLIArgs;
+use a2x::args::{CLIArgs, Command};
 use a2x::context::Config;
 use a2x::context::Context;
-use a2x::AlfaFile;
+use a2x::context::IdStrategy;
+use a2x::context::Provenance;
+use a2x::context::XacmlVersion;
+use a2x::xacml::bundle::{bundle, BundleRoot};
+#[cfg(feature = "cache")]
+use a2x::cache::BuildCache;
+use a2x::check::{check_output, unified_diff};
+use a2x::manifest::{prune, Manifest, ManifestEntry, Pruned};
+use a2x::sourcemap::SourceMap;
+use a2x::xacml::layout::{output_paths, OutputLayout};
+use a2x::xacml::reader::read_xacml;
+use a2x::xacml::style::{LineEnding, OutputStyle, XmlEncoding};
+use a2x::xacml::XTopPolicy;
+use a2x::watch::{InputSnapshot, POLL_INTERVAL};
+use a2x::{AlfaFile, Written};
 use clap::Parser;
 use log::{info, warn};
 use miette::Report;
 use std::io;
-use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::str::FromStr;
 use std::rc::Rc;
 use walkdir::WalkDir;
 use std::time;
@@ -30,81 +44,32 @@ fn main() -> ExitCode {
         let _ = ctx.serialize_builtins(&mut stdout);
         return ExitCode::SUCCESS;
     }
+    if let Some(Command::Fmt { paths, check }) = &args.command {
+        return format_sources(paths, *check);
+    }
+    if let Some(Command::Lookup { id, paths }) = &args.command {
+        return lookup(&args, id, paths);
+    }
+    #[cfg(feature = "lsp")]
+    if let Some(Command::Lsp) = &args.command {
+        return lsp(&args);
+    }
+    #[cfg(feature = "serde")]
+    if let Some(form) = &args.dump_json {
+        return dump_json(&args, form);
+    }
     print_program_header();
+    if let Some(alfa_path) = &args.decompile {
+        return decompile(&args.alfa_dir, alfa_path, args.decompile_namespace.as_deref());
+    }
     if let Some(outdir) = &args.output_dir {
-        // Print out the input files/paths, and output path
-        for ip in &args.alfa_dir {
-            eprintln!("Input:  {}", ip);
+        if args.watch {
+            return watch(&args, outdir);
         }
-        eprintln!("Output: {}", outdir);
-        eprintln!();
-        eprintln!("Scanning input directory...");
-        // input paths that we should attempt to parse
-        let input_paths: Vec<PathBuf> = get_input_paths(&args.alfa_dir);
-        eprintln!("Found {} ALFA policy files", input_paths.len());
-        eprintln!();
-        // define a configuration for the conversion
-        let ctx = Rc::new(Context::new(Config {
-            base_namespace: args.base_namespace,
-            enable_builtins: !args.disable_builtins,
-            version: Some("1.0".to_string()),
-        }));
-        // get alfa file contents
-        let alfa_sources: Vec<AlfaFile> = get_alfa_sources(input_paths);
-        let alfa_sources_count = alfa_sources.len();
-        // Now that we have the Alfa source files as strings, compile them all.
-        let xfilesres = a2x::alfa_compile(&ctx, alfa_sources);
-        eprintln!();
-        let mut xacml_written = 0;
-        let mut rules_written = 0;
-        let mut policysets_written = 0;
-        let mut policies_written = 0;
-        // check the result
-        match xfilesres {
-            Err(pe) => {
-                warn!("compilation of ALFA sources failed: {:?}", pe);
-                eprintln!("Conversion to XACML Failed:");
-                // If we checked for a PestParseError, and converted
-                // the wrapped error using into_miette(), we could get
-                // consistent miette output.  But as of pest 2.8.3,
-                // the miette adapter does not preserve the path or
-                // position in the file (the message is correct; but
-                // the annotated line numbers always start at 0).
-                //match pe {
-                //    ParseError::PestParseError(ppe) => {
-                //        eprintln!("{:?}", Report::new(ppe.into_miette()));
-                //    } ...
-                //}
-                eprintln!("{:?}", Report::new(pe));
-                return ExitCode::FAILURE;
-            }
-            Ok(xfiles) => {
-                let policy_output_path = Path::new(outdir);
-                eprintln!("Writing XACML policies:");
-                for x in xfiles {
-                    let write_res = a2x::write_xentry(policy_output_path, &x);
-                    xacml_written += 1;
-                    rules_written += x.rule_count();
-                    policysets_written += x.policyset_count();
-                    policies_written += x.policy_count();
-                    // get details for policy
-                    if let Ok(output_fn) = write_res {
-                        eprintln!("  ✓ {}", output_fn);
-                    } else {
-                        eprintln!("Failed to write XACML policy: {write_res:?}");
-                    }
-                }
-            }
+        let status = convert(&args, outdir);
+        if status != ExitCode::SUCCESS || args.check {
+            return status;
         }
-        eprintln!();
-        eprintln!("Summary:");
-        eprintln!("{}", "-".repeat(8));
-        eprintln!("ALFA files processed  : {}", alfa_sources_count);
-        eprintln!("XACML files generated : {}", xacml_written);
-        eprintln!("Policy Sets written   : {}", policysets_written);
-        eprintln!("Policies written      : {}", policies_written);
-        eprintln!("Rules written         : {}", rules_written);
-        eprintln!();
     } else {
         println!("Provide an output directory for XACML policies using the --output option.");
     }
@@ -114,6 +79,291 @@ fn main() -> ExitCode {
     ExitCode::SUCCESS
 }
 
+/// Compile the inputs, and write XACML into the output directory.
+/// Files whose contents would not change are not rewritten.
+fn convert(args: &CLIArgs, outdir: &str) -> ExitCode {
+    // Print out the input files/paths, and output path
+    for ip in &args.alfa_dir {
+        eprintln!("Input:  {}", ip);
+    }
+    eprintln!("Output: {}", outdir);
+    eprintln!();
+    eprintln!("Scanning input directory...");
+    // input paths that we should attempt to parse
+    let input_paths: Vec<PathBuf> = get_input_paths(&args.alfa_dir, "alfa");
+    eprintln!("Found {} ALFA policy files", input_paths.len());
+    eprintln!();
+    // define a configuration for the conversion
+    let ctx = match config(args) {
+        Ok(c) => Rc::new(Context::new(c)),
+        Err(e) => {
+            eprintln!("{:?}", Report::new(e));
+            return ExitCode::FAILURE;
+        }
+    };
+    // get alfa file contents
+    let alfa_sources: Vec<AlfaFile> = match get_alfa_sources(input_paths) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{:?}", Report::new(e));
+            return ExitCode::FAILURE;
+        }
+    };
+    let alfa_sources_count = alfa_sources.len();
+    // Now that we have the Alfa source files as strings, compile them all.
+    let bundle_root = bundle_root(&args.bundle, &args.bundle_all, &ctx);
+    let xfilesres = compile(args, &ctx, alfa_sources).and_then(|xfiles| {
+        // optionally combine everything into a single file
+        let xfiles = match &bundle_root {
+            Some(root) => vec![bundle(&ctx, xfiles, root)?],
+            None => xfiles,
+        };
+        // find where each file goes, before writing any of them
+        let paths = output_paths(&xfiles, &ctx.config.output_layout)?;
+        Ok(xfiles.into_iter().zip(paths).collect::<Vec<_>>())
+    });
+    eprintln!();
+    let mut xacml_written = 0;
+    let mut xacml_unchanged = 0;
+    let mut rules_written = 0;
+    let mut policysets_written = 0;
+    let mut policies_written = 0;
+    // check the result
+    match xfilesres {
+        Err(pe) => {
+            warn!("compilation of ALFA sources failed: {:?}", pe);
+            eprintln!("Conversion to XACML Failed:");
+            // If we checked for a PestParseError, and converted
+            // the wrapped error using into_miette(), we could get
+            // consistent miette output.  But as of pest 2.8.3,
+            // the miette adapter does not preserve the path or
+            // position in the file (the message is correct; but
+            // the annotated line numbers always start at 0).
+            //match pe {
+            //    ParseError::PestParseError(ppe) => {
+            //        eprintln!("{:?}", Report::new(ppe.into_miette()));
+            //    } ...
+            //}
+            eprintln!("{:?}", Report::new(pe));
+            return ExitCode::FAILURE;
+        }
+        Ok(xfiles) => {
+            let policy_output_path = Path::new(outdir);
+            if args.check {
+                return check(policy_output_path, &xfiles, &ctx, args.canonical);
+            }
+            // files generated by the previous run
+            let previous = match Manifest::read(policy_output_path) {
+                Ok(m) => m,
+                Err(e) => {
+                    eprintln!("{:?}", Report::new(e));
+                    return ExitCode::FAILURE;
+                }
+            };
+            let mut generated = Manifest::default();
+            let source_map = SourceMap::new(&xfiles);
+            eprintln!("Writing XACML policies:");
+            for (x, path) in xfiles {
+                let style = &ctx.config.output_style;
+                let target_version = ctx.config.target_version;
+                let write_res = if args.canonical {
+                    a2x::serialize_xentry_canonical(&x, target_version, style)
+                } else {
+                    a2x::serialize_xentry(&x, target_version, style)
+                }
+                .and_then(|mut f| {
+                    f.filename = path;
+                    let written = a2x::write_xacml_file_if_changed(policy_output_path, &f)?;
+                    generated.entries.push(ManifestEntry::new(&f, &x));
+                    Ok(written)
+                });
+                xacml_written += 1;
+                rules_written += x.rule_count();
+                policysets_written += x.policyset_count();
+                policies_written += x.policy_count();
+                // get details for policy
+                if let Ok(Written::Written(output_fn)) = write_res {
+                    eprintln!("  ✓ {}", output_fn);
+                } else if let Ok(Written::Unchanged(output_fn)) = write_res {
+                    xacml_unchanged += 1;
+                    eprintln!("  = {} (unchanged)", output_fn);
+                } else if let Err(e) = write_res {
+                    eprintln!("Failed to write XACML policy: {e:?}");
+                    // files not rewritten by this run keep their
+                    // previous entries, so a later run cannot prune
+                    // the last good copy.
+                    if let Some(previous) = &previous {
+                        let kept: Vec<ManifestEntry> =
+                            previous.stale(&generated).into_iter().cloned().collect();
+                        generated.entries.extend(kept);
+                    }
+                    if let Err(e) = generated.write(policy_output_path) {
+                        eprintln!("{:?}", Report::new(e));
+                    }
+                    return ExitCode::FAILURE;
+                }
+            }
+            let manifest_res =
+                update_manifest(policy_output_path, previous, generated, args.prune)
+                    .and_then(|()| source_map.write(policy_output_path));
+            if let Err(e) = manifest_res {
+                eprintln!("{:?}", Report::new(e));
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    eprintln!();
+    eprintln!("Summary:");
+    eprintln!("{}", "-".repeat(8));
+    eprintln!("ALFA files processed  : {}", alfa_sources_count);
+    eprintln!("XACML files generated : {}", xacml_written);
+    eprintln!("XACML files unchanged : {}", xacml_unchanged);
+    eprintln!("Policy Sets written   : {}", policysets_written);
+    eprintln!("Policies written      : {}", policies_written);
+    eprintln!("Rules written         : {}", rules_written);
+    eprintln!();
+    ExitCode::SUCCESS
+}
+
+/// Compile ALFA sources, reusing and updating the cache if there is
+/// one.
+fn compile(
+    args: &CLIArgs,
+    ctx: &Rc<Context>,
+    alfa_sources: Vec<AlfaFile>,
+) -> Result<Vec<XTopPolicy>, a2x::ParseError> {
+    #[cfg(feature = "cache")]
+    if let Some(dir) = &args.cache {
+        let mut cache = BuildCache::open(Path::new(dir), ctx.config.clone());
+        let xfiles = cache.compile(alfa_sources)?;
+        cache.write()?;
+        let stats = cache.stats();
+        // sources, in filename order, with how each was handled
+        let mut lines: Vec<(&String, String)> = vec![];
+        lines.extend(stats.converted.iter().map(|n| (n, format!("  ✓ {n}"))));
+        lines.extend(
+            stats.parsed.iter().map(|n| (n, format!("  = {n} (cached, parsed for definitions)"))),
+        );
+        lines.extend(stats.reused.iter().map(|n| (n, format!("  = {n} (cached)"))));
+        lines.sort_unstable();
+        eprintln!("Parsing ALFA policies:");
+        for (_, line) in lines {
+            eprintln!("{line}");
+        }
+        eprintln!();
+        eprintln!(
+            "Cache: {} converted, {} parsed for definitions, {} reused",
+            stats.converted.len(),
+            stats.parsed.len(),
+            stats.reused.len()
+        );
+        return Ok(xfiles);
+    }
+    #[cfg(not(feature = "cache"))]
+    let _ = args;
+    a2x::alfa_compile(ctx, alfa_sources)
+}
+
+/// Convert, then convert again whenever the inputs change, until
+/// interrupted.  Failed conversions leave earlier outputs in place.
+fn watch(args: &CLIArgs, outdir: &str) -> ExitCode {
+    if let Err(e) = config(args) {
+        eprintln!("{:?}", Report::new(e));
+        return ExitCode::FAILURE;
+    }
+    let poll = || InputSnapshot::new(&get_input_paths(&args.alfa_dir, "alfa"));
+    let mut snapshot = poll();
+    loop {
+        let start = time::Instant::now();
+        if convert(args, outdir) == ExitCode::SUCCESS {
+            eprintln!(
+                "✓ Conversion completed in {}",
+                format_duration(start.elapsed())
+            );
+        } else {
+            eprintln!("✗ Conversion failed; previous XACML files were kept");
+        }
+        eprintln!();
+        eprintln!("Watching for changes (Ctrl-C to stop)...");
+        let changes = loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let mut next = poll();
+            if snapshot.changes(&next).is_empty() {
+                continue;
+            }
+            // wait for the inputs to settle, so that a save touching
+            // several files is converted once.
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                let again = poll();
+                if again == next {
+                    break;
+                }
+                next = again;
+            }
+            let changes = snapshot.changes(&next);
+            snapshot = next;
+            if !changes.is_empty() {
+                break changes;
+            }
+        };
+        eprintln!();
+        eprintln!("Changed inputs:");
+        for c in changes {
+            eprintln!("  {c}");
+        }
+        eprintln!();
+    }
+}
+
+/// Build the conversion configuration from the command line.
+fn config(args: &CLIArgs) -> Result<Config, a2x::ParseError> {
+    let target_version = match args.xacml_version.as_deref() {
+        None => XacmlVersion::default(),
+        Some(v) => XacmlVersion::from_str(v)?,
+    };
+    let id_strategy = match args.id_strategy.as_deref() {
+        None => IdStrategy::default(),
+        Some(s) => IdStrategy::from_str(s)?,
+    };
+    let provenance = match args.provenance.as_deref() {
+        None => Provenance::default(),
+        Some(p) => Provenance::from_str(p)?,
+    };
+    // canonical XML has no comments, so the provenance would be lost.
+    if provenance == Provenance::Comment && args.canonical {
+        return Err(a2x::ParseError::InvalidOutputStyle(
+            "--provenance comment cannot be used with --canonical, which removes comments"
+                .to_owned(),
+        ));
+    }
+    Ok(Config {
+        base_namespace: args.base_namespace.clone(),
+        enable_builtins: !args.disable_builtins,
+        version: args.policy_version.clone(),
+        target_version,
+        id_strategy,
+        output_style: output_style(args)?,
+        output_layout: output_layout(args)?,
+        provenance,
+    })
+}
+
+/// Determine the root of a bundle, if one was requested.
+fn bundle_root(
+    policyset: &Option<String>,
+    combining_alg: &Option<String>,
+    ctx: &Context,
+) -> Option<BundleRoot> {
+    if let Some(name) = policyset {
+        return Some(BundleRoot::PolicySet(name.clone()));
+    }
+    combining_alg.as_ref().map(|alg| BundleRoot::Synthetic {
+        id: format!("{}bundle", ctx.config.get_base_namespace()),
+        combining_alg: alg.clone(),
+    })
+}
+
 /// Print the program name and version, with a header separator and whitespace.
 fn print_program_header() {
     let hdr_text = format!("ALFA to XACML Converter v{}",env!("CARGO_PKG_VERSION"));
@@ -128,29 +378,30 @@ fn print_program_header() {
 /// # Arguments
 /// * `input` - A vector of path names to alfa source files.
 ///
-/// # Panics
+/// # Errors
 ///
-/// The function panics if any file could not be opened or read.
-fn get_alfa_sources(input: Vec<PathBuf>) -> Vec<AlfaFile> {
+/// Returns `AlfaReadError`, naming the file, if any file could not be
+/// opened or read, or is not valid UTF-8.
+fn get_alfa_sources(input: Vec<PathBuf>) -> Result<Vec<AlfaFile>, a2x::ParseError> {
     let mut alfa_sources: Vec<AlfaFile> = vec![];
     for i in input {
         info!("Alfa path to parse: {}", i.display());
-        // read file to string
-        let mut f = std::fs::File::open(&i).expect("could not open file");
-        let mut buffer = String::new();
-        f.read_to_string(&mut buffer).expect("could not read file");
+        // read file to string; inputs can disappear, or be
+        // unreadable, while an editor saves them.
+        let buffer = std::fs::read_to_string(&i)
+            .map_err(|e| a2x::ParseError::AlfaReadError(format!("{}: {e}", i.display())))?;
         alfa_sources.push(AlfaFile {
             filename: i.to_str().unwrap_or("<unknown path>").to_owned(),
             contents: buffer,
         });
     }
-    alfa_sources
+    Ok(alfa_sources)
 }
 
-/// Expand a set of paths into all the child files ending in ".alfa".
-fn get_input_paths(args: &Vec<String>) -> Vec<PathBuf> {
+/// Expand a set of paths into all the child files ending in
+/// `suffix` (such as "alfa").
+fn get_input_paths(args: &Vec<String>, suffix: &str) -> Vec<PathBuf> {
     info!("input paths: {args:?}");
-    let suffix = "alfa";
     // input paths that we should attempt to parse
     let mut input_paths: Vec<PathBuf> = vec![];
     // loop through args, and add any paths that exist (error on non-existing paths)
@@ -158,24 +409,333 @@ fn get_input_paths(args: &Vec<String>) -> Vec<PathBuf> {
         let p = Path::new(&a);
         if p.is_dir() {
             for entry in WalkDir::new(p)
+                .sort_by_file_name()
                 .into_iter()
                 .filter_map(std::result::Result::ok)
             {
-                if let Some(ext) = entry.path().extension() {
-                    if ext == suffix {
-                        input_paths.push(entry.path().to_path_buf());
-                    }
+                if let Some(ext) = entry.path().extension()
+                    && ext == suffix
+                {
+                    input_paths.push(entry.path().to_path_buf());
                 }
             }
-        } else if p.is_file() {
-            if let Some(ext) = p.extension() {
-                if ext == suffix {
-                    input_paths.push(p.to_path_buf());
+        } else if p.is_file()
+            && let Some(ext) = p.extension()
+            && ext == suffix
+        {
+            input_paths.push(p.to_path_buf());
+        }
+    }
+    input_paths
+}
+
+/// Serve the language server over stdio.
+#[cfg(feature = "lsp")]
+fn lsp(args: &CLIArgs) -> ExitCode {
+    let result = config(args)
+        .map_err(|e| e.to_string())
+        .and_then(|cfg| a2x::lsp::run(cfg).map_err(|e| e.to_string()));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Language server failed: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Read XACML files from the input paths, and decompile them into a
+/// single ALFA file.
+fn decompile(inputs: &Vec<String>, alfa_path: &str, namespace: Option<&str>) -> ExitCode {
+    let input_paths = get_input_paths(inputs, "xml");
+    eprintln!("Found {} XACML policy files", input_paths.len());
+    let mut policies = vec![];
+    for p in &input_paths {
+        info!("XACML path to read: {}", p.display());
+        let read = std::fs::read(p)
+            .map_err(|e| a2x::ParseError::XacmlReadError(format!("{}: {e}", p.display())))
+            .and_then(|xml| read_xacml(&xml));
+        match read {
+            Ok(x) => policies.push(x),
+            Err(e) => {
+                eprintln!("{}: {:?}", p.display(), Report::new(e));
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let alfa = match a2x::decompile::decompile(
+        &policies,
+        namespace.unwrap_or(a2x::decompile::DEFAULT_NAMESPACE),
+    ) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("{:?}", Report::new(e));
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(e) = std::fs::write(alfa_path, alfa) {
+        eprintln!("Could not write {alfa_path}: {e}");
+        return ExitCode::FAILURE;
+    }
+    eprintln!("Wrote {alfa_path}");
+    ExitCode::SUCCESS
+}
+
+/// Write the syntax trees or the compiled XACML model of the input
+/// files to stdout as JSON.
+#[cfg(feature = "serde")]
+fn dump_json(args: &CLIArgs, form: &str) -> ExitCode {
+    let ctx = match config(args) {
+        Ok(c) => Rc::new(Context::new(c)),
+        Err(e) => {
+            eprintln!("{:?}", Report::new(e));
+            return ExitCode::FAILURE;
+        }
+    };
+    let alfa_sources = match get_alfa_sources(get_input_paths(&args.alfa_dir, "alfa")) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{:?}", Report::new(e));
+            return ExitCode::FAILURE;
+        }
+    };
+    let json = if form == "ast" {
+        alfa_sources
+            .iter()
+            .map(|s| {
+                let src = miette::NamedSource::new(&s.filename, s.contents.clone());
+                a2x::make_alfa_ast(&s.contents, &src, ctx.clone())
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(|asts| serde_json::to_string_pretty(&asts))
+    } else {
+        a2x::alfa_compile(&ctx, alfa_sources).map(|xfiles| serde_json::to_string_pretty(&xfiles))
+    };
+    match json {
+        Ok(Ok(j)) => {
+            println!("{j}");
+            ExitCode::SUCCESS
+        }
+        Ok(Err(e)) => {
+            eprintln!("Could not serialize to JSON: {e}");
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("{:?}", Report::new(e));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Compile ALFA files, and print the source of the elements generated
+/// with an ID.
+fn lookup(args: &CLIArgs, id: &str, inputs: &Vec<String>) -> ExitCode {
+    let ctx = match config(args) {
+        Ok(c) => Rc::new(Context::new(c)),
+        Err(e) => {
+            eprintln!("{:?}", Report::new(e));
+            return ExitCode::FAILURE;
+        }
+    };
+    let xfilesres = get_alfa_sources(get_input_paths(inputs, "alfa"))
+        .and_then(|alfa_sources| a2x::alfa_compile(&ctx, alfa_sources))
+        .and_then(|xfiles| {
+            let paths = output_paths(&xfiles, &ctx.config.output_layout)?;
+            Ok(xfiles.into_iter().zip(paths).collect::<Vec<_>>())
+        });
+    let source_map = match xfilesres {
+        Ok(xfiles) => SourceMap::new(&xfiles),
+        Err(e) => {
+            eprintln!("{:?}", Report::new(e));
+            return ExitCode::FAILURE;
+        }
+    };
+    let entries = source_map.lookup(id);
+    if entries.is_empty() {
+        eprintln!("No element was generated with the ID {id}");
+        return ExitCode::FAILURE;
+    }
+    for e in entries {
+        println!("{} {} ({})", e.element, e.path, e.output.display());
+        match std::fs::read_to_string(&e.range.file) {
+            Ok(src) => println!("{}", e.range.snippet(&src)),
+            Err(err) => eprintln!("Could not read {}: {err}", e.range.file),
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Format ALFA files in place, or with `check`, report the files that
+/// are not formatted, with a diff of the changes.
+fn format_sources(inputs: &Vec<String>, check: bool) -> ExitCode {
+    let input_paths = get_input_paths(inputs, "alfa");
+    let mut unformatted = 0;
+    for p in &input_paths {
+        let formatted = std::fs::read_to_string(p)
+            .map_err(|e| format!("Could not read {}: {e}", p.display()))
+            .and_then(|src| {
+                let e = match a2x::format::format_alfa(&src) {
+                    Ok(f) => return Ok((src, f)),
+                    Err(a2x::ParseError::PestParseError(pe)) => {
+                        // annotate with filename
+                        let ppe = pe.with_path(&p.display().to_string());
+                        a2x::ParseError::PestParseError(Box::new(ppe))
+                    }
+                    Err(e) => e,
+                };
+                Err(format!("{:?}", Report::new(e)))
+            });
+        let (src, formatted) = match formatted {
+            Ok(f) => f,
+            Err(e) => {
+                eprintln!("{e}");
+                return ExitCode::FAILURE;
+            }
+        };
+        if src == formatted {
+            continue;
+        }
+        unformatted += 1;
+        if check {
+            print!("{}", unified_diff(p, src.as_bytes(), formatted.as_bytes()));
+        } else if let Err(e) = std::fs::write(p, formatted) {
+            eprintln!("Could not write {}: {e}", p.display());
+            return ExitCode::FAILURE;
+        } else {
+            eprintln!("Formatted {}", p.display());
+        }
+    }
+    if check && unformatted > 0 {
+        eprintln!("✗ {unformatted} of {} ALFA files are not formatted", input_paths.len());
+        ExitCode::FAILURE
+    } else {
+        if check {
+            eprintln!("✓ {} ALFA files are formatted", input_paths.len());
+        }
+        ExitCode::SUCCESS
+    }
+}
+
+/// Build the style of generated XML from the command line.
+fn output_style(args: &CLIArgs) -> Result<OutputStyle, a2x::ParseError> {
+    let mut style = OutputStyle {
+        prefix: args.xml_prefix.clone(),
+        default_namespace: args.xml_default_namespace,
+        xml_declaration: !args.no_xml_declaration,
+        schema_location: args.schema_location,
+        ..OutputStyle::default()
+    };
+    if let Some(n) = args.indent {
+        style.indent = (n > 0).then_some(n);
+    }
+    if let Some(e) = &args.encoding {
+        style.encoding = XmlEncoding::from_str(e)?;
+    }
+    if let Some(l) = &args.line_endings {
+        style.line_ending = LineEnding::from_str(l)?;
+    }
+    Ok(style)
+}
+
+/// Compare the generated XACML with the output directory, printing
+/// a diff of each changed file, and the files that would be added or
+/// removed.  Nothing is written.
+fn check(
+    outdir: &Path,
+    xfiles: &[(XTopPolicy, PathBuf)],
+    ctx: &Context,
+    canonical: bool,
+) -> ExitCode {
+    let version = ctx.config.target_version;
+    let style = &ctx.config.output_style;
+    let files_res: Result<Vec<_>, _> = xfiles
+        .iter()
+        .map(|(x, path)| {
+            let mut f = if canonical {
+                a2x::serialize_xentry_canonical(x, version, style)?
+            } else {
+                a2x::serialize_xentry(x, version, style)?
+            };
+            f.filename.clone_from(path);
+            Ok(f)
+        })
+        .collect();
+    let report_res = files_res.and_then(|files| check_output(outdir, &files));
+    let report = match report_res {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{:?}", Report::new(e));
+            return ExitCode::FAILURE;
+        }
+    };
+    for (_, diff) in &report.changed {
+        print!("{diff}");
+    }
+    for path in &report.added {
+        println!("Added:   {}", path.display());
+    }
+    for path in &report.removed {
+        println!("Removed: {}", path.display());
+    }
+    if report.is_clean() {
+        eprintln!("✓ {} XACML files are up to date", xfiles.len());
+        ExitCode::SUCCESS
+    } else {
+        eprintln!();
+        eprintln!(
+            "✗ XACML differs from ALFA: {} changed, {} added, {} removed",
+            report.changed.len(),
+            report.added.len(),
+            report.removed.len()
+        );
+        ExitCode::FAILURE
+    }
+}
+
+/// Report or remove files that the previous run generated but this
+/// one did not, and record the generated files in the manifest.
+/// Stale files that are kept stay in the manifest, so they are
+/// reported again on the next run.
+fn update_manifest(
+    outdir: &Path,
+    previous: Option<Manifest>,
+    mut generated: Manifest,
+    remove: bool,
+) -> Result<(), a2x::ParseError> {
+    let previous = previous.unwrap_or_default();
+    let stale = previous.stale(&generated);
+    if !stale.is_empty() {
+        eprintln!();
+        if remove {
+            eprintln!("Removing stale XACML files:");
+            for (p, e) in prune(outdir, &stale)?.into_iter().zip(&stale) {
+                match p {
+                    Pruned::Removed(path) => eprintln!("  ✗ {}", path.display()),
+                    Pruned::Modified(path) => {
+                        eprintln!("  ! {} (modified since generated; kept)", path.display());
+                        generated.entries.push((*e).clone());
+                    }
+                    Pruned::Missing(_) => {}
                 }
             }
+        } else {
+            eprintln!("Stale XACML files (remove with --prune):");
+            for e in &stale {
+                eprintln!("  ! {} ({})", e.path.display(), e.id);
+            }
+            generated.entries.extend(stale.into_iter().cloned());
         }
     }
-    input_paths
+    generated.write(outdir)
+}
+
+/// Choose the layout of XACML files from the command line.
+fn output_layout(args: &CLIArgs) -> Result<OutputLayout, a2x::ParseError> {
+    match (&args.layout, &args.layout_template) {
+        (_, Some(t)) => OutputLayout::template(t),
+        (Some(l), None) => OutputLayout::from_str(l),
+        (None, None) => Ok(OutputLayout::default()),
+    }
 }
 
 /// Display a duration with a single digit after the decimal.
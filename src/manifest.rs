@@ -0,0 +1,236 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Manifest of generated XACML files.
+//!
+//! Each run writes a manifest into the output directory, listing
+//! every file it generated.  On the next run, files listed in the old
+//! manifest that are no longer generated are stale, and can be
+//! reported or removed.  Files that are not in a manifest were not
+//! created by a2x, and are never touched.
+//!
+//! The manifest is a text file, with one tab-separated line per
+//! generated file: path, PolicyId/PolicySetId, version, ALFA source
+//! file, and a hash of the contents.
+
+use crate::ast::naming::hash_bytes;
+use crate::errors::ParseError;
+use crate::xacml::XTopPolicy;
+use crate::XacmlFile;
+use log::info;
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+/// Filename of the manifest, within the output directory.
+pub const MANIFEST_FILENAME: &str = ".a2x-manifest";
+
+/// First line of a manifest.
+const MANIFEST_HEADER: &str = "# a2x manifest 1";
+
+/// A generated XACML file.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ManifestEntry {
+    /// Path of the file, relative to the output directory.
+    pub path: PathBuf,
+    /// PolicyId or PolicySetId.
+    pub id: String,
+    /// Version of the policy or policyset.
+    pub version: String,
+    /// ALFA source file, if the policy came from one.
+    pub source: Option<String>,
+    /// Hash of the file contents.
+    pub hash: String,
+}
+
+impl ManifestEntry {
+    /// Entry for a policy or policyset serialized into a file.
+    #[must_use]
+    pub fn new(file: &XacmlFile, p: &XTopPolicy) -> ManifestEntry {
+        let (id, version, source) = match p {
+            XTopPolicy::Policy(xp) => (&xp.id, &xp.version, &xp.source),
+            XTopPolicy::PolicySet(xps) => (&xps.id, &xps.version, &xps.source),
+        };
+        ManifestEntry {
+            path: file.filename.clone(),
+            id: id.clone(),
+            version: version.clone(),
+            source: source.clone(),
+            hash: hash_bytes(&file.contents),
+        }
+    }
+}
+
+/// Generated XACML files in an output directory.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Manifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// Outcome of removing a stale file.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Pruned {
+    /// The file was deleted.
+    Removed(PathBuf),
+    /// The file was changed since it was generated, and was kept.
+    Modified(PathBuf),
+    /// The file no longer exists.
+    Missing(PathBuf),
+}
+
+impl Manifest {
+    /// Read the manifest from an output directory, or `None` if
+    /// there is no manifest.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the manifest cannot be read or parsed.
+    pub fn read(dir: &Path) -> Result<Option<Manifest>, ParseError> {
+        let path = dir.join(MANIFEST_FILENAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| ParseError::InvalidManifest(e.to_string()))?;
+        Manifest::parse(&contents).map(Some)
+    }
+
+    /// Write the manifest into an output directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the manifest cannot be written.
+    pub fn write(&self, dir: &Path) -> Result<(), ParseError> {
+        std::fs::create_dir_all(dir).map_err(|_x| ParseError::XacmlWriteIoError)?;
+        std::fs::write(dir.join(MANIFEST_FILENAME), self.to_string())
+            .map_err(|_x| ParseError::XacmlWriteIoError)
+    }
+
+    /// Parse the contents of a manifest.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the contents are not a manifest, or an entry
+    /// is malformed.
+    pub fn parse(contents: &str) -> Result<Manifest, ParseError> {
+        let mut lines = contents.lines();
+        if lines.next() != Some(MANIFEST_HEADER) {
+            return Err(ParseError::InvalidManifest("missing manifest header".to_owned()));
+        }
+        let mut entries = vec![];
+        for line in lines.filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            let fields: Vec<String> = line.split('\t').map(unescape).collect();
+            let [path, id, version, source, hash] = fields.as_slice() else {
+                return Err(ParseError::InvalidManifest(format!("malformed entry {line:?}")));
+            };
+            let path = PathBuf::from(path);
+            if !is_within(&path) {
+                return Err(ParseError::InvalidManifest(format!(
+                    "path {:?} is not within the output directory",
+                    path.display().to_string()
+                )));
+            }
+            entries.push(ManifestEntry {
+                path,
+                id: id.clone(),
+                version: version.clone(),
+                source: (!source.is_empty()).then(|| source.clone()),
+                hash: hash.clone(),
+            });
+        }
+        Ok(Manifest { entries })
+    }
+
+    /// Entries of this (older) manifest whose files are not in
+    /// `current`.
+    #[must_use]
+    pub fn stale(&self, current: &Manifest) -> Vec<&ManifestEntry> {
+        let generated: HashSet<&PathBuf> = current.entries.iter().map(|e| &e.path).collect();
+        self.entries
+            .iter()
+            .filter(|e| !generated.contains(&e.path))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Manifest {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "{MANIFEST_HEADER}")?;
+        writeln!(f, "# path\tid\tversion\tsource\thash")?;
+        for e in &self.entries {
+            writeln!(
+                f,
+                "{}\t{}\t{}\t{}\t{}",
+                escape(&e.path.to_string_lossy()),
+                escape(&e.id),
+                escape(&e.version),
+                escape(e.source.as_deref().unwrap_or_default()),
+                escape(&e.hash)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Remove stale files from an output directory, along with any
+/// directories left empty.  Files that were changed since they were
+/// generated are kept.
+///
+/// # Errors
+///
+/// Returns `Err` if a stale file cannot be read or removed.
+pub fn prune(dir: &Path, stale: &[&ManifestEntry]) -> Result<Vec<Pruned>, ParseError> {
+    let mut pruned = vec![];
+    for e in stale {
+        let full_path = dir.join(&e.path);
+        let Ok(contents) = std::fs::read(&full_path) else {
+            pruned.push(Pruned::Missing(e.path.clone()));
+            continue;
+        };
+        if hash_bytes(&contents) != e.hash {
+            pruned.push(Pruned::Modified(e.path.clone()));
+            continue;
+        }
+        info!("removing stale file {}", full_path.display());
+        std::fs::remove_file(&full_path).map_err(|_x| ParseError::XacmlWriteIoError)?;
+        // remove_dir only succeeds on empty directories
+        for parent in e.path.ancestors().skip(1) {
+            if parent.as_os_str().is_empty() || std::fs::remove_dir(dir.join(parent)).is_err() {
+                break;
+            }
+        }
+        pruned.push(Pruned::Removed(e.path.clone()));
+    }
+    Ok(pruned)
+}
+
+/// Whether a relative path stays within the output directory.
+fn is_within(path: &Path) -> bool {
+    path.components().next().is_some()
+        && path.components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('t') => out.push('\t'),
+                Some('n') => out.push('\n'),
+                Some('r') => out.push('\r'),
+                Some(other) => out.push(other),
+                None => out.push('\\'),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
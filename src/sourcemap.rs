@@ -0,0 +1,276 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Source maps, from generated XACML elements back to ALFA.
+//!
+//! Each run writes a source map into the output directory, listing
+//! the ALFA location of every generated PolicySet, Policy, and Rule,
+//! and of their Target and Condition elements.  Elements are
+//! identified by the PolicySetId, PolicyId, or RuleId that contains
+//! them, and by a path of element names and IDs from the top-level
+//! policy.
+//!
+//! The source map is a JSON document, with one entry per line:
+//!
+//! ```json
+//! {"version": 1, "entries": [
+//!   {"id": "https://example.com/main/p#rule_1", "element": "Rule", ...}
+//! ]}
+//! ```
+
+use crate::ast::SrcLoc;
+use crate::errors::ParseError;
+use crate::xacml::XTopPolicy;
+use crate::xacml::xcondition::XCondition;
+use crate::xacml::xpolicy::XPolicy;
+use crate::xacml::xpolicyentry::XPolicyEntry;
+use crate::xacml::xpolicyset::XPolicySet;
+use crate::xacml::xrule::XRule;
+use crate::xacml::xtarget::XTarget;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Filename of the source map, within the output directory.
+pub const SOURCEMAP_FILENAME: &str = ".a2x-sourcemap.json";
+
+/// Version of the source map format.
+const SOURCEMAP_VERSION: u32 = 1;
+
+/// A range of text in an ALFA source file.  Lines and columns start
+/// at 1, columns count characters, and the end is the last character
+/// of the range.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceRange {
+    /// ALFA source file.
+    pub file: String,
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl SourceRange {
+    /// The range of a source location, or `None` if the location is
+    /// not within an ALFA source (such as syntax built in Rust).
+    #[must_use]
+    pub fn from_src_loc(loc: &SrcLoc) -> Option<SourceRange> {
+        let src = loc.get_src();
+        let contents = src.inner();
+        let span = loc.get_span();
+        if contents.is_empty() || span.offset() + span.len() > contents.len() {
+            return None;
+        }
+        let (start_line, start_column) = line_column(contents, span.offset());
+        let last = span.offset() + span.len().saturating_sub(1);
+        let (end_line, end_column) = line_column(contents, last);
+        Some(SourceRange {
+            file: src.name().to_owned(),
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+        })
+    }
+
+    /// The lines of `contents` (the source file) covered by this
+    /// range, with line numbers.
+    #[must_use]
+    pub fn snippet(&self, contents: &str) -> String {
+        let width = self.end_line.to_string().len();
+        let mut s = format!(
+            "{:width$}--> {}:{}:{}\n",
+            "", self.file, self.start_line, self.start_column
+        );
+        for (i, line) in contents
+            .lines()
+            .enumerate()
+            .skip(self.start_line - 1)
+            .take(self.end_line + 1 - self.start_line)
+        {
+            s.push_str(&format!("{:>width$} | {line}\n", i + 1));
+        }
+        s
+    }
+}
+
+impl fmt::Display for SourceRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}-{}:{}",
+            self.file, self.start_line, self.start_column, self.end_line, self.end_column
+        )
+    }
+}
+
+/// Line and column (from 1) of a byte offset.
+fn line_column(contents: &str, offset: usize) -> (usize, usize) {
+    let mut offset = offset;
+    while !contents.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &contents[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// The ALFA location of a generated XACML element.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SourceMapEntry {
+    /// PolicySetId, PolicyId, or RuleId of the element, or of the
+    /// element containing it.
+    pub id: String,
+    /// XACML element name, such as `Rule` or `Target`.
+    pub element: String,
+    /// Element names and IDs from the top-level policy, such as
+    /// `PolicySet[a]/Policy[b]/Rule[c]/Condition`.
+    pub path: String,
+    /// XACML file the element was written to.
+    pub output: PathBuf,
+    /// Location of the ALFA source.
+    pub range: SourceRange,
+}
+
+/// Locations of generated XACML elements.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct SourceMap {
+    pub entries: Vec<SourceMapEntry>,
+}
+
+impl SourceMap {
+    /// Source map of policies and policysets, and the XACML files
+    /// they are written to.
+    #[must_use]
+    pub fn new(xfiles: &[(XTopPolicy, PathBuf)]) -> SourceMap {
+        let mut m = SourceMap::default();
+        for (x, path) in xfiles {
+            match x {
+                XTopPolicy::Policy(p) => m.add_policy(p, "", path),
+                XTopPolicy::PolicySet(ps) => m.add_policyset(ps, "", path),
+            }
+        }
+        m
+    }
+
+    /// Entries for an ID, with the element of that ID first.
+    #[must_use]
+    pub fn lookup(&self, id: &str) -> Vec<&SourceMapEntry> {
+        self.entries.iter().filter(|e| e.id == id).collect()
+    }
+
+    /// Write the source map into an output directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the source map cannot be written.
+    pub fn write(&self, dir: &Path) -> Result<(), ParseError> {
+        std::fs::create_dir_all(dir).map_err(|_x| ParseError::XacmlWriteIoError)?;
+        std::fs::write(dir.join(SOURCEMAP_FILENAME), self.to_string())
+            .map_err(|_x| ParseError::XacmlWriteIoError)
+    }
+
+    fn add_policyset(&mut self, ps: &XPolicySet, parent: &str, output: &Path) {
+        let path = format!("{parent}PolicySet[{}]", ps.id);
+        self.add(&ps.id, "PolicySet", &path, output, ps.location.as_ref());
+        self.add_target(&ps.id, &path, output, &ps.target);
+        for c in &ps.children {
+            match c {
+                XPolicyEntry::Policy(p) => self.add_policy(p, &format!("{path}/"), output),
+                XPolicyEntry::PolicySet(child) => {
+                    self.add_policyset(child, &format!("{path}/"), output);
+                }
+                XPolicyEntry::PolicyIdRef(_) | XPolicyEntry::PolicySetIdRef(_) => {}
+            }
+        }
+    }
+
+    fn add_policy(&mut self, p: &XPolicy, parent: &str, output: &Path) {
+        let path = format!("{parent}Policy[{}]", p.id);
+        self.add(&p.id, "Policy", &path, output, p.location.as_ref());
+        self.add_target(&p.id, &path, output, &p.target);
+        for r in &p.rules {
+            self.add_rule(r, &path, output);
+        }
+    }
+
+    fn add_rule(&mut self, r: &XRule, parent: &str, output: &Path) {
+        let path = format!("{parent}/Rule[{}]", r.id);
+        self.add(&r.id, "Rule", &path, output, r.location.as_ref());
+        self.add_target(&r.id, &path, output, &r.target);
+        if let Some(XCondition { location, .. }) = &r.condition {
+            let cond_path = format!("{path}/Condition");
+            self.add(&r.id, "Condition", &cond_path, output, location.as_ref());
+        }
+    }
+
+    fn add_target(&mut self, id: &str, parent: &str, output: &Path, t: &XTarget) {
+        let path = format!("{parent}/Target");
+        self.add(id, "Target", &path, output, t.location.as_ref());
+    }
+
+    fn add(
+        &mut self,
+        id: &str,
+        element: &str,
+        path: &str,
+        output: &Path,
+        range: Option<&SourceRange>,
+    ) {
+        if let Some(range) = range {
+            self.entries.push(SourceMapEntry {
+                id: id.to_owned(),
+                element: element.to_owned(),
+                path: path.to_owned(),
+                output: output.to_path_buf(),
+                range: range.clone(),
+            });
+        }
+    }
+}
+
+impl fmt::Display for SourceMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{\"version\": {SOURCEMAP_VERSION}, \"entries\": [")?;
+        for (i, e) in self.entries.iter().enumerate() {
+            let sep = if i == 0 { "" } else { "," };
+            let r = &e.range;
+            write!(
+                f,
+                "{sep}\n  {{\"id\": {}, \"element\": {}, \"path\": {}, \"output\": {}, \
+                 \"file\": {}, \"start_line\": {}, \"start_column\": {}, \
+                 \"end_line\": {}, \"end_column\": {}}}",
+                json_string(&e.id),
+                json_string(&e.element),
+                json_string(&e.path),
+                json_string(&e.output.display().to_string()),
+                json_string(&r.file),
+                r.start_line,
+                r.start_column,
+                r.end_line,
+                r.end_column
+            )?;
+        }
+        writeln!(f, "\n]}}")
+    }
+}
+
+/// Quote a string for JSON.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,95 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Detecting changes to ALFA inputs, for `--watch`.
+//!
+//! Inputs are polled: each poll records the size and modification
+//! time of every input file, and is compared with the previous one.
+//! Files that appear or disappear between polls are changes too, so
+//! the set of input paths should be re-expanded before each poll.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Time between polls of the input files.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Size and modification time of a file.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct FileStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+/// The state of a set of input files at one time.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct InputSnapshot {
+    files: BTreeMap<PathBuf, FileStamp>,
+}
+
+/// A difference between two snapshots.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum InputChange {
+    Added(PathBuf),
+    Removed(PathBuf),
+    Modified(PathBuf),
+}
+
+impl fmt::Display for InputChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputChange::Added(p) => write!(f, "+ {}", p.display()),
+            InputChange::Removed(p) => write!(f, "- {}", p.display()),
+            InputChange::Modified(p) => write!(f, "~ {}", p.display()),
+        }
+    }
+}
+
+impl InputSnapshot {
+    /// Record the current state of files.  Files that cannot be read
+    /// are left out, as if they had been removed.
+    #[must_use]
+    pub fn new(paths: &[PathBuf]) -> InputSnapshot {
+        let files = paths
+            .iter()
+            .filter_map(|p| stamp(p).map(|s| (p.clone(), s)))
+            .collect();
+        InputSnapshot { files }
+    }
+
+    /// Changes from this snapshot to a later one, in path order.
+    #[must_use]
+    pub fn changes(&self, later: &InputSnapshot) -> Vec<InputChange> {
+        let mut changes = vec![];
+        for (p, s) in &self.files {
+            match later.files.get(p) {
+                None => changes.push(InputChange::Removed(p.clone())),
+                Some(l) if l != s => changes.push(InputChange::Modified(p.clone())),
+                Some(_) => {}
+            }
+        }
+        for p in later.files.keys() {
+            if !self.files.contains_key(p) {
+                changes.push(InputChange::Added(p.clone()));
+            }
+        }
+        changes.sort_by(|a, b| change_path(a).cmp(change_path(b)));
+        changes
+    }
+}
+
+fn stamp(path: &Path) -> Option<FileStamp> {
+    let m = std::fs::metadata(path).ok()?;
+    Some(FileStamp {
+        len: m.len(),
+        modified: m.modified().ok(),
+    })
+}
+
+fn change_path(c: &InputChange) -> &Path {
+    match c {
+        InputChange::Added(p) | InputChange::Removed(p) | InputChange::Modified(p) => p,
+    }
+}
@@ -0,0 +1,245 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Single-document bundles.
+//!
+//! Normally each top-level policy or policyset is written to its own
+//! file, and policysets refer to other files with
+//! `<PolicyIdReference>` and `<PolicySetIdReference>`.  A bundle is a
+//! single policyset with every referenced policy inlined, for PDPs
+//! that accept only one document.
+//!
+//! References to external policies (`extern` declarations) are not
+//! defined in ALFA, so they remain references.
+
+use super::xpolicyentry::XPolicyEntry;
+use super::xpolicyset::XPolicySet;
+use super::XTopPolicy;
+use crate::ast::version::VersionConstraints;
+use crate::context::{Context, SYSTEM_NS};
+use crate::errors::ParseError;
+use log::{info, warn};
+use std::collections::{HashMap, HashSet};
+
+/// Filename of a bundle with a synthetic root.
+pub const BUNDLE_FILENAME: &str = "bundle.xml";
+
+/// Name of a bundle with a synthetic root.
+pub const BUNDLE_NAME: &str = "bundle";
+
+/// The root of a bundle.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BundleRoot {
+    /// An ALFA policyset, identified by its fully-qualified name
+    /// (ex: `main.corePolicies`).
+    PolicySet(String),
+    /// A new policyset, containing every top-level policy and
+    /// policyset, combined with an ALFA policy-combining algorithm
+    /// (ex: `denyOverrides`).
+    Synthetic {
+        /// Identifier for the new policyset
+        id: String,
+        /// Name of the policy-combining algorithm
+        combining_alg: String,
+    },
+}
+
+/// Combine compiled policies into a single policyset, with all
+/// references to policies and policysets defined in ALFA inlined.
+/// Each policy and policyset appears once; further references to an
+/// entry that is already in the bundle are kept as references.
+///
+/// # Errors
+///
+/// Returns `Err` if the root policyset or combining algorithm
+/// cannot be found, or if policy references form a cycle.
+pub fn bundle(
+    ctx: &Context,
+    policies: Vec<XTopPolicy>,
+    root: &BundleRoot,
+) -> Result<XTopPolicy, ParseError> {
+    let entries: Vec<XPolicyEntry> = policies
+        .into_iter()
+        .map(|p| match p {
+            XTopPolicy::Policy(xp) => XPolicyEntry::Policy(xp),
+            XTopPolicy::PolicySet(xps) => XPolicyEntry::PolicySet(xps),
+        })
+        .collect();
+    // every policy and policyset that can be referenced, by id.
+    let mut index = HashMap::new();
+    for e in &entries {
+        index_entry(e, &mut index);
+    }
+    let root_ps = match root {
+        BundleRoot::PolicySet(name) => {
+            let id = ctx.lookup_policyset(name, &[SYSTEM_NS.to_string()])?.get_id();
+            match index.get(id.as_str()).and_then(|defs| defs.first()) {
+                Some(XPolicyEntry::PolicySet(ps)) => (*ps).clone(),
+                _ => return Err(ParseError::BundleRootNotFound(name.clone())),
+            }
+        }
+        BundleRoot::Synthetic { id, combining_alg } => {
+            // entries that are referenced are inlined where they are
+            // referenced, rather than also being children of the root.
+            let mut referenced = HashSet::new();
+            for e in &entries {
+                references(e, &mut referenced);
+            }
+            XPolicySet {
+                id: id.clone(),
+                filename: Some(BUNDLE_FILENAME.to_owned()),
+                name: Some(BUNDLE_NAME.to_owned()),
+                combining_alg: ctx
+                    .lookup_policy_combinator(combining_alg, &[SYSTEM_NS.to_string()])?
+                    .uri
+                    .clone(),
+                version: ctx.config.get_version(),
+                children: entries
+                    .iter()
+                    .filter(|e| entry_key(e).is_some_and(|(id, _)| !referenced.contains(id)))
+                    .cloned()
+                    .collect(),
+                ..XPolicySet::default()
+            }
+        }
+    };
+    info!("bundling policies under {}", root_ps.id);
+    let mut stack = vec![];
+    let mut inlined = HashSet::new();
+    let Some(XPolicyEntry::PolicySet(mut ps)) = inline(
+        XPolicyEntry::PolicySet(root_ps),
+        &index,
+        &mut stack,
+        &mut inlined,
+    )?
+    else {
+        return Err(ParseError::AstConvertError);
+    };
+    if matches!(root, BundleRoot::Synthetic { .. }) {
+        // anything not reached from the root (such as policies that
+        // are only referenced with other versions, or from a cycle)
+        // is still included.
+        for e in &entries {
+            if let Some(c) = inline(e.clone(), &index, &mut stack, &mut inlined)? {
+                ps.children.push(c);
+            }
+        }
+    }
+    Ok(XTopPolicy::PolicySet(ps))
+}
+
+/// Id and version of a policy or policyset.
+fn entry_key(e: &XPolicyEntry) -> Option<(&str, &str)> {
+    match e {
+        XPolicyEntry::Policy(p) => Some((&p.id, &p.version)),
+        XPolicyEntry::PolicySet(ps) => Some((&ps.id, &ps.version)),
+        XPolicyEntry::PolicyIdRef(_) | XPolicyEntry::PolicySetIdRef(_) => None,
+    }
+}
+
+/// Record a policy or policyset, and all of its descendants.
+fn index_entry<'a>(e: &'a XPolicyEntry, index: &mut HashMap<&'a str, Vec<&'a XPolicyEntry>>) {
+    match e {
+        XPolicyEntry::Policy(p) => {
+            index.entry(&p.id).or_default().push(e);
+        }
+        XPolicyEntry::PolicySet(ps) => {
+            index.entry(&ps.id).or_default().push(e);
+            for c in &ps.children {
+                index_entry(c, index);
+            }
+        }
+        XPolicyEntry::PolicyIdRef(_) | XPolicyEntry::PolicySetIdRef(_) => {}
+    }
+}
+
+/// Record the ids referenced within an entry.
+fn references<'a>(e: &'a XPolicyEntry, ids: &mut HashSet<&'a str>) {
+    match e {
+        XPolicyEntry::Policy(_) => {}
+        XPolicyEntry::PolicySet(ps) => {
+            for c in &ps.children {
+                references(c, ids);
+            }
+        }
+        XPolicyEntry::PolicyIdRef(r) | XPolicyEntry::PolicySetIdRef(r) => {
+            ids.insert(&r.id);
+        }
+    }
+}
+
+/// Replace references within an entry with their definitions.  The
+/// stack holds the policysets currently being inlined, to detect
+/// cycles.  Entries already in the bundle (by id and version) are
+/// dropped, and references to them are kept.
+fn inline(
+    e: XPolicyEntry,
+    index: &HashMap<&str, Vec<&XPolicyEntry>>,
+    stack: &mut Vec<String>,
+    inlined: &mut HashSet<(String, String)>,
+) -> Result<Option<XPolicyEntry>, ParseError> {
+    match e {
+        XPolicyEntry::Policy(ref p) => {
+            let added = inlined.insert((p.id.clone(), p.version.clone()));
+            Ok(added.then_some(e))
+        }
+        XPolicyEntry::PolicySet(mut ps) => {
+            if stack.contains(&ps.id) {
+                let mut cycle = stack.clone();
+                cycle.push(ps.id.clone());
+                return Err(ParseError::ReferenceCycle(cycle.join(" -> ")));
+            }
+            if !inlined.insert((ps.id.clone(), ps.version.clone())) {
+                return Ok(None);
+            }
+            stack.push(ps.id.clone());
+            let mut children = vec![];
+            for c in std::mem::take(&mut ps.children) {
+                children.extend(inline(c, index, stack, inlined)?);
+            }
+            ps.children = children;
+            stack.pop();
+            Ok(Some(XPolicyEntry::PolicySet(ps)))
+        }
+        XPolicyEntry::PolicyIdRef(ref r) | XPolicyEntry::PolicySetIdRef(ref r) => {
+            let Some(defs) = index.get(r.id.as_str()) else {
+                info!("reference to {} is not defined in ALFA, keeping it", r.id);
+                return Ok(Some(e));
+            };
+            let versions = VersionConstraints {
+                version: r.version.clone(),
+                earliest: r.earliest_version.clone(),
+                latest: r.latest_version.clone(),
+            };
+            let Some(def) = defs
+                .iter()
+                .find(|d| entry_key(d).is_some_and(|(_, v)| versions.matches(v)))
+            else {
+                warn!("no version of {} matches {versions}, keeping the reference", r.id);
+                return Ok(Some(e));
+            };
+            // a cycle is reported when the policyset is inlined again.
+            if let Some((id, version)) = entry_key(def)
+                && inlined.contains(&(id.to_owned(), version.to_owned()))
+                && !stack.iter().any(|s| s == id)
+            {
+                info!("{id} is already in the bundle, keeping the reference");
+                return Ok(Some(e));
+            }
+            // the reference supplies the parameters for the
+            // combining algorithm of its parent.
+            let def = match (*def).clone() {
+                XPolicyEntry::Policy(mut p) => {
+                    p.entry_params.clone_from(&r.entry_params);
+                    XPolicyEntry::Policy(p)
+                }
+                XPolicyEntry::PolicySet(mut ps) => {
+                    ps.entry_params.clone_from(&r.entry_params);
+                    XPolicyEntry::PolicySet(ps)
+                }
+                other => other,
+            };
+            inline(def, index, stack, inlined)
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Canonical XML.
+//!
+//! Serialized policies are re-written following Exclusive XML
+//! Canonicalization (without comments), so that identical policies
+//! always produce identical bytes, regardless of how they were
+//! indented:
+//!
+//! * no XML declaration, and no whitespace outside the root element
+//! * empty elements are written with start and end tags
+//! * namespace declarations appear only on the elements that use
+//!   them, sorted by prefix, ahead of attributes
+//! * attributes are sorted by namespace URI and local name
+//! * text and attribute values are escaped as C14N requires
+//!
+//! The input should be written without indentation, since all text
+//! (including whitespace) within the root element is significant.
+
+use crate::errors::ParseError;
+use std::collections::BTreeMap;
+use xml::name::OwnedName;
+use xml::reader::{EventReader, ParserConfig, XmlEvent};
+
+/// Re-write an XML document in canonical form.
+///
+/// # Errors
+///
+/// Returns `Err` if the input is not well-formed XML.
+pub fn canonicalize(xml: &[u8]) -> Result<Vec<u8>, ParseError> {
+    let reader = EventReader::new_with_config(
+        xml,
+        ParserConfig::new()
+            .trim_whitespace(false)
+            .whitespace_to_characters(true)
+            .cdata_to_characters(true)
+            .coalesce_characters(true)
+            .ignore_comments(true),
+    );
+    let mut out = String::with_capacity(xml.len());
+    // namespace declarations rendered by each open element, and its
+    // ancestors.
+    let mut scopes: Vec<BTreeMap<String, String>> = vec![BTreeMap::new()];
+    for e in reader {
+        match e.map_err(|e| ParseError::XacmlSerializeError(e.to_string()))? {
+            XmlEvent::StartElement {
+                name,
+                attributes,
+                namespace,
+            } => {
+                let in_scope = scopes.last().cloned().unwrap_or_default();
+                // namespaces visibly used by this element
+                let mut used = vec![name.prefix.clone().unwrap_or_default()];
+                used.extend(attributes.iter().filter_map(|a| a.name.prefix.clone()));
+                let mut decls = BTreeMap::new();
+                for prefix in used {
+                    if prefix == "xml" {
+                        continue;
+                    }
+                    let uri = namespace.get(&prefix).unwrap_or_default().to_owned();
+                    let rendered = in_scope.get(&prefix).map_or("", String::as_str);
+                    if rendered != uri {
+                        decls.insert(prefix, uri);
+                    }
+                }
+                out.push('<');
+                out.push_str(&qualified(&name));
+                for (prefix, uri) in &decls {
+                    if prefix.is_empty() {
+                        out.push_str(" xmlns=\"");
+                    } else {
+                        out.push_str(&format!(" xmlns:{prefix}=\""));
+                    }
+                    escape_attr(uri, &mut out);
+                    out.push('"');
+                }
+                let mut attributes = attributes;
+                attributes.sort_by(|a, b| {
+                    (&a.name.namespace, &a.name.local_name)
+                        .cmp(&(&b.name.namespace, &b.name.local_name))
+                });
+                for a in &attributes {
+                    out.push(' ');
+                    out.push_str(&qualified(&a.name));
+                    out.push_str("=\"");
+                    escape_attr(&a.value, &mut out);
+                    out.push('"');
+                }
+                out.push('>');
+                let mut scope = in_scope;
+                scope.extend(decls);
+                scopes.push(scope);
+            }
+            XmlEvent::EndElement { name } => {
+                out.push_str("</");
+                out.push_str(&qualified(&name));
+                out.push('>');
+                scopes.pop();
+            }
+            XmlEvent::Characters(s) if scopes.len() > 1 => escape_text(&s, &mut out),
+            XmlEvent::ProcessingInstruction { name, data } if scopes.len() > 1 => {
+                out.push_str("<?");
+                out.push_str(&name);
+                if let Some(d) = data {
+                    out.push(' ');
+                    out.push_str(&d);
+                }
+                out.push_str("?>");
+            }
+            _ => {}
+        }
+    }
+    Ok(out.into_bytes())
+}
+
+/// Element or attribute name, with its prefix.
+fn qualified(name: &OwnedName) -> String {
+    match &name.prefix {
+        Some(p) => format!("{p}:{}", name.local_name),
+        None => name.local_name.clone(),
+    }
+}
+
+fn escape_text(s: &str, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\r' => out.push_str("&#xD;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+fn escape_attr(s: &str, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' => out.push_str("&#x9;"),
+            '\n' => out.push_str("&#xA;"),
+            '\r' => out.push_str("&#xD;"),
+            _ => out.push(c),
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Layout of generated XACML files within the output directory.
+//!
+//! Each top-level policy or policyset is written to a relative path
+//! chosen by an [`OutputLayout`].  Layouts are checked so that two
+//! outputs never map to the same path, and so that no path escapes
+//! the output directory.
+
+use super::XTopPolicy;
+use crate::errors::ParseError;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+/// Placeholders that may appear in a layout template.
+const PLACEHOLDERS: [&str; 5] = ["namespace", "namespace_dir", "name", "id", "version"];
+
+/// Strategy for naming output files.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub enum OutputLayout {
+    /// One directory, with files named by qualified name
+    /// (`main.retail.xml`).
+    #[default]
+    Flat,
+    /// A directory per namespace component (`main/retail.xml`).
+    Nested,
+    /// One directory, with files named by the URL-encoded
+    /// PolicyId/PolicySetId (`urn%3Aexample%3Aretail.xml`).
+    ById,
+    /// A template with placeholders, such as
+    /// `{namespace_dir}/{name}-{version}.xml`.  The placeholders are:
+    ///
+    /// * `{namespace}` - namespace, joined with `.`
+    /// * `{namespace_dir}` - namespace, joined with `/`
+    /// * `{name}` - name within the namespace
+    /// * `{id}` - URL-encoded PolicyId/PolicySetId
+    /// * `{version}` - policy version
+    Template(String),
+}
+
+impl OutputLayout {
+    /// Layout from a template, checking that each placeholder is
+    /// known.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the template has an unknown or unterminated
+    /// placeholder.
+    pub fn template(template: &str) -> Result<OutputLayout, ParseError> {
+        expand(template, |_| Some(String::new()))?;
+        Ok(OutputLayout::Template(template.to_owned()))
+    }
+
+    /// Relative path for a top-level policy or policyset.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the policy has no name, or if the path is
+    /// not a relative path within the output directory.
+    pub fn path(&self, p: &XTopPolicy) -> Result<PathBuf, ParseError> {
+        let (id, ns, name, version) = match p {
+            XTopPolicy::Policy(xp) => (&xp.id, &xp.ns, &xp.name, &xp.version),
+            XTopPolicy::PolicySet(xps) => (&xps.id, &xps.ns, &xps.name, &xps.version),
+        };
+        let name = name.as_ref().ok_or(ParseError::XacmlMissingFilename);
+        let path = match self {
+            OutputLayout::Flat => {
+                PathBuf::from(p.filename().ok_or(ParseError::XacmlMissingFilename)?)
+            }
+            OutputLayout::Nested => {
+                let mut path: PathBuf = ns.iter().collect();
+                path.push(format!("{}.xml", name?));
+                path
+            }
+            OutputLayout::ById => PathBuf::from(format!("{}.xml", url_encode(id))),
+            OutputLayout::Template(t) => {
+                let name = name?;
+                let expanded = expand(t, |placeholder| match placeholder {
+                    "namespace" => Some(ns.join(".")),
+                    "namespace_dir" => Some(ns.join("/")),
+                    "name" => Some(name.clone()),
+                    "id" => Some(url_encode(id)),
+                    "version" => Some(version.clone()),
+                    _ => None,
+                })?;
+                // an empty namespace leaves no empty directory names
+                expanded.split('/').filter(|c| !c.is_empty()).collect()
+            }
+        };
+        check_relative(&path)?;
+        Ok(path)
+    }
+}
+
+impl FromStr for OutputLayout {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "flat" => Ok(OutputLayout::Flat),
+            "nested" => Ok(OutputLayout::Nested),
+            "id" => Ok(OutputLayout::ById),
+            _ => Err(ParseError::InvalidOutputLayout(format!(
+                "unsupported layout {s:?} (expected flat, nested, or id)"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for OutputLayout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputLayout::Flat => write!(f, "flat"),
+            OutputLayout::Nested => write!(f, "nested"),
+            OutputLayout::ById => write!(f, "id"),
+            OutputLayout::Template(t) => write!(f, "{t}"),
+        }
+    }
+}
+
+/// Relative paths for top-level policies and policysets, in the same
+/// order.
+///
+/// # Errors
+///
+/// Returns `Err` if any path cannot be determined, or if two
+/// policies would be written to the same path.
+pub fn output_paths(
+    ps: &[XTopPolicy],
+    layout: &OutputLayout,
+) -> Result<Vec<PathBuf>, ParseError> {
+    let mut seen: HashMap<PathBuf, &str> = HashMap::new();
+    let mut paths = vec![];
+    for p in ps {
+        let path = layout.path(p)?;
+        let id = match p {
+            XTopPolicy::Policy(xp) => xp.id.as_str(),
+            XTopPolicy::PolicySet(xps) => xps.id.as_str(),
+        };
+        if let Some(first) = seen.insert(path.clone(), id) {
+            return Err(ParseError::OutputPathCollision(
+                path.display().to_string(),
+                first.to_owned(),
+                id.to_owned(),
+            ));
+        }
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Replace `{placeholder}`s in a template.
+fn expand<F>(template: &str, value: F) -> Result<String, ParseError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            return Err(ParseError::InvalidOutputLayout(format!(
+                "unterminated placeholder in {template:?}"
+            )));
+        };
+        let placeholder = &rest[start + 1..start + len];
+        match value(placeholder).filter(|_| PLACEHOLDERS.contains(&placeholder)) {
+            Some(v) => out.push_str(&v),
+            None => {
+                return Err(ParseError::InvalidOutputLayout(format!(
+                    "unknown placeholder {{{placeholder}}} in {template:?} (expected one of {})",
+                    PLACEHOLDERS.map(|p| format!("{{{p}}}")).join(", ")
+                )));
+            }
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Check that a path stays within the output directory.
+fn check_relative(path: &Path) -> Result<(), ParseError> {
+    let within = path.components().next().is_some()
+        && path.components().all(|c| matches!(c, Component::Normal(_)));
+    if within {
+        Ok(())
+    } else {
+        Err(ParseError::InvalidOutputLayout(format!(
+            "output path {:?} is not within the output directory",
+            path.display().to_string()
+        )))
+    }
+}
+
+/// Percent-encode everything except unreserved URI characters.
+fn url_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            out.push(char::from(b));
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
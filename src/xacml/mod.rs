@@ -3,19 +3,31 @@
 
 //! XACML structures for serializing to XML.
 
+pub mod bundle;
+pub mod canonical;
+pub mod layout;
+pub mod reader;
+pub mod style;
+pub mod xacml2;
 pub mod xapply;
 pub mod xattr_designator;
+pub mod xcombinerparam;
 pub mod xcondition;
 pub mod xexpression;
 pub mod xfunction;
 pub mod xpolicy;
 pub mod xpolicyentry;
+pub mod xpolicyissuer;
 pub mod xpolicyset;
 pub mod xprescription;
 pub mod xrule;
 pub mod xtarget;
+use style::OutputStyle;
+use crate::context::XacmlVersion;
 use std::io::Write;
+use xacml2::Xacml2Writer;
 use xml::writer::EventWriter;
+use xml::writer::XmlEvent;
 use xpolicy::XPolicy;
 use xpolicyset::XPolicySet;
 use xprescription::XAttrValue;
@@ -39,17 +51,67 @@ pub trait XacmlWriter {
     ///
     /// Returns `Err` if the XML events are semantically invalid or
     /// the writer has an I/O error.
-    fn write_xml<W: Write>(&self, writer: &mut EventWriter<W>) -> Result<(), xml::writer::Error>;
+    fn write_xml<W: Write>(
+        &self,
+        writer: &mut EventWriter<W>,
+        style: &OutputStyle,
+    ) -> Result<(), xml::writer::Error>;
+}
+
+/// Write an XML comment, if there is one.
+pub(crate) fn write_comment<W: Write>(
+    comment: Option<&String>,
+    writer: &mut EventWriter<W>,
+) -> Result<(), xml::writer::Error> {
+    if let Some(c) = comment {
+        writer.write(XmlEvent::comment(c))?;
+    }
+    Ok(())
 }
 
 /// Top level of a XACML file will be either one policy set or one
 /// policy.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum XTopPolicy {
     Policy(XPolicy),
     PolicySet(XPolicySet),
 }
 
 impl XTopPolicy {
+    /// Filename that this policy or policyset should be written to.
+    #[must_use]
+    pub fn filename(&self) -> Option<&String> {
+        match self {
+            XTopPolicy::Policy(xp) => xp.filename.as_ref(),
+            XTopPolicy::PolicySet(xps) => xps.filename.as_ref(),
+        }
+    }
+
+    /// Write an XML document for a version of XACML, in the given
+    /// style.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the writer fails.
+    pub fn write_xml_version<W: Write>(
+        &self,
+        version: XacmlVersion,
+        writer: &mut EventWriter<W>,
+        style: &OutputStyle,
+    ) -> Result<(), xml::writer::Error> {
+        let style = &style.for_version(version);
+        if let Some(decl) = style.declaration() {
+            writer.write(decl)?;
+        }
+        match (self, version) {
+            (XTopPolicy::Policy(xp), XacmlVersion::V3) => xp.write_xml(writer, style),
+            (XTopPolicy::Policy(xp), XacmlVersion::V2) => xp.write_xml2(writer, style),
+            (XTopPolicy::PolicySet(xps), XacmlVersion::V3) => xps.write_xml(writer, style),
+            (XTopPolicy::PolicySet(xps), XacmlVersion::V2) => xps.write_xml2(writer, style),
+        }
+    }
+
     /// Count the total number of rules defined in this policy/set and
     /// its children.
     pub fn rule_count(&self) -> usize {
@@ -0,0 +1,547 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Read XACML 3.0 policies from XML.
+//!
+//! This is the reverse of serialization: a `<Policy>` or
+//! `<PolicySet>` document is read into the same X-prefixed structs
+//! that are produced from ALFA, so that it can be decompiled.
+//!
+//! Variable definitions are inlined where they are referenced.
+//! Attribute selectors, and other parts of XACML that ALFA has no way
+//! to express, are rejected.
+
+use super::style::XACML3_NS;
+use super::xapply::XApply;
+use super::xattr_designator::XAttrDesignator;
+use super::xcombinerparam::XCombinerParam;
+use super::xcondition::{FunctionTypeResolved, ResolvedAtomicName, XCondition};
+use super::xexpression::XExpression;
+use super::xfunction::XFunction;
+use super::xpolicy::XPolicy;
+use super::xpolicyentry::{XIdReference, XPolicyEntry};
+use super::xpolicyissuer::XIssuerAttribute;
+use super::xpolicyset::XPolicySet;
+use super::xprescription::{
+    XAttrValue, XAttributeAssignment, XAttributeAssignmentArgument, XPrescriptionExpr,
+};
+use super::xrule::XRule;
+use super::xtarget::{AllOf, AnyOf, XMatch, XTarget};
+use super::XTopPolicy;
+use crate::ast::function::FunctionOutputArg;
+use crate::ast::prescription::PrescriptionType;
+use crate::ast::rule::Effect;
+use crate::ast::std_functions::standard_functions;
+use crate::ast::typedef::standard_types;
+use crate::context::TypedLiteral;
+use crate::errors::ParseError;
+use log::info;
+use std::collections::HashMap;
+use xml::reader::{EventReader, ParserConfig, XmlEvent};
+
+/// Read a XACML 3.0 `<Policy>` or `<PolicySet>` document.
+///
+/// # Errors
+///
+/// Returns `Err` if the input is not well-formed XML, is not a XACML
+/// 3.0 policy or policyset, or uses elements that are not supported.
+pub fn read_xacml(xml: &[u8]) -> Result<XTopPolicy, ParseError> {
+    let root = parse_elements(xml)?;
+    let reader = Reader::new();
+    match root.name.as_str() {
+        "Policy" => Ok(XTopPolicy::Policy(reader.policy(&root)?)),
+        "PolicySet" => Ok(XTopPolicy::PolicySet(reader.policyset(&root)?)),
+        other => Err(read_err(&format!(
+            "expected a Policy or PolicySet, found {other}"
+        ))),
+    }
+}
+
+fn read_err(msg: &str) -> ParseError {
+    ParseError::XacmlReadError(msg.to_owned())
+}
+
+/// An XML element in the XACML namespace.
+#[derive(Debug, Default)]
+struct Element {
+    /// Local name of the element.
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    /// Text content, including whitespace.
+    text: String,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn required_attr(&self, name: &str) -> Result<&str, ParseError> {
+        self.attr(name)
+            .ok_or_else(|| read_err(&format!("{} is missing the {name} attribute", self.name)))
+    }
+
+    /// The only child element, which must exist.
+    fn only_child(&self) -> Result<&Element, ParseError> {
+        match self.children.as_slice() {
+            [c] => Ok(c),
+            _ => Err(read_err(&format!(
+                "{} must have exactly one child",
+                self.name
+            ))),
+        }
+    }
+}
+
+/// Build a tree of elements from an XML document.
+fn parse_elements(xml: &[u8]) -> Result<Element, ParseError> {
+    let reader = EventReader::new_with_config(
+        xml,
+        ParserConfig::new()
+            .trim_whitespace(false)
+            .whitespace_to_characters(true)
+            .cdata_to_characters(true)
+            .coalesce_characters(true)
+            .ignore_comments(true),
+    );
+    let mut stack: Vec<Element> = vec![];
+    for e in reader {
+        match e.map_err(|e| read_err(&e.to_string()))? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                if name.namespace.as_deref() != Some(XACML3_NS) {
+                    return Err(read_err(&format!(
+                        "element {} is not in the XACML 3.0 namespace",
+                        name.local_name
+                    )));
+                }
+                stack.push(Element {
+                    name: name.local_name,
+                    attributes: attributes
+                        .into_iter()
+                        .map(|a| (a.name.local_name, a.value))
+                        .collect(),
+                    ..Element::default()
+                });
+            }
+            XmlEvent::EndElement { .. } => {
+                let elem = stack.pop().ok_or_else(|| read_err("unbalanced elements"))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(elem),
+                    None => return Ok(elem),
+                }
+            }
+            XmlEvent::Characters(s) => {
+                if let Some(e) = stack.last_mut() {
+                    e.text.push_str(&s);
+                }
+            }
+            _ => {}
+        }
+    }
+    Err(read_err("no root element"))
+}
+
+/// State for reading a document.
+struct Reader {
+    /// Return types of the standard functions, by URI.
+    return_types: HashMap<String, FunctionTypeResolved>,
+}
+
+impl Reader {
+    fn new() -> Self {
+        let type_uris: HashMap<String, String> = standard_types()
+            .into_iter()
+            .map(|t| (t.id, t.uri))
+            .collect();
+        let resolve = |name: &String| {
+            ResolvedAtomicName::new(type_uris.get(name).map_or(name.as_str(), String::as_str))
+        };
+        let return_types = standard_functions()
+            .into_iter()
+            .map(|f| {
+                let t = match &f.output_arg {
+                    FunctionOutputArg::Atomic(a) => FunctionTypeResolved::Atomic(resolve(a)),
+                    FunctionOutputArg::AtomicBag(a) => FunctionTypeResolved::AtomicBag(resolve(a)),
+                    FunctionOutputArg::AnyAtomicBag => FunctionTypeResolved::AnyAtomicBag,
+                    FunctionOutputArg::AnyAtomic => FunctionTypeResolved::AnyAtomic,
+                };
+                (f.function_uri, t)
+            })
+            .collect();
+        Reader { return_types }
+    }
+
+    fn policy(&self, e: &Element) -> Result<XPolicy, ParseError> {
+        let mut xp = XPolicy {
+            id: e.required_attr("PolicyId")?.to_owned(),
+            combining_alg: e.required_attr("RuleCombiningAlgId")?.to_owned(),
+            version: version(e),
+            max_delegation_depth: max_delegation_depth(e)?,
+            ..XPolicy::default()
+        };
+        info!("reading policy {}", xp.id);
+        let mut vars = HashMap::new();
+        // parameters for rules, by RuleId
+        let mut rule_params = vec![];
+        for c in &e.children {
+            match c.name.as_str() {
+                "Description" => xp.description = Some(c.text.clone()),
+                "PolicyIssuer" => xp.issuer = issuer(c)?,
+                "PolicyDefaults" => {}
+                "Target" => xp.target = target(c)?,
+                "CombinerParameters" => xp.combiner_params.extend(combiner_params(c)?),
+                "RuleCombinerParameters" => {
+                    rule_params.push((c.required_attr("RuleIdRef")?, combiner_params(c)?));
+                }
+                "VariableDefinition" => {
+                    let expr = self.expression(c.only_child()?, &vars)?;
+                    vars.insert(c.required_attr("VariableId")?.to_owned(), expr);
+                }
+                "Rule" => xp.rules.push(self.rule(c, &vars)?),
+                "ObligationExpressions" | "AdviceExpressions" => {
+                    xp.prescriptions.exprs.extend(self.prescriptions(c)?);
+                }
+                other => return Err(unexpected(other, &e.name)),
+            }
+        }
+        for (id, params) in rule_params {
+            let r =
+                xp.rules.iter_mut().find(|r| r.id == id).ok_or_else(|| {
+                    read_err(&format!("combiner parameters for unknown rule {id}"))
+                })?;
+            r.entry_params.extend(params);
+        }
+        Ok(xp)
+    }
+
+    fn policyset(&self, e: &Element) -> Result<XPolicySet, ParseError> {
+        let mut xps = XPolicySet {
+            id: e.required_attr("PolicySetId")?.to_owned(),
+            combining_alg: e.required_attr("PolicyCombiningAlgId")?.to_owned(),
+            version: version(e),
+            max_delegation_depth: max_delegation_depth(e)?,
+            ..XPolicySet::default()
+        };
+        info!("reading policyset {}", xps.id);
+        // parameters for children, by kind and id
+        let mut child_params = vec![];
+        for c in &e.children {
+            match c.name.as_str() {
+                "Description" => xps.description = Some(c.text.clone()),
+                "PolicyIssuer" => xps.issuer = issuer(c)?,
+                "PolicySetDefaults" => {}
+                "Target" => xps.target = target(c)?,
+                "CombinerParameters" => xps.combiner_params.extend(combiner_params(c)?),
+                "PolicyCombinerParameters" => {
+                    child_params.push((false, c.required_attr("PolicyIdRef")?, combiner_params(c)?))
+                }
+                "PolicySetCombinerParameters" => child_params.push((
+                    true,
+                    c.required_attr("PolicySetIdRef")?,
+                    combiner_params(c)?,
+                )),
+                "Policy" => xps.children.push(XPolicyEntry::Policy(self.policy(c)?)),
+                "PolicySet" => xps
+                    .children
+                    .push(XPolicyEntry::PolicySet(self.policyset(c)?)),
+                "PolicyIdReference" => xps.children.push(XPolicyEntry::PolicyIdRef(id_ref(c))),
+                "PolicySetIdReference" => {
+                    xps.children.push(XPolicyEntry::PolicySetIdRef(id_ref(c)));
+                }
+                "ObligationExpressions" | "AdviceExpressions" => {
+                    xps.prescriptions.exprs.extend(self.prescriptions(c)?);
+                }
+                other => return Err(unexpected(other, &e.name)),
+            }
+        }
+        for (is_set, id, params) in child_params {
+            let entry_params = xps
+                .children
+                .iter_mut()
+                .find_map(|child| match child {
+                    XPolicyEntry::Policy(p) if !is_set && p.id == id => Some(&mut p.entry_params),
+                    XPolicyEntry::PolicySet(p) if is_set && p.id == id => Some(&mut p.entry_params),
+                    XPolicyEntry::PolicyIdRef(r) if !is_set && r.id == id => {
+                        Some(&mut r.entry_params)
+                    }
+                    XPolicyEntry::PolicySetIdRef(r) if is_set && r.id == id => {
+                        Some(&mut r.entry_params)
+                    }
+                    _ => None,
+                })
+                .ok_or_else(|| read_err(&format!("combiner parameters for unknown child {id}")))?;
+            entry_params.extend(params);
+        }
+        Ok(xps)
+    }
+
+    fn rule(&self, e: &Element, vars: &HashMap<String, XExpression>) -> Result<XRule, ParseError> {
+        let effect = e.required_attr("Effect")?;
+        if effect != Effect::Permit.to_string() && effect != Effect::Deny.to_string() {
+            return Err(read_err(&format!("unknown rule effect {effect}")));
+        }
+        let mut xr = XRule {
+            id: e.required_attr("RuleId")?.to_owned(),
+            effect: effect.to_owned(),
+            ..XRule::default()
+        };
+        for c in &e.children {
+            match c.name.as_str() {
+                "Description" => xr.description = Some(c.text.clone()),
+                "Target" => xr.target = target(c)?,
+                "Condition" => {
+                    xr.condition = Some(XCondition {
+                        expr: self.expression(c.only_child()?, vars)?,
+                        location: None,
+                    });
+                }
+                "ObligationExpressions" | "AdviceExpressions" => {
+                    xr.prescriptions.exprs.extend(self.prescriptions(c)?);
+                }
+                other => return Err(unexpected(other, &e.name)),
+            }
+        }
+        Ok(xr)
+    }
+
+    fn expression(
+        &self,
+        e: &Element,
+        vars: &HashMap<String, XExpression>,
+    ) -> Result<XExpression, ParseError> {
+        match e.name.as_str() {
+            "Apply" => {
+                let function_uri = e.required_attr("FunctionId")?.to_owned();
+                let arguments = e
+                    .children
+                    .iter()
+                    .filter(|c| c.name != "Description")
+                    .map(|c| self.expression(c, vars))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let return_type = self
+                    .return_types
+                    .get(&function_uri)
+                    .cloned()
+                    .unwrap_or(FunctionTypeResolved::AnyAtomic);
+                Ok(XExpression::Apply(XApply {
+                    function_uri,
+                    arguments,
+                    return_type,
+                }))
+            }
+            "Function" => Ok(XExpression::Function(XFunction {
+                function_uri: e.required_attr("FunctionId")?.to_owned(),
+            })),
+            "AttributeValue" => Ok(XExpression::Value(attr_value(e)?)),
+            "AttributeDesignator" => Ok(XExpression::Attrib(attr_designator(e)?)),
+            "VariableReference" => {
+                let id = e.required_attr("VariableId")?;
+                vars.get(id)
+                    .cloned()
+                    .ok_or_else(|| read_err(&format!("reference to undefined variable {id}")))
+            }
+            other => Err(read_err(&format!("{other} expressions are not supported"))),
+        }
+    }
+
+    fn prescriptions(&self, e: &Element) -> Result<Vec<XPrescriptionExpr>, ParseError> {
+        let mut exprs = vec![];
+        for c in &e.children {
+            let (ptype, id, effect) = match c.name.as_str() {
+                "ObligationExpression" => (
+                    PrescriptionType::Obligation,
+                    c.required_attr("ObligationId")?,
+                    c.required_attr("FulfillOn")?,
+                ),
+                "AdviceExpression" => (
+                    PrescriptionType::Advice,
+                    c.required_attr("AdviceId")?,
+                    c.required_attr("AppliesTo")?,
+                ),
+                other => return Err(unexpected(other, &e.name)),
+            };
+            let fulfill_on = match effect {
+                "Permit" => Effect::Permit,
+                "Deny" => Effect::Deny,
+                other => return Err(read_err(&format!("unknown effect {other}"))),
+            };
+            let mut assignments = vec![];
+            for a in &c.children {
+                expect(a, "AttributeAssignmentExpression")?;
+                let arg = match self.expression(a.only_child()?, &HashMap::new())? {
+                    XExpression::Value(v) => XAttributeAssignmentArgument::Value(v),
+                    XExpression::Attrib(d) => XAttributeAssignmentArgument::Attrib(d),
+                    _ => {
+                        return Err(read_err(
+                            "attribute assignments must be a value or an attribute designator",
+                        ));
+                    }
+                };
+                assignments.push(XAttributeAssignment {
+                    id: a.required_attr("AttributeId")?.to_owned(),
+                    category: a.required_attr("Category")?.to_owned(),
+                    arg,
+                });
+            }
+            exprs.push(XPrescriptionExpr {
+                ptype,
+                id: id.to_owned(),
+                fulfill_on,
+                assignments,
+            });
+        }
+        Ok(exprs)
+    }
+}
+
+fn combiner_params(e: &Element) -> Result<Vec<XCombinerParam>, ParseError> {
+    e.children
+        .iter()
+        .map(|c| {
+            expect(c, "CombinerParameter")?;
+            Ok(XCombinerParam {
+                name: c.required_attr("ParameterName")?.to_owned(),
+                value: attr_value(c.only_child()?)?,
+            })
+        })
+        .collect()
+}
+
+fn issuer(e: &Element) -> Result<Vec<XIssuerAttribute>, ParseError> {
+    let mut attrs = vec![];
+    for c in &e.children {
+        expect(c, "Attribute")?;
+        let id = c.required_attr("AttributeId")?;
+        for v in &c.children {
+            attrs.push(XIssuerAttribute {
+                id: id.to_owned(),
+                value: attr_value(v)?,
+            });
+        }
+    }
+    Ok(attrs)
+}
+
+fn target(e: &Element) -> Result<XTarget, ParseError> {
+    let mut anyofs = vec![];
+    for any in &e.children {
+        expect(any, "AnyOf")?;
+        let mut allofs = vec![];
+        for all in &any.children {
+            expect(all, "AllOf")?;
+            let matches = all
+                .children
+                .iter()
+                .map(target_match)
+                .collect::<Result<Vec<_>, _>>()?;
+            allofs.push(AllOf { matches });
+        }
+        anyofs.push(AnyOf { allofs });
+    }
+    Ok(XTarget {
+        anyofs,
+        location: None,
+    })
+}
+
+fn target_match(e: &Element) -> Result<XMatch, ParseError> {
+    expect(e, "Match")?;
+    let mut value = None;
+    let mut designator = None;
+    for c in &e.children {
+        match c.name.as_str() {
+            "AttributeValue" => value = Some(attr_value(c)?),
+            "AttributeDesignator" => designator = Some(attr_designator(c)?),
+            other => return Err(unexpected(other, &e.name)),
+        }
+    }
+    let (Some(v), Some(d)) = (value, designator) else {
+        return Err(read_err(
+            "Match requires an AttributeValue and AttributeDesignator",
+        ));
+    };
+    Ok(XMatch {
+        matchid: e.required_attr("MatchId")?.to_owned(),
+        value: v.v.value,
+        value_type: v.v.type_uri,
+        designator_id: d.uri,
+        designator_category: d.category,
+        designator_type: d.type_uri,
+        must_be_present: d.must_be_present,
+        issuer: d.issuer,
+    })
+}
+
+fn expect(e: &Element, name: &str) -> Result<(), ParseError> {
+    if e.name == name {
+        Ok(())
+    } else {
+        Err(read_err(&format!("expected {name}, found {}", e.name)))
+    }
+}
+
+fn unexpected(name: &str, parent: &str) -> ParseError {
+    read_err(&format!("{name} is not supported within {parent}"))
+}
+
+/// Version of a policy or policyset, which defaults to 1.0.
+fn version(e: &Element) -> String {
+    e.attr("Version").unwrap_or("1.0").to_owned()
+}
+
+fn max_delegation_depth(e: &Element) -> Result<Option<u32>, ParseError> {
+    e.attr("MaxDelegationDepth")
+        .map(|d| {
+            d.trim()
+                .parse()
+                .map_err(|_x| read_err(&format!("invalid MaxDelegationDepth {d}")))
+        })
+        .transpose()
+}
+
+fn id_ref(e: &Element) -> XIdReference {
+    XIdReference {
+        id: e.text.trim().to_owned(),
+        version: e.attr("Version").map(str::to_owned),
+        earliest_version: e.attr("EarliestVersion").map(str::to_owned),
+        latest_version: e.attr("LatestVersion").map(str::to_owned),
+        entry_params: vec![],
+    }
+}
+
+fn attr_value(e: &Element) -> Result<XAttrValue, ParseError> {
+    expect(e, "AttributeValue")?;
+    if !e.children.is_empty() {
+        return Err(read_err(
+            "attribute values with XML content are not supported",
+        ));
+    }
+    Ok(XAttrValue {
+        v: TypedLiteral {
+            type_uri: e.required_attr("DataType")?.to_owned(),
+            value: e.text.clone(),
+        },
+    })
+}
+
+fn attr_designator(e: &Element) -> Result<XAttrDesignator, ParseError> {
+    expect(e, "AttributeDesignator")?;
+    let must_be_present = match e.required_attr("MustBePresent")?.trim() {
+        "true" | "1" => true,
+        "false" | "0" => false,
+        other => return Err(read_err(&format!("invalid MustBePresent value {other}"))),
+    };
+    Ok(XAttrDesignator {
+        uri: e.required_attr("AttributeId")?.to_owned(),
+        category: e.required_attr("Category")?.to_owned(),
+        type_uri: e.required_attr("DataType")?.to_owned(),
+        must_be_present,
+        issuer: e.attr("Issuer").map(str::to_owned),
+    })
+}
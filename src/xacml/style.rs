@@ -0,0 +1,249 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Output style for generated XACML files.
+//!
+//! The style controls how elements are named (namespace prefix or
+//! default namespace), indentation, the XML declaration and
+//! encoding, `xsi:schemaLocation`, and line endings.  It does not
+//! change the meaning of the policies.
+
+use super::xacml2::XACML2_NS;
+use crate::context::XacmlVersion;
+use crate::errors::ParseError;
+use std::fmt;
+use std::str::FromStr;
+use xml::common::XmlVersion;
+use xml::name::Name;
+use xml::writer::events::StartElementBuilder;
+use xml::writer::{EmitterConfig, XmlEvent};
+
+/// XACML 3.0 namespace.
+pub const XACML3_NS: &str = "urn:oasis:names:tc:xacml:3.0:core:schema:wd-17";
+
+/// XML Schema instance namespace, for `xsi:schemaLocation`.
+const XSI_NS: &str = "http://www.w3.org/2001/XMLSchema-instance";
+
+/// `xsi:schemaLocation` values: each namespace and its OASIS schema.
+const XACML3_SCHEMA_LOCATION: &str = "urn:oasis:names:tc:xacml:3.0:core:schema:wd-17 \
+    http://docs.oasis-open.org/xacml/3.0/xacml-core-v3-schema-wd-17.xsd";
+const XACML2_SCHEMA_LOCATION: &str = "urn:oasis:names:tc:xacml:2.0:policy:schema:os \
+    http://docs.oasis-open.org/xacml/2.0/access_control-xacml-2.0-policy-schema-os.xsd";
+
+/// Style of generated XML.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OutputStyle {
+    /// Prefix for XACML elements.  When not set, `xacml3` (or
+    /// `xacml2` for XACML 2.0) is used.
+    pub prefix: Option<String>,
+    /// Declare the XACML namespace as the default namespace, and
+    /// write elements without a prefix.
+    pub default_namespace: bool,
+    /// Spaces per level of indentation, or `None` for no indentation
+    /// (or line breaks).
+    pub indent: Option<usize>,
+    /// Write an XML declaration (`<?xml version="1.0" ...?>`).
+    pub xml_declaration: bool,
+    /// Character encoding of the file.
+    pub encoding: XmlEncoding,
+    /// Write `xsi:schemaLocation` on the root element, pointing to
+    /// the OASIS schema.
+    pub schema_location: bool,
+    /// Line endings, when indenting.
+    pub line_ending: LineEnding,
+}
+
+impl Default for OutputStyle {
+    fn default() -> Self {
+        OutputStyle {
+            prefix: None,
+            default_namespace: false,
+            indent: Some(2),
+            xml_declaration: true,
+            encoding: XmlEncoding::default(),
+            schema_location: false,
+            line_ending: LineEnding::default(),
+        }
+    }
+}
+
+/// Character encodings for XACML files.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum XmlEncoding {
+    #[default]
+    Utf8,
+    /// Characters outside of ASCII are written as character
+    /// references.
+    Ascii,
+    /// Characters outside of Latin-1 are written as character
+    /// references.
+    Latin1,
+}
+
+impl XmlEncoding {
+    /// Name for the XML declaration.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            XmlEncoding::Utf8 => "UTF-8",
+            XmlEncoding::Ascii => "US-ASCII",
+            XmlEncoding::Latin1 => "ISO-8859-1",
+        }
+    }
+
+    /// Convert UTF-8 XML to this encoding.
+    ///
+    /// Generated element and attribute names are always ASCII, so
+    /// any other character is within text or an attribute value, and
+    /// can be replaced by a character reference.
+    #[must_use]
+    pub fn encode(self, utf8: Vec<u8>) -> Vec<u8> {
+        let max = match self {
+            XmlEncoding::Utf8 => return utf8,
+            XmlEncoding::Ascii => 0x7f,
+            XmlEncoding::Latin1 => 0xff,
+        };
+        let s = String::from_utf8_lossy(&utf8);
+        let mut out = Vec::with_capacity(s.len());
+        for c in s.chars() {
+            match u8::try_from(u32::from(c)) {
+                Ok(b) if u32::from(b) <= max => out.push(b),
+                _ => out.extend(format!("&#x{:X};", u32::from(c)).bytes()),
+            }
+        }
+        out
+    }
+}
+
+impl FromStr for XmlEncoding {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "UTF-8" | "UTF8" => Ok(XmlEncoding::Utf8),
+            "US-ASCII" | "ASCII" => Ok(XmlEncoding::Ascii),
+            "ISO-8859-1" | "LATIN1" => Ok(XmlEncoding::Latin1),
+            _ => Err(ParseError::InvalidOutputStyle(format!(
+                "unsupported encoding {s:?} (expected UTF-8, US-ASCII, or ISO-8859-1)"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for XmlEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Line endings.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl FromStr for LineEnding {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "lf" => Ok(LineEnding::Lf),
+            "crlf" => Ok(LineEnding::CrLf),
+            _ => Err(ParseError::InvalidOutputStyle(format!(
+                "unsupported line ending {s:?} (expected lf or crlf)"
+            ))),
+        }
+    }
+}
+
+impl OutputStyle {
+    /// Style for writing a version of XACML, with the standard
+    /// prefix for that version (`xacml3` or `xacml2`) unless another
+    /// was chosen.
+    #[must_use]
+    pub fn for_version(&self, version: XacmlVersion) -> OutputStyle {
+        let standard = match version {
+            XacmlVersion::V2 => "xacml2",
+            XacmlVersion::V3 => "xacml3",
+        };
+        OutputStyle {
+            prefix: Some(self.prefix.clone().unwrap_or_else(|| standard.to_owned())),
+            ..self.clone()
+        }
+    }
+
+    /// Name of a XACML element.
+    #[must_use]
+    pub fn name<'a>(&'a self, local_name: &'a str) -> Name<'a> {
+        Name {
+            local_name,
+            namespace: None,
+            prefix: if self.default_namespace {
+                None
+            } else {
+                Some(self.prefix.as_deref().unwrap_or("xacml3"))
+            },
+        }
+    }
+
+    /// Start a `Policy` or `PolicySet` element, declaring the XACML
+    /// namespace (and schema location).
+    #[must_use]
+    pub fn root_element<'a>(
+        &'a self,
+        version: XacmlVersion,
+        local_name: &'a str,
+    ) -> StartElementBuilder<'a> {
+        let (ns, location) = match version {
+            XacmlVersion::V2 => (XACML2_NS, XACML2_SCHEMA_LOCATION),
+            XacmlVersion::V3 => (XACML3_NS, XACML3_SCHEMA_LOCATION),
+        };
+        let name = self.name(local_name);
+        let mut elem = XmlEvent::start_element(name);
+        elem = match name.prefix {
+            Some(p) => elem.ns(p, ns),
+            None => elem.default_ns(ns),
+        };
+        if self.schema_location {
+            elem = elem
+                .ns("xsi", XSI_NS)
+                .attr("xsi:schemaLocation", location);
+        }
+        elem
+    }
+
+    /// Style for elements nested within the root element.
+    #[must_use]
+    pub fn nested(&self) -> OutputStyle {
+        OutputStyle {
+            schema_location: false,
+            ..self.clone()
+        }
+    }
+
+    /// XML emitter settings for this style.
+    #[must_use]
+    pub fn emitter_config(&self) -> EmitterConfig {
+        let mut cfg = EmitterConfig::new()
+            .perform_indent(self.indent.is_some())
+            .write_document_declaration(false)
+            .line_separator(match self.line_ending {
+                LineEnding::Lf => "\n",
+                LineEnding::CrLf => "\r\n",
+            });
+        if let Some(n) = self.indent {
+            cfg = cfg.indent_string(" ".repeat(n));
+        }
+        cfg
+    }
+
+    /// The XML declaration, if one should be written.
+    #[must_use]
+    pub fn declaration(&self) -> Option<XmlEvent<'static>> {
+        self.xml_declaration.then(|| XmlEvent::StartDocument {
+            version: XmlVersion::Version10,
+            encoding: Some(self.encoding.name()),
+            standalone: None,
+        })
+    }
+}
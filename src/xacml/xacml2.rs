@@ -0,0 +1,824 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! XACML 2.0 serialization.
+//!
+//! The X-prefixed structs model XACML 3.0.  This module writes the
+//! same structs as XACML 2.0 documents, for older PDPs.  Targets are
+//! regrouped into `<Subjects>`, `<Resources>`, `<Actions>`, and
+//! `<Environments>`, designators are written with their
+//! category-specific element names, and identifiers that were
+//! renamed in 3.0 are written with their 2.0 equivalents.  Infix
+//! operators on bags are written with the 1.0 `any-of-any`, which
+//! only accepts bags, so single values are wrapped in bags.
+//!
+//! Not everything in 3.0 can be expressed in 2.0.  The `check_*`
+//! functions are called during conversion (when the configured
+//! target version is 2.0), so that unsupported constructs are
+//! reported with their location in the ALFA source.
+
+use super::xapply::XApply;
+use super::xattr_designator::XAttrDesignator;
+use super::xcombinerparam::{write_combiner_params, XCombinerParam};
+use super::xcondition::{FunctionTypeResolved, XCondition};
+use super::xexpression::XExpression;
+use super::xpolicy::XPolicy;
+use super::xpolicyentry::XPolicyEntry;
+use super::style::OutputStyle;
+use super::write_comment;
+use super::xpolicyset::XPolicySet;
+use super::xprescription::{
+    XAttributeAssignmentArgument, XPrescriptionByType, XPrescriptionExpr, XPrescriptions,
+};
+use super::xrule::XRule;
+use super::xtarget::{AnyOf, XMatch, XTarget};
+use crate::ast::prescription::PrescriptionType;
+use crate::ast::SrcLoc;
+use crate::context::XacmlVersion;
+use crate::errors::{ParseError, SrcError};
+use std::io::Write;
+use xml::writer::EventWriter;
+use xml::writer::XmlEvent;
+
+/// Namespace of XACML 2.0 policies.
+pub const XACML2_NS: &str = "urn:oasis:names:tc:xacml:2.0:policy:schema:os";
+
+/// Produce XML events for XACML 2.0 serialization.
+pub trait Xacml2Writer {
+    /// Write XML events to serialize this object as XACML 2.0.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the XML events are semantically invalid or
+    /// the writer has an I/O error.
+    fn write_xml2<W: Write>(
+        &self,
+        writer: &mut EventWriter<W>,
+        style: &OutputStyle,
+    ) -> Result<(), xml::writer::Error>;
+}
+
+/// The target sections of XACML 2.0, which determine the attribute
+/// categories that can be used.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Section {
+    Subject,
+    Resource,
+    Action,
+    Environment,
+}
+
+impl Section {
+    const ALL: [Section; 4] = [
+        Section::Subject,
+        Section::Resource,
+        Section::Action,
+        Section::Environment,
+    ];
+
+    /// Find the section for an attribute category.  Any of the
+    /// subject categories may be used, as 2.0 designators carry a
+    /// `SubjectCategory`.
+    fn for_category(category: &str) -> Option<Section> {
+        match category {
+            c if c.starts_with("urn:oasis:names:tc:xacml:1.0:subject-category:") => {
+                Some(Section::Subject)
+            }
+            "urn:oasis:names:tc:xacml:3.0:attribute-category:resource" => Some(Section::Resource),
+            "urn:oasis:names:tc:xacml:3.0:attribute-category:action" => Some(Section::Action),
+            "urn:oasis:names:tc:xacml:3.0:attribute-category:environment" => {
+                Some(Section::Environment)
+            }
+            _ => None,
+        }
+    }
+
+    /// The element name prefix for this section (ex: `Subject`).
+    fn name(self) -> &'static str {
+        match self {
+            Section::Subject => "Subject",
+            Section::Resource => "Resource",
+            Section::Action => "Action",
+            Section::Environment => "Environment",
+        }
+    }
+}
+
+/// Convert a rule-combining algorithm to XACML 2.0, if it exists.
+fn rule_combining_alg(uri: &str) -> Option<String> {
+    combining_alg(uri, "rule-combining-algorithm")
+}
+
+/// Convert a policy-combining algorithm to XACML 2.0, if it exists.
+fn policy_combining_alg(uri: &str) -> Option<String> {
+    combining_alg(uri, "policy-combining-algorithm")
+}
+
+fn combining_alg(uri: &str, kind: &str) -> Option<String> {
+    let v3 = format!("urn:oasis:names:tc:xacml:3.0:{kind}:");
+    let Some(name) = uri.strip_prefix(&v3) else {
+        // 1.0 and 1.1 algorithms are unchanged
+        return Some(uri.to_owned());
+    };
+    // the 3.0 overrides algorithms replaced the 1.0 and 1.1
+    // algorithms, which had different handling of indeterminates.
+    match name {
+        "deny-overrides" | "permit-overrides" => {
+            Some(format!("urn:oasis:names:tc:xacml:1.0:{kind}:{name}"))
+        }
+        "ordered-deny-overrides" | "ordered-permit-overrides" => {
+            Some(format!("urn:oasis:names:tc:xacml:1.1:{kind}:{name}"))
+        }
+        _ => None,
+    }
+}
+
+/// Convert a function identifier to XACML 2.0, if it exists.
+fn function_uri(uri: &str) -> Option<String> {
+    let Some(name) = uri.strip_prefix("urn:oasis:names:tc:xacml:3.0:function:") else {
+        return Some(uri.to_owned());
+    };
+    // duration and xpath functions were renamed in 3.0; everything
+    // else in the 3.0 namespace is new.
+    let renamed = ["dayTimeDuration-", "yearMonthDuration-", "xpath-node-"]
+        .iter()
+        .any(|p| name.starts_with(p))
+        && !name.ends_with("-from-string");
+    let arith = ["dateTime-add-", "dateTime-subtract-", "date-add-", "date-subtract-"]
+        .iter()
+        .any(|p| name.starts_with(p));
+    if renamed || arith {
+        Some(format!("urn:oasis:names:tc:xacml:1.0:function:{name}"))
+    } else {
+        None
+    }
+}
+
+/// The 3.0 function used to apply infix operators to bags.
+const ANY_OF_ANY: &str = "urn:oasis:names:tc:xacml:3.0:function:any-of-any";
+
+/// The 1.0 equivalent of [`ANY_OF_ANY`], which takes exactly two
+/// bags.
+const ANY_OF_ANY_V1: &str = "urn:oasis:names:tc:xacml:1.0:function:any-of-any";
+
+/// The 2.0 function that makes a bag from a single value of a data
+/// type, if the type exists in XACML 2.0.
+fn bag_function(type_uri: &str) -> Option<String> {
+    let name = type_uri.rsplit(['#', ':']).next()?;
+    let version = match name {
+        "string" | "boolean" | "integer" | "double" | "time" | "date" | "dateTime"
+        | "anyURI" | "hexBinary" | "base64Binary" | "dayTimeDuration"
+        | "yearMonthDuration" | "x500Name" | "rfc822Name" => "1.0",
+        "ipAddress" | "dnsName" => "2.0",
+        _ => return None,
+    };
+    Some(format!("urn:oasis:names:tc:xacml:{version}:function:{name}-bag"))
+}
+
+/// For each argument of an [`ANY_OF_ANY`] application after the
+/// function, the function that makes it a bag for [`ANY_OF_ANY_V1`],
+/// or `None` if it is already a bag.
+fn any_of_any_bags(a: &XApply) -> Result<Vec<Option<String>>, String> {
+    let [XExpression::Function(_), args @ ..] = &a.arguments[..] else {
+        return Err(format!("{ANY_OF_ANY:?} requires a function argument"));
+    };
+    if args.len() != 2 {
+        return Err(format!("{ANY_OF_ANY:?} with more than two values does not exist in XACML 2.0"));
+    }
+    args.iter()
+        .map(|e| {
+            let atomic_type = match e {
+                XExpression::Attrib(_) => return Ok(None),
+                XExpression::Apply(x) => match &x.return_type {
+                    FunctionTypeResolved::Atomic(n) => Some(n.uri()),
+                    FunctionTypeResolved::AtomicBag(_) | FunctionTypeResolved::AnyAtomicBag => {
+                        return Ok(None);
+                    }
+                    FunctionTypeResolved::AnyAtomic => None,
+                },
+                XExpression::Value(v) => Some(v.v.type_uri.as_str()),
+                XExpression::Function(_) => None,
+            };
+            atomic_type
+                .and_then(bag_function)
+                .map(Some)
+                .ok_or_else(|| format!("an argument of {ANY_OF_ANY:?} cannot be made a bag in XACML 2.0"))
+        })
+        .collect()
+}
+
+/// Convert a data type identifier to XACML 2.0.
+fn type_uri(uri: &str) -> &str {
+    // durations were defined by a draft of XQuery before 3.0
+    match uri {
+        "http://www.w3.org/2001/XMLSchema#dayTimeDuration" => {
+            "http://www.w3.org/TR/2002/WD-xquery-operators-20020816#dayTimeDuration"
+        }
+        "http://www.w3.org/2001/XMLSchema#yearMonthDuration" => {
+            "http://www.w3.org/TR/2002/WD-xquery-operators-20020816#yearMonthDuration"
+        }
+        u => u,
+    }
+}
+
+/// A 2.0 target: for each section, a disjunction of conjunctions of
+/// matches.
+type Sections<'a> = Vec<(Section, Vec<Vec<&'a XMatch>>)>;
+
+/// Regroup a target into 2.0 sections.
+///
+/// Sections are combined by conjunction, so a clause with a single
+/// `<AllOf>` is split across the sections it uses, and multiple
+/// clauses for the same section are expanded into a single
+/// disjunction.
+fn target_sections(t: &XTarget) -> Result<Sections<'_>, String> {
+    let mut sections: Sections = vec![];
+    for anyof in &t.anyofs {
+        for (section, allofs) in clause_sections(anyof)? {
+            if let Some((_, existing)) = sections.iter_mut().find(|(s, _)| *s == section) {
+                // (a or b) and (c or d) => (a and c) or (a and d) or ...
+                let mut product = vec![];
+                for e in existing.iter() {
+                    for a in &allofs {
+                        product.push(e.iter().chain(a.iter()).copied().collect());
+                    }
+                }
+                *existing = product;
+            } else {
+                sections.push((section, allofs));
+            }
+        }
+    }
+    Ok(sections)
+}
+
+/// Regroup a single target clause into 2.0 sections.  A clause with
+/// several `<AllOf>` elements is a disjunction, which must use a
+/// single section.
+fn clause_sections(anyof: &AnyOf) -> Result<Sections<'_>, String> {
+    let mut sections: Sections = vec![];
+    if let [allof] = &anyof.allofs[..] {
+        for m in &allof.matches {
+            let s = match_section(m)?;
+            match sections.iter_mut().find(|(x, _)| *x == s) {
+                Some((_, allofs)) => allofs[0].push(m),
+                None => sections.push((s, vec![vec![m]])),
+            }
+        }
+        return Ok(sections);
+    }
+    let mut section = None;
+    for m in anyof.allofs.iter().flat_map(|a| &a.matches) {
+        let s = match_section(m)?;
+        if section.is_some_and(|prev| prev != s) {
+            return Err(format!(
+                "the alternatives of a target clause cannot mix {} and {} attributes",
+                section.map_or("", Section::name),
+                s.name()
+            ));
+        }
+        section = Some(s);
+    }
+    if let Some(section) = section {
+        let allofs = anyof.allofs.iter().map(|a| a.matches.iter().collect()).collect();
+        sections.push((section, allofs));
+    }
+    Ok(sections)
+}
+
+/// Find the section for the attribute of a match.
+fn match_section(m: &XMatch) -> Result<Section, String> {
+    Section::for_category(&m.designator_category)
+        .ok_or_else(|| format!("category {:?} does not exist in XACML 2.0", m.designator_category))
+}
+
+/// Ensure a target clause can be written as XACML 2.0.
+///
+/// # Errors
+///
+/// Returns `Err` if the clause uses a function or category that does
+/// not exist in XACML 2.0, or alternatives with attributes of
+/// different categories.
+pub fn check_target_clause(anyof: &AnyOf, src_loc: &SrcLoc) -> Result<(), ParseError> {
+    let target_err = |e: &str| {
+        SrcError::err("Target cannot be expressed in XACML 2.0", e, src_loc.clone())
+    };
+    clause_sections(anyof).map_err(|e| target_err(&e))?;
+    for m in anyof.allofs.iter().flat_map(|a| &a.matches) {
+        if function_uri(&m.matchid).is_none() {
+            return Err(target_err(&format!(
+                "function {:?} does not exist in XACML 2.0",
+                m.matchid
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Ensure obligations and advice can be written as XACML 2.0.
+fn check_prescriptions(
+    p: &XPrescriptions,
+    owner: &str,
+    src_loc: &SrcLoc,
+) -> Result<(), ParseError> {
+    for e in &p.exprs {
+        if e.ptype == PrescriptionType::Advice {
+            return Err(SrcError::err(
+                "Advice cannot be expressed in XACML 2.0",
+                &format!("{owner} has advice {:?}", e.id),
+                src_loc.clone(),
+            ));
+        }
+        if e.assignments
+            .iter()
+            .any(|a| matches!(a.arg, XAttributeAssignmentArgument::Attrib(_)))
+        {
+            return Err(SrcError::err(
+                "Obligations in XACML 2.0 can only assign literal values",
+                &format!("{owner} assigns an attribute in obligation {:?}", e.id),
+                src_loc.clone(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Ensure a rule can be written as XACML 2.0.  Rule targets and
+/// conditions are checked as they are converted.
+fn check_rule(r: &XRule, src_loc: &SrcLoc) -> Result<(), ParseError> {
+    if !r.prescriptions.exprs.is_empty() {
+        return Err(SrcError::err(
+            "Rules cannot have obligations or advice in XACML 2.0",
+            &format!("rule {:?} has obligations or advice", r.id),
+            src_loc.clone(),
+        ));
+    }
+    Ok(())
+}
+
+/// Ensure the 3.0-only attributes of a policy or policyset are absent.
+fn check_delegation(
+    has_issuer: bool,
+    max_delegation_depth: Option<u32>,
+    src_loc: &SrcLoc,
+) -> Result<(), ParseError> {
+    if has_issuer || max_delegation_depth.is_some() {
+        return Err(SrcError::err(
+            "Delegation cannot be expressed in XACML 2.0",
+            "issuer and maxDelegationDepth require XACML 3.0",
+            src_loc.clone(),
+        ));
+    }
+    Ok(())
+}
+
+/// Ensure a policy (but not its targets or rule conditions) can be
+/// written as XACML 2.0.
+///
+/// # Errors
+///
+/// Returns `Err` identifying the first construct that has no XACML
+/// 2.0 equivalent.
+pub fn check_policy(p: &XPolicy, src_loc: &SrcLoc, apply_loc: &SrcLoc) -> Result<(), ParseError> {
+    if rule_combining_alg(&p.combining_alg).is_none() {
+        return Err(SrcError::err(
+            "Combining algorithm cannot be expressed in XACML 2.0",
+            &format!("{:?} does not exist in XACML 2.0", p.combining_alg),
+            apply_loc.clone(),
+        ));
+    }
+    check_delegation(!p.issuer.is_empty(), p.max_delegation_depth, src_loc)?;
+    check_prescriptions(&p.prescriptions, &format!("policy {:?}", p.id), src_loc)?;
+    for r in &p.rules {
+        check_rule(r, src_loc)?;
+    }
+    Ok(())
+}
+
+/// Ensure a policyset (but not its target or children) can be
+/// written as XACML 2.0.
+///
+/// # Errors
+///
+/// Returns `Err` identifying the first construct that has no XACML
+/// 2.0 equivalent.
+pub fn check_policyset(
+    p: &XPolicySet,
+    src_loc: &SrcLoc,
+    apply_loc: &SrcLoc,
+) -> Result<(), ParseError> {
+    if policy_combining_alg(&p.combining_alg).is_none() {
+        return Err(SrcError::err(
+            "Combining algorithm cannot be expressed in XACML 2.0",
+            &format!("{:?} does not exist in XACML 2.0", p.combining_alg),
+            apply_loc.clone(),
+        ));
+    }
+    check_delegation(!p.issuer.is_empty(), p.max_delegation_depth, src_loc)?;
+    check_prescriptions(&p.prescriptions, &format!("policyset {:?}", p.id), src_loc)?;
+    Ok(())
+}
+
+/// Ensure a condition can be written as XACML 2.0.
+///
+/// # Errors
+///
+/// Returns `Err` if the condition uses a function or category that
+/// does not exist in XACML 2.0.
+pub fn check_condition(c: &XCondition, src_loc: &SrcLoc) -> Result<(), ParseError> {
+    check_expr(&c.expr).map_err(|e| {
+        SrcError::err("Condition cannot be expressed in XACML 2.0", &e, src_loc.clone())
+    })
+}
+
+fn check_expr(e: &XExpression) -> Result<(), String> {
+    let unknown_fn = |uri: &str| format!("function {uri:?} does not exist in XACML 2.0");
+    match e {
+        XExpression::Apply(a) if a.function_uri == ANY_OF_ANY => {
+            // written as the 1.0 function, with single values in bags
+            any_of_any_bags(a)?;
+            a.arguments.iter().try_for_each(check_expr)
+        }
+        XExpression::Apply(a) => {
+            if function_uri(&a.function_uri).is_none() {
+                return Err(unknown_fn(&a.function_uri));
+            }
+            a.arguments.iter().try_for_each(check_expr)
+        }
+        XExpression::Function(f) => match function_uri(&f.function_uri) {
+            Some(_) => Ok(()),
+            None => Err(unknown_fn(&f.function_uri)),
+        },
+        XExpression::Attrib(a) => match Section::for_category(&a.category) {
+            Some(_) => Ok(()),
+            None => Err(format!("category {:?} does not exist in XACML 2.0", a.category)),
+        },
+        XExpression::Value(_) => Ok(()),
+    }
+}
+
+/// Write an element with a text value and data type.
+fn write_value<W: Write>(
+    elem_name: &str,
+    id_attr: Option<(&str, &str)>,
+    type_id: &str,
+    value: &str,
+    writer: &mut EventWriter<W>,
+    style: &OutputStyle,
+) -> Result<(), xml::writer::Error> {
+    let mut elem = XmlEvent::start_element(style.name(elem_name));
+    if let Some((attr, id)) = id_attr {
+        elem = elem.attr(attr, id);
+    }
+    writer.write(elem.attr("DataType", type_uri(type_id)))?;
+    writer.write(XmlEvent::characters(value))?;
+    writer.write(XmlEvent::end_element())?;
+    Ok(())
+}
+
+/// Write a designator, using the element for its category.
+fn write_designator<W: Write>(
+    id: &str,
+    category: &str,
+    data_type: &str,
+    must_be_present: bool,
+    issuer: Option<&String>,
+    writer: &mut EventWriter<W>,
+    style: &OutputStyle,
+) -> Result<(), xml::writer::Error> {
+    let section = Section::for_category(category).unwrap_or(Section::Environment);
+    let elem_name = format!("{}AttributeDesignator", section.name());
+    let mbp = must_be_present.to_string();
+    let mut elem = XmlEvent::start_element(style.name(&elem_name))
+        .attr("AttributeId", id)
+        .attr("DataType", type_uri(data_type));
+    if let Some(i) = issuer {
+        elem = elem.attr("Issuer", i);
+    }
+    elem = elem.attr("MustBePresent", &mbp);
+    if section == Section::Subject {
+        elem = elem.attr("SubjectCategory", category);
+    }
+    writer.write(elem)?;
+    writer.write(XmlEvent::end_element())?;
+    Ok(())
+}
+
+fn write_target<W: Write>(
+    t: &XTarget,
+    writer: &mut EventWriter<W>,
+    style: &OutputStyle,
+) -> Result<(), xml::writer::Error> {
+    writer.write(XmlEvent::start_element(style.name("Target")))?;
+    // targets are checked during conversion
+    let sections = target_sections(t).unwrap_or_default();
+    for s in Section::ALL {
+        let Some((_, allofs)) = sections.iter().find(|(x, _)| *x == s) else {
+            continue;
+        };
+        let name = s.name();
+        let section_name = format!("{name}s");
+        let match_name = format!("{name}Match");
+        writer.write(XmlEvent::start_element(style.name(&section_name)))?;
+        for allof in allofs {
+            writer.write(XmlEvent::start_element(style.name(name)))?;
+            for m in allof {
+                let matchid = function_uri(&m.matchid).unwrap_or_else(|| m.matchid.clone());
+                writer.write(
+                    XmlEvent::start_element(style.name(&match_name))
+                        .attr("MatchId", &matchid),
+                )?;
+                write_value("AttributeValue", None, &m.value_type, &m.value, writer, style)?;
+                write_designator(
+                    &m.designator_id,
+                    &m.designator_category,
+                    &m.designator_type,
+                    m.must_be_present,
+                    m.issuer.as_ref(),
+                    writer,
+                    style,
+                )?;
+                writer.write(XmlEvent::end_element())?;
+            }
+            writer.write(XmlEvent::end_element())?;
+        }
+        writer.write(XmlEvent::end_element())?;
+    }
+    writer.write(XmlEvent::end_element())?;
+    Ok(())
+}
+
+fn write_expr<W: Write>(
+    e: &XExpression,
+    writer: &mut EventWriter<W>,
+    style: &OutputStyle,
+) -> Result<(), xml::writer::Error> {
+    match e {
+        XExpression::Apply(a) if a.function_uri == ANY_OF_ANY => {
+            // conditions are checked during conversion
+            let bags = any_of_any_bags(a).unwrap_or_default();
+            writer.write(
+                XmlEvent::start_element(style.name("Apply")).attr("FunctionId", ANY_OF_ANY_V1),
+            )?;
+            write_expr(&a.arguments[0], writer, style)?;
+            for (arg, bag) in a.arguments[1..].iter().zip(bags) {
+                if let Some(bag) = &bag {
+                    writer.write(
+                        XmlEvent::start_element(style.name("Apply")).attr("FunctionId", bag),
+                    )?;
+                }
+                write_expr(arg, writer, style)?;
+                if bag.is_some() {
+                    writer.write(XmlEvent::end_element())?;
+                }
+            }
+            writer.write(XmlEvent::end_element())?;
+        }
+        XExpression::Apply(XApply {
+            function_uri: f,
+            arguments,
+            ..
+        }) => {
+            let fid = function_uri(f).unwrap_or_else(|| f.clone());
+            writer.write(XmlEvent::start_element(style.name("Apply")).attr("FunctionId", &fid))?;
+            for a in arguments {
+                write_expr(a, writer, style)?;
+            }
+            writer.write(XmlEvent::end_element())?;
+        }
+        XExpression::Function(f) => {
+            let fid = function_uri(&f.function_uri).unwrap_or_else(|| f.function_uri.clone());
+            writer.write(XmlEvent::start_element(style.name("Function")).attr("FunctionId", &fid))?;
+            writer.write(XmlEvent::end_element())?;
+        }
+        XExpression::Value(v) => {
+            write_value("AttributeValue", None, &v.v.type_uri, &v.v.value, writer, style)?;
+        }
+        XExpression::Attrib(XAttrDesignator {
+            uri,
+            category,
+            type_uri,
+            must_be_present,
+            issuer,
+        }) => {
+            write_designator(uri, category, type_uri, *must_be_present, issuer.as_ref(), writer, style)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write `<Obligations>`; advice is rejected during conversion.
+fn write_obligations<W: Write>(
+    p: &XPrescriptions,
+    writer: &mut EventWriter<W>,
+    style: &OutputStyle,
+) -> Result<(), xml::writer::Error> {
+    let xpt = XPrescriptionByType::from(p.clone());
+    if xpt.obligations.is_empty() {
+        return Ok(());
+    }
+    writer.write(XmlEvent::start_element(style.name("Obligations")))?;
+    for XPrescriptionExpr {
+        id,
+        fulfill_on,
+        assignments,
+        ..
+    } in &xpt.obligations
+    {
+        writer.write(
+            XmlEvent::start_element(style.name("Obligation"))
+                .attr("ObligationId", id)
+                .attr("FulfillOn", &fulfill_on.to_string()),
+        )?;
+        for a in assignments {
+            if let XAttributeAssignmentArgument::Value(v) = &a.arg {
+                write_value(
+                    "AttributeAssignment",
+                    Some(("AttributeId", &a.id)),
+                    &v.v.type_uri,
+                    &v.v.value,
+                    writer,
+                    style,
+                )?;
+            }
+        }
+        writer.write(XmlEvent::end_element())?;
+    }
+    writer.write(XmlEvent::end_element())?;
+    Ok(())
+}
+
+fn write_description<W: Write>(
+    d: Option<&String>,
+    writer: &mut EventWriter<W>,
+    style: &OutputStyle,
+) -> Result<(), xml::writer::Error> {
+    if let Some(d) = d {
+        writer.write(XmlEvent::start_element(style.name("Description")))?;
+        writer.write(XmlEvent::characters(d))?;
+        writer.write(XmlEvent::end_element())?;
+    }
+    Ok(())
+}
+
+fn write_params<W: Write>(
+    elem_name: &str,
+    id_ref: Option<(&str, &str)>,
+    params: &[XCombinerParam],
+    writer: &mut EventWriter<W>,
+    style: &OutputStyle,
+) -> Result<(), xml::writer::Error> {
+    // parameters are structured identically in 2.0, except for the
+    // duration types.
+    let params: Vec<XCombinerParam> = params
+        .iter()
+        .cloned()
+        .map(|mut p| {
+            p.value.v.type_uri = type_uri(&p.value.v.type_uri).to_owned();
+            p
+        })
+        .collect();
+    write_combiner_params(elem_name, id_ref, &params, writer, style)
+}
+
+fn write_rule<W: Write>(
+    r: &XRule,
+    writer: &mut EventWriter<W>,
+    style: &OutputStyle,
+) -> Result<(), xml::writer::Error> {
+    write_params(
+        "RuleCombinerParameters",
+        Some(("RuleIdRef", &r.id)),
+        &r.entry_params,
+        writer,
+        style,
+    )?;
+    writer.write(
+        XmlEvent::start_element(style.name("Rule"))
+            .attr("RuleId", &r.id)
+            .attr("Effect", &r.effect),
+    )?;
+    write_comment(r.comment.as_ref(), writer)?;
+    write_description(r.description.as_ref(), writer, style)?;
+    write_target(&r.target, writer, style)?;
+    if let Some(c) = &r.condition {
+        writer.write(XmlEvent::start_element(style.name("Condition")))?;
+        write_expr(&c.expr, writer, style)?;
+        writer.write(XmlEvent::end_element())?;
+    }
+    writer.write(XmlEvent::end_element())?;
+    Ok(())
+}
+
+impl Xacml2Writer for XPolicy {
+    /// Write an XML (XACML 2.0) representation of an `XPolicy` to a
+    /// stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the writer fails.
+    fn write_xml2<W: Write>(
+        &self,
+        writer: &mut EventWriter<W>,
+        style: &OutputStyle,
+    ) -> Result<(), xml::writer::Error> {
+        let alg = rule_combining_alg(&self.combining_alg)
+            .unwrap_or_else(|| self.combining_alg.clone());
+        writer.write(
+            style
+                .root_element(XacmlVersion::V2, "Policy")
+                .attr("PolicyId", &self.id)
+                .attr("Version", &self.version)
+                .attr("RuleCombiningAlgId", &alg)
+        )?;
+        write_comment(self.comment.as_ref(), writer)?;
+        // the schema location is only given on the root element
+        let style = &style.nested();
+        write_description(self.description.as_ref(), writer, style)?;
+        write_target(&self.target, writer, style)?;
+        write_params("CombinerParameters", None, &self.combiner_params, writer, style)?;
+        for r in &self.rules {
+            write_rule(r, writer, style)?;
+        }
+        write_obligations(&self.prescriptions, writer, style)?;
+        writer.write(XmlEvent::end_element())?;
+        Ok(())
+    }
+}
+
+impl Xacml2Writer for XPolicySet {
+    /// Write an XML (XACML 2.0) representation of an `XPolicySet` to
+    /// a stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the writer fails.
+    fn write_xml2<W: Write>(
+        &self,
+        writer: &mut EventWriter<W>,
+        style: &OutputStyle,
+    ) -> Result<(), xml::writer::Error> {
+        let alg = policy_combining_alg(&self.combining_alg)
+            .unwrap_or_else(|| self.combining_alg.clone());
+        writer.write(
+            style
+                .root_element(XacmlVersion::V2, "PolicySet")
+                .attr("PolicySetId", &self.id)
+                .attr("Version", &self.version)
+                .attr("PolicyCombiningAlgId", &alg)
+        )?;
+        write_comment(self.comment.as_ref(), writer)?;
+        // the schema location is only given on the root element
+        let style = &style.nested();
+        write_description(self.description.as_ref(), writer, style)?;
+        write_target(&self.target, writer, style)?;
+        write_params("CombinerParameters", None, &self.combiner_params, writer, style)?;
+        for p in &self.children {
+            match p {
+                XPolicyEntry::PolicyIdRef(r) => {
+                    write_params(
+                        "PolicyCombinerParameters",
+                        Some(("PolicyIdRef", &r.id)),
+                        &r.entry_params,
+                        writer,
+                        style,
+                    )?;
+                    r.write_xml("PolicyIdReference", writer, style)?;
+                }
+                XPolicyEntry::PolicySetIdRef(r) => {
+                    write_params(
+                        "PolicySetCombinerParameters",
+                        Some(("PolicySetIdRef", &r.id)),
+                        &r.entry_params,
+                        writer,
+                        style,
+                    )?;
+                    r.write_xml("PolicySetIdReference", writer, style)?;
+                }
+                XPolicyEntry::Policy(sub_p) => {
+                    write_params(
+                        "PolicyCombinerParameters",
+                        Some(("PolicyIdRef", &sub_p.id)),
+                        &sub_p.entry_params,
+                        writer,
+                        style,
+                    )?;
+                    sub_p.write_xml2(writer, style)?;
+                }
+                XPolicyEntry::PolicySet(sub_p) => {
+                    write_params(
+                        "PolicySetCombinerParameters",
+                        Some(("PolicySetIdRef", &sub_p.id)),
+                        &sub_p.entry_params,
+                        writer,
+                        style,
+                    )?;
+                    sub_p.write_xml2(writer, style)?;
+                }
+            }
+        }
+        write_obligations(&self.prescriptions, writer, style)?;
+        writer.write(XmlEvent::end_element())?;
+        Ok(())
+    }
+}
@@ -5,12 +5,14 @@
 
 use super::xcondition::FunctionTypeResolved;
 use super::xexpression::XExpression;
+use super::style::OutputStyle;
 use std::io::Write;
 use xml::writer::EventWriter;
 use xml::writer::XmlEvent;
 
 /// An `<Apply>` element that applies a function to arguments.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XApply {
     // Apply must have a function identifier, and then arguments, which themselves can be expressions.
     pub function_uri: String,
@@ -28,13 +30,14 @@ impl XApply {
     pub fn write_xml<W: Write>(
         &self,
         writer: &mut EventWriter<W>,
+        style: &OutputStyle,
     ) -> Result<(), xml::writer::Error> {
         writer.write(
-            XmlEvent::start_element("xacml3:Apply").attr("FunctionId", &self.function_uri),
+            XmlEvent::start_element(style.name("Apply")).attr("FunctionId", &self.function_uri),
         )?;
         // write out the arguments
         for a in &self.arguments {
-            a.write_xml(writer)?;
+            a.write_xml(writer, style)?;
         }
         writer.write(XmlEvent::end_element())?;
         Ok(())
@@ -3,12 +3,14 @@
 
 //! XACML Attribute Designators
 
+use super::style::OutputStyle;
 use std::io::Write;
 use xml::writer::EventWriter;
 use xml::writer::XmlEvent;
 
 /// An `<AttributeDesignator>` element, referencing a XACML attribute.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XAttrDesignator {
     pub uri: String,
     pub category: String,
@@ -27,9 +29,10 @@ impl XAttrDesignator {
     pub fn write_xml<W: Write>(
         &self,
         writer: &mut EventWriter<W>,
+        style: &OutputStyle,
     ) -> Result<(), xml::writer::Error> {
         let mbp = &self.must_be_present.to_string();
-        let mut attrdesig = XmlEvent::start_element("xacml3:AttributeDesignator")
+        let mut attrdesig = XmlEvent::start_element(style.name("AttributeDesignator"))
             .attr("AttributeId", &self.uri)
             .attr("Category", &self.category)
             .attr("DataType", &self.type_uri);
@@ -0,0 +1,85 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! XACML Combiner Parameters
+
+use super::xprescription::XAttrValue;
+use super::style::OutputStyle;
+use crate::ast::combinerparam::CombinerParam;
+use crate::context::Context;
+use crate::errors::ParseError;
+use std::io::Write;
+use xml::writer::EventWriter;
+use xml::writer::XmlEvent;
+
+/// `<CombinerParameter>` element.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct XCombinerParam {
+    /// Parameter name.
+    pub name: String,
+    /// Typed value of the parameter.
+    pub value: XAttrValue,
+}
+
+impl XCombinerParam {
+    /// Convert ALFA combiner parameters, resolving the types of their
+    /// values from a namespace.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a value cannot be converted to a typed
+    /// literal.
+    pub fn from_params(
+        params: &[CombinerParam],
+        source_ns: &[String],
+        ctx: &Context,
+    ) -> Result<Vec<XCombinerParam>, ParseError> {
+        params
+            .iter()
+            .map(|p| {
+                Ok(XCombinerParam {
+                    name: p.name.clone(),
+                    value: XAttrValue {
+                        v: ctx.constant_to_typedliteral(p.value.clone(), source_ns)?,
+                    },
+                })
+            })
+            .collect()
+    }
+}
+
+/// Write a `<CombinerParameters>` element (or one of the rule,
+/// policy, or policyset variants), if there are any parameters.
+///
+/// The `id_ref` is the name and value of the attribute that
+/// identifies the rule, policy, or policyset the parameters apply to.
+///
+/// # Errors
+///
+/// Returns `Err` if the writer fails.
+pub fn write_combiner_params<W: Write>(
+    elem_name: &str,
+    id_ref: Option<(&str, &str)>,
+    params: &[XCombinerParam],
+    writer: &mut EventWriter<W>,
+    style: &OutputStyle,
+) -> Result<(), xml::writer::Error> {
+    if params.is_empty() {
+        return Ok(());
+    }
+    let mut elem = XmlEvent::start_element(style.name(elem_name));
+    if let Some((attr, id)) = id_ref {
+        elem = elem.attr(attr, id);
+    }
+    writer.write(elem)?;
+    for p in params {
+        writer.write(
+            XmlEvent::start_element(style.name("CombinerParameter")).attr("ParameterName", &p.name),
+        )?;
+        p.value.write_xml(writer, style)?;
+        writer.write(XmlEvent::end_element())?;
+    }
+    writer.write(XmlEvent::end_element())?;
+    Ok(())
+}
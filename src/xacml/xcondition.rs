@@ -3,11 +3,13 @@
 
 //! XACML Conditions
 
+use super::xacml2::check_condition;
 use super::xapply::XApply;
 use super::xattr_designator::XAttrDesignator;
 use super::xexpression::XExpression;
 use super::xfunction::XFunction;
 use super::XAttrValue;
+use super::style::OutputStyle;
 use crate::ast::Spanned;
 use crate::ast::condition::{CondExpression, CondFunctionCall, Condition, FunctionReference};
 use crate::ast::constant::Constant;
@@ -16,8 +18,9 @@ use crate::ast::function::FunctionOutputArg;
 use crate::ast::infix::Infix;
 use crate::ast::infix::InfixSignature;
 use crate::ast::operator::Operator;
-use crate::context::Context;
+use crate::context::{Context, XacmlVersion};
 use crate::errors::{ParseError, SrcError};
+use crate::sourcemap::SourceRange;
 use log::debug;
 use log::error;
 use log::info;
@@ -28,8 +31,11 @@ use std::fmt;
 
 /// A `<Condition>` element that contains an expression.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XCondition {
     pub expr: XExpression,
+    /// Location of the condition in the ALFA source.
+    pub location: Option<SourceRange>,
 }
 
 /// Conversion of Alfa Rule to XACML Rule
@@ -63,9 +69,14 @@ impl TryFrom<&Condition> for XCondition {
         }
         // The ALFA spec seems to imply literals are not allowed, but
         // the resulting XACML would still be valid, so we don't care.
-        Ok(XCondition {
+        let xc = XCondition {
             expr: expr_to_xexpr(&c.cond_expr, &c.ns, &ctx)?,
-        })
+            location: SourceRange::from_src_loc(&c.src_loc),
+        };
+        if ctx.config.target_version == XacmlVersion::V2 {
+            check_condition(&xc, c.span())?;
+        }
+        Ok(xc)
     }
 }
 
@@ -79,6 +90,14 @@ fn type_for_expr(
         CondExpression::Infix(e1, o, e2) => {
             sig_and_type_for_infix(e1, o, e2, source_ns, ctx).map(|(_sig, typeres)| typeres)
         }
+        CondExpression::Lit(c @ Constant::Named(_)) => {
+            // report resolution errors for named constants, rather
+            // than a generic conversion error.
+            let tl = ctx.constant_to_typedliteral(c.clone(), source_ns)?;
+            Ok(FunctionTypeResolved::Atomic(ResolvedAtomicName {
+                uri: tl.type_uri,
+            }))
+        }
         CondExpression::Lit(c) => Ok(FunctionTypeResolved::Atomic(
             resolve_literal_types(c, source_ns, ctx).ok_or(ParseError::AstConvertError)?,
         )),
@@ -412,11 +431,12 @@ impl XCondition {
     pub fn write_xml<W: Write>(
         &self,
         writer: &mut EventWriter<W>,
+        style: &OutputStyle,
     ) -> Result<(), xml::writer::Error> {
         // Top-level Condition Element
-        writer.write(XmlEvent::start_element("xacml3:Condition"))?;
+        writer.write(XmlEvent::start_element(style.name("Condition")))?;
         // Expression Element
-        self.expr.write_xml(writer)?;
+        self.expr.write_xml(writer, style)?;
         writer.write(XmlEvent::end_element())?;
         Ok(())
     }
@@ -424,14 +444,30 @@ impl XCondition {
 
 /// A type URI that has been resolved from an ALFA name.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ResolvedAtomicName {
     uri: String, // XACML type URI
 }
 
+impl ResolvedAtomicName {
+    /// Name for a XACML type URI.
+    #[must_use]
+    pub fn new(uri: &str) -> Self {
+        ResolvedAtomicName { uri: uri.to_owned() }
+    }
+
+    /// The XACML type URI.
+    #[must_use]
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+}
+
 /// A function argument type that has been resolved to a URI and
 /// (optionally) an ALFA fully qualified name.
 ///
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FunctionTypeResolved {
     /// A reference to a specific atomic type (`string`, `inetAddress`, etc.)
     Atomic(ResolvedAtomicName),
@@ -491,7 +527,7 @@ impl fmt::Display for FunctionTypeResolved {
 ///
 /// # Returns
 /// * `Ok(FunctionTypeResolved)` - The function's return type resolved
-///    to a XACML URI
+///   to a XACML URI
 /// * `Err(ParseError)` - Any errors resolving the attribute or its type
 ///
 fn fn_output_to_resolved(
@@ -544,6 +580,11 @@ fn resolve_literal_types(
             todo!("resolve custom types");
             //None
         }
+        Constant::Named(_) => {
+            // named constants carry their own declared type
+            let tl = ctx.constant_to_typedliteral(c.clone(), source_ns).ok()?;
+            Some(ResolvedAtomicName { uri: tl.type_uri })
+        }
         Constant::Undefined => None,
     }
 }
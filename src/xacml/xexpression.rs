@@ -7,11 +7,13 @@ use super::xapply::XApply;
 use super::xattr_designator::XAttrDesignator;
 use super::xfunction::XFunction;
 use super::XAttrValue;
+use super::style::OutputStyle;
 use std::io::Write;
 use xml::writer::EventWriter;
 
 /// An expression within a `<Condition>` element.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum XExpression {
     Apply(XApply),
     Function(XFunction),
@@ -34,19 +36,20 @@ impl XExpression {
     pub fn write_xml<W: Write>(
         &self,
         writer: &mut EventWriter<W>,
+        style: &OutputStyle,
     ) -> Result<(), xml::writer::Error> {
         match self {
             XExpression::Value(v) => {
-                v.write_xml(writer)?;
+                v.write_xml(writer, style)?;
             }
             XExpression::Attrib(a) => {
-                a.write_xml(writer)?;
+                a.write_xml(writer, style)?;
             }
             XExpression::Apply(a) => {
-                a.write_xml(writer)?;
+                a.write_xml(writer, style)?;
             }
             XExpression::Function(f) => {
-                f.write_xml(writer)?;
+                f.write_xml(writer, style)?;
             }
         }
         Ok(())
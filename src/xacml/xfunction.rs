@@ -3,12 +3,14 @@
 
 //! XACML Functions
 
+use super::style::OutputStyle;
 use std::io::Write;
 use xml::writer::EventWriter;
 use xml::writer::XmlEvent;
 
 /// `<Function>` element, used to reference a XACML function as an argument.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XFunction {
     pub function_uri: String,
 }
@@ -23,9 +25,10 @@ impl XFunction {
     pub fn write_xml<W: Write>(
         &self,
         writer: &mut EventWriter<W>,
+        style: &OutputStyle,
     ) -> Result<(), xml::writer::Error> {
         writer.write(
-            XmlEvent::start_element("xacml3:Function").attr("FunctionId", &self.function_uri),
+            XmlEvent::start_element(style.name("Function")).attr("FunctionId", &self.function_uri),
         )?;
         writer.write(XmlEvent::end_element())?;
         Ok(())
@@ -3,14 +3,20 @@
 
 //! XACML Policy Sets
 
+use super::xacml2::check_policy;
+use super::xcombinerparam::{write_combiner_params, XCombinerParam};
+use super::xpolicyissuer::{write_policy_issuer, XIssuerAttribute};
 use super::xprescription::XPrescriptions;
 use super::xrule::XRule;
 use super::xtarget::XTarget;
-use super::XacmlWriter;
-use crate::ast::policy::Policy;
+use super::{write_comment, XacmlWriter};
+use super::style::OutputStyle;
+use crate::ast::policy::{Policy, PolicyId};
 use crate::ast::rule::RuleEntry;
 use crate::ast::QualifiedName;
+use crate::context::{AnonymousKind, XacmlVersion};
 use crate::errors::ParseError;
+use crate::sourcemap::SourceRange;
 use crate::xacml::xprescription::XPrescriptionByType;
 use crate::xacml::xprescription::XPrescriptionExpr;
 use log::debug;
@@ -20,13 +26,34 @@ use xml::writer::EventWriter;
 use xml::writer::XmlEvent;
 
 /// `<Policy>` element and all children.
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XPolicy {
     /// Unique identifier for the policy.
     pub id: String,
     pub filename: Option<String>, // Todo: make non-optional
+    /// Namespace the policy was defined in.
+    pub ns: Vec<String>,
+    /// Name (given or generated) of the policy within its namespace.
+    pub name: Option<String>,
+    /// ALFA source file the policy was defined in.
+    pub source: Option<String>,
+    /// Location of the policy name in the ALFA source.
+    pub location: Option<SourceRange>,
+    /// XML comment at the start of the element.
+    pub comment: Option<String>,
     pub combining_alg: String,
     pub description: Option<String>,
+    /// Version of the policy.
+    pub version: String,
+    /// Attributes of the issuer of the policy.
+    pub issuer: Vec<XIssuerAttribute>,
+    /// Maximum depth of delegation from the policy.
+    pub max_delegation_depth: Option<u32>,
+    /// Parameters for the rule-combining algorithm.
+    pub combiner_params: Vec<XCombinerParam>,
+    /// Parameters for the combining algorithm of the parent policyset.
+    pub entry_params: Vec<XCombinerParam>,
     pub target: XTarget,
     pub prescriptions: XPrescriptions,
     pub rules: Vec<XRule>,
@@ -48,47 +75,67 @@ impl XacmlWriter for XPolicy {
     /// # Errors
     ///
     /// Returns `Err` if the writer fails.
-    fn write_xml<W: Write>(&self, writer: &mut EventWriter<W>) -> Result<(), xml::writer::Error> {
+    fn write_xml<W: Write>(
+        &self,
+        writer: &mut EventWriter<W>,
+        style: &OutputStyle,
+    ) -> Result<(), xml::writer::Error> {
         debug!("about to write xml for a policy");
         // Define the Policy element
-        writer.write(
-            XmlEvent::start_element("xacml3:Policy")
-                .attr("PolicyId", &self.id)
-                .attr("RuleCombiningAlgId", &self.combining_alg)
-                .attr("Version", "1.0")
-                .ns("xacml3", "urn:oasis:names:tc:xacml:3.0:core:schema:wd-17"),
-        )?;
+        let depth = self.max_delegation_depth.map(|d| d.to_string());
+        let mut elem = style
+            .root_element(XacmlVersion::V3, "Policy")
+            .attr("PolicyId", &self.id)
+            .attr("RuleCombiningAlgId", &self.combining_alg)
+            .attr("Version", &self.version);
+        if let Some(d) = depth.as_ref() {
+            elem = elem.attr("MaxDelegationDepth", d);
+        }
+        writer.write(elem)?;
+        write_comment(self.comment.as_ref(), writer)?;
+        // the schema location is only given on the root element
+        let style = &style.nested();
         // Add description
         if let Some(d) = self.description.as_ref() {
-            writer.write(XmlEvent::start_element("xacml3:Description"))?;
+            writer.write(XmlEvent::start_element(style.name("Description")))?;
             writer.write(XmlEvent::characters(d))?;
             writer.write(XmlEvent::end_element())?;
         }
+        write_policy_issuer(&self.issuer, writer, style)?;
         debug!("writing target...");
-        self.target.write_xml(writer)?;
+        self.target.write_xml(writer, style)?;
+        write_combiner_params("CombinerParameters", None, &self.combiner_params, writer, style)?;
         // Write Rules
         // TODO: rule references broken?
         for rule in &self.rules {
-            rule.write_xml(writer)?;
+            // parameters for a rule precede it
+            write_combiner_params(
+                "RuleCombinerParameters",
+                Some(("RuleIdRef", &rule.id)),
+                &rule.entry_params,
+                writer,
+                style,
+            )?;
+            rule.write_xml(writer, style)?;
         }
         // convert prescriptions into obligations/advice
         let xpt = XPrescriptionByType::from(self.prescriptions.clone());
         // Serialize Obligations
         if !xpt.obligations.is_empty() {
-            writer.write(XmlEvent::start_element("xacml3:ObligationExpressions"))?;
+            writer.write(XmlEvent::start_element(style.name("ObligationExpressions")))?;
             // serialize each prescription (obligation) expression
             for expr in xpt.obligations {
                 info!("serializing obligation expression");
-                expr.write_xml(writer)?;
+                expr.write_xml(writer, style)?;
             }
             writer.write(XmlEvent::end_element())?;
         }
         // Serialize Advice
         if !xpt.associated_advice.is_empty() {
-            writer.write(XmlEvent::start_element("xacml3:AdviceExpressions"))?;
+            writer.write(XmlEvent::start_element(style.name("AdviceExpressions")))?;
             // serialize each prescription (advice) expression
             for expr in xpt.associated_advice {
-                expr.write_xml(writer)?;
+                expr.write_xml(writer, style)?;
                 info!("serializing advice expression");
             }
             writer.write(XmlEvent::end_element())?;
@@ -103,6 +150,16 @@ impl XacmlWriter for XPolicy {
 impl TryFrom<&Policy> for XPolicy {
     type Error = ParseError;
     fn try_from(p: &Policy) -> Result<Self, Self::Error> {
+        // errors in expanded templates also point at the instance.
+        XPolicy::from_policy(p).map_err(|e| match &p.expansion {
+            Some(x) => x.locate(e),
+            None => e,
+        })
+    }
+}
+
+impl XPolicy {
+    fn from_policy(p: &Policy) -> Result<Self, ParseError> {
         info!("running try_from for policy -> XPolicy");
         // if there is a condition for the policy, we cannot convert
         // to an XPolicy.
@@ -112,13 +169,18 @@ impl TryFrom<&Policy> for XPolicy {
         }
 
         p.finalize_id();
+        let id = p.get_id();
+        // generated IDs must not collide with assigned ones.
+        if !matches!(p.id, PolicyId::PolicyNameAndId(..)) {
+            p.ctx.check_generated_uri(&id)?;
+        }
         // TODO: why does looking up combining algorithm fail with an import?
 
         // ID and description are straightforward copies.
         // the rule combining algorithm needs to be resolved.
         let combining_alg = p
             .ctx
-            .lookup_rule_combinator(&p.apply.id, &p.ns, &p.apply.src_loc)?
+            .lookup_rule_combinator(&p.apply.id, p.scope(), &p.apply.src_loc)?
             .uri
             .to_string();
 
@@ -134,22 +196,29 @@ impl TryFrom<&Policy> for XPolicy {
             match r {
                 RuleEntry::Ref(rr) => {
                     // lookup this rule
-                    info!("trying to lookup....{}, from namespace {:?}", &rr.id, &p.ns);
+                    info!("trying to lookup....{}, from namespace {:?}", &rr.id, p.scope());
                     // the above shows up we are NOT getting the full reference.
 		    // we need the source location of the rule.
                     let resolved_r = p.ctx.lookup_rule(
                         &rr.fully_qualified_name()
                             .ok_or(ParseError::AstConvertError)?,
-                        &p.ns,
+                        p.scope(),
 			&rr.src_loc
                     )?;
                     // The Rule ID here needs to be made unique.  Only
                     // the Original RuleDef can use the rule path.
                     let mut xr = XRule::try_from(resolved_r.as_ref())?;
-                    xr.id.push_str(&format!(
-                        "#rule_{}",
-                        &p.ctx.get_next_rule_id(&resolved_r.ns.join(".")).to_string()
-                    ));
+                    // parameters come from the reference, not the
+                    // original definition.
+                    xr.entry_params = XCombinerParam::from_params(&rr.entry_params, p.scope(), &p.ctx)?;
+                    let name = p.ctx.generate_name(
+                        AnonymousKind::Rule,
+                        &resolved_r.ns.join("."),
+                        &format!("{id}/{}", xr.id),
+                        &resolved_r.content_hash,
+                    );
+                    xr.id.push('#');
+                    xr.id.push_str(&name);
                     rules.push(xr);
                 }
                 RuleEntry::Def(d) => rules.push(XRule::try_from(d.as_ref())?),
@@ -167,14 +236,38 @@ impl TryFrom<&Policy> for XPolicy {
         };
         let filename = p.get_filename();
         info!("creating an xpolicy with filename: {filename:?}");
-        Ok(XPolicy {
-            id: p.get_id(),
+        let location = SourceRange::from_src_loc(&p.src_loc);
+        let (description, comment) = p.ctx.config.provenance.annotate(
+            p.description.clone(),
+            location.as_ref(),
+            &p.policy_ns.qualified_name(&p.ns),
+            p.expansion.as_deref(),
+        );
+        let xp = XPolicy {
+            id,
             filename,
+            ns: p.ns.clone(),
+            name: p.get_name(),
+            source: Some(p.src_loc.get_src().name().to_owned()),
+            location,
+            comment,
             combining_alg,
-            description: p.description.clone(),
+            description,
+            version: p
+                .version
+                .clone()
+                .unwrap_or_else(|| p.ctx.config.get_version()),
+            issuer: XIssuerAttribute::from_attrs(&p.issuer, p.scope(), &p.ctx)?,
+            max_delegation_depth: p.max_delegation_depth,
+            combiner_params: XCombinerParam::from_params(&p.apply.params, p.scope(), &p.ctx)?,
+            entry_params: XCombinerParam::from_params(&p.entry_params, p.scope(), &p.ctx)?,
             target,
             prescriptions,
             rules,
-        })
+        };
+        if p.ctx.config.target_version == XacmlVersion::V2 {
+            check_policy(&xp, &p.src_loc, &p.apply.src_loc)?;
+        }
+        Ok(xp)
     }
 }
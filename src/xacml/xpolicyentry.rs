@@ -3,22 +3,126 @@
 
 //! XACML Policy Entries (generic policy/policyset)
 
+use super::xcombinerparam::{write_combiner_params, XCombinerParam};
 use super::xpolicy::XPolicy;
 use super::xpolicyset::XPolicySet;
+use super::style::OutputStyle;
 use crate::ast::policy::Policy;
+use crate::ast::version::VersionConstraints;
 use crate::errors::ParseError;
 use log::info;
+use std::io::Write;
+use xml::writer::EventWriter;
+use xml::writer::XmlEvent;
+
+/// A `<PolicyIdReference>` or `<PolicySetIdReference>`.
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct XIdReference {
+    /// Identifier of the referenced policy/policyset.
+    pub id: String,
+    /// Required version pattern.
+    pub version: Option<String>,
+    /// Earliest acceptable version pattern.
+    pub earliest_version: Option<String>,
+    /// Latest acceptable version pattern.
+    pub latest_version: Option<String>,
+    /// Parameters for the combining algorithm of the parent policyset.
+    pub entry_params: Vec<XCombinerParam>,
+}
+
+impl XIdReference {
+    /// Create a reference with the version constraints and combiner
+    /// parameters of an ALFA policy reference.
+    #[must_use]
+    pub fn new(
+        id: String,
+        versions: &VersionConstraints,
+        entry_params: Vec<XCombinerParam>,
+    ) -> Self {
+        XIdReference {
+            id,
+            version: versions.version.clone(),
+            earliest_version: versions.earliest.clone(),
+            latest_version: versions.latest.clone(),
+            entry_params,
+        }
+    }
+
+    /// Write this reference as an element with the given (local)
+    /// name.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the writer fails.
+    pub fn write_xml<W: Write>(
+        &self,
+        elem_name: &str,
+        writer: &mut EventWriter<W>,
+        style: &OutputStyle,
+    ) -> Result<(), xml::writer::Error> {
+        let mut elem = XmlEvent::start_element(style.name(elem_name));
+        if let Some(v) = &self.version {
+            elem = elem.attr("Version", v);
+        }
+        if let Some(v) = &self.earliest_version {
+            elem = elem.attr("EarliestVersion", v);
+        }
+        if let Some(v) = &self.latest_version {
+            elem = elem.attr("LatestVersion", v);
+        }
+        writer.write(elem)?;
+        writer.write(XmlEvent::characters(&self.id))?;
+        writer.write(XmlEvent::end_element())?;
+        Ok(())
+    }
+}
 
 /// A Policy/Policyset child of a `PolicySet`.  Used to preserve order.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum XPolicyEntry {
-    PolicyIdRef(String),
-    PolicySetIdRef(String),
+    PolicyIdRef(XIdReference),
+    PolicySetIdRef(XIdReference),
     PolicySet(XPolicySet),
     Policy(XPolicy),
 }
 
 impl XPolicyEntry {
+    /// Write the parameters for the combining algorithm of the parent
+    /// policyset that apply to this entry, if there are any.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the writer fails.
+    pub fn write_entry_params<W: Write>(
+        &self,
+        writer: &mut EventWriter<W>,
+        style: &OutputStyle,
+    ) -> Result<(), xml::writer::Error> {
+        let (elem_name, attr, id, params) = match self {
+            XPolicyEntry::PolicyIdRef(r) => {
+                ("PolicyCombinerParameters", "PolicyIdRef", &r.id, &r.entry_params)
+            }
+            XPolicyEntry::PolicySetIdRef(r) => (
+                "PolicySetCombinerParameters",
+                "PolicySetIdRef",
+                &r.id,
+                &r.entry_params,
+            ),
+            XPolicyEntry::Policy(p) => {
+                ("PolicyCombinerParameters", "PolicyIdRef", &p.id, &p.entry_params)
+            }
+            XPolicyEntry::PolicySet(p) => (
+                "PolicySetCombinerParameters",
+                "PolicySetIdRef",
+                &p.id,
+                &p.entry_params,
+            ),
+        };
+        write_combiner_params(elem_name, Some((attr, id)), params, writer, style)
+    }
+
     /// Count the total number of rules contained under this policy
     /// entry.
     pub fn rule_count(&self) -> usize {
@@ -0,0 +1,83 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+//! XACML Policy Issuers
+
+use super::xprescription::XAttrValue;
+use super::style::OutputStyle;
+use crate::ast::policyissuer::IssuerAttribute;
+use crate::context::Context;
+use crate::errors::ParseError;
+use std::io::Write;
+use xml::writer::EventWriter;
+use xml::writer::XmlEvent;
+
+/// `<Attribute>` element within a `<PolicyIssuer>`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct XIssuerAttribute {
+    /// Attribute identifier (URI).
+    pub id: String,
+    /// Typed value of the attribute.
+    pub value: XAttrValue,
+}
+
+impl XIssuerAttribute {
+    /// Convert ALFA issuer attributes, resolving attribute names and
+    /// value types from a namespace.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if an attribute cannot be found, or a value does
+    /// not match the attribute type.
+    pub fn from_attrs(
+        attrs: &[IssuerAttribute],
+        source_ns: &[String],
+        ctx: &Context,
+    ) -> Result<Vec<XIssuerAttribute>, ParseError> {
+        attrs
+            .iter()
+            .map(|a| {
+                Ok(XIssuerAttribute {
+                    id: ctx.lookup_attribute(&a.attribute, source_ns)?.uri.clone(),
+                    value: XAttrValue {
+                        v: ctx.resolve_attribute_value(
+                            &a.attribute,
+                            &a.value,
+                            source_ns,
+                            &a.src_loc,
+                        )?,
+                    },
+                })
+            })
+            .collect()
+    }
+}
+
+/// Write a `<PolicyIssuer>` element, if there are any issuer
+/// attributes.
+///
+/// # Errors
+///
+/// Returns `Err` if the writer fails.
+pub fn write_policy_issuer<W: Write>(
+    attrs: &[XIssuerAttribute],
+    writer: &mut EventWriter<W>,
+    style: &OutputStyle,
+) -> Result<(), xml::writer::Error> {
+    if attrs.is_empty() {
+        return Ok(());
+    }
+    writer.write(XmlEvent::start_element(style.name("PolicyIssuer")))?;
+    for a in attrs {
+        writer.write(
+            XmlEvent::start_element(style.name("Attribute"))
+                .attr("AttributeId", &a.id)
+                .attr("IncludeInResult", "false"),
+        )?;
+        a.value.write_xml(writer, style)?;
+        writer.write(XmlEvent::end_element())?;
+    }
+    writer.write(XmlEvent::end_element())?;
+    Ok(())
+}
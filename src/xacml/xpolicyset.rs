@@ -3,13 +3,22 @@
 
 //! XACML Policy Sets
 
-use super::xpolicyentry::XPolicyEntry;
+use super::xacml2::check_policyset;
+use super::xcombinerparam::{write_combiner_params, XCombinerParam};
+use super::xpolicyentry::{XIdReference, XPolicyEntry};
+use super::xpolicyissuer::{write_policy_issuer, XIssuerAttribute};
 use super::xprescription::XPrescriptions;
 use super::xtarget::XTarget;
-use super::XacmlWriter;
-use crate::ast::policy::Policy;
+use super::{write_comment, XacmlWriter};
+use super::style::OutputStyle;
+use crate::ast::condition::Condition;
+use crate::ast::externpolicy::ExternKind;
+use crate::ast::policy::{Policy, PolicyId};
 use crate::ast::policyset::{PolicyEntry, PolicySet};
-use crate::errors::ParseError;
+use crate::ast::Spanned;
+use crate::context::{Config, XacmlVersion};
+use crate::errors::{ParseError, SrcError};
+use crate::sourcemap::SourceRange;
 use crate::xacml::xprescription::{XPrescriptionByType, XPrescriptionExpr};
 use log::debug;
 use log::info;
@@ -19,13 +28,34 @@ use xml::writer::EventWriter;
 use xml::writer::XmlEvent;
 
 /// `<PolicySet>` element and all children.
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XPolicySet {
     /// Unique identifier for the policy.
     pub id: String,
     pub filename: Option<String>, // Todo: make non-optional
+    /// Namespace the policyset was defined in.
+    pub ns: Vec<String>,
+    /// Name (given or generated) of the policyset within its namespace.
+    pub name: Option<String>,
+    /// ALFA source file the policyset was defined in.
+    pub source: Option<String>,
+    /// Location of the policyset name in the ALFA source.
+    pub location: Option<SourceRange>,
+    /// XML comment at the start of the element.
+    pub comment: Option<String>,
     pub combining_alg: String,
     pub description: Option<String>,
+    /// Version of the policyset.
+    pub version: String,
+    /// Attributes of the issuer of the policyset.
+    pub issuer: Vec<XIssuerAttribute>,
+    /// Maximum depth of delegation from the policyset.
+    pub max_delegation_depth: Option<u32>,
+    /// Parameters for the policy-combining algorithm.
+    pub combiner_params: Vec<XCombinerParam>,
+    /// Parameters for the combining algorithm of the parent policyset.
+    pub entry_params: Vec<XCombinerParam>,
     pub target: XTarget,
     pub prescriptions: XPrescriptions,
     pub children: Vec<XPolicyEntry>,
@@ -65,68 +95,78 @@ impl XacmlWriter for XPolicySet {
     /// # Errors
     ///
     /// Returns `Err` if the writer fails.
-    fn write_xml<W: Write>(&self, writer: &mut EventWriter<W>) -> Result<(), xml::writer::Error> {
+    fn write_xml<W: Write>(
+        &self,
+        writer: &mut EventWriter<W>,
+        style: &OutputStyle,
+    ) -> Result<(), xml::writer::Error> {
         // Define the Policy element
-        writer.write(
-            XmlEvent::start_element("xacml3:PolicySet")
-                .attr("PolicySetId", &self.id)
-                .attr("PolicyCombiningAlgId", &self.combining_alg)
-                .attr("Version", "1.0")
-                .ns("xacml3", "urn:oasis:names:tc:xacml:3.0:core:schema:wd-17"),
-        )?;
+        let depth = self.max_delegation_depth.map(|d| d.to_string());
+        let mut elem = style
+            .root_element(XacmlVersion::V3, "PolicySet")
+            .attr("PolicySetId", &self.id)
+            .attr("PolicyCombiningAlgId", &self.combining_alg)
+            .attr("Version", &self.version);
+        if let Some(d) = depth.as_ref() {
+            elem = elem.attr("MaxDelegationDepth", d);
+        }
+        writer.write(elem)?;
+        write_comment(self.comment.as_ref(), writer)?;
+        // the schema location is only given on the root element
+        let style = &style.nested();
         // Add description
         if let Some(d) = self.description.as_ref() {
-            writer.write(XmlEvent::start_element("xacml3:Description"))?;
+            writer.write(XmlEvent::start_element(style.name("Description")))?;
             writer.write(XmlEvent::characters(d))?;
             writer.write(XmlEvent::end_element())?;
         }
+        write_policy_issuer(&self.issuer, writer, style)?;
         debug!("writing target...");
-        self.target.write_xml(writer)?;
+        self.target.write_xml(writer, style)?;
+        write_combiner_params("CombinerParameters", None, &self.combiner_params, writer, style)?;
         warn!("child count is {}", self.children.len());
         for p in &self.children {
             warn!("writing child policy or policyset");
+            // parameters for a child precede it
+            p.write_entry_params(writer, style)?;
             match p {
-                XPolicyEntry::PolicyIdRef(i) => {
-                    writer.write(XmlEvent::start_element("xacml3:PolicyIdReference"))?;
-                    writer.write(XmlEvent::characters(i))?;
-                    writer.write(XmlEvent::end_element())?;
+                XPolicyEntry::PolicyIdRef(r) => {
+                    r.write_xml("PolicyIdReference", writer, style)?;
                 }
-                XPolicyEntry::PolicySetIdRef(i) => {
-                    writer.write(XmlEvent::start_element("xacml3:PolicySetIdReference"))?;
-                    writer.write(XmlEvent::characters(i))?;
-                    writer.write(XmlEvent::end_element())?;
+                XPolicyEntry::PolicySetIdRef(r) => {
+                    r.write_xml("PolicySetIdReference", writer, style)?;
                 }
                 XPolicyEntry::Policy(sub_p) => {
-                    sub_p.write_xml(writer)?;
+                    sub_p.write_xml(writer, style)?;
                 }
                 XPolicyEntry::PolicySet(sub_p) => {
-                    sub_p.write_xml(writer)?;
+                    sub_p.write_xml(writer, style)?;
                 }
             }
         }
         // Write Policies
         //        for rule in &self.rules {
-        //            rule.write_xml(writer)?;
+        //            rule.write_xml(writer, style)?;
         //        }
 
         // convert prescriptions into obligations/advice
         let xpt = XPrescriptionByType::from(self.prescriptions.clone());
         // Serialize Obligations
         if !xpt.obligations.is_empty() {
-            writer.write(XmlEvent::start_element("xacml3:ObligationExpressions"))?;
+            writer.write(XmlEvent::start_element(style.name("ObligationExpressions")))?;
             // serialize each prescription (obligation) expression
             for expr in xpt.obligations {
                 info!("serializing obligation expression");
-                expr.write_xml(writer)?;
+                expr.write_xml(writer, style)?;
             }
             writer.write(XmlEvent::end_element())?;
         }
         // Serialize Advice
         if !xpt.associated_advice.is_empty() {
-            writer.write(XmlEvent::start_element("xacml3:AdviceExpressions"))?;
+            writer.write(XmlEvent::start_element(style.name("AdviceExpressions")))?;
             // serialize each prescription (advice) expression
             for expr in xpt.associated_advice {
-                expr.write_xml(writer)?;
+                expr.write_xml(writer, style)?;
                 info!("serializing advice expression");
             }
             writer.write(XmlEvent::end_element())?;
@@ -142,7 +182,8 @@ impl XacmlWriter for XPolicySet {
 impl TryFrom<&PolicySet> for XPolicySet {
     type Error = ParseError;
     fn try_from(p: &PolicySet) -> Result<Self, Self::Error> {
-        if p.condition.is_some() {
+        if let Some(c) = &p.condition {
+            no_v2_condition(&p.ctx.config, c)?;
             info!("this policy has conditions, converting to one without");
             let pd = p.clone().decondition()?;
             // convert this version of the policyset that does not
@@ -154,6 +195,11 @@ impl TryFrom<&PolicySet> for XPolicySet {
         let mut children = vec![];
         // Ensure we have a finalized name.
         p.finalize_id();
+        let id = p.get_id();
+        // generated IDs must not collide with assigned ones.
+        if !matches!(p.id, PolicyId::PolicyNameAndId(..)) {
+            p.ctx.check_generated_uri(&id)?;
+        }
         // ID and description are straightforward copies.
         // the rule combining algorithm needs to be resolved.
         let combining_alg = p
@@ -181,21 +227,50 @@ impl TryFrom<&PolicySet> for XPolicySet {
                     // sure there is no policy/policyset with the same
                     // name.
                     debug!("lookup for policy ref: {pr:?}");
+                    let entry_params =
+                        XCombinerParam::from_params(&pr.entry_params, &p.ns, &p.ctx)?;
                     // lookup a policyset with this name.
                     if let Ok(ps) = p.ctx.lookup_policyset(&pr.fully_qualified_name(), &p.ns) {
                         // get the ID, and print it out.
                         info!("we found a policyset");
-                        children.push(XPolicyEntry::PolicySetIdRef(ps.get_id()));
+                        children.push(XPolicyEntry::PolicySetIdRef(XIdReference::new(
+                            ps.get_id(),
+                            &pr.versions,
+                            entry_params.clone(),
+                        )));
                     } else if let Ok(ps) = p.ctx.lookup_policy(&pr.fully_qualified_name(), &p.ns) {
                         info!("we found a policy");
                         // if the policy has a condition, it will be
                         // transformed into a policyset, and we need
                         // to reference it appropriately.
                         if ps.condition.is_none() {
-                            children.push(XPolicyEntry::PolicyIdRef(ps.get_id()));
+                            children.push(XPolicyEntry::PolicyIdRef(XIdReference::new(
+                                ps.get_id(),
+                                &pr.versions,
+                                entry_params.clone(),
+                            )));
                         } else {
-                            children.push(XPolicyEntry::PolicySetIdRef(ps.get_id()));
+                            children.push(XPolicyEntry::PolicySetIdRef(XIdReference::new(
+                                ps.get_id(),
+                                &pr.versions,
+                                entry_params.clone(),
+                            )));
                         }
+                    } else if let Ok(ext) =
+                        p.ctx.lookup_extern(&pr.fully_qualified_name(), &p.ns)
+                    {
+                        info!("we found an external {}", ext.kind);
+                        // constraints on the reference take precedence
+                        // over those of the declaration.
+                        let r = XIdReference::new(
+                            ext.uri.clone(),
+                            &pr.versions.or(&ext.versions),
+                            entry_params,
+                        );
+                        children.push(match ext.kind {
+                            ExternKind::Policy => XPolicyEntry::PolicyIdRef(r),
+                            ExternKind::PolicySet => XPolicyEntry::PolicySetIdRef(r),
+                        });
                     } else {
                         warn!("failed to resolve policy reference in a policyset");
                         return Err(ParseError::UnexpectedRuleError(
@@ -235,26 +310,64 @@ impl TryFrom<&PolicySet> for XPolicySet {
         };
         let filename = p.get_filename();
         info!("creating an xpolicyset with filename: {filename:?}");
-        Ok(XPolicySet {
-            id: p.get_id(),
+        let location = SourceRange::from_src_loc(&p.src_loc);
+        let (description, comment) = p.ctx.config.provenance.annotate(
+            p.description.clone(),
+            location.as_ref(),
+            &p.policy_ns.qualified_name(&p.ns),
+            None,
+        );
+        let xps = XPolicySet {
+            id,
             filename,
+            ns: p.ns.clone(),
+            name: p.get_name(),
+            source: Some(p.src_loc.get_src().name().to_owned()),
+            location,
+            comment,
             combining_alg,
-            description: p.description.clone(),
+            description,
+            version: p
+                .version
+                .clone()
+                .unwrap_or_else(|| p.ctx.config.get_version()),
+            issuer: XIssuerAttribute::from_attrs(&p.issuer, &p.ns, &p.ctx)?,
+            max_delegation_depth: p.max_delegation_depth,
+            combiner_params: XCombinerParam::from_params(&p.apply.params, &p.ns, &p.ctx)?,
+            entry_params: XCombinerParam::from_params(&p.entry_params, &p.ns, &p.ctx)?,
             target,
             prescriptions,
             children,
-        })
+        };
+        if p.ctx.config.target_version == XacmlVersion::V2 {
+            check_policyset(&xps, &p.src_loc, &p.apply.src_loc)?;
+        }
+        Ok(xps)
     }
 }
 
 impl TryFrom<&Policy> for XPolicySet {
     type Error = ParseError;
     fn try_from(p: &Policy) -> Result<Self, Self::Error> {
-        if p.condition.is_none() {
+        let Some(c) = &p.condition else {
             info!("this policy has no condition, cannot convert to XPolicySet");
             return Err(ParseError::PolicyHasCondition);
-        }
+        };
+        no_v2_condition(&p.ctx.config, c)?;
         let policyset = p.decondition()?;
         XPolicySet::try_from(&policyset)
     }
 }
+
+/// Ensure a policy or policyset condition is not used with XACML 2.0,
+/// which has no combining algorithm to implement it.
+fn no_v2_condition(config: &Config, c: &Condition) -> Result<(), ParseError> {
+    if config.target_version == XacmlVersion::V2 {
+        return Err(SrcError::err(
+            "Policy and policyset conditions cannot be expressed in XACML 2.0",
+            "move this condition into the rules",
+            c.span().clone(),
+        ));
+    }
+    Ok(())
+}
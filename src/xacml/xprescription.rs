@@ -4,6 +4,7 @@
 //! XACML Prescriptions; Obligations and Advice
 
 use super::xattr_designator::XAttrDesignator;
+use super::style::OutputStyle;
 use crate::ast::prescription::{AttrAssignmentSource, Prescription, PrescriptionType};
 use crate::ast::rule::Effect;
 use crate::context::TypedLiteral;
@@ -16,6 +17,7 @@ use xml::writer::XmlEvent;
 /// `<AttributeValue>` element, found in [`XExpression`] and
 /// [`XAttributeAssignment`].
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XAttrValue {
     // Attribute values consist of a type URI and value
     pub v: TypedLiteral,
@@ -31,9 +33,10 @@ impl XAttrValue {
     pub fn write_xml<W: Write>(
         &self,
         writer: &mut EventWriter<W>,
+        style: &OutputStyle,
     ) -> Result<(), xml::writer::Error> {
         writer.write(
-            XmlEvent::start_element("xacml3:AttributeValue").attr("DataType", &self.v.type_uri),
+            XmlEvent::start_element(style.name("AttributeValue")).attr("DataType", &self.v.type_uri),
         )?;
         writer.write(XmlEvent::characters(&self.v.value))?;
         writer.write(XmlEvent::end_element())?;
@@ -44,6 +47,7 @@ impl XAttrValue {
 /// Container for prescriptions that have been separated into
 /// obligations and advice.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XPrescriptionByType {
     pub obligations: Vec<XPrescriptionExpr>,
     pub associated_advice: Vec<XPrescriptionExpr>,
@@ -51,6 +55,7 @@ pub struct XPrescriptionByType {
 
 /// Container for Obligations and Advice.
 #[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XPrescriptions {
     pub exprs: Vec<XPrescriptionExpr>,
 }
@@ -75,6 +80,7 @@ impl From<XPrescriptions> for XPrescriptionByType {
 
 /// An `<ObligationExpression>` or `<AdviceExpression>` element.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XPrescriptionExpr {
     pub ptype: PrescriptionType,
     pub id: String,
@@ -92,18 +98,19 @@ impl XPrescriptionExpr {
     pub fn write_xml<W: Write>(
         &self,
         writer: &mut EventWriter<W>,
+        style: &OutputStyle,
     ) -> Result<(), xml::writer::Error> {
         match self.ptype {
             PrescriptionType::Advice => {
                 writer.write(
-                    XmlEvent::start_element("xacml3:AdviceExpression")
+                    XmlEvent::start_element(style.name("AdviceExpression"))
                         .attr("AdviceId", &self.id)
                         .attr("AppliesTo", &self.fulfill_on.to_string()),
                 )?;
             }
             PrescriptionType::Obligation => {
                 writer.write(
-                    XmlEvent::start_element("xacml3:ObligationExpression")
+                    XmlEvent::start_element(style.name("ObligationExpression"))
                         .attr("ObligationId", &self.id)
                         .attr("FulfillOn", &self.fulfill_on.to_string()),
                 )?;
@@ -113,13 +120,13 @@ impl XPrescriptionExpr {
         // write the attribute assignment expression
         for a in &self.assignments {
             writer.write(
-                XmlEvent::start_element("xacml3:AttributeAssignmentExpression")
+                XmlEvent::start_element(style.name("AttributeAssignmentExpression"))
                     .attr("AttributeId", &a.id)
                     .attr("Category", &a.category),
             )?;
             match &a.arg {
-                XAttributeAssignmentArgument::Value(v) => v.write_xml(writer)?,
-                XAttributeAssignmentArgument::Attrib(ad) => ad.write_xml(writer)?,
+                XAttributeAssignmentArgument::Value(v) => v.write_xml(writer, style)?,
+                XAttributeAssignmentArgument::Attrib(ad) => ad.write_xml(writer, style)?,
             };
             writer.write(XmlEvent::end_element())?;
         }
@@ -130,6 +137,7 @@ impl XPrescriptionExpr {
 
 /// Assignments to Prescription Attributes.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum XAttributeAssignmentArgument {
     /// Constant/Literal value.
     Value(XAttrValue),
@@ -139,6 +147,7 @@ pub enum XAttributeAssignmentArgument {
 
 /// An individual attribute assignment within an [`XPrescriptionExpr`].
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XAttributeAssignment {
     /// The identifier for the attribute value returned within this
     /// obligation/advice.
@@ -3,20 +3,26 @@
 
 //! XACML Rules
 
+use super::xcombinerparam::XCombinerParam;
 use super::xcondition::XCondition;
 use super::xprescription::XPrescriptionByType;
 use super::xprescription::XPrescriptionExpr;
 use super::xprescription::XPrescriptions;
 use super::xtarget::XTarget;
+use super::style::OutputStyle;
+use super::write_comment;
 use crate::ast::rule::RuleDef;
+use crate::ast::QualifiedName;
 use crate::errors::ParseError;
+use crate::sourcemap::SourceRange;
 use log::info;
 use std::io::Write;
 use xml::writer::EventWriter;
 use xml::writer::XmlEvent;
 
 /// `<Rule>` elements within an [`XPolicy`].
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XRule {
     pub id: String,
     pub description: Option<String>,
@@ -24,13 +30,29 @@ pub struct XRule {
     pub target: XTarget,
     pub condition: Option<XCondition>,
     pub prescriptions: XPrescriptions,
+    /// Parameters for the combining algorithm of the parent policy.
+    pub entry_params: Vec<XCombinerParam>,
+    /// Location of the rule in the ALFA source.
+    pub location: Option<SourceRange>,
+    /// XML comment at the start of the element.
+    pub comment: Option<String>,
 }
 
 /// Conversion of Alfa Rule to XACML Rule
 impl TryFrom<&RuleDef> for XRule {
     type Error = ParseError;
     fn try_from(r: &RuleDef) -> Result<Self, Self::Error> {
-        let _ctx = r.ctx.upgrade().ok_or(ParseError::ContextMissing)?;
+        // errors in expanded templates also point at the instance.
+        XRule::from_rule(r).map_err(|e| match &r.expansion {
+            Some(x) => x.locate(e),
+            None => e,
+        })
+    }
+}
+
+impl XRule {
+    fn from_rule(r: &RuleDef) -> Result<Self, ParseError> {
+        let ctx = r.ctx.upgrade().ok_or(ParseError::ContextMissing)?;
         let target = r
             .target
             .as_ref()
@@ -53,13 +75,30 @@ impl TryFrom<&RuleDef> for XRule {
                 .collect::<Vec<XPrescriptionExpr>>(),
         };
 
+        // anonymous rules are named by the policy containing them.
+        let location = SourceRange::from_src_loc(&r.src_loc);
+        let name = r
+            .fully_qualified_name()
+            .unwrap_or_else(|| r.policy_ns.qualified_name(&r.ns));
+        let (description, comment) =
+            ctx.config
+                .provenance
+                .annotate(
+                    r.description.clone(),
+                    location.as_ref(),
+                    &name,
+                    r.expansion.as_deref(),
+                );
         Ok(XRule {
             id: r.get_id(),
-            description: r.description.clone(),
+            description,
             effect: format!("{}", r.effect),
             target,
             condition,
             prescriptions,
+            entry_params: XCombinerParam::from_params(&r.entry_params, r.scope(), &ctx)?,
+            location,
+            comment,
         })
     }
 }
@@ -74,24 +113,26 @@ impl XRule {
     pub fn write_xml<W: Write>(
         &self,
         writer: &mut EventWriter<W>,
+        style: &OutputStyle,
     ) -> Result<(), xml::writer::Error> {
         // <xacml3:Rule Effect="Permit" RuleId="main.main/main.main.internalRule">
         writer.write(
-            XmlEvent::start_element("xacml3:Rule")
+            XmlEvent::start_element(style.name("Rule"))
                 .attr("Effect", &self.effect)
                 .attr("RuleId", &self.id),
         )?;
+        write_comment(self.comment.as_ref(), writer)?;
         // Write a description
         if let Some(d) = self.description.as_ref() {
-            writer.write(XmlEvent::start_element("xacml3:Description"))?;
+            writer.write(XmlEvent::start_element(style.name("Description")))?;
             writer.write(XmlEvent::characters(d))?;
             writer.write(XmlEvent::end_element())?;
         }
         // Serialize the <Target> element
-        self.target.write_xml(writer)?;
+        self.target.write_xml(writer, style)?;
         if let Some(c) = self.condition.as_ref() {
             // Serialize the condition, if it exists
-            c.write_xml(writer)?;
+            c.write_xml(writer, style)?;
         }
 
         // convert prescriptions into obligations/advice
@@ -99,20 +140,20 @@ impl XRule {
 
         // Serialize Obligations
         if !xpt.obligations.is_empty() {
-            writer.write(XmlEvent::start_element("xacml3:ObligationExpressions"))?;
+            writer.write(XmlEvent::start_element(style.name("ObligationExpressions")))?;
             // serialize each prescription (obligation) expression
             for expr in xpt.obligations {
                 info!("serializing obligation expression");
-                expr.write_xml(writer)?;
+                expr.write_xml(writer, style)?;
             }
             writer.write(XmlEvent::end_element())?;
         }
         // Serialize Advice
         if !xpt.associated_advice.is_empty() {
-            writer.write(XmlEvent::start_element("xacml3:AdviceExpressions"))?;
+            writer.write(XmlEvent::start_element(style.name("AdviceExpressions")))?;
             // serialize each prescription (advice) expression
             for expr in xpt.associated_advice {
-                expr.write_xml(writer)?;
+                expr.write_xml(writer, style)?;
                 info!("serializing advice expression");
             }
             writer.write(XmlEvent::end_element())?;
@@ -3,10 +3,13 @@
 
 //! XACML Targets
 
+use super::style::OutputStyle;
+use super::xacml2::check_target_clause;
 use crate::ast::target::Match;
 use crate::ast::target::Target;
-use crate::context::Context;
+use crate::context::{Context, XacmlVersion};
 use crate::errors::ParseError;
+use crate::sourcemap::SourceRange;
 use log::debug;
 use log::error;
 use log::info;
@@ -15,26 +18,32 @@ use xml::writer::EventWriter;
 use xml::writer::XmlEvent;
 
 /// `<Target>` elements within an [`XRule`], [`XPolicy`], or [`XPolicySet`].
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XTarget {
     pub anyofs: Vec<AnyOf>,
+    /// Location of the target in the ALFA source.
+    pub location: Option<SourceRange>,
 }
 
 /// `<AnyOf>` elements within an [`XTarget`].
 // AnyOf elements contain AllOf
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AnyOf {
     pub allofs: Vec<AllOf>,
 }
 
 /// `<AllOf>` elements within an [`AnyOf`].
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AllOf {
     pub matches: Vec<XMatch>,
 }
 
 /// `<Match>` elements contained within an [`AllOf`].
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct XMatch {
     // A match statement requires:
     // match function ID, value, value datatype, designator ID, designator category ID, designator type, and must-be-present.x
@@ -58,6 +67,7 @@ impl XMatch {
     pub fn write_xml<W: Write>(
         &self,
         writer: &mut EventWriter<W>,
+        style: &OutputStyle,
     ) -> Result<(), xml::writer::Error> {
         // will look something like;
         // <xacml3:Match MatchId="urn:simple">
@@ -65,16 +75,16 @@ impl XMatch {
         //   12</xacml3:AttributeValue>
         //   <xacml3:AttributeDesignator AttributeId="urn:oasis:names:tc:xacml:1.0:resource:resource-id" Category="urn:oasis:names:tc:xacml:3.0:attribute-category:resource" DataType="http://www.w3.org/2001/XMLSchema#string" MustBePresent="false"/>
         // </xacml3:Match>
-        writer.write(XmlEvent::start_element("xacml3:Match").attr("MatchId", &self.matchid))?;
+        writer.write(XmlEvent::start_element(style.name("Match")).attr("MatchId", &self.matchid))?;
         // Write Attribute Value
         writer.write(
-            XmlEvent::start_element("xacml3:AttributeValue").attr("DataType", &self.value_type),
+            XmlEvent::start_element(style.name("AttributeValue")).attr("DataType", &self.value_type),
         )?;
         writer.write(XmlEvent::characters(&self.value))?;
         writer.write(XmlEvent::end_element())?;
         // Write Attribute Designator
         let mbp = &self.must_be_present.to_string();
-        let mut attrdesig = XmlEvent::start_element("xacml3:AttributeDesignator")
+        let mut attrdesig = XmlEvent::start_element(style.name("AttributeDesignator"))
             .attr("AttributeId", &self.designator_id)
             .attr("Category", &self.designator_category)
             .attr("DataType", &self.designator_type)
@@ -100,19 +110,20 @@ impl XTarget {
     pub fn write_xml<W: Write>(
         &self,
         writer: &mut EventWriter<W>,
+        style: &OutputStyle,
     ) -> Result<(), xml::writer::Error> {
         // Add (empty) Target
-        writer.write(XmlEvent::start_element("xacml3:Target"))?;
+        writer.write(XmlEvent::start_element(style.name("Target")))?;
 
         // Write AnyOfs
         for a in &self.anyofs {
-            writer.write(XmlEvent::start_element("xacml3:AnyOf"))?;
+            writer.write(XmlEvent::start_element(style.name("AnyOf")))?;
             // Write AllOfs
             for b in &a.allofs {
-                writer.write(XmlEvent::start_element("xacml3:AllOf"))?;
+                writer.write(XmlEvent::start_element(style.name("AllOf")))?;
                 // write Matches
                 for m in &b.matches {
-                    m.write_xml(writer)?;
+                    m.write_xml(writer, style)?;
                 }
                 writer.write(XmlEvent::end_element())?;
             }
@@ -132,7 +143,7 @@ impl XTarget {
 /// # Returns
 /// * `Ok(XMatch)` - Reference-counted XACML representation of a Match.
 /// * `Err(ParseError)` - Parse error if the symbols in the Match.
-///    could not be resolved
+///   could not be resolved
 fn match_to_xmatch(m: &Match, source_ns: &[String], ctx: &Context) -> Result<XMatch, ParseError> {
     // the ns is where this match is located.
     // we need to find the function, which means looking up
@@ -258,8 +269,15 @@ impl TryFrom<&Target> for XTarget {
                 }
                 allofs.push(AllOf { matches });
             }
-            anyofs.push(AnyOf { allofs });
+            let anyof = AnyOf { allofs };
+            if ctx.config.target_version == XacmlVersion::V2 {
+                check_target_clause(&anyof, &c.src_loc)?;
+            }
+            anyofs.push(anyof);
         }
-        Ok(XTarget { anyofs })
+        Ok(XTarget {
+            anyofs,
+            location: SourceRange::from_src_loc(&t.src_loc),
+        })
     }
 }
@@ -0,0 +1,131 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use common::{compile_alfa_src, compile_alfa_srcs, emit_alfa};
+use pretty_assertions::assert_eq;
+mod common;
+
+// Integration tests for emitting a syntax tree as ALFA source.
+// Every source compiled through `common` is also checked to survive
+// a round trip through `AsAlfa`; these tests cover the emitted text.
+
+/// Emitted text for a policy touching most statements.
+#[test]
+fn emit_policy() {
+    let src = r#"
+namespace main {
+  import attr.*
+  obligation audit = "urn:example:audit"
+  /* Documents
+     readable by owners */
+  policy documents = "urn:example:documents" {
+    version "2.1"
+    maxDelegationDepth 3
+    issuer { subjectId = "admin" }
+    target clause actionId == "read" or actionId == "list" clause 3 <= clearance
+    condition (subjectId == owner) && not(isWeekday(currentDate))
+    apply denyOverrides with (w = 2)
+    // owners only
+    rule readable with (weight = 1) {
+      permit
+      condition (integerOneAndOnly(clearance) + 1) * 2 > 3
+      on permit { obligation audit { subjectId = subjectId owner = "a \"quoted\"\npath" } }
+      on deny { advice notice {} }
+    }
+    rule { deny }
+  }
+}"#;
+    assert_eq!(
+        emit_alfa("", src),
+        r#"namespace main {
+  import attr.*
+
+  obligation audit = "urn:example:audit"
+
+  /* Documents
+     readable by owners */
+  policy documents = "urn:example:documents" {
+    version "2.1"
+    maxDelegationDepth 3
+    issuer {
+      subjectId = "admin"
+    }
+    target clause actionId == "read" or actionId == "list"
+           clause 3 <= clearance
+    condition (subjectId == owner) && not(isWeekday(currentDate))
+    apply denyOverrides with (w = 2)
+    // owners only
+    rule readable with (weight = 1) {
+      permit
+      condition (integerOneAndOnly(clearance) + 1) * (2 > 3)
+      on permit {
+        obligation audit {
+          subjectId = subjectId
+          owner = "a \"quoted\"\npath"
+        }
+      }
+      on deny {
+        advice notice {}
+      }
+    }
+    rule {
+      deny
+    }
+  }
+}
+"#
+    );
+}
+
+/// Nested namespaces are emitted relative to their parent.
+#[test]
+fn nested_namespaces() {
+    let src = r#"
+namespace a {
+  namespace b.c {
+    policy p { apply firstApplicable rule { permit } }
+  }
+  policyset s { apply firstApplicable b.c.p }
+}"#;
+    compile_alfa_src(src);
+    let emitted = emit_alfa("", src);
+    assert!(emitted.contains("\n  namespace b.c {\n    policy p {\n"), "{emitted}");
+}
+
+/// Templates are emitted with their body text, and instances with
+/// their arguments.
+#[test]
+fn templates() {
+    let src = r#"
+namespace main {
+  template policy allowRead(role, act) {
+    target clause subjectId == param[role] and param[act] == "read"
+    apply firstApplicable
+    rule { permit }
+  }
+  instance customerRead = allowRead(role = "customer", act = actionId)
+}"#;
+    compile_alfa_src(src);
+    let emitted = emit_alfa("", src);
+    assert!(
+        emitted.contains("  instance customerRead = allowRead(role = \"customer\", act = actionId)\n"),
+        "{emitted}"
+    );
+}
+
+/// The retail samples survive a round trip.
+#[test]
+fn samples_round_trip() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/retail");
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|e| e == "alfa"))
+        .collect();
+    paths.sort();
+    let srcs = paths
+        .iter()
+        .map(|p| std::fs::read_to_string(p).unwrap())
+        .collect();
+    assert!(!compile_alfa_srcs(srcs).is_empty());
+}
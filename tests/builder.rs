@@ -0,0 +1,249 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::ast::version::VersionConstraints;
+use a2x::builder::{
+    NamespaceBuilder, PolicyBuilder, PolicySetBuilder, PrescriptionBuilder, RuleBuilder,
+    TargetBuilder, attr, call, infix, lit, match_fn, match_op, syntax_tree,
+};
+use a2x::context::Context;
+use a2x::xacml::XTopPolicy;
+use a2x::{ParseError, ast_compile};
+use common::{compile_alfa_srcs, xentry_to_str};
+use pretty_assertions::assert_eq;
+use std::rc::Rc;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for building policies without ALFA source.
+
+/// Compile built namespaces with a fresh context.
+fn compile_built(namespaces: Vec<NamespaceBuilder>) -> Result<Vec<XTopPolicy>, ParseError> {
+    let ctx = Rc::new(Context::default());
+    let ns = namespaces
+        .into_iter()
+        .map(|n| n.build(&ctx))
+        .collect::<Result<Vec<_>, _>>()?;
+    ast_compile(&ctx, vec![syntax_tree(&ctx, ns)])
+}
+
+fn xml(ps: &[XTopPolicy]) -> Vec<String> {
+    ps.iter().map(xentry_to_str).collect()
+}
+
+const ATTR_SRC: &str = r#"
+namespace attrs {
+  attribute role {
+    id = "urn:example:role"
+    type = string
+    category = subjectCat
+  }
+  attribute clearance {
+    id = "urn:example:clearance"
+    type = integer
+    category = subjectCat
+  }
+  attribute reason {
+    id = "urn:example:reason"
+    type = string
+    category = environmentCat
+  }
+}"#;
+
+const MAIN_SRC: &str = r#"
+namespace main {
+  import attrs.*
+  obligation audit = "urn:example:audit"
+  advice notice = "urn:example:notice"
+
+  rule denyAll {
+    deny
+  }
+
+  /* Documents */
+  policyset documents = "urn:example:documents" {
+    version "1.2"
+    apply denyOverrides
+    policy read {
+      target clause actionId == "read"
+             clause stringStartsWith("/docs", resourceId) or role == "admin"
+      condition integerOneAndOnly(clearance) >= 2
+      apply firstApplicable with (mode = "strict")
+      // Readers
+      rule readers with (weight = 2) {
+        permit
+        condition stringIsIn("reader", role)
+        on permit {
+          obligation audit {
+            subjectId = subjectId
+            reason = "read"
+          }
+        }
+      }
+      denyAll
+    }
+    policy {
+      apply firstApplicable
+      rule {
+        deny
+        on deny {
+          advice notice {}
+        }
+      }
+    }
+    legacy(earliest = "2.0")
+  }
+
+  policy legacy = "urn:example:legacy" {
+    version "2.1"
+    apply firstApplicable
+    rule {
+      permit
+    }
+  }
+}"#;
+
+fn attr_ns() -> NamespaceBuilder {
+    NamespaceBuilder::new("attrs")
+        .attribute("role", "urn:example:role", "string", "subjectCat")
+        .attribute(
+            "clearance",
+            "urn:example:clearance",
+            "integer",
+            "subjectCat",
+        )
+        .attribute("reason", "urn:example:reason", "string", "environmentCat")
+}
+
+fn main_ns() -> NamespaceBuilder {
+    NamespaceBuilder::new("main")
+        .import("attrs.*")
+        .obligation("audit", "urn:example:audit")
+        .advice("notice", "urn:example:notice")
+        .rule(RuleBuilder::deny().name("denyAll"))
+        .policyset(
+            PolicySetBuilder::new("documents", "denyOverrides")
+                .id("urn:example:documents")
+                .description("Documents")
+                .version("1.2")
+                .policy(
+                    PolicyBuilder::new("read", "firstApplicable")
+                        .target(
+                            TargetBuilder::new()
+                                .clause(vec![match_op("actionId", "==", "read")])
+                                .clause_any(vec![
+                                    vec![match_fn("stringStartsWith", "/docs", "resourceId")],
+                                    vec![match_op("role", "==", "admin")],
+                                ]),
+                        )
+                        .condition(infix(
+                            call("integerOneAndOnly", vec![attr("clearance")]),
+                            ">=",
+                            lit(2),
+                        ))
+                        .apply_param("mode", "strict")
+                        .rule(
+                            RuleBuilder::permit()
+                                .name("readers")
+                                .description("Readers")
+                                .with_param("weight", 2)
+                                .condition(call("stringIsIn", vec![lit("reader"), attr("role")]))
+                                .on_permit(
+                                    PrescriptionBuilder::obligation("audit")
+                                        .attribute("subjectId", "subjectId")
+                                        .value("reason", "read"),
+                                ),
+                        )
+                        .rule_ref("denyAll"),
+                )
+                .policy(
+                    PolicyBuilder::anonymous("firstApplicable")
+                        .rule(RuleBuilder::deny().on_deny(PrescriptionBuilder::advice("notice"))),
+                )
+                .policy_ref_versions(
+                    "legacy",
+                    VersionConstraints {
+                        earliest: Some("2.0".to_owned()),
+                        ..Default::default()
+                    },
+                ),
+        )
+        .policy(
+            PolicyBuilder::new("legacy", "firstApplicable")
+                .id("urn:example:legacy")
+                .version("2.1")
+                .rule(RuleBuilder::permit()),
+        )
+}
+
+/// Built policies compile to the same XACML as the equivalent ALFA.
+#[test]
+fn same_as_alfa() {
+    let from_alfa = compile_alfa_srcs(vec![ATTR_SRC.to_owned(), MAIN_SRC.to_owned()]);
+    let built = unwrap!(compile_built(vec![attr_ns(), main_ns()]), "build failed");
+    assert_eq!(xml(&from_alfa), xml(&built));
+}
+
+/// Child namespace names are relative to the parent.
+#[test]
+fn nested_namespace() {
+    let built = unwrap!(
+        compile_built(vec![
+            NamespaceBuilder::new("a")
+                .namespace(NamespaceBuilder::new("b.c").policy(
+                    PolicyBuilder::new("p", "firstApplicable").rule(RuleBuilder::permit())
+                ),)
+        ]),
+        "build failed"
+    );
+    assert_eq!(built[0].filename().map(String::as_str), Some("a.b.c.p.xml"));
+}
+
+/// Names are resolved when compiling, as for ALFA source.
+#[test]
+fn unresolved_reference() {
+    let r =
+        compile_built(vec![NamespaceBuilder::new("main").policy(
+            PolicyBuilder::new("p", "firstApplicable").rule_ref("missing"),
+        )]);
+    assert!(matches!(r, Err(ParseError::SrcError(_))), "{r:?}");
+}
+
+/// Declaring a name twice is an error when building.
+#[test]
+fn duplicate_symbol() {
+    let ctx = Rc::new(Context::default());
+    let r = NamespaceBuilder::new("main")
+        .rule(RuleBuilder::permit().name("r"))
+        .rule(RuleBuilder::deny().name("r"))
+        .build(&ctx);
+    assert!(r.is_err());
+}
+
+/// Invalid statements are reported when building.
+#[test]
+fn invalid_statements() {
+    let ctx = Rc::new(Context::default());
+    let build = |p: PolicyBuilder| NamespaceBuilder::new("main").policy(p).build(&ctx);
+    assert!(matches!(
+        build(PolicyBuilder::new("a", "firstApplicable").version("1.x")),
+        Err(ParseError::InvalidVersion(_))
+    ));
+    assert!(matches!(
+        build(PolicyBuilder::anonymous("firstApplicable").id("urn:example:b")),
+        Err(ParseError::BuildError(_))
+    ));
+    assert!(matches!(
+        build(
+            PolicyBuilder::new("c", "firstApplicable")
+                .apply_param("w", 1)
+                .apply_param("w", 2)
+        ),
+        Err(ParseError::BuildError(_))
+    ));
+    // parameters for a parent combining algorithm need a parent
+    assert!(matches!(
+        build(PolicyBuilder::new("d", "firstApplicable").with_param("w", 1)),
+        Err(ParseError::BuildError(_))
+    ));
+}
@@ -0,0 +1,182 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::context::Context;
+use a2x::xacml::bundle::{bundle, BundleRoot, BUNDLE_FILENAME};
+use a2x::xacml::xpolicyentry::XPolicyEntry;
+use a2x::xacml::XTopPolicy;
+use a2x::{alfa_compile, AlfaFile, ParseError};
+use common::xentry_to_str;
+use pretty_assertions::assert_eq;
+use std::rc::Rc;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for single-document bundles.
+
+/// Compile a single ALFA source text, and bundle the result.
+fn bundle_src(src: &str, root: &BundleRoot) -> Result<XTopPolicy, ParseError> {
+    let ctx = Rc::new(Context::default());
+    let sources = vec![AlfaFile {
+        filename: "main.alfa".to_owned(),
+        contents: src.to_owned(),
+    }];
+    let x = unwrap!(alfa_compile(&ctx, sources), "compile failed");
+    bundle(&ctx, x, root)
+}
+
+const SRC: &str = r#"
+namespace main {
+  extern policy legacy = "urn:example:legacy"
+  policyset top = "top" {
+    apply firstApplicable
+    a with (weight = 2)
+    inner
+    legacy
+  }
+  policyset inner = "inner" {
+    apply denyOverrides
+    a
+  }
+  policy a = "a" {
+    apply firstApplicable
+    rule { permit }
+  }
+}"#;
+
+/// References are replaced with the referenced definitions, once;
+/// later references to the same policy are kept.
+#[test]
+fn bundle_policyset() {
+    let x = unwrap!(
+        bundle_src(SRC, &BundleRoot::PolicySet("main.top".to_owned())),
+        "bundle failed"
+    );
+    let xacml = xentry_to_str(&x);
+    assert_eq!(
+        xacml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<xacml3:PolicySet xmlns:xacml3="urn:oasis:names:tc:xacml:3.0:core:schema:wd-17" PolicySetId="top" PolicyCombiningAlgId="urn:oasis:names:tc:xacml:1.0:policy-combining-algorithm:first-applicable" Version="1.0">
+  <xacml3:Target />
+  <xacml3:PolicyCombinerParameters PolicyIdRef="a">
+    <xacml3:CombinerParameter ParameterName="weight">
+      <xacml3:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#integer">2</xacml3:AttributeValue>
+    </xacml3:CombinerParameter>
+  </xacml3:PolicyCombinerParameters>
+  <xacml3:Policy PolicyId="a" RuleCombiningAlgId="urn:oasis:names:tc:xacml:1.0:rule-combining-algorithm:first-applicable" Version="1.0">
+    <xacml3:Target />
+    <xacml3:Rule Effect="Permit" RuleId="https://sr.ht/~gheartsfield/a2x/alfa/ident/main/a#rule_0">
+      <xacml3:Target />
+    </xacml3:Rule>
+  </xacml3:Policy>
+  <xacml3:PolicySet PolicySetId="inner" PolicyCombiningAlgId="urn:oasis:names:tc:xacml:3.0:policy-combining-algorithm:deny-overrides" Version="1.0">
+    <xacml3:Target />
+    <xacml3:PolicyIdReference>a</xacml3:PolicyIdReference>
+  </xacml3:PolicySet>
+  <xacml3:PolicyIdReference>urn:example:legacy</xacml3:PolicyIdReference>
+</xacml3:PolicySet>"#
+    );
+}
+
+/// All top-level policies are wrapped in a new root.
+#[test]
+fn bundle_synthetic() {
+    let x = unwrap!(
+        bundle_src(
+            SRC,
+            &BundleRoot::Synthetic {
+                id: "urn:example:bundle".to_owned(),
+                combining_alg: "denyOverrides".to_owned(),
+            },
+        ),
+        "bundle failed"
+    );
+    let XTopPolicy::PolicySet(ps) = x else {
+        panic!("expected a policyset");
+    };
+    assert_eq!(ps.id, "urn:example:bundle");
+    assert_eq!(ps.filename.as_deref(), Some(BUNDLE_FILENAME));
+    assert_eq!(
+        ps.combining_alg,
+        "urn:oasis:names:tc:xacml:3.0:policy-combining-algorithm:deny-overrides"
+    );
+    // inner and a are inlined within top, so they are not also
+    // children of the root.
+    let ids: Vec<&str> = ps
+        .children
+        .iter()
+        .map(|c| match c {
+            XPolicyEntry::Policy(p) => p.id.as_str(),
+            XPolicyEntry::PolicySet(p) => p.id.as_str(),
+            _ => panic!("unexpected reference"),
+        })
+        .collect();
+    assert_eq!(ids, vec!["top"]);
+    let xacml = xentry_to_str(&XTopPolicy::PolicySet(ps));
+    assert_eq!(xacml.matches("PolicySetId=\"inner\"").count(), 1);
+    assert_eq!(xacml.matches("PolicyId=\"a\"").count(), 1);
+}
+
+/// References resolve to the version of a policy they accept.
+#[test]
+fn bundle_versions() {
+    let src = |version: &str| {
+        format!(
+            r#"
+namespace main {{
+  policyset top = "top" {{
+    apply firstApplicable
+    a(version = "{version}")
+  }}
+  policy a = "a" {{
+    version "2.1"
+    apply firstApplicable
+    rule {{ permit }}
+  }}
+}}"#
+        )
+    };
+    let root = BundleRoot::PolicySet("main.top".to_owned());
+    let x = unwrap!(bundle_src(&src("2.*"), &root), "bundle failed");
+    let XTopPolicy::PolicySet(ps) = x else {
+        panic!("expected a policyset");
+    };
+    assert!(matches!(&ps.children[..], [XPolicyEntry::Policy(p)] if p.id == "a"));
+    // no definition of `a` has version 1.x, so the reference stays.
+    let x = unwrap!(bundle_src(&src("1.*"), &root), "bundle failed");
+    let XTopPolicy::PolicySet(ps) = x else {
+        panic!("expected a policyset");
+    };
+    assert!(matches!(&ps.children[..], [XPolicyEntry::PolicyIdRef(r)] if r.id == "a"));
+}
+
+/// Cyclic references cannot be inlined.
+#[test]
+fn bundle_cycle() {
+    let r = bundle_src(
+        r"
+namespace foo {
+  policyset one = 'one' {
+    apply firstApplicable
+    two
+  }
+  policyset two = 'two' {
+    apply firstApplicable
+    one
+  }
+}",
+        &BundleRoot::PolicySet("foo.one".to_owned()),
+    );
+    assert!(
+        matches!(&r, Err(ParseError::ReferenceCycle(c)) if c == "one -> two -> one"),
+        "{:?}",
+        r.err()
+    );
+}
+
+/// The root must be an ALFA policyset.
+#[test]
+fn bundle_missing_root() {
+    let r = bundle_src(SRC, &BundleRoot::PolicySet("main.nothing".to_owned()));
+    assert!(r.is_err());
+}
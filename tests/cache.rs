@@ -0,0 +1,214 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+#![cfg(feature = "cache")]
+
+use a2x::cache::{BuildCache, CACHE_FILENAME, CacheStats};
+use a2x::context::{Config, Context};
+use a2x::errors::ParseError;
+use a2x::xacml::XTopPolicy;
+use a2x::{AlfaFile, alfa_compile};
+use common::temp_dir;
+use pretty_assertions::assert_eq;
+use std::path::Path;
+use std::rc::Rc;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for incremental compilation: results must be
+// identical to a clean build, while reusing unaffected sources.
+
+fn sources(srcs: &[(&str, &str)]) -> Vec<AlfaFile> {
+    srcs.iter()
+        .map(|(f, c)| AlfaFile {
+            filename: (*f).to_owned(),
+            contents: (*c).to_owned(),
+        })
+        .collect()
+}
+
+fn clean_build(srcs: &[(&str, &str)]) -> Result<Vec<XTopPolicy>, ParseError> {
+    alfa_compile(&Rc::new(Context::default()), sources(srcs))
+}
+
+/// Compile with the cache in `dir`, check the result against a clean
+/// build, and return how the sources were handled.
+fn cached_build(dir: &Path, srcs: &[(&str, &str)]) -> CacheStats {
+    let mut cache = BuildCache::open(dir, Config::default());
+    let policies = unwrap!(cache.compile(sources(srcs)));
+    assert_eq!(policies, unwrap!(clean_build(srcs)));
+    unwrap!(cache.write());
+    cache.stats().clone()
+}
+
+fn names(n: &[&str]) -> Vec<String> {
+    n.iter().map(|s| (*s).to_owned()).collect()
+}
+
+const ATTRS: &str = r#"namespace attrs {
+  attribute level { category = resourceCat id = "urn:example:level" type = integer }
+  attribute role { category = subjectCat id = "urn:example:role" type = string }
+}"#;
+
+const RULES: &str = r"namespace shared {
+  import attrs.*
+  rule common { permit condition integerOneAndOnly(level) > 3 }
+  policy { apply firstApplicable rule { deny } }
+}";
+
+const TEMPLATES: &str = r"namespace tmpl {
+  template policy allow(r) {
+    target clause attrs.role == param[r]
+    apply firstApplicable
+    rule { permit }
+  }
+}";
+
+const MAIN: &str = r#"namespace main {
+  import shared.*
+  policy usesRef { apply firstApplicable common rule { deny } }
+  instance admins = tmpl.allow(r = "admin")
+  policyset top { apply firstApplicable usesRef admins policy { apply firstApplicable rule { permit } } }
+}"#;
+
+const LAST: &str = r"namespace shared {
+  policy { apply firstApplicable rule { permit } rule { deny } }
+}";
+
+/// A second compilation of unchanged sources reuses all of them.
+#[test]
+fn unchanged_sources_reused() {
+    let dir = temp_dir("cache", "unchanged");
+    let srcs = [
+        ("attrs.alfa", ATTRS),
+        ("main.alfa", MAIN),
+        ("rules.alfa", RULES),
+        ("tmpl.alfa", TEMPLATES),
+        ("zz.alfa", LAST),
+    ];
+    let first = cached_build(&dir, &srcs);
+    assert_eq!(first.converted.len(), 5);
+    assert!(dir.join(CACHE_FILENAME).exists());
+    let second = cached_build(&dir, &srcs);
+    assert_eq!(second.converted, Vec::<String>::new());
+    assert_eq!(second.reused.len(), 5);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Changing an attribute converts the sources whose XACML uses it,
+/// through a template instance or a rule reference, and nothing
+/// else.
+#[test]
+fn changed_attribute_converts_dependents() {
+    let dir = temp_dir("cache", "attribute");
+    let mut srcs = vec![
+        ("attrs.alfa", ATTRS),
+        ("main.alfa", MAIN),
+        (
+            "other.alfa",
+            "namespace other { policy p { apply firstApplicable rule { deny } } }",
+        ),
+        ("rules.alfa", RULES),
+        ("tmpl.alfa", TEMPLATES),
+    ];
+    cached_build(&dir, &srcs);
+    // `role` is used through the template instance in main
+    let changed = ATTRS.replace("urn:example:role", "urn:example:role2");
+    srcs[0].1 = &changed;
+    let stats = cached_build(&dir, &srcs);
+    assert_eq!(stats.converted, names(&["attrs.alfa", "main.alfa"]));
+    assert_eq!(stats.reused, names(&["other.alfa"]));
+    // `level` is used by a rule in rules.alfa, which main references
+    let changed = changed.replace("urn:example:level", "urn:example:level2");
+    srcs[0].1 = &changed;
+    let stats = cached_build(&dir, &srcs);
+    assert_eq!(stats.converted, names(&["attrs.alfa", "main.alfa"]));
+    assert_eq!(stats.parsed, names(&["rules.alfa", "tmpl.alfa"]));
+    assert_eq!(stats.reused, names(&["other.alfa"]));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Anonymous policies added to an earlier source shift the IDs of
+/// later sources in the same namespace, which are converted again.
+#[test]
+fn shifted_counters_keep_ids() {
+    let dir = temp_dir("cache", "counters");
+    let mut srcs = vec![
+        ("attrs.alfa", ATTRS),
+        ("main.alfa", MAIN),
+        ("rules.alfa", RULES),
+        ("tmpl.alfa", TEMPLATES),
+        ("zz.alfa", LAST),
+    ];
+    cached_build(&dir, &srcs);
+    let changed = RULES.replace(
+        "policy { apply firstApplicable rule { deny } }",
+        "policy { apply firstApplicable rule { deny } }\n  policy { apply firstApplicable rule { deny } }",
+    );
+    srcs[2].1 = &changed;
+    let stats = cached_build(&dir, &srcs);
+    assert!(stats.converted.contains(&"zz.alfa".to_owned()));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Removing the last source leaves the others untouched, and a
+/// source added later that shadows a referenced name is noticed.
+#[test]
+fn added_and_removed_sources() {
+    let dir = temp_dir("cache", "added");
+    let mut srcs = vec![
+        ("attrs.alfa", ATTRS),
+        ("main.alfa", MAIN),
+        ("rules.alfa", RULES),
+        ("tmpl.alfa", TEMPLATES),
+        ("zz.alfa", LAST),
+    ];
+    cached_build(&dir, &srcs);
+    srcs.pop();
+    let stats = cached_build(&dir, &srcs);
+    assert_eq!(stats.reused.len(), 4);
+    // a rule named `common` in `main` takes precedence over the import
+    srcs.push(("b.alfa", "namespace main { rule common { deny } }"));
+    let stats = cached_build(&dir, &srcs);
+    assert!(stats.converted.contains(&"main.alfa".to_owned()));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Errors are the same as in a clean build, and a failed compilation
+/// leaves the cache usable.
+#[test]
+fn errors_match_clean_build() {
+    let dir = temp_dir("cache", "errors");
+    let mut srcs = vec![("attrs.alfa", ATTRS), ("rules.alfa", RULES)];
+    cached_build(&dir, &srcs);
+    srcs.push(("dup.alfa", "namespace shared { rule common { permit } }"));
+    let mut cache = BuildCache::open(&dir, Config::default());
+    let cached = cache
+        .compile(sources(&srcs))
+        .map(|_| ())
+        .map_err(|e| e.to_string());
+    let clean = clean_build(&srcs).map(|_| ()).map_err(|e| e.to_string());
+    assert!(clean.is_err());
+    assert_eq!(cached, clean);
+    srcs.pop();
+    let stats = cached_build(&dir, &srcs);
+    assert_eq!(stats.reused.len(), 2);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// A cache written with another configuration is not used.
+#[test]
+fn configuration_change_starts_empty() {
+    let dir = temp_dir("cache", "config");
+    let srcs = [("attrs.alfa", ATTRS), ("rules.alfa", RULES)];
+    cached_build(&dir, &srcs);
+    let mut cache = BuildCache::open(
+        &dir,
+        Config {
+            base_namespace: Some("https://example.com/".to_owned()),
+            ..Config::default()
+        },
+    );
+    unwrap!(cache.compile(sources(&srcs)));
+    assert_eq!(cache.stats().converted.len(), 2);
+    let _ = std::fs::remove_dir_all(&dir);
+}
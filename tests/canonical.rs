@@ -0,0 +1,126 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::context::{Context, XacmlVersion};
+use a2x::xacml::canonical::canonicalize;
+use a2x::xacml::style::OutputStyle;
+use a2x::{alfa_compile, serialize_xentries, serialize_xentry_canonical, AlfaFile, XacmlFile};
+use common::compile_alfa_src;
+use pretty_assertions::assert_eq;
+use std::rc::Rc;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for reproducible and canonical output.
+
+fn canonical_str(xml: &str) -> String {
+    let c = unwrap!(canonicalize(xml.as_bytes()), "canonicalize failed");
+    unwrap!(String::from_utf8(c), "invalid utf-8")
+}
+
+fn compile_files(files: &[(&str, &str)]) -> Vec<XacmlFile> {
+    let ctx = Rc::new(Context::default());
+    let sources = files
+        .iter()
+        .map(|(f, c)| AlfaFile {
+            filename: (*f).to_owned(),
+            contents: (*c).to_owned(),
+        })
+        .collect();
+    let x = unwrap!(alfa_compile(&ctx, sources), "compile failed");
+    unwrap!(
+        serialize_xentries(&x, XacmlVersion::V3, &OutputStyle::default()),
+        "serialize failed"
+    )
+}
+
+/// Attributes and namespace declarations are sorted, empty elements
+/// are expanded, and text is escaped.
+#[test]
+fn canonical_form() {
+    assert_eq!(
+        canonical_str(
+            "<?xml version=\"1.0\"?>\n<!-- c --><a:r z=\"1\" xmlns:b=\"urn:b\" b:y=\"&#9;\" \
+             x=\"&gt;\" xmlns:a=\"urn:a\"><a:e b='\"'/><b:e>1 &lt; 2 &amp;&amp; 3 &gt; 2\
+             <![CDATA[<x>]]></b:e></a:r>"
+        ),
+        "<a:r xmlns:a=\"urn:a\" xmlns:b=\"urn:b\" x=\">\" z=\"1\" b:y=\"&#x9;\">\
+         <a:e b=\"&quot;\"></a:e><b:e>1 &lt; 2 &amp;&amp; 3 &gt; 2\
+         &lt;x&gt;</b:e></a:r>"
+    );
+}
+
+/// Canonical form is a fixed point.
+#[test]
+fn canonical_idempotent() {
+    let once = canonical_str("<r b=\"2\" a=\"1\"><c/></r>");
+    assert_eq!(once, "<r a=\"1\" b=\"2\"><c></c></r>");
+    assert_eq!(canonical_str(&once), once);
+}
+
+#[test]
+fn canonical_policy() {
+    let x = compile_alfa_src(
+        r#"
+namespace main {
+  policy p = "urn:example:p" {
+    apply firstApplicable
+    maxDelegationDepth 2
+    rule r { permit }
+  }
+}"#,
+    );
+    let f = unwrap!(serialize_xentry_canonical(&x[0], XacmlVersion::V3, &OutputStyle::default()), "failed");
+    assert_eq!(f.filename.to_str(), Some("main.p.xml"));
+    assert_eq!(
+        unwrap!(String::from_utf8(f.contents), "invalid utf-8"),
+        "<xacml3:Policy xmlns:xacml3=\"urn:oasis:names:tc:xacml:3.0:core:schema:wd-17\" \
+         MaxDelegationDepth=\"2\" PolicyId=\"urn:example:p\" \
+         RuleCombiningAlgId=\"urn:oasis:names:tc:xacml:1.0:rule-combining-algorithm:first-applicable\" \
+         Version=\"1.0\"><xacml3:Target></xacml3:Target>\
+         <xacml3:Rule Effect=\"Permit\" \
+         RuleId=\"https://sr.ht/~gheartsfield/a2x/alfa/ident/main/p/r\">\
+         <xacml3:Target></xacml3:Target></xacml3:Rule></xacml3:Policy>"
+    );
+}
+
+/// Output does not depend on the order sources are given in.
+#[test]
+fn source_order_independent() {
+    let a = (
+        "a.alfa",
+        r"
+namespace a {
+  policy {
+    apply firstApplicable
+    rule { permit }
+  }
+}",
+    );
+    let b = (
+        "b.alfa",
+        r"
+namespace a {
+  policy {
+    apply firstApplicable
+    rule { deny }
+  }
+}",
+    );
+    let ab = compile_files(&[a, b]);
+    let ba = compile_files(&[b, a]);
+    assert_eq!(ab.len(), 2);
+    for (x, y) in ab.iter().zip(ba.iter()) {
+        assert_eq!(x.filename, y.filename);
+        assert_eq!(x.contents, y.contents);
+    }
+}
+
+/// Namespaces are declared where they are first used.
+#[test]
+fn canonical_namespaces() {
+    assert_eq!(
+        canonical_str("<a:r xmlns:a=\"urn:a\" xmlns:b=\"urn:b\"><b:e><b:f/></b:e></a:r>"),
+        "<a:r xmlns:a=\"urn:a\"><b:e xmlns:b=\"urn:b\"><b:f></b:f></b:e></a:r>"
+    );
+}
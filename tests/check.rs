@@ -0,0 +1,109 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::check::check_output;
+use a2x::context::XacmlVersion;
+use a2x::manifest::{Manifest, ManifestEntry};
+use a2x::xacml::style::OutputStyle;
+use a2x::{serialize_xentries, write_xacml_file, XacmlFile};
+use common::{compile_alfa_src, temp_dir};
+use pretty_assertions::assert_eq;
+use std::path::{Path, PathBuf};
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for comparing generated XACML with an output
+// directory.
+
+const SRC: &str = r#"
+namespace main {
+  policy a = "urn:a" { apply firstApplicable rule { permit } }
+  policy b = "urn:b" { apply firstApplicable rule { deny } }
+}"#;
+
+fn files(src: &str) -> Vec<XacmlFile> {
+    let x = compile_alfa_src(src);
+    unwrap!(serialize_xentries(&x, XacmlVersion::V3, &OutputStyle::default()))
+}
+
+/// Write the XACML for `src` into a directory, with its manifest.
+fn generate(dir: &Path, src: &str) {
+    let x = compile_alfa_src(src);
+    let fs = unwrap!(serialize_xentries(&x, XacmlVersion::V3, &OutputStyle::default()));
+    let mut m = Manifest::default();
+    for (f, xp) in fs.iter().zip(&x) {
+        unwrap!(write_xacml_file(dir, f));
+        m.entries.push(ManifestEntry::new(f, xp));
+    }
+    unwrap!(m.write(dir));
+}
+
+/// A directory written from the same ALFA is clean.
+#[test]
+fn up_to_date() {
+    let dir = temp_dir("check", "clean");
+    let fs = files(SRC);
+    for f in &fs {
+        unwrap!(write_xacml_file(&dir, f));
+    }
+    let report = unwrap!(check_output(&dir, &fs));
+    assert!(report.is_clean(), "{report:?}");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Changed files have a unified diff; added and removed files are
+/// listed.  Only files in the manifest can be removed, so XACML that
+/// a2x did not generate is not reported.  Nothing is written.
+#[test]
+fn differences() {
+    let dir = temp_dir("check", "diff");
+    generate(&dir, SRC);
+    std::fs::write(dir.join("notes.txt"), "not xml").unwrap();
+    std::fs::write(dir.join("legacy.xml"), "<xacml3:Policy />").unwrap();
+    let fs = files(
+        r#"
+namespace main {
+  policy a = "urn:a" { apply firstApplicable rule { deny } }
+  policy c = "urn:c" { apply firstApplicable rule { deny } }
+}"#,
+    );
+    let before = std::fs::read(dir.join("main.a.xml")).unwrap();
+    let report = unwrap!(check_output(&dir, &fs));
+    assert!(!report.is_clean());
+    assert_eq!(report.added, vec![PathBuf::from("main.c.xml")]);
+    assert_eq!(report.removed, vec![PathBuf::from("main.b.xml")]);
+    assert_eq!(report.changed.len(), 1);
+    let (path, diff) = &report.changed[0];
+    assert_eq!(path, &PathBuf::from("main.a.xml"));
+    assert!(diff.starts_with("--- a/main.a.xml\n+++ b/main.a.xml\n@@ "), "{diff}");
+    assert!(diff.contains("\n-  <xacml3:Rule Effect=\"Permit\""), "{diff}");
+    assert!(diff.contains("\n+  <xacml3:Rule Effect=\"Deny\""), "{diff}");
+    assert_eq!(std::fs::read(dir.join("main.a.xml")).unwrap(), before);
+    assert!(!dir.join("main.c.xml").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Without a manifest, no files are reported as removed.
+#[test]
+fn without_manifest() {
+    let dir = temp_dir("check", "unmanaged");
+    for f in &files(SRC) {
+        unwrap!(write_xacml_file(&dir, f));
+    }
+    let report = unwrap!(check_output(&dir, &files(&SRC.replace("policy b", "policy c"))));
+    assert_eq!(report.added, vec![PathBuf::from("main.c.xml")]);
+    assert!(report.removed.is_empty(), "{report:?}");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// A missing output directory means every file would be added.
+#[test]
+fn missing_directory() {
+    let dir = temp_dir("check", "missing");
+    let report = unwrap!(check_output(&dir, &files(SRC)));
+    assert_eq!(
+        report.added,
+        vec![PathBuf::from("main.a.xml"), PathBuf::from("main.b.xml")]
+    );
+    assert!(report.changed.is_empty() && report.removed.is_empty());
+}
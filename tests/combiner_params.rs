@@ -0,0 +1,137 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::xacml::XTopPolicy;
+use common::{compile_alfa_src, get_nth_policyset, xentry_to_str};
+use pretty_assertions::assert_eq;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for combiner parameters on combining algorithms,
+// rules, policies, and policysets.
+
+/// Parameters on a rule-combining algorithm and on child rules.
+#[test]
+fn rule_combiner_params() {
+    let x = compile_alfa_src(
+        r#"
+namespace main {
+  rule shared { deny }
+  policy p = "p" {
+    apply firstApplicable with (threshold = 5, mode = "strict")
+    rule a with (weight = 2) { permit }
+    shared with (weight = 1.5)
+  }
+}"#,
+    );
+    let xp = unwrap!(x.first(), "at least one policy");
+    let xacml = xentry_to_str(xp);
+    assert_eq!(
+        xacml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<xacml3:Policy xmlns:xacml3="urn:oasis:names:tc:xacml:3.0:core:schema:wd-17" PolicyId="p" RuleCombiningAlgId="urn:oasis:names:tc:xacml:1.0:rule-combining-algorithm:first-applicable" Version="1.0">
+  <xacml3:Target />
+  <xacml3:CombinerParameters>
+    <xacml3:CombinerParameter ParameterName="threshold">
+      <xacml3:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#integer">5</xacml3:AttributeValue>
+    </xacml3:CombinerParameter>
+    <xacml3:CombinerParameter ParameterName="mode">
+      <xacml3:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">strict</xacml3:AttributeValue>
+    </xacml3:CombinerParameter>
+  </xacml3:CombinerParameters>
+  <xacml3:RuleCombinerParameters RuleIdRef="https://sr.ht/~gheartsfield/a2x/alfa/ident/main/p/a">
+    <xacml3:CombinerParameter ParameterName="weight">
+      <xacml3:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#integer">2</xacml3:AttributeValue>
+    </xacml3:CombinerParameter>
+  </xacml3:RuleCombinerParameters>
+  <xacml3:Rule Effect="Permit" RuleId="https://sr.ht/~gheartsfield/a2x/alfa/ident/main/p/a">
+    <xacml3:Target />
+  </xacml3:Rule>
+  <xacml3:RuleCombinerParameters RuleIdRef="https://sr.ht/~gheartsfield/a2x/alfa/ident/main/shared#rule_0">
+    <xacml3:CombinerParameter ParameterName="weight">
+      <xacml3:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#double">1.5</xacml3:AttributeValue>
+    </xacml3:CombinerParameter>
+  </xacml3:RuleCombinerParameters>
+  <xacml3:Rule Effect="Deny" RuleId="https://sr.ht/~gheartsfield/a2x/alfa/ident/main/shared#rule_0">
+    <xacml3:Target />
+  </xacml3:Rule>
+</xacml3:Policy>"#
+    );
+}
+
+/// Parameters on policy and policyset children of a policyset.
+#[test]
+fn policy_combiner_params() {
+    let x = compile_alfa_src(
+        r#"
+namespace main {
+  constant heavy : integer = 10
+  policyset top = "top" {
+    apply firstApplicable with (quorum = true)
+    policy inner = "inner" with (weight = constant[heavy]) {
+      apply firstApplicable
+      rule { permit }
+    }
+    other with (weight = 1)
+  }
+  policyset other = "other" {
+    apply firstApplicable
+    policy {
+      apply firstApplicable
+      rule { deny }
+    }
+  }
+}"#,
+    );
+    let ps = get_nth_policyset(0, x);
+    assert_eq!(ps.combiner_params.len(), 1);
+    assert_eq!(ps.combiner_params[0].name, "quorum");
+    assert_eq!(ps.children.len(), 2);
+    let xacml = xentry_to_str(&XTopPolicy::PolicySet(ps));
+    assert!(xacml.contains(
+        r#"<xacml3:PolicyCombinerParameters PolicyIdRef="inner">
+    <xacml3:CombinerParameter ParameterName="weight">
+      <xacml3:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#integer">10</xacml3:AttributeValue>
+    </xacml3:CombinerParameter>
+  </xacml3:PolicyCombinerParameters>
+  <xacml3:Policy"#
+    ));
+    assert!(xacml.contains(
+        r#"<xacml3:PolicySetCombinerParameters PolicySetIdRef="other">
+    <xacml3:CombinerParameter ParameterName="weight">
+      <xacml3:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#integer">1</xacml3:AttributeValue>
+    </xacml3:CombinerParameter>
+  </xacml3:PolicySetCombinerParameters>
+  <xacml3:PolicySetIdReference>other</xacml3:PolicySetIdReference>"#
+    ));
+}
+
+/// Top-level elements have no parent to receive parameters.
+#[test]
+#[should_panic(expected = "compile failed")]
+fn top_level_params() {
+    compile_alfa_src(
+        r"
+namespace main {
+  policy p with (weight = 1) {
+    apply firstApplicable
+    rule { permit }
+  }
+}",
+    );
+}
+
+/// Parameter names cannot be repeated.
+#[test]
+#[should_panic(expected = "compile failed")]
+fn duplicate_params() {
+    compile_alfa_src(
+        r"
+namespace main {
+  policy p {
+    apply firstApplicable with (weight = 1, weight = 2)
+    rule { permit }
+  }
+}",
+    );
+}
@@ -1,15 +1,21 @@
+use a2x::AlfaFile;
 use a2x::alfa_compile;
+use a2x::ast::{AlfaSyntaxTree, AsAlfa};
 use a2x::context::Context;
+use a2x::make_alfa_ast;
+use a2x::xacml::style::OutputStyle;
 use a2x::xacml::xpolicy::XPolicy;
 use a2x::xacml::xpolicyset::XPolicySet;
 use a2x::xacml::{XTopPolicy, XacmlWriter};
-use a2x::AlfaFile;
+use miette::NamedSource;
+use std::path::PathBuf;
 use std::rc::Rc;
 use unwrap::unwrap;
 use xml::EmitterConfig;
 
 /// Compile a single ALFA source text.
 /// Panics on compilation failure.
+#[allow(dead_code)]
 pub fn compile_alfa_src(src: &str) -> Vec<XTopPolicy> {
     // default context.
     let ctx = Rc::new(Context::default());
@@ -18,7 +24,9 @@ pub fn compile_alfa_src(src: &str) -> Vec<XTopPolicy> {
         filename: "".to_owned(),
         contents: src.to_owned(),
     }];
-    unwrap!(alfa_compile(&ctx, sources), "compile failed")
+    let policies = unwrap!(alfa_compile(&ctx, sources), "compile failed");
+    assert_alfa_round_trip(&[("", src)], &policies);
+    policies
 }
 
 /// Compile multiple ALFA source texts from strings
@@ -28,17 +36,25 @@ pub fn compile_alfa_src(src: &str) -> Vec<XTopPolicy> {
 pub fn compile_alfa_srcs(src: Vec<String>) -> Vec<XTopPolicy> {
     let base_name = "alfa_";
     // name the files
-    let alfa_sources = src
-        .into_iter()
-        .enumerate()
-        .map(|(index, s)| AlfaFile {
-            filename: format!("{base_name}{index}"),
-            contents: s,
+    let names: Vec<String> = (0..src.len()).map(|i| format!("{base_name}{i}")).collect();
+    let alfa_sources = names
+        .iter()
+        .zip(&src)
+        .map(|(n, s)| AlfaFile {
+            filename: n.clone(),
+            contents: s.clone(),
         })
         .collect();
     // default context.
     let ctx = Rc::new(Context::default());
-    unwrap!(alfa_compile(&ctx, alfa_sources), "compile failed")
+    let policies = unwrap!(alfa_compile(&ctx, alfa_sources), "compile failed");
+    let named: Vec<(&str, &str)> = names
+        .iter()
+        .map(String::as_str)
+        .zip(src.iter().map(String::as_str))
+        .collect();
+    assert_alfa_round_trip(&named, &policies);
+    policies
 }
 
 /// Get the Nth (0-indexed) Top-Level Policy (or panic)
@@ -71,15 +87,121 @@ pub fn xentry_to_str(p: &XTopPolicy) -> String {
             let mut writer = EmitterConfig::new()
                 .perform_indent(true)
                 .create_writer(&mut target);
-            xps.write_xml(&mut writer).expect("unable to write");
+            xps.write_xml(&mut writer, &OutputStyle::default())
+                .expect("unable to write");
         }
         XTopPolicy::Policy(xp) => {
             let mut writer = EmitterConfig::new()
                 .perform_indent(true)
                 .create_writer(&mut target);
-            xp.write_xml(&mut writer).expect("unable to write");
+            xp.write_xml(&mut writer, &OutputStyle::default())
+                .expect("unable to write");
         }
     }
     // convert to a string
     String::from_utf8(target).expect("can't convert to utf8")
 }
+
+/// Emit each (filename, source) pair as ALFA, and check that the
+/// emitted text parses to the same syntax tree as the original, apart
+/// from source locations and hashes of the source text, and that
+/// emitting it again gives the same text.  Syntax trees can only be
+/// compared with the `serde` feature; without it, the emitted text is
+/// checked to compile to the same XACML as the original.
+///
+/// Panics if the round trip changes anything.
+#[allow(dead_code)]
+pub fn assert_alfa_round_trip(srcs: &[(&str, &str)], expected: &[XTopPolicy]) {
+    let emitted: Vec<String> = srcs.iter().map(|(n, s)| emit_alfa(n, s)).collect();
+    for (e, (n, _)) in emitted.iter().zip(srcs) {
+        assert_eq!(*e, emit_alfa(n, e), "ALFA emission is not stable");
+    }
+    #[cfg(feature = "serde")]
+    for (e, (n, s)) in emitted.iter().zip(srcs) {
+        assert_eq!(
+            ast_shape(n, s),
+            ast_shape(n, e),
+            "emitted ALFA parsed differently:\n{e}"
+        );
+    }
+    if cfg!(feature = "serde") {
+        return;
+    }
+    let files = srcs
+        .iter()
+        .zip(&emitted)
+        .map(|((n, _), e)| AlfaFile {
+            filename: (*n).to_owned(),
+            contents: e.clone(),
+        })
+        .collect();
+    let ctx = Rc::new(Context::default());
+    let policies = match alfa_compile(&ctx, files) {
+        Ok(p) => p,
+        Err(e) => panic!(
+            "emitted ALFA failed to compile ({e:?}):\n{}",
+            emitted.join("\n")
+        ),
+    };
+    let expected: Vec<String> = expected.iter().map(xentry_to_str).collect();
+    let actual: Vec<String> = policies.iter().map(xentry_to_str).collect();
+    assert_eq!(
+        expected,
+        actual,
+        "emitted ALFA compiled differently:\n{}",
+        emitted.join("\n")
+    );
+}
+
+/// Parse a single source and emit it as ALFA.
+#[allow(dead_code)]
+pub fn emit_alfa(filename: &str, src: &str) -> String {
+    parse_alfa(filename, src).to_alfa(0)
+}
+
+/// Parse a single source, with a new context.
+fn parse_alfa(filename: &str, src: &str) -> AlfaSyntaxTree {
+    let named = NamedSource::new(filename, src.to_owned());
+    unwrap!(
+        make_alfa_ast(src, &named, Rc::new(Context::default())),
+        "parse failed"
+    )
+}
+
+/// The syntax tree of a single source as JSON, with every source
+/// location and hash of the source text replaced by `null`.
+#[cfg(feature = "serde")]
+fn ast_shape(filename: &str, src: &str) -> serde_json::Value {
+    fn strip(v: &mut serde_json::Value) {
+        match v {
+            serde_json::Value::Object(m) => {
+                let mut keys: Vec<&str> = m.keys().map(String::as_str).collect();
+                keys.sort_unstable();
+                if keys == ["file", "length", "offset"] {
+                    *v = serde_json::Value::Null;
+                } else {
+                    if let Some(h) = m.get_mut("content_hash") {
+                        *h = serde_json::Value::Null;
+                    }
+                    m.values_mut().for_each(strip);
+                }
+            }
+            serde_json::Value::Array(a) => a.iter_mut().for_each(strip),
+            _ => {}
+        }
+    }
+    let mut json = unwrap!(serde_json::to_value(parse_alfa(filename, src)));
+    strip(&mut json);
+    json
+}
+
+/// Path to a temporary directory for one test, unique to the test
+/// file (`suite`), the test (`name`), and this process.  Anything
+/// left there by an earlier run is removed; the directory itself is
+/// not created.
+#[allow(dead_code)]
+pub fn temp_dir(suite: &str, name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("a2x-{suite}-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
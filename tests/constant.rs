@@ -0,0 +1,204 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::context::Context;
+use a2x::xacml::xtarget::XMatch;
+use a2x::{AlfaFile, alfa_compile};
+use common::{compile_alfa_src, compile_alfa_srcs, get_nth_policy, xentry_to_str};
+use pretty_assertions::assert_eq;
+use std::rc::Rc;
+mod common;
+use unwrap::unwrap;
+
+// Integration tests for named constant declarations, and their use
+// in targets, conditions, and prescriptions.
+
+/// Constants used in a target, condition, and obligation.
+#[test]
+fn constant_everywhere() {
+    let x = compile_alfa_src(
+        r#"
+namespace limits {
+  constant maxAge : integer = 65
+  constant admin : string = "admin"
+  constant launch : date = "2025-07-10"
+}
+namespace main {
+  import limits.*
+  attribute age {
+    id = "urn:example:age"
+    type = integer
+    category = subjectCat
+  }
+  obligation o1 = "urn:example:oblig1"
+  policy p = "p" {
+    apply firstApplicable
+    rule r {
+      target clause subjectId == constant[admin]
+      permit
+      condition age < constant[maxAge]
+      on permit {
+        obligation o1 {
+          subjectId = constant[limits.launch]
+        }
+      }
+    }
+  }
+}"#,
+    );
+    let xp = unwrap!(x.first(), "at least one policy");
+    let xacml = xentry_to_str(xp);
+    assert_eq!(
+        xacml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<xacml3:Policy xmlns:xacml3="urn:oasis:names:tc:xacml:3.0:core:schema:wd-17" PolicyId="p" RuleCombiningAlgId="urn:oasis:names:tc:xacml:1.0:rule-combining-algorithm:first-applicable" Version="1.0">
+  <xacml3:Target />
+  <xacml3:Rule Effect="Permit" RuleId="https://sr.ht/~gheartsfield/a2x/alfa/ident/main/p/r">
+    <xacml3:Target>
+      <xacml3:AnyOf>
+        <xacml3:AllOf>
+          <xacml3:Match MatchId="urn:oasis:names:tc:xacml:1.0:function:string-equal">
+            <xacml3:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">admin</xacml3:AttributeValue>
+            <xacml3:AttributeDesignator AttributeId="urn:oasis:names:tc:xacml:1.0:subject:subject-id" Category="urn:oasis:names:tc:xacml:1.0:subject-category:access-subject" DataType="http://www.w3.org/2001/XMLSchema#string" MustBePresent="false" />
+          </xacml3:Match>
+        </xacml3:AllOf>
+      </xacml3:AnyOf>
+    </xacml3:Target>
+    <xacml3:Condition>
+      <xacml3:Apply FunctionId="urn:oasis:names:tc:xacml:3.0:function:any-of-any">
+        <xacml3:Function FunctionId="urn:oasis:names:tc:xacml:1.0:function:integer-less-than" />
+        <xacml3:AttributeDesignator AttributeId="urn:example:age" Category="urn:oasis:names:tc:xacml:1.0:subject-category:access-subject" DataType="http://www.w3.org/2001/XMLSchema#integer" MustBePresent="false" />
+        <xacml3:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#integer">65</xacml3:AttributeValue>
+      </xacml3:Apply>
+    </xacml3:Condition>
+    <xacml3:ObligationExpressions>
+      <xacml3:ObligationExpression ObligationId="urn:example:oblig1" FulfillOn="Permit">
+        <xacml3:AttributeAssignmentExpression AttributeId="urn:oasis:names:tc:xacml:1.0:subject:subject-id" Category="urn:oasis:names:tc:xacml:1.0:subject-category:access-subject">
+          <xacml3:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#date">2025-07-10</xacml3:AttributeValue>
+        </xacml3:AttributeAssignmentExpression>
+      </xacml3:ObligationExpression>
+    </xacml3:ObligationExpressions>
+  </xacml3:Rule>
+</xacml3:Policy>"#
+    );
+}
+
+/// Constants in a reversed target match, defined in another file.
+#[test]
+fn constant_target_other_file() {
+    let x = compile_alfa_srcs(vec![
+        r#"
+namespace roles {
+  constant admin : string = "admin"
+}"#
+        .to_string(),
+        r"
+namespace main {
+  import roles.admin
+  policy {
+    apply firstApplicable
+    rule {
+      target clause constant[admin] == subjectId
+      permit
+    }
+  }
+}"
+        .to_string(),
+    ]);
+    let p = get_nth_policy(0, x);
+    let mch = &p.rules[0].target.anyofs[0].allofs[0].matches[0];
+    let xm = XMatch {
+        matchid: "urn:oasis:names:tc:xacml:1.0:function:string-equal".to_owned(),
+        value: "admin".to_owned(),
+        value_type: "http://www.w3.org/2001/XMLSchema#string".to_owned(),
+        designator_id: "urn:oasis:names:tc:xacml:1.0:subject:subject-id".to_owned(),
+        designator_category: "urn:oasis:names:tc:xacml:1.0:subject-category:access-subject"
+            .to_owned(),
+        designator_type: "http://www.w3.org/2001/XMLSchema#string".to_owned(),
+        must_be_present: false,
+        issuer: None,
+    };
+    assert_eq!(mch, &xm);
+}
+
+/// Constants are not visible without an import.
+#[test]
+#[should_panic(expected = "compile failed")]
+fn constant_requires_import() {
+    compile_alfa_src(
+        r#"
+namespace roles {
+  constant admin : string = "admin"
+}
+namespace main {
+  policy {
+    apply firstApplicable
+    rule {
+      target clause subjectId == constant[admin]
+      permit
+    }
+  }
+}"#,
+    );
+}
+
+/// Constant values must agree with their declared type.
+#[test]
+#[should_panic(expected = "compile failed")]
+fn constant_type_mismatch() {
+    compile_alfa_src(
+        r"
+namespace main {
+  constant admin : string = 42
+  policy {
+    apply firstApplicable
+    rule {
+      target clause subjectId == constant[admin]
+      permit
+    }
+  }
+}",
+    );
+}
+
+/// String values must be valid for the declared type.
+#[test]
+fn constant_invalid_string() {
+    let ctx = Rc::new(Context::default());
+    let sources = vec![AlfaFile {
+        filename: "main.alfa".to_owned(),
+        contents: r#"
+namespace main {
+  constant x : integer = "abc"
+  attribute age { id = "urn:example:age" type = integer category = subjectCat }
+  policy {
+    apply firstApplicable
+    rule {
+      target clause age == constant[x]
+      permit
+    }
+  }
+}"#
+        .to_owned(),
+    }];
+    let Err(e) = alfa_compile(&ctx, sources) else {
+        panic!("expected compilation to fail");
+    };
+    let e = format!("{:?}", miette::Report::new(e));
+    assert!(e.contains("Constant values must match the declared type"), "{e}");
+    assert!(e.contains(r#""abc" is not a valid integer"#), "{e}");
+    assert!(e.contains("main.alfa:3:3"), "{e}");
+}
+
+/// Constants cannot be defined twice in a namespace.
+#[test]
+#[should_panic(expected = "compile failed")]
+fn constant_duplicate() {
+    compile_alfa_src(
+        r#"
+namespace main {
+  constant admin : string = "admin"
+  constant admin : string = "root"
+}"#,
+    );
+}
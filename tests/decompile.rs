@@ -0,0 +1,273 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::context::XacmlVersion;
+use a2x::decompile::decompile;
+use a2x::xacml::reader::read_xacml;
+use a2x::xacml::style::OutputStyle;
+use a2x::xacml::XTopPolicy;
+use a2x::{serialize_xentries, ParseError};
+use common::compile_alfa_src;
+use pretty_assertions::assert_eq;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for decompiling XACML into ALFA.
+
+const SRC: &str = r#"
+namespace main {
+  attribute clearance {
+    id = "urn:example:attr:clearance"
+    type = integer
+    category = subjectCat
+  }
+  attribute owner {
+    id = "urn:example:attr:owner"
+    type = string
+    category = resourceCat
+  }
+  obligation audit = "urn:example:oblig:audit"
+  advice notice = "urn:example:advice:notice"
+  function isWeekday = "urn:example:fn:is-weekday" : anyAtomicOrBag* -> boolean
+  extern policy legacy = "urn:example:legacy"
+
+  /* Documents */
+  policy documents = "urn:example:documents" {
+    version "2.1"
+    maxDelegationDepth 3
+    target clause actionId == "read" or actionId == "list"
+           clause 3 <= clearance
+    condition (subjectId == owner) && isWeekday(currentDate)
+    apply denyOverrides with (w = 2)
+    rule readable {
+      permit
+      target clause owner == "alice"
+      condition (integerOneAndOnly(clearance) + 1) > 3
+      on permit {
+        obligation audit {
+          subjectId = subjectId
+          owner = "a \"quoted\" path\u{5c}"
+        }
+      }
+      on deny {
+        advice notice {}
+      }
+    }
+    rule denyRest {
+      deny
+    }
+  }
+
+  policyset all = "urn:example:all" {
+    issuer {
+      subjectId = "admin"
+    }
+    apply firstApplicable
+    documents(version = "2.*") with (w = 1)
+    legacy(latest = "3.0")
+    policy inner = "urn:example:inner" {
+      condition subjectId == "bob"
+      apply firstApplicable
+      rule {
+        permit
+      }
+    }
+  }
+
+  policyset guarded = "urn:example:guarded" {
+    condition clearance > 1
+    apply denyOverrides
+    policy locked = "urn:example:locked" {
+      apply firstApplicable
+      rule {
+        deny
+      }
+    }
+  }
+}"#;
+
+/// Serialize policies, and read them back from XML.
+fn reread(x: &[XTopPolicy]) -> Vec<XTopPolicy> {
+    let files = unwrap!(serialize_xentries(x, XacmlVersion::V3, &OutputStyle::default()));
+    files
+        .iter()
+        .map(|f| unwrap!(read_xacml(&f.contents), "read failed"))
+        .collect()
+}
+
+/// XML for policies, without rule identifiers, which are not always
+/// preserved by decompiling.
+fn xml_without_rule_ids(x: &[XTopPolicy]) -> Vec<String> {
+    let files = unwrap!(serialize_xentries(x, XacmlVersion::V3, &OutputStyle::default()));
+    files
+        .iter()
+        .map(|f| {
+            let xml = String::from_utf8(f.contents.clone()).unwrap();
+            xml.split(" RuleId=\"")
+                .enumerate()
+                .map(|(i, s)| if i == 0 { s } else { &s[s.find('"').unwrap() + 1..] })
+                .collect()
+        })
+        .collect()
+}
+
+/// Decompiled ALFA compiles into the same XACML, and decompiles into
+/// the same ALFA again.
+#[test]
+fn round_trip() {
+    let original = compile_alfa_src(SRC);
+    let alfa = unwrap!(decompile(&reread(&original), "main"));
+    let recompiled = compile_alfa_src(&alfa);
+    assert_eq!(xml_without_rule_ids(&original), xml_without_rule_ids(&recompiled));
+    assert_eq!(unwrap!(decompile(&reread(&recompiled), "main")), alfa);
+}
+
+/// RuleIds that a2x generated are kept, and RuleIds that ALFA cannot
+/// express are named in a comment.
+#[test]
+fn rule_ids() {
+    let original = compile_alfa_src(SRC);
+    let alfa = unwrap!(decompile(&reread(&original), "main"));
+    assert!(!alfa.contains("// RuleId"), "{alfa}");
+    let recompiled = compile_alfa_src(&alfa);
+    let xml = |x: &[XTopPolicy]| -> Vec<Vec<u8>> {
+        unwrap!(serialize_xentries(x, XacmlVersion::V3, &OutputStyle::default()))
+            .into_iter()
+            .map(|f| f.contents)
+            .collect()
+    };
+    assert_eq!(xml(&original), xml(&recompiled));
+    // a rule referenced from a policy has the RuleId of the
+    // referenced rule, which ALFA cannot give an inline rule.
+    let referenced = compile_alfa_src(
+        r"
+namespace main {
+  rule ageRestricted { deny }
+  policy clerk = 'urn:example:clerk' { apply firstApplicable ageRestricted }
+}",
+    );
+    let alfa = unwrap!(decompile(&reread(&referenced), "main"));
+    assert!(
+        alfa.contains("      // RuleId \"https://sr.ht/~gheartsfield/a2x/alfa/ident/main/ageRestricted#rule_0\" cannot be kept in ALFA\n      deny\n"),
+        "{alfa}"
+    );
+}
+
+/// Builtin names, infix operators, and the condition of a policy
+/// are recovered.
+#[test]
+fn recovered_statements() {
+    let alfa = unwrap!(decompile(&reread(&compile_alfa_src(SRC)), "main"));
+    for s in [
+        "  /* Documents */\n  policy documents = \"urn:example:documents\" {\n",
+        "    version \"2.1\"\n    maxDelegationDepth 3\n",
+        "    target clause actionId == \"read\" or actionId == \"list\"\n",
+        "           clause 3 <= clearance\n",
+        // the return type of an unknown function is not known, so `&&`
+        // cannot be used
+        "    condition andFunction(subjectId == owner, isWeekday(currentDate))\n",
+        "    apply denyOverrides with (w = 2)\n",
+        "      condition (integerOneAndOnly(clearance) + 1) > 3\n",
+        "          owner = \"a \\\"quoted\\\" path\\u{5c}\"\n",
+        "    issuer {\n      subjectId = \"admin\"\n    }\n",
+        "    documents(version = \"2.*\") with (w = 1)\n",
+        "    legacy(latest = \"3.0\")\n",
+        "  policyset guarded = \"urn:example:guarded\" {\n    condition clearance > 1\n",
+        "    policy inner = \"urn:example:inner\" {\n      condition subjectId == \"bob\"\n",
+        "  extern policy legacy = \"urn:example:legacy\"\n",
+        "  function isWeekday = \"urn:example:fn:is-weekday\" : anyAtomicOrBag * -> boolean\n",
+    ] {
+        assert!(alfa.contains(s), "missing {s:?} in:\n{alfa}");
+    }
+    // builtins are not declared
+    assert!(!alfa.contains("attribute subjectId"), "{alfa}");
+}
+
+const UNKNOWN: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Policy xmlns="urn:oasis:names:tc:xacml:3.0:core:schema:wd-17"
+    PolicyId="urn:example:policy:1" Version="1.0"
+    RuleCombiningAlgId="urn:example:alg:custom">
+  <Target>
+    <AnyOf><AllOf>
+      <Match MatchId="urn:example:fn:matches">
+        <AttributeValue DataType="urn:example:type:color">red</AttributeValue>
+        <AttributeDesignator AttributeId="urn:example:attr:shade"
+            Category="urn:example:cat:paint" DataType="urn:example:type:color"
+            MustBePresent="true" Issuer="urn:example:issuer"/>
+      </Match>
+    </AllOf></AnyOf>
+  </Target>
+  <Rule RuleId="urn:example:rule:true" Effect="Deny">
+    <Condition>
+      <Apply FunctionId="urn:example:fn:check">
+        <Apply FunctionId="urn:example:fn:lookup">
+          <AttributeValue DataType="http://www.w3.org/2001/XMLSchema#integer">7</AttributeValue>
+        </Apply>
+      </Apply>
+    </Condition>
+    <ObligationExpressions>
+      <ObligationExpression ObligationId="urn:example:oblig:log" FulfillOn="Deny">
+        <AttributeAssignmentExpression AttributeId="urn:example:attr:reason"
+            Category="urn:example:cat:paint">
+          <AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">no</AttributeValue>
+        </AttributeAssignmentExpression>
+      </ObligationExpression>
+    </ObligationExpressions>
+  </Rule>
+</Policy>"#;
+
+/// Unknown URIs get declarations, which compile back to the same
+/// XACML.
+#[test]
+fn unknown_uris() {
+    let mut x = unwrap!(read_xacml(UNKNOWN.as_bytes()));
+    if let XTopPolicy::Policy(p) = &mut x {
+        // read policies have no filename, which is needed to serialize
+        p.filename = Some("legacy.imported.1.xml".to_owned());
+    }
+    let x = vec![x];
+    let alfa = unwrap!(decompile(&x, "legacy.imported"));
+    for s in [
+        "namespace legacy.imported {\n",
+        "  type color = \"urn:example:type:color\"\n",
+        "  category paint = \"urn:example:cat:paint\"\n",
+        "  attribute shade {\n    id = \"urn:example:attr:shade\"\n    type = color\n",
+        "  function matches = \"urn:example:fn:matches\" : anyAtomicOrBag * -> boolean\n",
+        "  function lookup = \"urn:example:fn:lookup\" : anyAtomicOrBag * -> anyAtomic\n",
+        "  ruleCombinator custom = \"urn:example:alg:custom\"\n",
+        "  obligation log = \"urn:example:oblig:log\"\n",
+        "    target clause matches(\"red\":color, shade[mustbepresent issuer=\"urn:example:issuer\"])\n",
+        "    rule True {\n",
+        "      condition check(lookup(7))\n",
+        "      on deny {\n        obligation log {\n          reason = \"no\"\n",
+    ] {
+        assert!(alfa.contains(s), "missing {s:?} in:\n{alfa}");
+    }
+    let recompiled = compile_alfa_src(&alfa);
+    assert_eq!(xml_without_rule_ids(&x), xml_without_rule_ids(&recompiled));
+}
+
+/// XACML that ALFA cannot express is rejected.
+#[test]
+fn unsupported_xacml() {
+    let selector = r#"<Policy xmlns="urn:oasis:names:tc:xacml:3.0:core:schema:wd-17"
+        PolicyId="urn:p" Version="1.0"
+        RuleCombiningAlgId="urn:oasis:names:tc:xacml:1.0:rule-combining-algorithm:first-applicable">
+      <Target/>
+      <Rule RuleId="r" Effect="Permit"><Condition>
+        <AttributeSelector Category="urn:c" Path="/a" DataType="urn:t" MustBePresent="false"/>
+      </Condition></Rule>
+    </Policy>"#;
+    assert!(matches!(
+        read_xacml(selector.as_bytes()),
+        Err(ParseError::XacmlReadError(_))
+    ));
+    assert!(matches!(
+        read_xacml(b"<Policy/>"),
+        Err(ParseError::XacmlReadError(_))
+    ));
+    assert!(matches!(
+        decompile(&[], "not valid"),
+        Err(ParseError::DecompileError(_))
+    ));
+}
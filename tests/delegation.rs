@@ -0,0 +1,125 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::xacml::XTopPolicy;
+use common::{compile_alfa_src, get_nth_policyset, xentry_to_str};
+use pretty_assertions::assert_eq;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for policy issuers and maximum delegation depth
+// (XACML Administration and Delegation profile).
+
+/// A policy with an issuer and a delegation depth.
+#[test]
+fn policy_issuer() {
+    let x = compile_alfa_src(
+        r#"
+namespace main {
+  attribute clearance {
+    id = "urn:example:clearance"
+    type = integer
+    category = subjectCat
+  }
+  /* Delegated administration */
+  policy p = "p" {
+    apply firstApplicable
+    maxDelegationDepth 2
+    issuer {
+      subjectId = "alice"
+      clearance = 3
+    }
+    rule { permit }
+  }
+}"#,
+    );
+    let xp = unwrap!(x.first(), "at least one policy");
+    let xacml = xentry_to_str(xp);
+    assert_eq!(
+        xacml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<xacml3:Policy xmlns:xacml3="urn:oasis:names:tc:xacml:3.0:core:schema:wd-17" PolicyId="p" RuleCombiningAlgId="urn:oasis:names:tc:xacml:1.0:rule-combining-algorithm:first-applicable" Version="1.0" MaxDelegationDepth="2">
+  <xacml3:Description>Delegated administration</xacml3:Description>
+  <xacml3:PolicyIssuer>
+    <xacml3:Attribute AttributeId="urn:oasis:names:tc:xacml:1.0:subject:subject-id" IncludeInResult="false">
+      <xacml3:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">alice</xacml3:AttributeValue>
+    </xacml3:Attribute>
+    <xacml3:Attribute AttributeId="urn:example:clearance" IncludeInResult="false">
+      <xacml3:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#integer">3</xacml3:AttributeValue>
+    </xacml3:Attribute>
+  </xacml3:PolicyIssuer>
+  <xacml3:Target />
+  <xacml3:Rule Effect="Permit" RuleId="https://sr.ht/~gheartsfield/a2x/alfa/ident/main/p#rule_0">
+    <xacml3:Target />
+  </xacml3:Rule>
+</xacml3:Policy>"#
+    );
+}
+
+/// A policyset with an issuer, and a delegation-info target.
+#[test]
+fn policyset_issuer() {
+    let x = compile_alfa_src(
+        r#"
+namespace main {
+  policyset ps = "ps" {
+    apply firstApplicable
+    issuer { subjectId = "admin" }
+    maxDelegationDepth 0
+    policy {
+      apply firstApplicable
+      target clause delegationDecision == "Permit"
+      rule { permit }
+    }
+  }
+}"#,
+    );
+    let ps = get_nth_policyset(0, x);
+    assert_eq!(ps.max_delegation_depth, Some(0));
+    assert_eq!(ps.issuer.len(), 1);
+    let xacml = xentry_to_str(&XTopPolicy::PolicySet(ps));
+    assert!(xacml.contains(r#"Version="1.0" MaxDelegationDepth="0">"#));
+    assert!(xacml.contains(
+        r#"<xacml3:PolicyIssuer>
+    <xacml3:Attribute AttributeId="urn:oasis:names:tc:xacml:1.0:subject:subject-id" IncludeInResult="false">
+      <xacml3:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">admin</xacml3:AttributeValue>
+    </xacml3:Attribute>
+  </xacml3:PolicyIssuer>"#
+    ));
+    assert!(xacml.contains(
+        r#"Category="urn:oasis:names:tc:xacml:3.0:attribute-category:delegation-info""#
+    ));
+}
+
+/// Issuer values must match the attribute type.
+#[test]
+#[should_panic(expected = "compile failed")]
+fn issuer_type_mismatch() {
+    compile_alfa_src(
+        r"
+namespace main {
+  policy p {
+    apply firstApplicable
+    issuer { subjectId = 3 }
+    rule { permit }
+  }
+}",
+    );
+}
+
+/// Only one issuer can be given.
+#[test]
+#[should_panic(expected = "compile failed")]
+fn duplicate_issuer() {
+    compile_alfa_src(
+        r#"
+namespace main {
+  policy p {
+    apply firstApplicable
+    issuer { subjectId = "alice" }
+    issuer { subjectId = "bob" }
+    rule { permit }
+  }
+}"#,
+    );
+}
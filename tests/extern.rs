@@ -0,0 +1,122 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::xacml::xpolicyentry::XPolicyEntry;
+use common::{compile_alfa_src, compile_alfa_srcs, get_nth_policyset, xentry_to_str};
+use pretty_assertions::assert_eq;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for references to policies and policysets that
+// are not defined in ALFA.
+
+/// External policies and policysets, with and without versions.
+#[test]
+fn extern_references() {
+    let x = compile_alfa_src(
+        r#"
+namespace main {
+  extern policyset legacyAuth = "urn:example:legacy:auth"
+  extern policy legacyAudit = "urn:example:legacy:audit" (latest = "2.*")
+  policyset top = "top" {
+    apply firstApplicable
+    legacyAuth
+    legacyAudit
+    legacyAudit(earliest = "1.5", latest = "1.*")
+  }
+}"#,
+    );
+    // externs do not produce any output of their own
+    assert_eq!(x.len(), 1);
+    let xp = unwrap!(x.first(), "at least one policyset");
+    let xacml = xentry_to_str(xp);
+    assert_eq!(
+        xacml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<xacml3:PolicySet xmlns:xacml3="urn:oasis:names:tc:xacml:3.0:core:schema:wd-17" PolicySetId="top" PolicyCombiningAlgId="urn:oasis:names:tc:xacml:1.0:policy-combining-algorithm:first-applicable" Version="1.0">
+  <xacml3:Target />
+  <xacml3:PolicySetIdReference>urn:example:legacy:auth</xacml3:PolicySetIdReference>
+  <xacml3:PolicyIdReference LatestVersion="2.*">urn:example:legacy:audit</xacml3:PolicyIdReference>
+  <xacml3:PolicyIdReference EarliestVersion="1.5" LatestVersion="1.*">urn:example:legacy:audit</xacml3:PolicyIdReference>
+</xacml3:PolicySet>"#
+    );
+}
+
+/// External declarations are imported like any other policy.
+#[test]
+fn extern_other_file() {
+    let x = compile_alfa_srcs(vec![
+        r#"
+namespace legacy {
+  extern policyset auth = "urn:example:legacy:auth"
+}"#
+        .to_string(),
+        r"
+namespace main {
+  import legacy.*
+  policyset top {
+    apply firstApplicable
+    auth
+  }
+}"
+        .to_string(),
+    ]);
+    let ps = get_nth_policyset(0, x);
+    assert_eq!(ps.children.len(), 1);
+}
+
+/// External declarations cannot share a name with a policy.
+#[test]
+#[should_panic(expected = "compile failed")]
+fn extern_duplicate_policy() {
+    compile_alfa_src(
+        r#"
+namespace main {
+  policy auth {
+    apply firstApplicable
+    rule { permit }
+  }
+  extern policy auth = "urn:example:legacy:auth"
+}"#,
+    );
+}
+
+/// Version constraints on external declarations are validated.
+#[test]
+#[should_panic(expected = "compile failed")]
+fn extern_invalid_version() {
+    compile_alfa_src(
+        r#"
+namespace main {
+  extern policy auth = "urn:example:legacy:auth" (version = "one")
+}"#,
+    );
+}
+
+/// Comments following external declarations and bare references are
+/// kept as descriptions.
+#[test]
+fn extern_comment_description() {
+    let x = compile_alfa_src(
+        r#"
+namespace main {
+  extern policyset legacyAuth = "urn:example:legacy:auth"
+  /* Top Policyset */
+  policyset top = "top" {
+    apply firstApplicable
+    legacyAuth
+    /* Inner Policy */
+    policy inner {
+      apply firstApplicable
+      rule { permit }
+    }
+  }
+}"#,
+    );
+    let ps = get_nth_policyset(0, x);
+    assert_eq!(ps.description.as_deref(), Some("Top Policyset"));
+    let XPolicyEntry::Policy(inner) = &ps.children[1] else {
+        panic!("Expected Policy");
+    };
+    assert_eq!(inner.description.as_deref(), Some("Inner Policy"));
+}
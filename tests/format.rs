@@ -0,0 +1,197 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::context::XacmlVersion;
+use a2x::format::{format_alfa, is_formatted};
+use a2x::xacml::style::OutputStyle;
+use a2x::{serialize_xentries, ParseError};
+use common::compile_alfa_src;
+use pretty_assertions::assert_eq;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for formatting ALFA source.
+
+const MESSY: &str = r#"// Documents
+namespace   main{
+    type   color="urn:example:color"   // a custom type
+attribute shade{ type=color id="urn:example:shade"
+category=resourceCat }
+obligation audit="urn:example:audit"
+function isRed="urn:example:is-red":color color* -> boolean
+
+
+/* Readable documents */
+policy documents="urn:example:documents"{apply firstApplicable
+   target clause actionId=="read" and resourceId=="document" or isRed("red":color, shade[mustbepresent  issuer = "i"])
+      clause "alice"==subjectId
+  // only the owner
+  condition (stringOneAndOnly(subjectId) == stringOneAndOnly(subjectIdQualifier)) && stringIsIn("admin", actionId)
+  rule read { permit on permit { obligation audit { resourceId = "read" subjectIdQualifier = subjectId } } }
+  rule { deny }
+}
+}
+"#;
+
+const FORMATTED: &str = r#"// Documents
+namespace main {
+  type color = "urn:example:color" // a custom type
+  attribute shade {
+    type = color
+    id = "urn:example:shade"
+    category = resourceCat
+  }
+  obligation audit = "urn:example:audit"
+  function isRed = "urn:example:is-red" : color color* -> boolean
+
+  /* Readable documents */
+  policy documents = "urn:example:documents" {
+    apply firstApplicable
+    target clause actionId   == "read"
+              and resourceId == "document"
+               or isRed("red":color, shade[mustbepresent issuer="i"])
+           clause "alice" == subjectId
+    // only the owner
+    condition (stringOneAndOnly(subjectId)
+               == stringOneAndOnly(subjectIdQualifier))
+              && stringIsIn("admin", actionId)
+    rule read {
+      permit
+      on permit {
+        obligation audit {
+          resourceId = "read"
+          subjectIdQualifier = subjectId
+        }
+      }
+    }
+    rule {
+      deny
+    }
+  }
+}
+"#;
+
+/// XACML for ALFA source, as text.
+fn xml(src: &str) -> Vec<Vec<u8>> {
+    let x = compile_alfa_src(src);
+    unwrap!(serialize_xentries(&x, XacmlVersion::V3, &OutputStyle::default()))
+        .into_iter()
+        .map(|f| f.contents)
+        .collect()
+}
+
+/// Source is indented, targets are aligned, and long conditions are
+/// wrapped.
+#[test]
+fn canonical_style() {
+    assert_eq!(unwrap!(format_alfa(MESSY)), FORMATTED);
+    assert!(unwrap!(is_formatted(FORMATTED)));
+    assert!(!unwrap!(is_formatted(MESSY)));
+}
+
+/// Formatting does not change the generated XACML, including the
+/// descriptions taken from comments.
+#[test]
+fn same_xacml() {
+    let formatted = unwrap!(format_alfa(MESSY));
+    assert_eq!(xml(MESSY), xml(&formatted));
+    let xml = String::from_utf8(xml(&formatted).remove(0)).unwrap();
+    assert!(xml.contains("<xacml3:Description>Readable documents</xacml3:Description>"));
+}
+
+/// Blank lines are kept at the start and end of a block, but
+/// collapsed to one.
+#[test]
+fn blank_lines_kept() {
+    let src = "namespace main {\n\n\n  rule r {\n    permit\n\n  }\n\n  rule s { deny }\n}\n";
+    let formatted = "namespace main {\n\n  rule r {\n    permit\n\n  }\n\n  rule s {\n    deny\n  }\n}\n";
+    assert_eq!(unwrap!(format_alfa(src)), formatted);
+    assert!(unwrap!(is_formatted(formatted)));
+}
+
+/// Comments are kept, wherever they were written.
+#[test]
+fn comments_kept() {
+    let src = r#"namespace main { // after a brace
+  policy p = "urn:p" {
+    apply firstApplicable
+    target clause subjectId == "a" // first
+      and /* inline */ actionId == "b"
+    condition f(1, // an argument
+                2) // after a condition
+    rule { /* only */ permit }
+    /* last */
+  } // after a policy
+  /* end */
+}"#;
+    let formatted = unwrap!(format_alfa(src));
+    for c in [
+        "{ // after a brace\n",
+        "== \"a\" // first\n",
+        // operators are aligned with the first match
+        "and /* inline */ actionId  == \"b\"",
+        "// an argument\n",
+        "2) // after a condition\n",
+        "rule { /* only */\n",
+        "    /* last */\n  } // after a policy\n  /* end */\n}\n",
+    ] {
+        assert!(formatted.contains(c), "missing {c:?} in:\n{formatted}");
+    }
+    assert_eq!(unwrap!(format_alfa(&formatted)), formatted);
+}
+
+/// Function arguments are aligned when a condition is wrapped.
+#[test]
+fn wrapped_arguments() {
+    let src = r#"namespace main { rule { permit
+condition not(timeInRange(timeOneAndOnly(currentTime), timeOneAndOnly("8:00:00":time), timeOneAndOnly("17:00:00":time))) } }"#;
+    let expected = r#"namespace main {
+  rule {
+    permit
+    condition not(timeInRange(timeOneAndOnly(currentTime),
+                              timeOneAndOnly("8:00:00":time),
+                              timeOneAndOnly("17:00:00":time)))
+  }
+}
+"#;
+    assert_eq!(unwrap!(format_alfa(src)), expected);
+}
+
+/// Conditions are wrapped at their loosest operators, and the
+/// operands that fit are kept on one line.
+#[test]
+fn wrapped_operators() {
+    let src = r#"namespace main { rule { permit
+condition stringOneAndOnly(subjectRole) == "manager" && stringOneAndOnly(resourceOwner) == stringOneAndOnly(subjectId) && integerOneAndOnly(resourceCount) + integerOneAndOnly(resourceCountIncrement) * 2 < integerOneAndOnly(resourceLimit) } }"#;
+    let expected = r#"namespace main {
+  rule {
+    permit
+    condition stringOneAndOnly(subjectRole) == "manager"
+              && stringOneAndOnly(resourceOwner) == stringOneAndOnly(subjectId)
+              && integerOneAndOnly(resourceCount)
+                 + integerOneAndOnly(resourceCountIncrement) * 2
+                 < integerOneAndOnly(resourceLimit)
+  }
+}
+"#;
+    assert_eq!(unwrap!(format_alfa(src)), expected);
+}
+
+/// The sample policies are formatted.
+#[test]
+fn samples_formatted() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/retail");
+    for name in ["attr", "common", "empty", "main", "oblig"] {
+        let src = unwrap!(std::fs::read_to_string(dir.join(format!("{name}.alfa"))));
+        assert_eq!(unwrap!(format_alfa(&src)), src, "{name}.alfa");
+    }
+}
+
+/// Source that does not parse cannot be formatted.
+#[test]
+fn unparseable() {
+    assert!(matches!(
+        format_alfa("namespace main { policy {"),
+        Err(ParseError::PestParseError(_))
+    ));
+}
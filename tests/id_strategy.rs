@@ -0,0 +1,174 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::context::{Config, Context, IdStrategy};
+use a2x::xacml::XTopPolicy;
+use a2x::{alfa_compile, AlfaFile, ParseError};
+use common::{get_nth_policy, get_nth_policyset};
+use pretty_assertions::assert_eq;
+use std::rc::Rc;
+use std::str::FromStr;
+mod common;
+
+// Integration tests for naming anonymous rules, policies, and
+// policysets.
+
+/// Compile a single ALFA source text with an ID strategy.
+fn compile_with(strategy: IdStrategy, src: &str) -> Result<Vec<XTopPolicy>, ParseError> {
+    let ctx = Rc::new(Context::new(Config {
+        id_strategy: strategy,
+        ..Config::default()
+    }));
+    let sources = vec![AlfaFile {
+        filename: "main.alfa".to_owned(),
+        contents: src.to_owned(),
+    }];
+    alfa_compile(&ctx, sources)
+}
+
+/// IDs of the rules in the first policy.
+fn rule_ids(strategy: IdStrategy, src: &str) -> Vec<String> {
+    let p = get_nth_policy(0, compile_with(strategy, src).expect("compile failed"));
+    p.rules.iter().map(|r| r.id.clone()).collect()
+}
+
+#[test]
+fn parse_strategy() {
+    assert_eq!(IdStrategy::from_str("hash").ok(), Some(IdStrategy::ContentHash));
+    assert_eq!(IdStrategy::from_str("uuid").ok(), Some(IdStrategy::Uuid));
+    assert_eq!(IdStrategy::from_str("counter").ok(), Some(IdStrategy::Counter));
+    assert!(IdStrategy::from_str("random").is_err());
+}
+
+/// Adding a rule does not change the IDs of other rules.
+#[test]
+fn hash_ids_are_stable() {
+    let before = rule_ids(
+        IdStrategy::ContentHash,
+        r#"
+namespace main {
+  policy p {
+    apply firstApplicable
+    rule { deny target clause resourceId == "secret" }
+    rule { permit }
+  }
+}"#,
+    );
+    let after = rule_ids(
+        IdStrategy::ContentHash,
+        r#"
+namespace main {
+  policy p {
+    apply firstApplicable
+    rule { deny target clause resourceId == "top  secret" }
+    // comments and formatting do not matter
+    rule {
+      deny
+      target   clause resourceId   ==   "secret"
+    }
+    rule { permit }
+  }
+}"#,
+    );
+    assert_eq!(before.len(), 2);
+    assert_eq!(after.len(), 3);
+    assert_eq!(before[0], after[1]);
+    assert_eq!(before[1], after[2]);
+    assert_ne!(after[0], after[1]);
+    assert!(
+        before[1].starts_with("https://sr.ht/~gheartsfield/a2x/alfa/ident/main/p#rule_"),
+        "{}",
+        before[1]
+    );
+}
+
+/// Identical rules within a policy still receive distinct IDs.
+#[test]
+fn hash_ids_are_unique() {
+    let ids = rule_ids(
+        IdStrategy::ContentHash,
+        r"
+namespace main {
+  policy p {
+    apply firstApplicable
+    rule { permit }
+    rule { permit }
+  }
+}",
+    );
+    assert_eq!(ids[1], format!("{}_1", ids[0]));
+}
+
+#[test]
+fn uuid_ids() {
+    let src = r"
+namespace main {
+  policy p {
+    apply firstApplicable
+    rule { permit }
+    rule { permit }
+  }
+}";
+    let ids = rule_ids(IdStrategy::Uuid, src);
+    assert_ne!(ids[0], ids[1]);
+    for id in ids {
+        let (_, name) = id.split_once('#').expect("no rule name");
+        // "rule_" and a hyphenated UUID
+        assert_eq!(name.len(), 5 + 36, "{name}");
+    }
+}
+
+/// A generated ID may not be the same as an assigned one.
+#[test]
+fn generated_uri_collision() {
+    let r = compile_with(
+        IdStrategy::Counter,
+        r#"
+namespace main {
+  policy a = "https://sr.ht/~gheartsfield/a2x/alfa/ident/main/b" {
+    apply firstApplicable
+    rule { permit }
+  }
+  policy b {
+    apply firstApplicable
+    rule { permit }
+  }
+}"#,
+    );
+    assert!(
+        matches!(&r, Err(ParseError::DuplicateURI(u))
+                 if u == "https://sr.ht/~gheartsfield/a2x/alfa/ident/main/b"),
+        "{:?}",
+        r.err()
+    );
+}
+
+/// Anonymous policysets avoid names used by policysets.
+#[test]
+fn generated_name_avoids_policyset() {
+    let xs = compile_with(
+        IdStrategy::Counter,
+        r"
+namespace main {
+  policyset policyset_0 {
+    apply firstApplicable
+    policyset {
+      apply firstApplicable
+      policy p {
+        apply firstApplicable
+        rule { permit }
+      }
+    }
+  }
+}",
+    )
+    .expect("compile failed");
+    let ps = get_nth_policyset(0, xs);
+    let a2x::xacml::xpolicyentry::XPolicyEntry::PolicySet(inner) = &ps.children[0] else {
+        panic!("expected a policyset");
+    };
+    assert_eq!(
+        inner.id,
+        "https://sr.ht/~gheartsfield/a2x/alfa/ident/main/policyset_0/policyset_1"
+    );
+}
@@ -0,0 +1,145 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::context::XacmlVersion;
+use a2x::xacml::layout::{output_paths, OutputLayout};
+use a2x::xacml::style::OutputStyle;
+use a2x::{serialize_xentry, write_xacml_file, ParseError};
+use common::compile_alfa_src;
+use pretty_assertions::assert_eq;
+use std::path::PathBuf;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for the layout of output files.
+
+const SRC: &str = r#"
+namespace main {
+  namespace retail {
+    policy store = "urn:example:retail/store" {
+      version "1.2"
+      apply firstApplicable
+      rule { permit }
+    }
+  }
+  policyset top = "urn:example:top" {
+    apply firstApplicable
+    retail.store
+  }
+}"#;
+
+fn paths(src: &str, layout: &OutputLayout) -> Result<Vec<String>, ParseError> {
+    let x = compile_alfa_src(src);
+    Ok(output_paths(&x, layout)?
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect())
+}
+
+/// Flat layouts use qualified names.
+#[test]
+fn flat() {
+    assert_eq!(
+        unwrap!(paths(SRC, &OutputLayout::Flat)),
+        vec!["main.top.xml", "main.retail.store.xml"]
+    );
+}
+
+/// Nested layouts use a directory per namespace.
+#[test]
+fn nested() {
+    assert_eq!(
+        unwrap!(paths(SRC, &OutputLayout::Nested)),
+        vec!["main/top.xml", "main/retail/store.xml"]
+    );
+}
+
+/// Files can be named by URL-encoded PolicyId/PolicySetId.
+#[test]
+fn by_id() {
+    assert_eq!(
+        unwrap!(paths(SRC, &OutputLayout::ById)),
+        vec!["urn%3Aexample%3Atop.xml", "urn%3Aexample%3Aretail%2Fstore.xml"]
+    );
+}
+
+/// Templates can use the namespace, name, id, and version.
+#[test]
+fn template() {
+    let layout = unwrap!(OutputLayout::template("{namespace_dir}/{name}-{version}.xml"));
+    assert_eq!(
+        unwrap!(paths(SRC, &layout)),
+        vec!["main/top-1.0.xml", "main/retail/store-1.2.xml"]
+    );
+    let layout = unwrap!(OutputLayout::template("{namespace}/{id}.xml"));
+    assert_eq!(
+        unwrap!(paths(SRC, &layout)),
+        vec![
+            "main/urn%3Aexample%3Atop.xml",
+            "main.retail/urn%3Aexample%3Aretail%2Fstore.xml"
+        ]
+    );
+}
+
+/// Templates with unknown or unterminated placeholders are rejected.
+#[test]
+fn invalid_template() {
+    assert!(matches!(
+        OutputLayout::template("{namespace}/{policy}.xml"),
+        Err(ParseError::InvalidOutputLayout(_))
+    ));
+    assert!(matches!(
+        OutputLayout::template("{name.xml"),
+        Err(ParseError::InvalidOutputLayout(_))
+    ));
+    assert!(matches!(
+        "tree".parse::<OutputLayout>(),
+        Err(ParseError::InvalidOutputLayout(_))
+    ));
+}
+
+/// Paths may not leave the output directory.
+#[test]
+fn escaping_path() {
+    let layout = unwrap!(OutputLayout::template("../{name}.xml"));
+    assert!(matches!(
+        paths(SRC, &layout),
+        Err(ParseError::InvalidOutputLayout(_))
+    ));
+}
+
+/// Two outputs with the same path are reported.
+#[test]
+fn collision() {
+    let src = r#"
+namespace a {
+  policy p = "urn:a:p" { apply firstApplicable rule { permit } }
+}
+namespace b {
+  policy p = "urn:b:p" { apply firstApplicable rule { permit } }
+}"#;
+    let layout = unwrap!(OutputLayout::template("{name}.xml"));
+    let r = paths(src, &layout);
+    assert!(
+        matches!(&r, Err(ParseError::OutputPathCollision(path, first, second))
+            if path == "p.xml" && first == "urn:a:p" && second == "urn:b:p"),
+        "{r:?}"
+    );
+    assert_eq!(unwrap!(paths(src, &OutputLayout::Nested)), vec!["a/p.xml", "b/p.xml"]);
+}
+
+/// Directories within a path are created when writing.
+#[test]
+fn write_nested() {
+    let x = compile_alfa_src(SRC);
+    let layout_paths = unwrap!(output_paths(&x, &OutputLayout::Nested));
+    let dir = std::env::temp_dir().join(format!("a2x-layout-{}", std::process::id()));
+    for (xp, path) in x.iter().zip(layout_paths) {
+        let mut f = unwrap!(serialize_xentry(xp, XacmlVersion::V3, &OutputStyle::default()));
+        f.filename = path;
+        unwrap!(write_xacml_file(&dir, &f), "write failed");
+    }
+    assert!(dir.join(PathBuf::from("main/retail/store.xml")).is_file());
+    assert!(dir.join(PathBuf::from("main/top.xml")).is_file());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,276 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+#![cfg(feature = "lsp")]
+
+use a2x::context::Config;
+use a2x::lsp::Workspace;
+use a2x::lsp::index::{SymbolIndex, SymbolKind};
+use a2x::lsp::lines::LineIndex;
+use lsp_server::{Connection, Message, Notification, Request, RequestId};
+use lsp_types::{HoverContents, Location, Position, Range, Uri};
+use pretty_assertions::assert_eq;
+use std::str::FromStr;
+use unwrap::unwrap;
+
+// Integration tests for the language server.
+
+const MAIN: &str = r#"namespace main {
+  import Attributes.*
+  policy audit {
+    apply firstApplicable
+    rule check {
+      permit
+      condition integerOneAndOnly(level) > 3
+    }
+  }
+}
+"#;
+
+const ATTRS: &str = r#"namespace Attributes {
+  attribute level { category = resourceCat id = "urn:example:level" type = integer }
+}
+"#;
+
+fn uri(name: &str) -> Uri {
+    unwrap!(Uri::from_str(&format!("file:///ws/{name}")), "bad uri")
+}
+
+/// Position of the `n`th occurrence of `needle`, plus `offset`
+/// characters (all test sources are ASCII).
+fn pos(text: &str, needle: &str, n: usize, offset: u32) -> Position {
+    let i = unwrap!(text.match_indices(needle).nth(n), "needle not found").0;
+    let mut p = LineIndex::new(text).position(text, i);
+    p.character += offset;
+    p
+}
+
+fn workspace() -> Workspace {
+    let mut ws = Workspace::default();
+    ws.set_document(uri("main.alfa"), MAIN.to_owned());
+    ws.set_document(uri("attrs.alfa"), ATTRS.to_owned());
+    ws.analyze();
+    ws
+}
+
+#[test]
+fn positions() {
+    let text = "a\nbé𝄞c\n";
+    let lines = LineIndex::new(text);
+    // é is one UTF-16 unit, 𝄞 is two.
+    let c = text.find('c').unwrap();
+    assert_eq!(lines.position(text, c), Position::new(1, 4));
+    assert_eq!(lines.offset(text, Position::new(1, 4)), c);
+    // past the end of a line
+    assert_eq!(lines.offset(text, Position::new(0, 9)), 1);
+    assert_eq!(lines.offset(text, Position::new(7, 0)), text.len());
+}
+
+#[test]
+fn index() {
+    let index = unwrap!(SymbolIndex::new(MAIN), "parse failed");
+    let defs: Vec<(SymbolKind, Option<&str>)> = index
+        .definitions
+        .iter()
+        .map(|d| (d.kind, d.name.as_deref()))
+        .collect();
+    assert_eq!(
+        defs,
+        vec![
+            (SymbolKind::Namespace, Some("main")),
+            (SymbolKind::Policy, Some("main.audit")),
+            (SymbolKind::Rule, Some("main.audit.check")),
+        ]
+    );
+    let refs: Vec<(SymbolKind, &str)> = index
+        .references
+        .iter()
+        .map(|r| (r.kind, r.text.as_str()))
+        .collect();
+    assert_eq!(
+        refs,
+        vec![
+            (SymbolKind::Namespace, "Attributes.*"),
+            (SymbolKind::RuleCombinator, "firstApplicable"),
+            (SymbolKind::Function, "integerOneAndOnly"),
+            (SymbolKind::Attribute, "level"),
+            (SymbolKind::Operator, ">"),
+        ]
+    );
+}
+
+#[test]
+fn diagnostics() {
+    let mut ws = workspace();
+    // a syntax error
+    ws.set_document(
+        uri("attrs.alfa"),
+        ATTRS.replace("attribute level", "attribute"),
+    );
+    let published = ws.analyze();
+    assert_eq!(published.len(), 1);
+    assert_eq!(published[0].uri, uri("attrs.alfa"));
+    assert_eq!(
+        published[0].diagnostics[0].range.start,
+        Position::new(1, 12)
+    );
+    // an unresolved name
+    ws.set_document(uri("attrs.alfa"), ATTRS.replace("level", "height"));
+    let published = ws.analyze();
+    assert_eq!(published.len(), 2);
+    let main = &published[1];
+    assert_eq!(main.uri, uri("main.alfa"));
+    assert_eq!(main.diagnostics.len(), 1);
+    let start = pos(MAIN, "level", 0, 0);
+    assert_eq!(main.diagnostics[0].range.start, start);
+    assert_eq!(
+        main.diagnostics[0].message,
+        "Could not resolve attribute `level`"
+    );
+    // fixing it clears the diagnostics
+    ws.set_document(uri("attrs.alfa"), ATTRS.to_owned());
+    let published = ws.analyze();
+    assert_eq!(published.len(), 1);
+    assert!(published[0].diagnostics.is_empty());
+    assert!(ws.analyze().is_empty());
+}
+
+#[test]
+fn definition_and_references() {
+    let ws = workspace();
+    let level = pos(ATTRS, "level", 0, 0);
+    let decl = Location::new(
+        uri("attrs.alfa"),
+        Range::new(level, Position::new(level.line, level.character + 5)),
+    );
+    // through the wildcard import
+    let used = pos(MAIN, "level", 0, 2);
+    assert_eq!(ws.definition(&uri("main.alfa"), used), Some(decl.clone()));
+    // the import itself
+    let import = ws.definition(&uri("main.alfa"), pos(MAIN, "Attributes", 0, 0));
+    assert_eq!(import.map(|l| l.range.start), Some(Position::new(0, 10)));
+    // built-in functions have no declaration
+    assert_eq!(
+        ws.definition(&uri("main.alfa"), pos(MAIN, "integer", 0, 0)),
+        None
+    );
+    let refs = ws.references(&uri("attrs.alfa"), level, true);
+    let start = pos(MAIN, "level", 0, 0);
+    assert_eq!(
+        refs,
+        vec![
+            decl,
+            Location::new(
+                uri("main.alfa"),
+                Range::new(start, Position::new(start.line, start.character + 5))
+            )
+        ]
+    );
+}
+
+#[test]
+fn hover() {
+    let ws = workspace();
+    let h = unwrap!(
+        ws.hover(&uri("main.alfa"), pos(MAIN, "level", 0, 0)),
+        "no hover"
+    );
+    let HoverContents::Markup(m) = h.contents else {
+        panic!("expected markup");
+    };
+    assert!(m.value.starts_with("attribute `Attributes.level`"));
+    assert!(m.value.contains("- id: `urn:example:level`"));
+    assert!(
+        m.value
+            .contains("(`http://www.w3.org/2001/XMLSchema#integer`)")
+    );
+    let h = unwrap!(
+        ws.hover(&uri("main.alfa"), pos(MAIN, ">", 0, 0)),
+        "no hover"
+    );
+    let HoverContents::Markup(m) = h.contents else {
+        panic!("expected markup");
+    };
+    assert!(m.value.contains("integer-greater-than"));
+}
+
+#[test]
+fn completion() {
+    let mut ws = workspace();
+    let text = MAIN.replace("(level)", "(lev)");
+    ws.set_document(uri("main.alfa"), text.clone());
+    let items = ws.completion(&uri("main.alfa"), pos(&text, "lev)", 0, 3));
+    let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
+    assert_eq!(labels, vec!["level"]);
+    assert_eq!(
+        items[0].detail.as_deref(),
+        Some("attribute Attributes.level")
+    );
+    // built-in names are visible without qualification
+    let items = ws.completion(&uri("main.alfa"), pos(&text, "integerOne", 0, 10));
+    assert!(items.iter().any(|i| i.label == "integerOneAndOnly"));
+}
+
+#[test]
+fn document_symbols() {
+    let ws = workspace();
+    let symbols = ws.document_symbols(&uri("main.alfa"));
+    assert_eq!(symbols.len(), 1);
+    assert_eq!(symbols[0].name, "main");
+    let policy = &unwrap!(symbols[0].children.as_ref(), "no children")[0];
+    assert_eq!(policy.name, "audit");
+    let rule = &unwrap!(policy.children.as_ref(), "no children")[0];
+    assert_eq!(rule.name, "check");
+    assert_eq!(rule.detail.as_deref(), Some("main.audit.check"));
+}
+
+#[test]
+fn protocol() {
+    let (server, client) = Connection::memory();
+    let handle = std::thread::spawn(move || a2x::lsp::serve(&server, Config::default()).is_ok());
+    let send_request = |id: i32, method: &str, params: serde_json::Value| {
+        let req = Request::new(RequestId::from(id), method.to_owned(), params);
+        unwrap!(client.sender.send(Message::Request(req)), "send failed");
+    };
+    let send_notification = |method: &str, params: serde_json::Value| {
+        let n = Notification::new(method.to_owned(), params);
+        unwrap!(client.sender.send(Message::Notification(n)), "send failed");
+    };
+    send_request(1, "initialize", serde_json::json!({"capabilities": {}}));
+    send_notification("initialized", serde_json::json!({}));
+    send_notification(
+        "textDocument/didOpen",
+        serde_json::json!({"textDocument": {
+            "uri": "file:///ws/main.alfa", "languageId": "alfa", "version": 1,
+            "text": "namespace main { policy { apply nope } }"
+        }}),
+    );
+    send_request(
+        2,
+        "textDocument/documentSymbol",
+        serde_json::json!({"textDocument": {"uri": "file:///ws/main.alfa"}}),
+    );
+    let mut received = vec![];
+    while received.len() < 3 {
+        received.push(unwrap!(client.receiver.recv(), "receive failed"));
+    }
+    send_request(3, "shutdown", serde_json::Value::Null);
+    send_notification("exit", serde_json::Value::Null);
+    assert!(unwrap!(handle.join(), "server panicked"));
+    let Message::Response(init) = &received[0] else {
+        panic!("expected initialize response");
+    };
+    let caps = &unwrap!(init.result.as_ref(), "no result")["capabilities"];
+    assert_eq!(caps["hoverProvider"], true);
+    let Message::Notification(diags) = &received[1] else {
+        panic!("expected diagnostics");
+    };
+    assert_eq!(diags.method, "textDocument/publishDiagnostics");
+    assert_eq!(diags.params["diagnostics"][0]["source"], "a2x");
+    let Message::Response(symbols) = &received[2] else {
+        panic!("expected symbols");
+    };
+    assert_eq!(
+        symbols.result.as_ref().map(|r| r[0]["name"].clone()),
+        Some("main".into())
+    );
+}
@@ -0,0 +1,141 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::context::{Context, XacmlVersion};
+use a2x::manifest::{prune, Manifest, ManifestEntry, Pruned, MANIFEST_FILENAME};
+use a2x::xacml::layout::{output_paths, OutputLayout};
+use a2x::xacml::style::OutputStyle;
+use a2x::{alfa_compile, serialize_xentry, write_xacml_file, AlfaFile, ParseError};
+use common::temp_dir;
+use pretty_assertions::assert_eq;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for the manifest of generated files, and pruning
+// stale files.
+
+const SRC: &str = r#"
+namespace main {
+  policy a = "urn:a" { apply firstApplicable rule { permit } }
+  namespace sub {
+    policy b = "urn:b" { version "2.0" apply firstApplicable rule { deny } }
+  }
+}"#;
+
+/// Compile and write ALFA into a directory, returning the manifest
+/// of the generated files.
+fn generate(dir: &Path, src: &str) -> Manifest {
+    let ctx = Rc::new(Context::default());
+    let alfa = AlfaFile {
+        filename: "policies.alfa".to_owned(),
+        contents: src.to_owned(),
+    };
+    let x = unwrap!(alfa_compile(&ctx, vec![alfa]), "compile failed");
+    let paths = unwrap!(output_paths(&x, &OutputLayout::Nested));
+    let mut m = Manifest::default();
+    for (xp, path) in x.iter().zip(paths) {
+        let mut f = unwrap!(serialize_xentry(xp, XacmlVersion::V3, &OutputStyle::default()));
+        f.filename = path;
+        unwrap!(write_xacml_file(dir, &f), "write failed");
+        m.entries.push(ManifestEntry::new(&f, xp));
+    }
+    m
+}
+
+/// Entries record the path, id, version, source, and contents hash,
+/// and survive a round-trip through the output directory.
+#[test]
+fn manifest_round_trip() {
+    let dir = temp_dir("manifest", "round-trip");
+    assert_eq!(unwrap!(Manifest::read(&dir)), None);
+    let m = generate(&dir, SRC);
+    assert_eq!(m.entries.len(), 2);
+    let b = &m.entries[1];
+    assert_eq!(b.path, PathBuf::from("main/sub/b.xml"));
+    assert_eq!(b.id, "urn:b");
+    assert_eq!(b.version, "2.0");
+    assert_eq!(b.source.as_deref(), Some("policies.alfa"));
+    assert_eq!(b.hash.len(), 16);
+    unwrap!(m.write(&dir));
+    assert!(dir.join(MANIFEST_FILENAME).is_file());
+    assert_eq!(unwrap!(Manifest::read(&dir)), Some(m));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Files that are no longer generated are stale, and are removed
+/// along with empty directories.  Other files are not touched.
+#[test]
+fn prune_stale() {
+    let dir = temp_dir("manifest", "prune");
+    let previous = generate(&dir, SRC);
+    std::fs::write(dir.join("main/sub/notes.txt"), "not generated").unwrap();
+    let current = generate(
+        &dir,
+        "namespace main { policy a = \"urn:a\" { apply firstApplicable rule { permit } } }",
+    );
+    let stale = previous.stale(&current);
+    assert_eq!(stale.len(), 1);
+    assert_eq!(stale[0].id, "urn:b");
+    let pruned = unwrap!(prune(&dir, &stale));
+    assert_eq!(pruned, vec![Pruned::Removed(PathBuf::from("main/sub/b.xml"))]);
+    assert!(!dir.join("main/sub/b.xml").exists());
+    // the directory still holds a file that a2x did not create
+    assert!(dir.join("main/sub/notes.txt").is_file());
+    assert!(dir.join("main/a.xml").is_file());
+    std::fs::remove_dir_all(dir.join("main/sub")).unwrap();
+    // with nothing else in them, directories are removed too
+    let empty = generate(
+        &dir,
+        "namespace other { policy c = \"urn:c\" { apply firstApplicable rule { permit } } }",
+    );
+    let stale = current.stale(&empty);
+    let pruned = unwrap!(prune(&dir, &stale));
+    assert_eq!(pruned, vec![Pruned::Removed(PathBuf::from("main/a.xml"))]);
+    assert!(!dir.join("main").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Stale files that were changed after they were generated are kept.
+#[test]
+fn prune_keeps_modified() {
+    let dir = temp_dir("manifest", "modified");
+    let previous = generate(&dir, SRC);
+    std::fs::write(dir.join("main/sub/b.xml"), "<edited/>").unwrap();
+    let current = Manifest::default();
+    let stale = previous.stale(&current);
+    std::fs::remove_file(dir.join("main/a.xml")).unwrap();
+    let pruned = unwrap!(prune(&dir, &stale));
+    assert_eq!(
+        pruned,
+        vec![
+            Pruned::Missing(PathBuf::from("main/a.xml")),
+            Pruned::Modified(PathBuf::from("main/sub/b.xml")),
+        ]
+    );
+    assert!(dir.join("main/sub/b.xml").is_file());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Manifests are rejected if they are malformed, or name paths
+/// outside the output directory.
+#[test]
+fn invalid_manifest() {
+    assert!(matches!(
+        Manifest::parse("main.a.xml\turn:a\t1.0\t\t0\n"),
+        Err(ParseError::InvalidManifest(_))
+    ));
+    assert!(matches!(
+        Manifest::parse("# a2x manifest 1\nmain.a.xml\turn:a\n"),
+        Err(ParseError::InvalidManifest(_))
+    ));
+    assert!(matches!(
+        Manifest::parse("# a2x manifest 1\n../x.xml\turn:a\t1.0\t\t0\n"),
+        Err(ParseError::InvalidManifest(_))
+    ));
+    let m = unwrap!(Manifest::parse("# a2x manifest 1\na\\tb.xml\turn:a\t1.0\t\t0\n"));
+    assert_eq!(m.entries[0].path, PathBuf::from("a\tb.xml"));
+    assert_eq!(m.entries[0].source, None);
+    assert_eq!(unwrap!(Manifest::parse(&m.to_string())), m);
+}
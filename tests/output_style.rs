@@ -0,0 +1,163 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::context::XacmlVersion;
+use a2x::xacml::style::{LineEnding, OutputStyle, XmlEncoding};
+use a2x::{serialize_xentry, ParseError};
+use common::compile_alfa_src;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for the style of generated XML.
+
+const SRC: &str = r#"
+namespace main {
+  policy a = "a" {
+    apply firstApplicable
+    rule {
+      permit
+      target clause resourceId == "café"
+    }
+  }
+}"#;
+
+fn styled(version: XacmlVersion, style: &OutputStyle) -> Vec<u8> {
+    let x = compile_alfa_src(SRC);
+    let f = unwrap!(serialize_xentry(&x[0], version, style), "serialize failed");
+    f.contents
+}
+
+fn styled_str(version: XacmlVersion, style: &OutputStyle) -> String {
+    unwrap!(String::from_utf8(styled(version, style)), "invalid utf-8")
+}
+
+/// The default style uses the `xacml3` prefix, two-space
+/// indentation, and an XML declaration.
+#[test]
+fn default_style() {
+    let s = styled_str(XacmlVersion::V3, &OutputStyle::default());
+    assert!(s.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+    assert!(s.contains(
+        "<xacml3:Policy xmlns:xacml3=\"urn:oasis:names:tc:xacml:3.0:core:schema:wd-17\""
+    ));
+    assert!(s.contains("\n  <xacml3:Target"));
+    assert!(!s.contains("schemaLocation"));
+}
+
+/// The XACML namespace can be the default namespace.
+#[test]
+fn default_namespace() {
+    let style = OutputStyle {
+        default_namespace: true,
+        ..OutputStyle::default()
+    };
+    let s = styled_str(XacmlVersion::V3, &style);
+    assert!(s.contains("<Policy xmlns=\"urn:oasis:names:tc:xacml:3.0:core:schema:wd-17\""));
+    assert!(s.contains("<Rule "));
+    assert!(!s.contains("xacml3:"));
+}
+
+/// Elements can use another prefix.
+#[test]
+fn custom_prefix() {
+    let style = OutputStyle {
+        prefix: Some("x".to_owned()),
+        ..OutputStyle::default()
+    };
+    let s = styled_str(XacmlVersion::V3, &style);
+    assert!(s.contains("<x:Policy xmlns:x=\"urn:oasis:names:tc:xacml:3.0:core:schema:wd-17\""));
+    assert!(s.contains("<x:Rule "));
+    assert!(s.contains("</x:Policy>"));
+    assert!(!s.contains("xacml3:"));
+}
+
+/// XACML 2.0 uses the `xacml2` prefix, unless another is chosen.
+#[test]
+fn v2_prefix() {
+    let s = styled_str(XacmlVersion::V2, &OutputStyle::default());
+    assert!(s.contains(
+        "<xacml2:Policy xmlns:xacml2=\"urn:oasis:names:tc:xacml:2.0:policy:schema:os\""
+    ));
+    assert!(!s.contains("xacml3:"));
+    let style = OutputStyle {
+        prefix: Some("p".to_owned()),
+        ..OutputStyle::default()
+    };
+    let s = styled_str(XacmlVersion::V2, &style);
+    assert!(s.contains("<p:Policy xmlns:p=\"urn:oasis:names:tc:xacml:2.0:policy:schema:os\""));
+}
+
+/// Indentation width, the declaration, and line endings can be
+/// changed.
+#[test]
+fn layout() {
+    let style = OutputStyle {
+        indent: Some(4),
+        xml_declaration: false,
+        line_ending: LineEnding::CrLf,
+        ..OutputStyle::default()
+    };
+    let s = styled_str(XacmlVersion::V3, &style);
+    assert!(s.starts_with("<xacml3:Policy "));
+    assert!(s.contains("\r\n    <xacml3:Target"));
+    assert!(!s.replace("\r\n", "").contains('\n'));
+    let style = OutputStyle {
+        indent: None,
+        ..OutputStyle::default()
+    };
+    let s = styled_str(XacmlVersion::V3, &style);
+    assert!(s.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?><xacml3:Policy "));
+    assert!(!s.contains('\n'));
+}
+
+/// The schema location is only written on the root element.
+#[test]
+fn schema_location() {
+    let style = OutputStyle {
+        schema_location: true,
+        ..OutputStyle::default()
+    };
+    let s = styled_str(XacmlVersion::V3, &style);
+    assert_eq!(s.matches("xsi:schemaLocation=").count(), 1);
+    assert!(s.contains(
+        "xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+         xsi:schemaLocation=\"urn:oasis:names:tc:xacml:3.0:core:schema:wd-17 \
+         http://docs.oasis-open.org/xacml/3.0/xacml-core-v3-schema-wd-17.xsd\""
+    ));
+}
+
+/// Characters outside of the encoding are written as character
+/// references.
+#[test]
+fn encodings() {
+    let s = styled_str(XacmlVersion::V3, &OutputStyle::default());
+    assert!(s.contains(">café<"));
+    let style = OutputStyle {
+        encoding: XmlEncoding::Ascii,
+        ..OutputStyle::default()
+    };
+    let s = styled_str(XacmlVersion::V3, &style);
+    assert!(s.starts_with("<?xml version=\"1.0\" encoding=\"US-ASCII\"?>"));
+    assert!(s.contains(">caf&#xE9;<"));
+    let style = OutputStyle {
+        encoding: XmlEncoding::Latin1,
+        ..OutputStyle::default()
+    };
+    let b = styled(XacmlVersion::V3, &style);
+    assert!(b.windows(5).any(|w| w == b">caf\xe9"));
+}
+
+/// Unknown encodings and line endings are rejected.
+#[test]
+fn invalid_style_options() {
+    assert!(matches!(
+        "EBCDIC".parse::<XmlEncoding>(),
+        Err(ParseError::InvalidOutputStyle(_))
+    ));
+    assert!(matches!(
+        "cr".parse::<LineEnding>(),
+        Err(ParseError::InvalidOutputStyle(_))
+    ));
+    assert_eq!(unwrap!("latin1".parse::<XmlEncoding>()), XmlEncoding::Latin1);
+    assert_eq!(unwrap!("CRLF".parse::<LineEnding>()), LineEnding::CrLf);
+}
@@ -128,7 +128,7 @@ namespace main {
     assert_eq!(p.children.len(), 1);
     let policy_c = p.children.first().unwrap();
     if let XPolicyEntry::PolicySetIdRef(c) = policy_c {
-	assert_eq!(c, "will-become-a-policyset");
+	assert_eq!(c.id, "will-become-a-policyset");
     } else {
 	panic!("Expected a policySetId, not a PolicyId");
     }
@@ -0,0 +1,147 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::context::{Config, Context, Provenance};
+use a2x::{alfa_compile, AlfaFile};
+use common::{get_nth_policy, xentry_to_str};
+use pretty_assertions::assert_eq;
+use std::rc::Rc;
+use std::str::FromStr;
+mod common;
+
+// Integration tests for recording the ALFA source of generated
+// policies and rules.
+
+const SRC: &str = r#"namespace main {
+  /* Audit access */
+  policy audit {
+    apply firstApplicable
+    rule check {
+      permit
+    }
+    rule {
+      deny
+    }
+  }
+}
+"#;
+
+/// Compile the source, with a provenance setting, into XACML text.
+fn compile_with(provenance: Provenance) -> String {
+    let ctx = Rc::new(Context::new(Config {
+        provenance,
+        ..Config::default()
+    }));
+    let sources = vec![AlfaFile {
+        filename: "main.alfa".to_owned(),
+        contents: SRC.to_owned(),
+    }];
+    let policies = alfa_compile(&ctx, sources).expect("compile failed");
+    xentry_to_str(&policies[0])
+}
+
+/// Provenance of an element, as written.
+fn line(loc: &str, name: &str) -> String {
+    format!(
+        "a2x provenance: source=main.alfa:{loc} name={name} version={}",
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+#[test]
+fn parse_provenance() {
+    assert_eq!(Provenance::from_str("comment").ok(), Some(Provenance::Comment));
+    assert_eq!(Provenance::from_str("description").ok(), Some(Provenance::Description));
+    assert_eq!(Provenance::from_str("off").ok(), Some(Provenance::Off));
+    assert!(Provenance::from_str("xml").is_err());
+}
+
+#[test]
+fn off_by_default() {
+    assert!(!compile_with(Provenance::default()).contains("a2x provenance"));
+}
+
+#[test]
+fn comments() {
+    let xml = compile_with(Provenance::Comment);
+    let comments: Vec<&str> = xml
+        .lines()
+        .map(str::trim)
+        .filter(|l| l.starts_with("<!--"))
+        .collect();
+    assert_eq!(
+        comments,
+        vec![
+            format!("<!-- {} -->", line("3", "main.audit")),
+            format!("<!-- {} -->", line("5", "main.audit.check")),
+            // anonymous rules are named by their policy
+            format!("<!-- {} -->", line("8", "main.audit")),
+        ]
+    );
+}
+
+#[test]
+fn descriptions() {
+    let ctx = Rc::new(Context::new(Config {
+        provenance: Provenance::Description,
+        ..Config::default()
+    }));
+    let sources = vec![AlfaFile {
+        filename: "main.alfa".to_owned(),
+        contents: SRC.to_owned(),
+    }];
+    let p = get_nth_policy(0, alfa_compile(&ctx, sources).expect("compile failed"));
+    // the line is appended to an existing description
+    assert_eq!(
+        p.description,
+        Some(format!("Audit access\n{}", line("3", "main.audit")))
+    );
+    assert_eq!(p.rules[0].description, Some(line("5", "main.audit.check")));
+    assert!(!compile_with(Provenance::Description).contains("<!--"));
+}
+
+/// Elements expanded from a template have the template as their
+/// source, and name the template and instance separately.
+#[test]
+fn template_instances() {
+    let ctx = Rc::new(Context::new(Config {
+        provenance: Provenance::Description,
+        ..Config::default()
+    }));
+    let sources = vec![
+        AlfaFile {
+            filename: "in/lib.alfa".to_owned(),
+            contents: r"namespace lib {
+  template policy allowRole(r) {
+    target clause subjectId == param[r]
+    apply firstApplicable
+    rule { permit }
+  }
+}"
+            .to_owned(),
+        },
+        AlfaFile {
+            filename: "in/main.alfa".to_owned(),
+            contents: r#"namespace main {
+  import lib.*
+  instance allowAdmin = allowRole(r = "admin")
+  instance allowRole(r = "clerk")
+}"#
+            .to_owned(),
+        },
+    ];
+    let policies = alfa_compile(&ctx, sources).expect("compile failed");
+    let version = env!("CARGO_PKG_VERSION");
+    let p = get_nth_policy(0, policies.clone());
+    assert_eq!(
+        p.description,
+        Some(format!(
+            "a2x provenance: source=in/lib.alfa:2 name=main.allowAdmin template=lib.allowRole instance=main.allowAdmin version={version}"
+        ))
+    );
+    // anonymous instances have no instance name
+    let p = get_nth_policy(1, policies);
+    let description = p.description.expect("provenance");
+    assert!(description.starts_with("a2x provenance: source=in/lib.alfa:2 name=main."));
+    assert!(description.ends_with(&format!(" template=lib.allowRole version={version}")));
+}
@@ -0,0 +1,70 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+#![cfg(feature = "serde")]
+
+use a2x::context::Context;
+use a2x::make_alfa_ast;
+use a2x::xacml::XTopPolicy;
+use common::compile_alfa_srcs;
+use miette::NamedSource;
+use pretty_assertions::assert_eq;
+use std::rc::Rc;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for JSON serialization of the syntax tree and
+// the XACML model.
+
+const SRC: &str = r#"namespace main {
+  import Attributes.*
+  namespace attr {
+    attribute role { category = subjectCat id = "urn:example:role" type = string }
+  }
+  policyset top {
+    apply firstApplicable
+    audit
+  }
+  policy audit {
+    apply denyOverrides
+    rule {
+      permit
+      target clause attr.role == "admin"
+      condition integerOneAndOnly(Attributes.resourceId) > 3
+      on permit { obligation Obligations.log { attr.role = "admin" } }
+    }
+  }
+}
+namespace Obligations {
+  obligation log = "urn:example:obligation:log"
+}
+namespace Attributes {
+  attribute resourceId { category = resourceCat id = "urn:example:resource-id" type = integer }
+}
+"#;
+
+#[test]
+fn xacml_round_trip() {
+    let policies = compile_alfa_srcs(vec![SRC.to_owned()]);
+    let json = unwrap!(serde_json::to_string(&policies), "serialize failed");
+    let read: Vec<XTopPolicy> = unwrap!(serde_json::from_str(&json), "deserialize failed");
+    assert_eq!(read, policies);
+}
+
+#[test]
+fn ast_json() {
+    let ctx = Rc::new(Context::default());
+    let src = NamedSource::new("main.alfa", SRC.to_owned());
+    let ast = unwrap!(make_alfa_ast(SRC, &src, ctx), "parse failed");
+    let json = unwrap!(serde_json::to_value(&ast), "serialize failed");
+    let policy = &json["namespaces"][0]["policies"][0];
+    assert_eq!(policy["id"]["PolicyName"], "audit");
+    // source locations refer to the file, by offset and length, here
+    // of the policy name.
+    let offset = SRC.find("policy audit").unwrap() + "policy ".len();
+    assert_eq!(
+        policy["src_loc"],
+        serde_json::json!({"file": "main.alfa", "offset": offset, "length": 5})
+    );
+    // the context is left out.
+    assert!(!json.to_string().contains("\"ctx\""));
+}
@@ -0,0 +1,111 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::sourcemap::{SourceMap, SourceRange};
+use a2x::xacml::layout::{OutputLayout, output_paths};
+use common::compile_alfa_src;
+use pretty_assertions::assert_eq;
+use std::path::PathBuf;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for mapping generated XACML back to ALFA.
+
+const SRC: &str = r#"namespace main {
+  import Attributes.*
+  policyset top {
+    apply firstApplicable
+    audit
+  }
+  policy audit {
+    apply denyOverrides
+    target clause level == 1
+    rule check {
+      permit
+      condition integerOneAndOnly(level) > 3
+    }
+  }
+}
+namespace Attributes {
+  attribute level { category = resourceCat id = "urn:example:level" type = integer }
+}
+"#;
+
+/// Source map of the compiled source.
+fn source_map() -> SourceMap {
+    let policies = compile_alfa_src(SRC);
+    let paths = unwrap!(output_paths(&policies, &OutputLayout::default()), "no paths");
+    SourceMap::new(&policies.into_iter().zip(paths).collect::<Vec<(_, PathBuf)>>())
+}
+
+#[test]
+fn elements() {
+    let m = source_map();
+    let summary: Vec<(String, String)> = m
+        .entries
+        .iter()
+        .map(|e| (e.element.clone(), e.range.to_string()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("PolicySet".to_owned(), ":3:13-3:15".to_owned()),
+            ("Policy".to_owned(), ":7:10-7:14".to_owned()),
+            ("Target".to_owned(), ":9:5-9:28".to_owned()),
+            ("Rule".to_owned(), ":10:5-13:5".to_owned()),
+            ("Condition".to_owned(), ":12:17-12:44".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn lookup() {
+    let m = source_map();
+    let rule_id = "https://sr.ht/~gheartsfield/a2x/alfa/ident/main/audit/check";
+    let entries = m.lookup(rule_id);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(
+        entries[1].path,
+        format!(
+            "Policy[https://sr.ht/~gheartsfield/a2x/alfa/ident/main/audit]/Rule[{rule_id}]/Condition"
+        )
+    );
+    assert_eq!(entries[0].output, PathBuf::from("main.audit.xml"));
+    assert_eq!(
+        entries[0].range.snippet(SRC),
+        "  --> :10:5
+10 |     rule check {
+11 |       permit
+12 |       condition integerOneAndOnly(level) > 3
+13 |     }
+"
+    );
+    assert!(m.lookup("urn:example:unknown").is_empty());
+}
+
+#[test]
+fn json() {
+    let range = SourceRange {
+        file: "a \"b\".alfa".to_owned(),
+        start_line: 1,
+        start_column: 2,
+        end_line: 3,
+        end_column: 4,
+    };
+    let m = SourceMap {
+        entries: vec![a2x::sourcemap::SourceMapEntry {
+            id: "urn:a".to_owned(),
+            element: "Policy".to_owned(),
+            path: "Policy[urn:a]".to_owned(),
+            output: PathBuf::from("a.xml"),
+            range,
+        }],
+    };
+    assert_eq!(
+        m.to_string(),
+        r#"{"version": 1, "entries": [
+  {"id": "urn:a", "element": "Policy", "path": "Policy[urn:a]", "output": "a.xml", "file": "a \"b\".alfa", "start_line": 1, "start_column": 2, "end_line": 3, "end_column": 4}
+]}
+"#
+    );
+}
@@ -0,0 +1,290 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::alfa_compile;
+use a2x::context::Context;
+use a2x::xacml::XTopPolicy;
+use a2x::AlfaFile;
+use common::{compile_alfa_src, compile_alfa_srcs, get_nth_policy, xentry_to_str};
+use miette::Diagnostic;
+use pretty_assertions::assert_eq;
+use std::rc::Rc;
+mod common;
+
+// Integration tests for policy and rule templates, and their
+// instantiation.
+
+/// A policy template with literal and attribute parameters.
+#[test]
+fn policy_template() {
+    let x = compile_alfa_src(
+        r#"
+namespace main {
+  /* Allow a role to read */
+  template policy allowRead(role, act) {
+    target clause subjectId == param[role] and param[act] == "read"
+    apply firstApplicable
+    rule { permit }
+  }
+  instance customerRead = allowRead(role = "customer", act = actionId)
+}"#,
+    );
+    let xp = x.first().expect("at least one policy");
+    let xacml = xentry_to_str(xp);
+    assert_eq!(
+        xacml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<xacml3:Policy xmlns:xacml3="urn:oasis:names:tc:xacml:3.0:core:schema:wd-17" PolicyId="https://sr.ht/~gheartsfield/a2x/alfa/ident/main/customerRead" RuleCombiningAlgId="urn:oasis:names:tc:xacml:1.0:rule-combining-algorithm:first-applicable" Version="1.0">
+  <xacml3:Description>Allow a role to read</xacml3:Description>
+  <xacml3:Target>
+    <xacml3:AnyOf>
+      <xacml3:AllOf>
+        <xacml3:Match MatchId="urn:oasis:names:tc:xacml:1.0:function:string-equal">
+          <xacml3:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">customer</xacml3:AttributeValue>
+          <xacml3:AttributeDesignator AttributeId="urn:oasis:names:tc:xacml:1.0:subject:subject-id" Category="urn:oasis:names:tc:xacml:1.0:subject-category:access-subject" DataType="http://www.w3.org/2001/XMLSchema#string" MustBePresent="false" />
+        </xacml3:Match>
+        <xacml3:Match MatchId="urn:oasis:names:tc:xacml:1.0:function:string-equal">
+          <xacml3:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">read</xacml3:AttributeValue>
+          <xacml3:AttributeDesignator AttributeId="urn:oasis:names:tc:xacml:1.0:action:action-id" Category="urn:oasis:names:tc:xacml:3.0:attribute-category:action" DataType="http://www.w3.org/2001/XMLSchema#string" MustBePresent="false" />
+        </xacml3:Match>
+      </xacml3:AllOf>
+    </xacml3:AnyOf>
+  </xacml3:Target>
+  <xacml3:Rule Effect="Permit" RuleId="https://sr.ht/~gheartsfield/a2x/alfa/ident/main/customerRead#rule_0">
+    <xacml3:Target />
+  </xacml3:Rule>
+</xacml3:Policy>"#
+    );
+}
+
+/// Anonymous instances receive distinct generated IDs.
+#[test]
+fn anonymous_instances() {
+    let x = compile_alfa_src(
+        r#"
+namespace main {
+  template policy allowRole(role) {
+    target clause subjectId == param[role]
+    apply firstApplicable
+    rule { permit }
+  }
+  instance allowRole(role = "customer")
+  instance allowRole(role = "clerk")
+}"#,
+    );
+    assert_eq!(x.len(), 2);
+    let ids: Vec<String> = x
+        .into_iter()
+        .map(|p| match p {
+            XTopPolicy::Policy(p) => p.id,
+            XTopPolicy::PolicySet(_) => panic!("Expected Policy"),
+        })
+        .collect();
+    assert_ne!(ids[0], ids[1]);
+}
+
+/// Rule templates declared in another file, referenced from a policy.
+#[test]
+fn rule_template_other_file() {
+    let x = compile_alfa_srcs(vec![
+        r"
+namespace main {
+  import lib.*
+  instance allowAdmin = permitRole(role = 'admin')
+  policy p {
+    apply firstApplicable
+    allowAdmin
+  }
+}"
+        .to_string(),
+        r"
+namespace lib {
+  template rule permitRole(role) {
+    target clause subjectId == param[role]
+    permit
+  }
+}"
+        .to_string(),
+    ]);
+    let p = get_nth_policy(0, x);
+    assert_eq!(p.rules.len(), 1);
+    let mch = &p.rules[0].target.anyofs[0].allofs[0].matches[0];
+    assert_eq!(mch.value, "admin");
+}
+
+/// Names in a template body resolve in the template namespace, while
+/// names in the arguments resolve from the instance.
+#[test]
+fn template_namespace_resolution() {
+    let x = compile_alfa_srcs(vec![
+        r#"
+namespace main {
+  import lib.permitRole
+  attribute level { category = subjectCat id = "urn:example:level" type = string }
+  constant admin : string = "admin"
+  instance allowAdmin = permitRole(r = constant[admin], lvl = level)
+  policy p {
+    apply firstApplicable
+    allowAdmin
+  }
+}"#
+        .to_string(),
+        r#"
+namespace lib {
+  attribute role { category = subjectCat id = "urn:example:role" type = string }
+  template rule permitRole(r, lvl) {
+    target clause role == param[r] and param[lvl] == "high"
+    permit
+  }
+}"#
+        .to_string(),
+    ]);
+    let p = get_nth_policy(0, x);
+    assert_eq!(p.rules.len(), 1);
+    assert!(p.rules[0].id.contains("/main/allowAdmin#"));
+    let matches = &p.rules[0].target.anyofs[0].allofs[0].matches;
+    assert_eq!(matches[0].value, "admin");
+    assert_eq!(matches[0].designator_id, "urn:example:role");
+    assert_eq!(matches[1].designator_id, "urn:example:level");
+}
+
+/// Unresolved names in a template body are reported at the template,
+/// and at the instance.
+#[test]
+fn template_body_error_locations() {
+    let ctx = Rc::new(Context::default());
+    let sources = vec![
+        AlfaFile {
+            filename: "lib.alfa".to_owned(),
+            contents: r"
+namespace lib {
+  template rule permitUnknown(r) {
+    target clause unknown == param[r]
+    permit
+  }
+}"
+            .to_owned(),
+        },
+        AlfaFile {
+            filename: "main.alfa".to_owned(),
+            contents: r#"
+namespace main {
+  import lib.*
+  instance denied = permitUnknown(r = "x")
+  policy p { apply firstApplicable denied }
+}"#
+            .to_owned(),
+        },
+    ];
+    let Err(e) = alfa_compile(&ctx, sources) else {
+        panic!("conversion must fail");
+    };
+    let src = e.source_code().expect("error has source");
+    let label = e.labels().expect("error has labels").next().unwrap();
+    let contents = src.read_span(label.inner(), 0, 0).unwrap();
+    assert_eq!(contents.name(), Some("lib.alfa"));
+    let related: Vec<_> = e.related().expect("error has related").collect();
+    let rsrc = related.last().unwrap().source_code().unwrap();
+    let rlabel = related.last().unwrap().labels().unwrap().next().unwrap();
+    let rcontents = rsrc.read_span(rlabel.inner(), 0, 0).unwrap();
+    assert_eq!(rcontents.name(), Some("main.alfa"));
+    assert_eq!(
+        std::str::from_utf8(rcontents.data()).unwrap(),
+        r#"instance denied = permitUnknown(r = "x")"#
+    );
+}
+
+/// Template bodies cannot reference undeclared parameters.
+#[test]
+#[should_panic(expected = "compile failed")]
+fn undeclared_parameter() {
+    compile_alfa_src(
+        r"
+namespace main {
+  template rule permitRole(role) {
+    target clause subjectId == param[other]
+    permit
+  }
+}",
+    );
+}
+
+/// Parameters cannot be used outside of templates.
+#[test]
+#[should_panic(expected = "compile failed")]
+fn parameter_outside_template() {
+    compile_alfa_src(
+        r"
+namespace main {
+  rule {
+    target clause subjectId == param[role]
+    permit
+  }
+}",
+    );
+}
+
+/// Instances must bind every parameter.
+#[test]
+#[should_panic(expected = "compile failed")]
+fn missing_argument() {
+    compile_alfa_src(
+        r"
+namespace main {
+  template rule permitRole(role) {
+    target clause subjectId == param[role]
+    permit
+  }
+  instance permitRole()
+}",
+    );
+}
+
+/// Errors in an expansion refer to both the template and instance.
+#[test]
+fn expansion_error_locations() {
+    let ctx = Rc::new(Context::default());
+    let sources = vec![
+        AlfaFile {
+            filename: "lib.alfa".to_owned(),
+            contents: r#"
+namespace lib {
+  template policy allowRead(act) {
+    target clause param[act] == "read"
+    apply firstApplicable
+    rule { permit }
+  }
+}"#
+            .to_owned(),
+        },
+        AlfaFile {
+            filename: "main.alfa".to_owned(),
+            contents: r#"
+namespace main {
+  import lib.*
+  // a literal cannot be used where an attribute is expected
+  instance readAll = allowRead(act = "read")
+}"#
+            .to_owned(),
+        },
+    ];
+    let Err(e) = alfa_compile(&ctx, sources) else {
+        panic!("expansion must fail");
+    };
+    // the primary error is in the expanded template
+    let src = e.source_code().expect("error has source");
+    let label = e.labels().expect("error has labels").next().unwrap();
+    let contents = src.read_span(label.inner(), 0, 0).unwrap();
+    assert_eq!(contents.name(), Some("lib.alfa"));
+    // the related error is the instance
+    let related: Vec<_> = e.related().expect("error has related").collect();
+    assert_eq!(related.len(), 1);
+    let rsrc = related[0].source_code().unwrap();
+    let rlabel = related[0].labels().unwrap().next().unwrap();
+    let rcontents = rsrc.read_span(rlabel.inner(), 0, 0).unwrap();
+    assert_eq!(rcontents.name(), Some("main.alfa"));
+    assert_eq!(
+        std::str::from_utf8(rcontents.data()).unwrap(),
+        r#"instance readAll = allowRead(act = "read")"#
+    );
+}
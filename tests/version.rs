@@ -0,0 +1,183 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::alfa_compile;
+use a2x::context::{Config, Context};
+use a2x::xacml::XTopPolicy;
+use a2x::AlfaFile;
+use common::{compile_alfa_src, get_nth_policy, xentry_to_str};
+use pretty_assertions::assert_eq;
+use std::rc::Rc;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for policy versions and versioned policy
+// references.
+
+/// Versions on a policyset, and version constraints on references.
+#[test]
+fn versioned_references() {
+    let x = compile_alfa_src(
+        r#"
+namespace main {
+  policyset top = "top" {
+    apply firstApplicable
+    version "2.1"
+    child(earliest = "1.0", latest = "1.*")
+    other(version = "3.+")
+  }
+  policy child = "child" {
+    apply firstApplicable
+    version "1.4.2"
+    rule { permit }
+  }
+  policyset other = "other" {
+    apply firstApplicable
+    child
+  }
+}"#,
+    );
+    let xp = unwrap!(x.first(), "at least one policyset");
+    let xacml = xentry_to_str(xp);
+    assert_eq!(
+        xacml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<xacml3:PolicySet xmlns:xacml3="urn:oasis:names:tc:xacml:3.0:core:schema:wd-17" PolicySetId="top" PolicyCombiningAlgId="urn:oasis:names:tc:xacml:1.0:policy-combining-algorithm:first-applicable" Version="2.1">
+  <xacml3:Target />
+  <xacml3:PolicyIdReference EarliestVersion="1.0" LatestVersion="1.*">child</xacml3:PolicyIdReference>
+  <xacml3:PolicySetIdReference Version="3.+">other</xacml3:PolicySetIdReference>
+</xacml3:PolicySet>"#
+    );
+}
+
+/// Policies without a version statement use the configured default.
+#[test]
+fn configured_default_version() {
+    let ctx = Rc::new(Context::new(Config {
+        version: Some("4.0".to_owned()),
+        ..Config::default()
+    }));
+    let sources = vec![AlfaFile {
+        filename: "main.alfa".to_owned(),
+        contents: r#"
+namespace main {
+  policy a {
+    apply firstApplicable
+    rule { permit }
+  }
+  policy b {
+    apply firstApplicable
+    version "1.1"
+    rule { permit }
+  }
+}"#
+        .to_owned(),
+    }];
+    let x = unwrap!(alfa_compile(&ctx, sources), "compile failed");
+    let versions: Vec<String> = x
+        .into_iter()
+        .map(|p| match p {
+            XTopPolicy::Policy(p) => p.version,
+            XTopPolicy::PolicySet(_) => panic!("Expected Policy"),
+        })
+        .collect();
+    assert_eq!(versions, vec!["4.0", "1.1"]);
+}
+
+/// A policy with a condition keeps its version when it becomes a
+/// policyset.
+#[test]
+fn deconditioned_version() {
+    let x = compile_alfa_src(
+        r#"
+namespace main {
+  policy a {
+    apply firstApplicable
+    version "7"
+    condition true
+    rule { permit }
+  }
+}"#,
+    );
+    let XTopPolicy::PolicySet(ps) = unwrap!(x.into_iter().next(), "one policyset") else {
+        panic!("Expected PolicySet");
+    };
+    assert_eq!(ps.version, "7");
+}
+
+/// Versions must follow the XACML version syntax.
+#[test]
+#[should_panic(expected = "compile failed")]
+fn invalid_version() {
+    compile_alfa_src(
+        r#"
+namespace main {
+  policy a {
+    apply firstApplicable
+    version "1.*"
+    rule { permit }
+  }
+}"#,
+    );
+}
+
+/// Version constraints must follow the XACML version match syntax.
+#[test]
+#[should_panic(expected = "compile failed")]
+fn invalid_version_constraint() {
+    compile_alfa_src(
+        r#"
+namespace main {
+  policyset top {
+    apply firstApplicable
+    child(latest = "1.+.2")
+  }
+  policy child {
+    apply firstApplicable
+    rule { permit }
+  }
+}"#,
+    );
+}
+
+/// Only one version statement is allowed.
+#[test]
+#[should_panic(expected = "compile failed")]
+fn duplicate_version() {
+    compile_alfa_src(
+        r#"
+namespace main {
+  policy a {
+    apply firstApplicable
+    version "1.0"
+    version "2.0"
+    rule { permit }
+  }
+}"#,
+    );
+}
+
+/// An invalid configured version is rejected.
+#[test]
+fn invalid_configured_version() {
+    let ctx = Rc::new(Context::new(Config {
+        version: Some("latest".to_owned()),
+        ..Config::default()
+    }));
+    assert!(alfa_compile(&ctx, vec![]).is_err());
+}
+
+/// Policy versions can be read from the converted policy.
+#[test]
+fn default_version() {
+    let x = compile_alfa_src(
+        r"
+namespace main {
+  policy a {
+    apply firstApplicable
+    rule { permit }
+  }
+}",
+    );
+    assert_eq!(get_nth_policy(0, x).version, "1.0");
+}
@@ -0,0 +1,78 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::watch::{InputChange, InputSnapshot};
+use a2x::{Written, XacmlFile, write_xacml_file_if_changed};
+use common::temp_dir;
+use pretty_assertions::assert_eq;
+use std::path::PathBuf;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for watch mode: detecting input changes, and
+// only rewriting changed outputs.
+
+/// Added, removed, and modified inputs are reported in path order.
+#[test]
+fn snapshot_changes() {
+    let dir = temp_dir("watch", "snapshot");
+    unwrap!(std::fs::create_dir_all(&dir));
+    let a = dir.join("a.alfa");
+    let b = dir.join("b.alfa");
+    let c = dir.join("c.alfa");
+    unwrap!(std::fs::write(&a, "namespace a {}"));
+    unwrap!(std::fs::write(&b, "namespace b {}"));
+    let before = InputSnapshot::new(&[a.clone(), b.clone(), c.clone()]);
+    assert_eq!(
+        before.changes(&InputSnapshot::new(&[a.clone(), b.clone()])),
+        vec![]
+    );
+    unwrap!(std::fs::write(&c, "namespace c {}"));
+    unwrap!(std::fs::remove_file(&a));
+    // a different length is a change, whatever the clock resolution
+    unwrap!(std::fs::write(&b, "namespace bb {}"));
+    let after = InputSnapshot::new(&[a.clone(), b.clone(), c.clone()]);
+    let changes = before.changes(&after);
+    assert_eq!(
+        changes,
+        vec![
+            InputChange::Removed(a),
+            InputChange::Modified(b.clone()),
+            InputChange::Added(c),
+        ]
+    );
+    assert_eq!(changes[1].to_string(), format!("~ {}", b.display()));
+    unwrap!(std::fs::remove_dir_all(&dir));
+}
+
+/// Files with the same contents are not rewritten.
+#[test]
+fn write_if_changed() {
+    let dir = temp_dir("watch", "write");
+    let mut file = XacmlFile {
+        filename: PathBuf::from("sub/p.xml"),
+        contents: b"<Policy/>".to_vec(),
+    };
+    let path = dir.join("sub/p.xml");
+    let shown = path.to_str().unwrap_or_default().to_owned();
+    assert_eq!(
+        unwrap!(write_xacml_file_if_changed(&dir, &file)),
+        Written::Written(shown.clone())
+    );
+    let modified = unwrap!(unwrap!(std::fs::metadata(&path)).modified());
+    assert_eq!(
+        unwrap!(write_xacml_file_if_changed(&dir, &file)),
+        Written::Unchanged(shown.clone())
+    );
+    assert_eq!(
+        unwrap!(unwrap!(std::fs::metadata(&path)).modified()),
+        modified
+    );
+    file.contents = b"<Policy></Policy>".to_vec();
+    assert_eq!(
+        unwrap!(write_xacml_file_if_changed(&dir, &file)),
+        Written::Written(shown)
+    );
+    assert_eq!(unwrap!(std::fs::read(&path)), file.contents);
+    unwrap!(std::fs::remove_dir_all(&dir));
+}
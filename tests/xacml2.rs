@@ -0,0 +1,414 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::alfa_compile;
+use a2x::context::{Config, Context, XacmlVersion};
+use a2x::xacml::style::OutputStyle;
+use a2x::xacml::XTopPolicy;
+use a2x::{AlfaFile, ParseError};
+use pretty_assertions::assert_eq;
+use std::rc::Rc;
+use unwrap::unwrap;
+use xml::EmitterConfig;
+
+// Integration tests for XACML 2.0 output.
+
+/// Compile a single ALFA source text for XACML 2.0.
+fn compile_v2(src: &str) -> Result<Vec<XTopPolicy>, ParseError> {
+    let ctx = Rc::new(Context::new(Config {
+        target_version: XacmlVersion::V2,
+        ..Config::default()
+    }));
+    let sources = vec![AlfaFile {
+        filename: "main.alfa".to_owned(),
+        contents: src.to_owned(),
+    }];
+    alfa_compile(&ctx, sources)
+}
+
+/// Compile a single ALFA source text, and write the first top-level
+/// policy as XACML 2.0.
+fn v2_str(src: &str) -> String {
+    let x = unwrap!(compile_v2(src), "compile failed");
+    let xp = unwrap!(x.first(), "at least one policy");
+    let mut target = vec![];
+    let mut writer = EmitterConfig::new()
+        .perform_indent(true)
+        .create_writer(&mut target);
+    xp.write_xml_version(XacmlVersion::V2, &mut writer, &OutputStyle::default())
+        .expect("unable to write");
+    String::from_utf8(target).expect("can't convert to utf8")
+}
+
+/// Compile a single ALFA source text for XACML 2.0, and return the
+/// error message.
+fn v2_err(src: &str) -> String {
+    match compile_v2(src) {
+        Ok(_) => panic!("expected compilation to fail"),
+        Err(e) => format!("{:?}", miette::Report::new(e)),
+    }
+}
+
+/// Targets are grouped by category, and designators use
+/// category-specific elements.
+#[test]
+fn policy_v2() {
+    let xacml = v2_str(
+        r#"
+namespace main {
+  obligation audit = "urn:example:audit"
+  policy p = "p" {
+    apply denyOverrides
+    target clause subjectId == "alice" or subjectId == "bob"
+           clause resourceId == "doc"
+           clause actionId == "read"
+    rule r {
+      permit
+      condition stringIsIn("x", subjectId)
+    }
+    on permit {
+      obligation audit { actionId = "logged" }
+    }
+  }
+}"#,
+    );
+    assert_eq!(
+        xacml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<xacml2:Policy xmlns:xacml2="urn:oasis:names:tc:xacml:2.0:policy:schema:os" PolicyId="p" Version="1.0" RuleCombiningAlgId="urn:oasis:names:tc:xacml:1.0:rule-combining-algorithm:deny-overrides">
+  <xacml2:Target>
+    <xacml2:Subjects>
+      <xacml2:Subject>
+        <xacml2:SubjectMatch MatchId="urn:oasis:names:tc:xacml:1.0:function:string-equal">
+          <xacml2:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">alice</xacml2:AttributeValue>
+          <xacml2:SubjectAttributeDesignator AttributeId="urn:oasis:names:tc:xacml:1.0:subject:subject-id" DataType="http://www.w3.org/2001/XMLSchema#string" MustBePresent="false" SubjectCategory="urn:oasis:names:tc:xacml:1.0:subject-category:access-subject" />
+        </xacml2:SubjectMatch>
+      </xacml2:Subject>
+      <xacml2:Subject>
+        <xacml2:SubjectMatch MatchId="urn:oasis:names:tc:xacml:1.0:function:string-equal">
+          <xacml2:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">bob</xacml2:AttributeValue>
+          <xacml2:SubjectAttributeDesignator AttributeId="urn:oasis:names:tc:xacml:1.0:subject:subject-id" DataType="http://www.w3.org/2001/XMLSchema#string" MustBePresent="false" SubjectCategory="urn:oasis:names:tc:xacml:1.0:subject-category:access-subject" />
+        </xacml2:SubjectMatch>
+      </xacml2:Subject>
+    </xacml2:Subjects>
+    <xacml2:Resources>
+      <xacml2:Resource>
+        <xacml2:ResourceMatch MatchId="urn:oasis:names:tc:xacml:1.0:function:string-equal">
+          <xacml2:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">doc</xacml2:AttributeValue>
+          <xacml2:ResourceAttributeDesignator AttributeId="urn:oasis:names:tc:xacml:1.0:resource:resource-id" DataType="http://www.w3.org/2001/XMLSchema#string" MustBePresent="false" />
+        </xacml2:ResourceMatch>
+      </xacml2:Resource>
+    </xacml2:Resources>
+    <xacml2:Actions>
+      <xacml2:Action>
+        <xacml2:ActionMatch MatchId="urn:oasis:names:tc:xacml:1.0:function:string-equal">
+          <xacml2:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">read</xacml2:AttributeValue>
+          <xacml2:ActionAttributeDesignator AttributeId="urn:oasis:names:tc:xacml:1.0:action:action-id" DataType="http://www.w3.org/2001/XMLSchema#string" MustBePresent="false" />
+        </xacml2:ActionMatch>
+      </xacml2:Action>
+    </xacml2:Actions>
+  </xacml2:Target>
+  <xacml2:Rule RuleId="https://sr.ht/~gheartsfield/a2x/alfa/ident/main/p/r" Effect="Permit">
+    <xacml2:Target />
+    <xacml2:Condition>
+      <xacml2:Apply FunctionId="urn:oasis:names:tc:xacml:1.0:function:string-is-in">
+        <xacml2:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">x</xacml2:AttributeValue>
+        <xacml2:SubjectAttributeDesignator AttributeId="urn:oasis:names:tc:xacml:1.0:subject:subject-id" DataType="http://www.w3.org/2001/XMLSchema#string" MustBePresent="false" SubjectCategory="urn:oasis:names:tc:xacml:1.0:subject-category:access-subject" />
+      </xacml2:Apply>
+    </xacml2:Condition>
+  </xacml2:Rule>
+  <xacml2:Obligations>
+    <xacml2:Obligation ObligationId="urn:example:audit" FulfillOn="Permit">
+      <xacml2:AttributeAssignment AttributeId="urn:oasis:names:tc:xacml:1.0:action:action-id" DataType="http://www.w3.org/2001/XMLSchema#string">logged</xacml2:AttributeAssignment>
+    </xacml2:Obligation>
+  </xacml2:Obligations>
+</xacml2:Policy>"#
+    );
+}
+
+/// Multiple target clauses for one category are combined into a
+/// single disjunction.
+#[test]
+fn target_clauses_combined() {
+    let xacml = v2_str(
+        r#"
+namespace main {
+  policy p = "p" {
+    apply firstApplicable
+    target clause subjectId == "a" or subjectId == "b"
+           clause subjectId == "c"
+    rule { permit }
+  }
+}"#,
+    );
+    assert_eq!(xacml.matches("<xacml2:Subject>").count(), 2);
+    assert_eq!(xacml.matches("<xacml2:SubjectMatch ").count(), 4);
+}
+
+/// A clause that requires attributes of several categories is split
+/// across their sections, which are combined by conjunction.
+#[test]
+fn target_clause_split() {
+    let xacml = v2_str(
+        r#"
+namespace main {
+  policy p = "p" {
+    apply firstApplicable
+    target clause subjectId == "clerk" and actionId == "read"
+    rule { permit }
+  }
+}"#,
+    );
+    assert!(xacml.contains(
+        r#"<xacml2:Subjects>
+      <xacml2:Subject>
+        <xacml2:SubjectMatch MatchId="urn:oasis:names:tc:xacml:1.0:function:string-equal">
+          <xacml2:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">clerk</xacml2:AttributeValue>"#
+    ), "{xacml}");
+    assert!(xacml.contains(
+        r#"<xacml2:Actions>
+      <xacml2:Action>
+        <xacml2:ActionMatch MatchId="urn:oasis:names:tc:xacml:1.0:function:string-equal">
+          <xacml2:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">read</xacml2:AttributeValue>"#
+    ), "{xacml}");
+}
+
+/// Alternatives in a clause cannot span categories, and the error
+/// identifies the clause.
+#[test]
+fn target_alternatives_rejected() {
+    let e = v2_err(
+        r#"
+namespace main {
+  policy p = "p" {
+    apply firstApplicable
+    target clause resourceId == "doc"
+           clause subjectId == "clerk" or actionId == "read"
+    rule { permit }
+  }
+}"#,
+    );
+    assert!(e.contains("Target cannot be expressed in XACML 2.0"), "{e}");
+    assert!(
+        e.contains("the alternatives of a target clause cannot mix Subject and Action attributes"),
+        "{e}"
+    );
+    assert!(e.contains("main.alfa:6:19"), "{e}");
+}
+
+/// Policysets use 2.0 combining algorithms, and may reference other
+/// policies.
+#[test]
+fn policyset_v2() {
+    let xacml = v2_str(
+        r#"
+namespace main {
+  policyset ps = "ps" {
+    apply permitOverrides
+    other(version = "1.*")
+    policy inner = "inner" {
+      apply orderedDenyOverrides
+      rule { deny }
+    }
+  }
+  policy other = "other" {
+    apply firstApplicable
+    rule { permit }
+  }
+}"#,
+    );
+    assert!(xacml.contains(
+        r#"PolicyCombiningAlgId="urn:oasis:names:tc:xacml:1.0:policy-combining-algorithm:permit-overrides""#
+    ));
+    assert!(xacml.contains(
+        r#"RuleCombiningAlgId="urn:oasis:names:tc:xacml:1.1:rule-combining-algorithm:ordered-deny-overrides""#
+    ));
+    assert!(xacml.contains(
+        r#"<xacml2:PolicyIdReference Version="1.*">other</xacml2:PolicyIdReference>"#
+    ));
+}
+
+/// Advice does not exist in XACML 2.0.
+#[test]
+fn advice_rejected() {
+    let e = v2_err(
+        r#"
+namespace main {
+  advice a1 = "urn:example:advice"
+  policy p {
+    apply firstApplicable
+    rule { permit }
+    on permit { advice a1 }
+  }
+}"#,
+    );
+    assert!(e.contains("Advice cannot be expressed in XACML 2.0"), "{e}");
+}
+
+/// Only the standard categories exist in XACML 2.0.
+#[test]
+fn category_rejected() {
+    let e = v2_err(
+        r#"
+namespace main {
+  policy p {
+    apply firstApplicable
+    target clause delegationDecision == "Permit"
+    rule { permit }
+  }
+}"#,
+    );
+    assert!(e.contains("Target cannot be expressed in XACML 2.0"), "{e}");
+    assert!(e.contains("delegation-info"), "{e}");
+}
+
+/// Functions introduced in 3.0 are rejected.
+#[test]
+fn function_rejected() {
+    let e = v2_err(
+        r#"
+namespace main {
+  policy p {
+    apply firstApplicable
+    rule {
+      permit
+      condition stringStartsWith("a", stringOneAndOnly(subjectId))
+    }
+  }
+}"#,
+    );
+    assert!(e.contains("Condition cannot be expressed in XACML 2.0"), "{e}");
+    assert!(e.contains("string-starts-with"), "{e}");
+}
+
+/// Combining algorithms introduced in 3.0 are rejected.
+#[test]
+fn combining_alg_rejected() {
+    let e = v2_err(
+        r"
+namespace main {
+  policy p {
+    apply denyUnlessPermit
+    rule { permit }
+  }
+}",
+    );
+    assert!(e.contains("Combining algorithm cannot be expressed in XACML 2.0"), "{e}");
+}
+
+/// Policy conditions require a 3.0 combining algorithm.
+#[test]
+fn policy_condition_rejected() {
+    let e = v2_err(
+        r"
+namespace main {
+  policy p {
+    apply firstApplicable
+    condition true
+    rule { permit }
+  }
+}",
+    );
+    assert!(e.contains("conditions cannot be expressed in XACML 2.0"), "{e}");
+}
+
+/// Rules cannot have obligations in XACML 2.0.
+#[test]
+fn rule_obligation_rejected() {
+    let e = v2_err(
+        r#"
+namespace main {
+  obligation o1 = "urn:example:oblig"
+  policy p {
+    apply firstApplicable
+    rule {
+      permit
+      on permit { obligation o1 }
+    }
+  }
+}"#,
+    );
+    assert!(e.contains("Rules cannot have obligations or advice"), "{e}");
+}
+
+/// Infix operators on attributes are written with the 1.0
+/// `any-of-any`, with single values in bags.
+#[test]
+fn infix_attribute_condition() {
+    let xacml = v2_str(
+        r#"
+namespace main {
+  attribute age { id = "urn:example:age" type = integer category = subjectCat }
+  policy p = "p" {
+    apply firstApplicable
+    rule { permit condition age < 65 }
+  }
+}"#,
+    );
+    assert!(xacml.contains(
+        r#"<xacml2:Condition>
+      <xacml2:Apply FunctionId="urn:oasis:names:tc:xacml:1.0:function:any-of-any">
+        <xacml2:Function FunctionId="urn:oasis:names:tc:xacml:1.0:function:integer-less-than" />
+        <xacml2:SubjectAttributeDesignator AttributeId="urn:example:age" DataType="http://www.w3.org/2001/XMLSchema#integer" MustBePresent="false" SubjectCategory="urn:oasis:names:tc:xacml:1.0:subject-category:access-subject" />
+        <xacml2:Apply FunctionId="urn:oasis:names:tc:xacml:1.0:function:integer-bag">
+          <xacml2:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#integer">65</xacml2:AttributeValue>
+        </xacml2:Apply>
+      </xacml2:Apply>
+    </xacml2:Condition>"#
+    ), "{xacml}");
+}
+
+/// The retail sample's attributes, rules, and obligations compile
+/// to 2.0, with a policy from the sample.  The sample's own policies
+/// use policy conditions and advice, which do not exist in 2.0.
+#[test]
+fn retail_sample_v2() {
+    let ctx = Rc::new(Context::new(Config {
+        target_version: XacmlVersion::V2,
+        ..Config::default()
+    }));
+    let mut sources: Vec<AlfaFile> = ["attr", "common", "oblig"]
+        .iter()
+        .map(|n| {
+            let filename = format!("samples/retail/{n}.alfa");
+            let contents = unwrap!(std::fs::read_to_string(&filename), "unreadable sample");
+            AlfaFile { filename, contents }
+        })
+        .collect();
+    sources.push(AlfaFile {
+        filename: "main.alfa".to_owned(),
+        contents: r#"
+namespace main {
+  import attr.*
+  policy customerAddToCart = "urn:example:a2x:policy:customer-add-to-cart" {
+    target clause roleId   == "customer"
+              and actionId == "addToCart"
+              and rType    == "cart"
+    apply firstApplicable
+    rule {
+      permit
+      condition subjectId == cartOwner
+    }
+    common.deny
+  }
+}"#
+        .to_owned(),
+    });
+    let x = unwrap!(alfa_compile(&ctx, sources), "compile failed");
+    let policy = unwrap!(
+        x.iter().find(|p| matches!(p, XTopPolicy::Policy(xp) if xp.id.ends_with("customer-add-to-cart"))),
+        "policy missing"
+    );
+    let mut target = vec![];
+    let mut writer = EmitterConfig::new().create_writer(&mut target);
+    unwrap!(policy.write_xml_version(XacmlVersion::V2, &mut writer, &OutputStyle::default()));
+    let xacml = String::from_utf8(target).expect("can't convert to utf8");
+    for s in [
+        "<xacml2:Subjects>",
+        "<xacml2:Resources>",
+        "<xacml2:Actions>",
+        r#"FunctionId="urn:oasis:names:tc:xacml:1.0:function:any-of-any""#,
+    ] {
+        assert!(xacml.contains(s), "missing {s:?} in:\n{xacml}");
+    }
+}
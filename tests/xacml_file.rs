@@ -0,0 +1,87 @@
+//  SPDX-FileCopyrightText: 2025 Greg Heartsfield <scsibug@imap.cc>
+//  SPDX-License-Identifier: GPL-3.0-or-later
+
+use a2x::context::XacmlVersion;
+use a2x::xacml::xpolicy::XPolicy;
+use a2x::xacml::style::OutputStyle;
+use a2x::xacml::XTopPolicy;
+use a2x::{serialize_xentries, serialize_xentry, write_xentry, ParseError};
+use common::{compile_alfa_src, xentry_to_str};
+use pretty_assertions::assert_eq;
+use std::path::PathBuf;
+use unwrap::unwrap;
+mod common;
+
+// Integration tests for serializing policies into in-memory XACML
+// files.
+
+const SRC: &str = r#"
+namespace main {
+  policy a = "a" {
+    apply firstApplicable
+    rule { permit }
+  }
+  policyset b = "b" {
+    apply firstApplicable
+    a
+  }
+}"#;
+
+/// Each top-level policy becomes a file with its contents in memory.
+#[test]
+fn in_memory_files() {
+    let x = compile_alfa_src(SRC);
+    let files = unwrap!(
+        serialize_xentries(&x, XacmlVersion::V3, &OutputStyle::default()),
+        "serialization failed"
+    );
+    assert_eq!(files.len(), 2);
+    for (f, xp) in files.iter().zip(&x) {
+        assert_eq!(Some(&f.filename), xp.filename().map(PathBuf::from).as_ref());
+        // identical to writing with the default emitter
+        assert_eq!(String::from_utf8(f.contents.clone()).unwrap(), xentry_to_str(xp));
+    }
+}
+
+/// Style options control the XML output.
+#[test]
+fn emitter_options() {
+    let x = compile_alfa_src(SRC);
+    let xp = unwrap!(x.first(), "at least one policy");
+    let style = OutputStyle {
+        indent: None,
+        xml_declaration: false,
+        ..OutputStyle::default()
+    };
+    let f = unwrap!(
+        serialize_xentry(xp, XacmlVersion::V3, &style),
+        "serialization failed"
+    );
+    let s = String::from_utf8(f.contents).unwrap();
+    assert!(s.starts_with("<xacml3:PolicySet"), "{s}");
+    assert!(!s.contains('\n'));
+}
+
+/// Policies without a filename cannot be serialized.
+#[test]
+fn missing_filename() {
+    let xp = XTopPolicy::Policy(XPolicy::default());
+    let r = serialize_xentry(&xp, XacmlVersion::V3, &OutputStyle::default());
+    assert!(matches!(r, Err(ParseError::XacmlMissingFilename)));
+}
+
+/// Writing to disk uses the same contents, and reports errors rather
+/// than panicking.
+#[test]
+fn write_to_disk() {
+    let x = compile_alfa_src(SRC);
+    let xp = unwrap!(x.first(), "at least one policy");
+    let dir = std::env::temp_dir().join(format!("a2x-xacml-file-{}", std::process::id()));
+    let path = unwrap!(write_xentry(&dir, xp, XacmlVersion::V3), "write failed");
+    let written = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(written, xentry_to_str(xp));
+    // a file cannot be used as a directory
+    let r = write_xentry(&PathBuf::from(&path), xp, XacmlVersion::V3);
+    assert!(matches!(r, Err(ParseError::XacmlWriteIoError)));
+    std::fs::remove_dir_all(&dir).unwrap();
+}